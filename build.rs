@@ -0,0 +1,73 @@
+//! Captures information about the build for the `version` command, as environment variables
+//! read with `env!`.
+//!
+//! Anything that cannot be found out is reported as `unknown` rather than failing the build.
+
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Runs `program` with `args`, and returns what it printed if it succeeded.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_owned()).filter(|output| !output.is_empty())
+}
+
+/// Formats `secs` seconds since the Unix epoch as a UTC date and time.
+fn utc(secs: u64) -> String {
+    // Converts days to a civil date, as in https://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn main() {
+    let unknown = || "unknown".to_owned();
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let rustc_version = output(&rustc, &["--version"]).unwrap_or_else(unknown);
+    let git = output("git", &["describe", "--always", "--dirty", "--tags"]).unwrap_or_else(unknown);
+    let profile = env::var("PROFILE").unwrap_or_else(|_| unknown());
+    let target = env::var("TARGET").unwrap_or_else(|_| unknown());
+    // SOURCE_DATE_EPOCH makes the build reproducible.
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .or_else(|| Some(SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs()))
+        .map_or_else(unknown, utc);
+
+    println!("cargo:rustc-env=KFS_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rustc-env=KFS_GIT_DESCRIBE={git}");
+    println!("cargo:rustc-env=KFS_PROFILE={profile}");
+    println!("cargo:rustc-env=KFS_TARGET={target}");
+    println!("cargo:rustc-env=KFS_BUILD_TIMESTAMP={timestamp}");
+
+    // Run again when a commit is made or checked out, so that the hash stays current.
+    println!("cargo:rerun-if-changed=build.rs");
+    for path in [".git/HEAD", ".git/index"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
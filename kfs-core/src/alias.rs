@@ -0,0 +1,232 @@
+//! Shortcuts standing for the start of a command line, such as `ll` for `stack`.
+//!
+//! Only the first word of a line is looked up, and only once: the expansion of an alias is not
+//! expanded again, so aliases cannot loop.
+
+use core::fmt;
+
+/// The number of aliases that can be defined at once.
+pub const MAX_ALIASES: usize = 16;
+
+/// The maximum length of the name of an alias, in bytes.
+pub const MAX_NAME_LEN: usize = 16;
+
+/// The maximum length of what an alias expands to, in bytes.
+pub const MAX_VALUE_LEN: usize = 64;
+
+/// Why an alias could not be defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasError {
+    /// The name is empty, or contains whitespace or `=`.
+    InvalidName,
+    /// The name or the value is too long.
+    TooLong,
+    /// The value is empty.
+    EmptyValue,
+    /// Every slot is taken.
+    Full,
+}
+
+impl AliasError {
+    /// Returns a description of the error.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            AliasError::InvalidName => "invalid alias name",
+            AliasError::TooLong => "alias too long",
+            AliasError::EmptyValue => "empty alias",
+            AliasError::Full => "too many aliases",
+        }
+    }
+}
+
+impl fmt::Display for AliasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A defined alias, or a free slot if `name_len` is 0.
+#[derive(Clone, Copy)]
+struct Alias {
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    value: [u8; MAX_VALUE_LEN],
+    value_len: usize,
+}
+
+impl Alias {
+    const EMPTY: Alias = Alias {
+        name: [0; MAX_NAME_LEN],
+        name_len: 0,
+        value: [0; MAX_VALUE_LEN],
+        value_len: 0,
+    };
+
+    fn name(&self) -> &str {
+        // Safety: the bytes were copied from a `&str`.
+        unsafe { core::str::from_utf8_unchecked(&self.name[..self.name_len]) }
+    }
+
+    fn value(&self) -> &str {
+        // Safety: the bytes were copied from a `&str`.
+        unsafe { core::str::from_utf8_unchecked(&self.value[..self.value_len]) }
+    }
+
+    fn is_free(&self) -> bool {
+        self.name_len == 0
+    }
+}
+
+/// The defined aliases.
+pub struct Aliases {
+    slots: [Alias; MAX_ALIASES],
+}
+
+impl Aliases {
+    pub const fn new() -> Self {
+        Aliases {
+            slots: [Alias::EMPTY; MAX_ALIASES],
+        }
+    }
+
+    /// Defines the alias `name` as `value`, replacing any previous definition.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), AliasError> {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '=') {
+            return Err(AliasError::InvalidName);
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(AliasError::EmptyValue);
+        }
+        if name.len() > MAX_NAME_LEN || value.len() > MAX_VALUE_LEN {
+            return Err(AliasError::TooLong);
+        }
+        let slot = match self
+            .slots
+            .iter()
+            .position(|slot| !slot.is_free() && slot.name() == name)
+        {
+            Some(i) => &mut self.slots[i],
+            None => self
+                .slots
+                .iter_mut()
+                .find(|slot| slot.is_free())
+                .ok_or(AliasError::Full)?,
+        };
+        slot.name[..name.len()].copy_from_slice(name.as_bytes());
+        slot.name_len = name.len();
+        slot.value[..value.len()].copy_from_slice(value.as_bytes());
+        slot.value_len = value.len();
+        Ok(())
+    }
+
+    /// Removes the alias `name`, and returns whether it was defined.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let Some(slot) = self
+            .slots
+            .iter_mut()
+            .find(|slot| !slot.is_free() && slot.name() == name)
+        else {
+            return false;
+        };
+        *slot = Alias::EMPTY;
+        true
+    }
+
+    /// Returns what the alias `name` expands to, if it is defined.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.slots
+            .iter()
+            .find(|slot| !slot.is_free() && slot.name() == name)
+            .map(Alias::value)
+    }
+
+    /// Returns the names and values of the aliases.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.slots
+            .iter()
+            .filter(|slot| !slot.is_free())
+            .map(|slot| (slot.name(), slot.value()))
+    }
+
+    /// If the first word of `line` is an alias, returns what it expands to and the rest of the
+    /// line, which starts with whitespace unless empty.
+    pub fn expand<'a>(&'a self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        let line = line.trim_start();
+        let end = line.find(char::is_whitespace).unwrap_or(line.len());
+        let (first, rest) = line.split_at(end);
+        self.get(first).map(|value| (value, rest))
+    }
+}
+
+impl Default for Aliases {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn define_and_remove() {
+        let mut aliases = Aliases::new();
+        assert_eq!(aliases.get("ll"), None);
+        aliases.set("ll", "stack").unwrap();
+        aliases.set("hd", " hexdump 0x1000 ").unwrap();
+        assert_eq!(aliases.get("ll"), Some("stack"));
+        assert_eq!(aliases.get("hd"), Some("hexdump 0x1000"));
+
+        aliases.set("ll", "stack 8").unwrap();
+        let all: Vec<_> = aliases.iter().collect();
+        assert_eq!(all, [("ll", "stack 8"), ("hd", "hexdump 0x1000")]);
+
+        assert!(aliases.remove("ll"));
+        assert!(!aliases.remove("ll"));
+        assert!(!aliases.remove(""));
+        assert_eq!(aliases.get("ll"), None);
+        assert_eq!(aliases.iter().count(), 1);
+    }
+
+    #[test]
+    fn invalid_definitions() {
+        let mut aliases = Aliases::new();
+        assert_eq!(aliases.set("", "stack"), Err(AliasError::InvalidName));
+        assert_eq!(aliases.set("a b", "stack"), Err(AliasError::InvalidName));
+        assert_eq!(aliases.set("a=b", "stack"), Err(AliasError::InvalidName));
+        assert_eq!(aliases.set("ll", "  "), Err(AliasError::EmptyValue));
+        let long = "x".repeat(MAX_NAME_LEN + 1);
+        assert_eq!(aliases.set(&long, "stack"), Err(AliasError::TooLong));
+        let long = "x".repeat(MAX_VALUE_LEN + 1);
+        assert_eq!(aliases.set("ll", &long), Err(AliasError::TooLong));
+        assert_eq!(aliases.iter().count(), 0);
+    }
+
+    #[test]
+    fn full() {
+        let mut aliases = Aliases::new();
+        for i in 0..MAX_ALIASES {
+            aliases.set(&format!("a{i}"), "help").unwrap();
+        }
+        assert_eq!(aliases.set("one_more", "help"), Err(AliasError::Full));
+        // Redefining an alias needs no new slot.
+        aliases.set("a0", "stack").unwrap();
+        assert!(aliases.remove("a3"));
+        aliases.set("one_more", "help").unwrap();
+    }
+
+    #[test]
+    fn expansion() {
+        let mut aliases = Aliases::new();
+        aliases.set("ll", "stack").unwrap();
+        aliases.set("loop", "loop again").unwrap();
+        assert_eq!(aliases.expand("ll"), Some(("stack", "")));
+        assert_eq!(aliases.expand("  ll 8 -x"), Some(("stack", " 8 -x")));
+        assert_eq!(aliases.expand("help ll"), None);
+        assert_eq!(aliases.expand("lll"), None);
+        assert_eq!(aliases.expand(""), None);
+        // The expansion is returned as is, without being expanded again.
+        assert_eq!(aliases.expand("loop"), Some(("loop again", "")));
+    }
+}
@@ -0,0 +1,323 @@
+//! Parsing of ANSI escape sequences.
+
+/// The maximum number of parameters of a control sequence. Extra parameters are dropped.
+const MAX_PARAMS: usize = 8;
+
+/// The maximum number of characters in a control sequence. Longer sequences are considered
+/// malformed, so that the parser never swallows an unbounded amount of text.
+const MAX_SEQUENCE_LEN: usize = 32;
+
+/// A control sequence: `ESC [`, parameters separated by `;`, and a final character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Csi {
+    params: [u16; MAX_PARAMS],
+    len: usize,
+    /// The final character, which selects the function of the sequence.
+    pub function: char,
+}
+
+impl Csi {
+    /// Returns the parameters of the sequence. Missing parameters are 0.
+    pub fn params(&self) -> &[u16] {
+        &self.params[..self.len]
+    }
+
+    /// Returns the `i`-th parameter, or `default` when it is missing or 0.
+    pub fn param(&self, i: usize, default: u16) -> u16 {
+        match self.params().get(i) {
+            None | Some(0) => default,
+            Some(&param) => param,
+        }
+    }
+}
+
+/// A control character with an effect on the terminal. The other C0 characters are text.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// Rings the bell.
+    Bell = 0x07,
+    /// Moves the cursor one cell to the left.
+    Backspace = 0x08,
+    /// Moves the cursor to the next tab stop.
+    Tab = 0x09,
+    /// Moves the cursor to the start of the next row.
+    LineFeed = 0x0A,
+    /// Moves the cursor down one row, staying in the same column.
+    VerticalTab = 0x0B,
+    /// Clears the screen, and moves the cursor to its top left corner.
+    FormFeed = 0x0C,
+    /// Moves the cursor to the start of the row.
+    CarriageReturn = 0x0D,
+    /// Moves the cursor one cell to the left, and blanks that cell.
+    Delete = 0x7F,
+}
+
+impl Control {
+    /// Returns the control character `c` stands for, if any.
+    pub fn from_char(c: char) -> Option<Self> {
+        Some(match c {
+            '\x07' => Control::Bell,
+            '\x08' => Control::Backspace,
+            '\t' => Control::Tab,
+            '\n' => Control::LineFeed,
+            '\x0B' => Control::VerticalTab,
+            '\x0C' => Control::FormFeed,
+            '\r' => Control::CarriageReturn,
+            '\x7F' => Control::Delete,
+            _ => return None,
+        })
+    }
+}
+
+/// What to do with a character fed to the [`Parser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// The character is part of an escape sequence that is not complete yet.
+    None,
+    /// The character is text.
+    Print(char),
+    /// The character is a control character.
+    Control(Control),
+    /// The character completed a control sequence.
+    Csi(Csi),
+}
+
+impl Action {
+    /// Returns the action for `c` outside of any escape sequence.
+    fn text(c: char) -> Self {
+        match Control::from_char(c) {
+            Some(control) => Action::Control(control),
+            None => Action::Print(c),
+        }
+    }
+}
+
+/// The state of the [`Parser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Outside of any escape sequence.
+    Ground,
+    /// `ESC` was received.
+    Escape,
+    /// `ESC [` was received, possibly followed by parameters.
+    Csi,
+}
+
+/// A parser splitting a character stream into text and escape sequences.
+///
+/// Malformed sequences are abandoned: the character that broke them is handled as if it came
+/// outside of any sequence.
+#[derive(Debug, Clone)]
+pub struct Parser {
+    state: State,
+    params: [u16; MAX_PARAMS],
+    /// The number of parameters seen so far, minus one.
+    param: usize,
+    /// The number of characters in the current sequence.
+    len: usize,
+}
+
+impl Parser {
+    /// Creates a parser, outside of any escape sequence.
+    pub const fn new() -> Self {
+        Parser {
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            param: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns whether the parser is outside of any escape sequence, in which case every
+    /// character but `ESC` is text.
+    pub fn is_ground(&self) -> bool {
+        self.state == State::Ground
+    }
+
+    /// Feeds a character to the parser.
+    pub fn advance(&mut self, c: char) -> Action {
+        match (self.state, c) {
+            (State::Ground, '\x1b') => {
+                self.state = State::Escape;
+                Action::None
+            }
+            (State::Ground, c) => Action::text(c),
+            (State::Escape, '[') => {
+                self.state = State::Csi;
+                self.params = [0; MAX_PARAMS];
+                self.param = 0;
+                self.len = 2;
+                Action::None
+            }
+            (State::Csi, _) if self.len < MAX_SEQUENCE_LEN => {
+                self.len += 1;
+                match c {
+                    '0'..='9' => {
+                        if let Some(param) = self.params.get_mut(self.param) {
+                            let digit = c as u16 - '0' as u16;
+                            *param = param.saturating_mul(10).saturating_add(digit);
+                        }
+                        Action::None
+                    }
+                    ';' => {
+                        self.param += 1;
+                        Action::None
+                    }
+                    // Private markers and intermediate characters.
+                    ' '..='/' | '<'..='?' => Action::None,
+                    '@'..='~' => {
+                        self.state = State::Ground;
+                        Action::Csi(Csi {
+                            params: self.params,
+                            len: (self.param + 1).min(MAX_PARAMS),
+                            function: c,
+                        })
+                    }
+                    c => {
+                        self.state = State::Ground;
+                        Action::text(c)
+                    }
+                }
+            }
+            (_, c) => {
+                self.state = State::Ground;
+                Action::text(c)
+            }
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maps the ANSI color numbers to VGA color numbers.
+const VGA_COLORS: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Applies the parameters of a Select Graphic Rendition sequence (`ESC [ ... m`) to the VGA
+/// attribute `attribute`, and returns the new attribute.
+///
+/// `default` is the attribute restored by parameter 0 and by the default foreground and
+/// background parameters. Unsupported parameters are ignored.
+pub fn apply_sgr(attribute: u8, default: u8, params: &[u16]) -> u8 {
+    let (mut fg, mut bg) = (attribute & 0x0F, attribute >> 4);
+    // An empty parameter list means reset.
+    let params = if params.is_empty() { &[0] } else { params };
+    for &param in params {
+        match param {
+            0 => (fg, bg) = (default & 0x0F, default >> 4),
+            1 => fg |= 0x08,
+            22 => fg &= 0x07,
+            30..=37 => fg = fg & 0x08 | VGA_COLORS[param as usize - 30],
+            39 => fg = default & 0x0F,
+            40..=47 => bg = VGA_COLORS[param as usize - 40],
+            49 => bg = default >> 4,
+            90..=97 => fg = 0x08 | VGA_COLORS[param as usize - 90],
+            100..=107 => bg = 0x08 | VGA_COLORS[param as usize - 100],
+            _ => {}
+        }
+    }
+    bg << 4 | fg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `s` to a new parser, and returns the text along with the last control sequence.
+    fn parse(s: &str) -> (std::string::String, Option<Csi>) {
+        let mut parser = Parser::new();
+        let mut text = std::string::String::new();
+        let mut csi = None;
+        for c in s.chars() {
+            match parser.advance(c) {
+                Action::None => {}
+                Action::Print(c) => text.push(c),
+                Action::Control(control) => text.push(control as u8 as char),
+                Action::Csi(seq) => csi = Some(seq),
+            }
+        }
+        (text, csi)
+    }
+
+    #[test]
+    fn splits_text_and_sequences() {
+        let (text, csi) = parse("a\x1b[1;44mb");
+        assert_eq!(text, "ab");
+        let csi = csi.unwrap();
+        assert_eq!(csi.function, 'm');
+        assert_eq!(csi.params(), &[1, 44]);
+    }
+
+    #[test]
+    fn ground_state() {
+        let mut parser = Parser::new();
+        assert!(parser.is_ground());
+        parser.advance('\x1b');
+        assert!(!parser.is_ground());
+        parser.advance('[');
+        parser.advance('1');
+        assert!(!parser.is_ground());
+        parser.advance('m');
+        assert!(parser.is_ground());
+    }
+
+    #[test]
+    fn missing_parameters() {
+        let (_, csi) = parse("\x1b[;5H");
+        let csi = csi.unwrap();
+        assert_eq!(csi.params(), &[0, 5]);
+        assert_eq!(csi.param(0, 1), 1);
+        assert_eq!(csi.param(1, 1), 5);
+        assert_eq!(csi.param(2, 1), 1);
+    }
+
+    #[test]
+    fn control_characters() {
+        let mut parser = Parser::new();
+        for (c, control) in [
+            ('\x07', Some(Control::Bell)),
+            ('\x08', Some(Control::Backspace)),
+            ('\t', Some(Control::Tab)),
+            ('\n', Some(Control::LineFeed)),
+            ('\x0B', Some(Control::VerticalTab)),
+            ('\x0C', Some(Control::FormFeed)),
+            ('\r', Some(Control::CarriageReturn)),
+            ('\x7F', Some(Control::Delete)),
+            ('\0', None),
+            ('\x01', None),
+            ('\x1F', None),
+            ('a', None),
+        ] {
+            assert_eq!(Control::from_char(c), control, "{c:?}");
+            let action = control.map_or(Action::Print(c), Action::Control);
+            assert_eq!(parser.advance(c), action, "{c:?}");
+        }
+        // A control character breaking a sequence still takes effect.
+        parser.advance('\x1b');
+        parser.advance('[');
+        assert_eq!(parser.advance('\x08'), Action::Control(Control::Backspace));
+        assert!(parser.is_ground());
+    }
+
+    #[test]
+    fn malformed_sequences_do_not_eat_text() {
+        assert_eq!(parse("\x1bxyz").0, "xyz");
+        assert_eq!(parse("\x1b[12\nabc").0, "\nabc");
+        let long = "\x1b[".to_owned() + &"1".repeat(MAX_SEQUENCE_LEN) + "abc";
+        assert!(parse(&long).0.ends_with("abc"));
+    }
+
+    #[test]
+    fn sgr() {
+        assert_eq!(apply_sgr(0x0F, 0x07, &[31]), 0x0C);
+        assert_eq!(apply_sgr(0x07, 0x07, &[1, 44]), 0x1F);
+        assert_eq!(apply_sgr(0x1F, 0x07, &[0]), 0x07);
+        assert_eq!(apply_sgr(0x1F, 0x07, &[]), 0x07);
+        assert_eq!(apply_sgr(0x0F, 0x07, &[92, 105]), 0xDA);
+        assert_eq!(apply_sgr(0x0F, 0x07, &[4, 38, 5]), 0x0F);
+    }
+}
@@ -0,0 +1,351 @@
+//! Evaluation of integer expressions, for the `calc` command.
+//!
+//! The operators are those of C, with the same precedence, from lowest to highest:
+//!
+//! ```text
+//! |   ^   &   << >>   + -   * / %   unary - ~ +
+//! ```
+//!
+//! Numbers are decimal, or hexadecimal and binary with a `0x` and `0b` prefix. Values are 64-bit
+//! and may be read as signed or unsigned: anything from `i64::MIN` to `u64::MAX` is accepted, and
+//! going past either end is an error rather than wrapping around.
+
+use core::fmt;
+
+/// How deeply parentheses and unary operators may nest, which bounds the recursion.
+pub const MAX_DEPTH: usize = 32;
+
+/// The smallest value an expression may take.
+const MIN: i128 = i64::MIN as i128;
+
+/// The largest value an expression may take.
+const MAX: i128 = u64::MAX as i128;
+
+/// Why an expression could not be evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalcError {
+    /// The expression ends where an operand or a closing parenthesis was expected.
+    UnexpectedEnd,
+    /// A character is not valid where it appears.
+    UnexpectedCharacter,
+    /// A number has a digit that its base does not allow, or no digits after its prefix.
+    InvalidNumber,
+    /// A division or a remainder by zero.
+    DivisionByZero,
+    /// A value or a shift amount does not fit in 64 bits.
+    Overflow,
+    /// Parentheses or unary operators are nested deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+impl CalcError {
+    /// Returns a description of the error.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            CalcError::UnexpectedEnd => "unexpected end of expression",
+            CalcError::UnexpectedCharacter => "unexpected character",
+            CalcError::InvalidNumber => "invalid number",
+            CalcError::DivisionByZero => "division by zero",
+            CalcError::Overflow => "overflow",
+            CalcError::TooDeep => "expression nested too deeply",
+        }
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Evaluates `expr`. Whitespace is ignored between tokens.
+///
+/// The result is between `i64::MIN` and `u64::MAX`.
+pub fn evaluate(expr: &str) -> Result<i128, CalcError> {
+    let mut parser = Parser {
+        s: expr.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.or()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some(_) => Err(CalcError::UnexpectedCharacter),
+    }
+}
+
+/// Checks that `value` fits in 64 bits.
+fn checked(value: Option<i128>) -> Result<i128, CalcError> {
+    value
+        .filter(|value| (MIN..=MAX).contains(value))
+        .ok_or(CalcError::Overflow)
+}
+
+/// Applies `op` to the 64 bits of `a` and `b`, negative values being in two's complement.
+///
+/// The result is read as signed if an operand is negative, and as unsigned otherwise.
+fn bitwise(a: i128, b: i128, op: fn(u64, u64) -> u64) -> i128 {
+    let bits = op(a as u64, b as u64);
+    if (a < 0 || b < 0) && bits >> 63 != 0 {
+        i128::from(bits as i64)
+    } else {
+        i128::from(bits)
+    }
+}
+
+/// The binary operators, each at the precedence level of the function parsing its operands.
+#[derive(Clone, Copy)]
+enum Op {
+    Or,
+    Xor,
+    And,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Op {
+    fn apply(self, a: i128, b: i128) -> Result<i128, CalcError> {
+        match self {
+            Op::Or => Ok(bitwise(a, b, |a, b| a | b)),
+            Op::Xor => Ok(bitwise(a, b, |a, b| a ^ b)),
+            Op::And => Ok(bitwise(a, b, |a, b| a & b)),
+            Op::Shl | Op::Shr => {
+                let shift = u32::try_from(b)
+                    .ok()
+                    .filter(|&shift| shift < 64)
+                    .ok_or(CalcError::Overflow)?;
+                match self {
+                    Op::Shl => checked(a.checked_mul(1 << shift)),
+                    _ => Ok(a >> shift),
+                }
+            }
+            Op::Add => checked(a.checked_add(b)),
+            Op::Sub => checked(a.checked_sub(b)),
+            Op::Mul => checked(a.checked_mul(b)),
+            Op::Div | Op::Rem if b == 0 => Err(CalcError::DivisionByZero),
+            Op::Div => checked(Some(a / b)),
+            Op::Rem => Ok(a % b),
+        }
+    }
+}
+
+/// A recursive-descent parser, evaluating as it goes.
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+    /// How many parentheses and unary operators enclose the current position.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace, and returns the next byte.
+    fn peek(&mut self) -> Option<u8> {
+        while self.s.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+        self.s.get(self.pos).copied()
+    }
+
+    /// Consumes `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.peek();
+        let found = self.s[self.pos..].starts_with(token.as_bytes());
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    /// Parses operands with `operand`, separated by any of `ops`, applied from left to right.
+    fn binary(
+        &mut self,
+        ops: &[(&str, Op)],
+        operand: fn(&mut Self) -> Result<i128, CalcError>,
+    ) -> Result<i128, CalcError> {
+        let mut value = operand(self)?;
+        'outer: loop {
+            for &(token, op) in ops {
+                if self.eat(token) {
+                    let rhs = operand(self)?;
+                    value = op.apply(value, rhs)?;
+                    continue 'outer;
+                }
+            }
+            return Ok(value);
+        }
+    }
+
+    fn or(&mut self) -> Result<i128, CalcError> {
+        self.binary(&[("|", Op::Or)], Self::xor)
+    }
+
+    fn xor(&mut self) -> Result<i128, CalcError> {
+        self.binary(&[("^", Op::Xor)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<i128, CalcError> {
+        self.binary(&[("&", Op::And)], Self::shift)
+    }
+
+    fn shift(&mut self) -> Result<i128, CalcError> {
+        self.binary(&[("<<", Op::Shl), (">>", Op::Shr)], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<i128, CalcError> {
+        self.binary(&[("+", Op::Add), ("-", Op::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<i128, CalcError> {
+        self.binary(
+            &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Rem)],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> Result<i128, CalcError> {
+        let Some(c @ (b'-' | b'~' | b'+' | b'(')) = self.peek() else {
+            return self.number();
+        };
+        if self.depth == MAX_DEPTH {
+            return Err(CalcError::TooDeep);
+        }
+        self.pos += 1;
+        self.depth += 1;
+        let value = match c {
+            b'(' => {
+                let value = self.or()?;
+                if !self.eat(")") {
+                    return Err(match self.peek() {
+                        None => CalcError::UnexpectedEnd,
+                        Some(_) => CalcError::UnexpectedCharacter,
+                    });
+                }
+                value
+            }
+            b'-' => checked(Some(-self.unary()?))?,
+            b'~' => {
+                // The complement of the 64 bits, whether the value is read as signed or not.
+                let value = self.unary()?;
+                if value > i64::MAX as i128 {
+                    i128::from(!(value as u64))
+                } else {
+                    !value
+                }
+            }
+            _ => self.unary()?,
+        };
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<i128, CalcError> {
+        let start = match self.peek() {
+            None => return Err(CalcError::UnexpectedEnd),
+            Some(c) if !c.is_ascii_digit() => return Err(CalcError::UnexpectedCharacter),
+            Some(_) => self.pos,
+        };
+        while self.s.get(self.pos).is_some_and(u8::is_ascii_alphanumeric) {
+            self.pos += 1;
+        }
+        // Safety: the token is made of ASCII bytes of a `&str`.
+        let token = unsafe { core::str::from_utf8_unchecked(&self.s[start..self.pos]) };
+        let (digits, radix) = if let Some(digits) = token.strip_prefix("0x") {
+            (digits, 16)
+        } else if let Some(digits) = token.strip_prefix("0b") {
+            (digits, 2)
+        } else {
+            (token, 10)
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(CalcError::InvalidNumber);
+        }
+        u64::from_str_radix(digits, radix)
+            .map(i128::from)
+            .map_err(|_| CalcError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7));
+        assert_eq!(evaluate("(1+2)*3"), Ok(9));
+        assert_eq!(evaluate("10 - 4 - 3"), Ok(3));
+        assert_eq!(evaluate("7 / 2"), Ok(3));
+        assert_eq!(evaluate("-7 / 2"), Ok(-3));
+        assert_eq!(evaluate("-7 % 3"), Ok(-1));
+        assert_eq!(evaluate("- -5 + +2"), Ok(7));
+        assert_eq!(evaluate("  42  "), Ok(42));
+    }
+
+    #[test]
+    fn bitwise() {
+        assert_eq!(evaluate("(0xb8000 + 80*2*24) & ~0xf"), Ok(0xb8f00));
+        assert_eq!(evaluate("1 << 4 | 1"), Ok(17));
+        assert_eq!(evaluate("0b1100 ^ 0b1010"), Ok(0b0110));
+        assert_eq!(evaluate("0xff >> 4"), Ok(0xf));
+        assert_eq!(evaluate("1 | 2 & 3"), Ok(3));
+        assert_eq!(evaluate("1 + 1 << 2"), Ok(8));
+        assert_eq!(evaluate("~0"), Ok(-1));
+        assert_eq!(evaluate("~0xffffffffffffffff"), Ok(0));
+        assert_eq!(evaluate("-1 & 0xff"), Ok(0xff));
+        assert_eq!(evaluate("-16 >> 2"), Ok(-4));
+    }
+
+    #[test]
+    fn limits() {
+        assert_eq!(evaluate("0xffffffffffffffff"), Ok(u64::MAX.into()));
+        assert_eq!(evaluate("-0x8000000000000000"), Ok(i64::MIN.into()));
+        assert_eq!(evaluate("0x10000000000000000"), Err(CalcError::Overflow));
+        assert_eq!(evaluate("0xffffffffffffffff + 1"), Err(CalcError::Overflow));
+        assert_eq!(
+            evaluate("-0x8000000000000000 - 1"),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(evaluate("-0xffffffffffffffff"), Err(CalcError::Overflow));
+        assert_eq!(evaluate("1 << 64"), Err(CalcError::Overflow));
+        assert_eq!(evaluate("1 << -1"), Err(CalcError::Overflow));
+        assert_eq!(evaluate("0x100 << 60"), Err(CalcError::Overflow));
+        assert_eq!(evaluate("1 << 63"), Ok(1 << 63));
+        assert_eq!(evaluate("0xffffffffffffffff ^ -1"), Ok(0));
+        assert_eq!(evaluate("-1 ^ 0x8000000000000000"), Ok(i64::MAX.into()));
+        assert_eq!(evaluate("-1 | 0x8000000000000000"), Ok(-1));
+        assert_eq!(evaluate("-1 & 0xffffffffffffffff"), Ok(-1));
+        assert_eq!(evaluate("0xffffffffffffffff | 1"), Ok(u64::MAX.into()));
+        assert_eq!(evaluate("1 / 0"), Err(CalcError::DivisionByZero));
+        assert_eq!(evaluate("1 % (2 - 2)"), Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(evaluate(""), Err(CalcError::UnexpectedEnd));
+        assert_eq!(evaluate("1 +"), Err(CalcError::UnexpectedEnd));
+        assert_eq!(evaluate("(1 + 2"), Err(CalcError::UnexpectedEnd));
+        assert_eq!(evaluate("(1 + 2 3"), Err(CalcError::UnexpectedCharacter));
+        assert_eq!(evaluate("1 + 2)"), Err(CalcError::UnexpectedCharacter));
+        assert_eq!(evaluate("1 < 2"), Err(CalcError::UnexpectedCharacter));
+        assert_eq!(evaluate("x"), Err(CalcError::UnexpectedCharacter));
+        assert_eq!(evaluate("0x"), Err(CalcError::InvalidNumber));
+        assert_eq!(evaluate("0b102"), Err(CalcError::InvalidNumber));
+        assert_eq!(evaluate("12ab"), Err(CalcError::InvalidNumber));
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate(&nested(MAX_DEPTH)), Ok(1));
+        assert_eq!(evaluate(&nested(MAX_DEPTH + 1)), Err(CalcError::TooDeep));
+        assert_eq!(
+            evaluate(&"-".repeat(MAX_DEPTH + 1)),
+            Err(CalcError::TooDeep)
+        );
+    }
+}
@@ -0,0 +1,507 @@
+use core::fmt;
+
+/// A line of input being edited.
+#[derive(Clone)]
+pub struct Cmdline {
+    buffer: [u8; 128],
+    len: usize,
+    /// The edit position, as a byte index on a character boundary.
+    cursor: usize,
+}
+
+impl Cmdline {
+    pub const fn new() -> Self {
+        Cmdline {
+            buffer: [0; 128],
+            len: 0,
+            cursor: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        unsafe { core::str::from_utf8_unchecked(self.buffer.get_unchecked(..self.len)) }
+    }
+
+    /// Returns the edit position, as a byte index in [`as_str`](Self::as_str).
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn take(&mut self) -> &str {
+        let result =
+            unsafe { core::str::from_utf8_unchecked(self.buffer.get_unchecked(..self.len)) };
+        self.len = 0;
+        self.cursor = 0;
+        result
+    }
+
+    /// Replaces the line with `s`, and returns whether it fits. If not, the line is left empty.
+    ///
+    /// The cursor goes to the end.
+    pub fn set(&mut self, s: &str) -> bool {
+        self.len = 0;
+        self.cursor = 0;
+        let Some(buffer) = self.buffer.get_mut(..s.len()) else {
+            return false;
+        };
+        buffer.copy_from_slice(s.as_bytes());
+        self.len = s.len();
+        self.cursor = s.len();
+        true
+    }
+
+    /// Inserts `c` at the cursor, and returns whether it fits.
+    pub fn push(&mut self, c: char) -> bool {
+        let len = c.len_utf8();
+        if self.buffer.len() - self.len < len {
+            return false;
+        }
+        self.buffer
+            .copy_within(self.cursor..self.len, self.cursor + len);
+        c.encode_utf8(&mut self.buffer[self.cursor..]);
+        self.len += len;
+        self.cursor += len;
+        true
+    }
+
+    /// Removes the bytes from `start` to the cursor, which then moves to `start`.
+    fn remove_before_cursor(&mut self, start: usize) {
+        self.buffer.copy_within(self.cursor..self.len, start);
+        self.len -= self.cursor - start;
+        self.cursor = start;
+    }
+
+    /// Removes the character before the cursor.
+    pub fn pop(&mut self) {
+        let start = self.as_str()[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index);
+        self.remove_before_cursor(start);
+    }
+
+    /// Removes the word before the cursor, along with the spaces between them.
+    pub fn pop_word(&mut self) {
+        let start = match self.as_str()[..self.cursor]
+            .char_indices()
+            .rev()
+            .skip_while(|(_, x)| x.is_whitespace())
+            .find(|(_, x)| x.is_whitespace())
+        {
+            Some((index, c)) => index + c.len_utf8(),
+            None => 0,
+        };
+        self.remove_before_cursor(start);
+    }
+
+    /// Removes the character at the cursor.
+    pub fn delete(&mut self) {
+        if self.right() {
+            self.pop();
+        }
+    }
+
+    /// Moves the cursor one character to the left, and returns whether it moved.
+    pub fn left(&mut self) -> bool {
+        match self.as_str()[..self.cursor].chars().next_back() {
+            Some(c) => {
+                self.cursor -= c.len_utf8();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the cursor one character to the right, and returns whether it moved.
+    pub fn right(&mut self) -> bool {
+        match self.as_str()[self.cursor..].chars().next() {
+            Some(c) => {
+                self.cursor += c.len_utf8();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to the start of the line.
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Moves the cursor to the end of the line.
+    pub fn end(&mut self) {
+        self.cursor = self.len;
+    }
+}
+
+impl Default for Cmdline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The number of lines kept in a [`History`].
+pub const HISTORY_LEN: usize = 32;
+
+/// Why a line recalling an earlier one, with `!!` or `!<n>`, could not be expanded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecallError {
+    /// No line was kept yet.
+    Empty,
+    /// No line kept has the number asked for.
+    OutOfRange {
+        /// The numbers of the oldest and the newest lines kept.
+        first: usize,
+        last: usize,
+    },
+}
+
+impl fmt::Display for RecallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecallError::Empty => f.write_str("the history is empty"),
+            RecallError::OutOfRange { first, last } => {
+                write!(f, "no such line, the history goes from {first} to {last}")
+            }
+        }
+    }
+}
+
+/// Returns whether `word` recalls an earlier line: `!!` for the last one, or `!<n>` for the one
+/// numbered `n`.
+fn is_recall(word: &str) -> bool {
+    word.strip_prefix('!')
+        .is_some_and(|n| n == "!" || !n.is_empty() && n.bytes().all(|c| c.is_ascii_digit()))
+}
+
+/// The last lines entered, browsed from the most recent one.
+///
+/// Lines are numbered from 1 in the order they were entered, including those that were dropped
+/// since to make room.
+pub struct History {
+    /// A ring of the lines, `next` being the slot of the next one.
+    entries: [Cmdline; HISTORY_LEN],
+    next: usize,
+    len: usize,
+    /// The number of lines ever kept, which is the number of the newest one.
+    count: usize,
+    /// How many lines back the browsing is, or 0 when not browsing.
+    browsing: usize,
+    /// What was being typed when the browsing started.
+    draft: Cmdline,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        History {
+            entries: [const { Cmdline::new() }; HISTORY_LEN],
+            next: 0,
+            len: 0,
+            count: 0,
+            browsing: 0,
+            draft: Cmdline::new(),
+        }
+    }
+
+    /// Returns the number of lines kept.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no line was kept yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the line entered `age` lines ago, 0 being the most recent one.
+    pub fn get(&self, age: usize) -> Option<&str> {
+        if age >= self.len {
+            return None;
+        }
+        let index = (self.next + HISTORY_LEN - 1 - age) % HISTORY_LEN;
+        Some(self.entries[index].as_str())
+    }
+
+    /// Returns the lines kept along with their numbers, oldest first.
+    pub fn numbered(&self) -> impl Iterator<Item = (usize, &str)> {
+        (0..self.len)
+            .rev()
+            .map(|age| (self.count - age, self.get(age).unwrap_or_default()))
+    }
+
+    /// Returns the line numbered `n`, if it is still kept.
+    pub fn by_number(&self, n: usize) -> Option<&str> {
+        self.get(self.count.checked_sub(n)?)
+    }
+
+    /// Adds a line entered, and stops browsing.
+    ///
+    /// Blank lines, repeats of the most recent line and lines recalling an earlier one are not
+    /// kept. What a recalling line expands to is meant to be pushed instead, once run.
+    pub fn push(&mut self, line: &str) {
+        self.browsing = 0;
+        let first = line.split_whitespace().next().unwrap_or_default();
+        if line.trim().is_empty() || is_recall(first) || self.get(0) == Some(line) {
+            return;
+        }
+        self.entries[self.next].set(line);
+        self.next = (self.next + 1) % HISTORY_LEN;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+        self.count += 1;
+    }
+
+    /// If the first word of `line` is `!!` or `!<n>`, returns the last line or the one numbered
+    /// `n`, and the rest of `line`, which starts with whitespace unless empty.
+    ///
+    /// Only the first word is looked at, and the line returned is not expanded again.
+    pub fn expand<'a>(&'a self, line: &'a str) -> Result<Option<(&'a str, &'a str)>, RecallError> {
+        let line = line.trim_start();
+        let end = line.find(char::is_whitespace).unwrap_or(line.len());
+        let (first, rest) = line.split_at(end);
+        if !is_recall(first) {
+            return Ok(None);
+        }
+        if self.is_empty() {
+            return Err(RecallError::Empty);
+        }
+        let recalled = match &first[1..] {
+            "!" => self.get(0),
+            n => n.parse().ok().and_then(|n| self.by_number(n)),
+        };
+        let recalled = recalled.ok_or(RecallError::OutOfRange {
+            first: self.count - self.len + 1,
+            last: self.count,
+        })?;
+        Ok(Some((recalled, rest)))
+    }
+
+    /// Replaces `cmdline` with the line before the one shown, and returns whether it changed.
+    ///
+    /// Browsing starts from what is being typed, and stays on the oldest line once there.
+    pub fn previous(&mut self, cmdline: &mut Cmdline) -> bool {
+        if self.browsing == self.len {
+            return false;
+        }
+        if self.browsing == 0 {
+            self.draft = cmdline.clone();
+        }
+        self.browsing += 1;
+        cmdline.set(self.get(self.browsing - 1).unwrap_or_default());
+        true
+    }
+
+    /// Replaces `cmdline` with the line after the one shown, and returns whether it changed.
+    ///
+    /// Past the most recent line, what was being typed before browsing is restored.
+    pub fn next(&mut self, cmdline: &mut Cmdline) -> bool {
+        match self.browsing {
+            0 => return false,
+            1 => *cmdline = self.draft.clone(),
+            browsing => _ = cmdline.set(self.get(browsing - 2).unwrap_or_default()),
+        }
+        self.browsing -= 1;
+        true
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(lines: &[&str]) -> History {
+        let mut history = History::new();
+        for line in lines {
+            history.push(line);
+        }
+        history
+    }
+
+    #[test]
+    fn set() {
+        let mut cmdline = Cmdline::new();
+        assert!(cmdline.set("echo hi"));
+        assert_eq!(cmdline.as_str(), "echo hi");
+        assert!(!cmdline.set(&"x".repeat(129)));
+        assert_eq!(cmdline.as_str(), "");
+    }
+
+    #[test]
+    fn editing() {
+        let mut cmdline = Cmdline::new();
+        for c in "echo wrld".chars() {
+            cmdline.push(c);
+        }
+        assert_eq!(cmdline.cursor(), 9);
+        for _ in 0..3 {
+            cmdline.left();
+        }
+        cmdline.push('o');
+        assert_eq!(cmdline.as_str(), "echo world");
+        assert_eq!(cmdline.cursor(), 7);
+
+        cmdline.delete();
+        assert_eq!(cmdline.as_str(), "echo wold");
+        cmdline.pop();
+        assert_eq!(cmdline.as_str(), "echo wld");
+        cmdline.pop_word();
+        assert_eq!(cmdline.as_str(), "echo ld");
+        assert_eq!(cmdline.cursor(), 5);
+
+        cmdline.home();
+        assert!(!cmdline.left());
+        cmdline.pop();
+        assert_eq!(cmdline.as_str(), "echo ld");
+        cmdline.push('>');
+        assert_eq!(cmdline.as_str(), ">echo ld");
+        cmdline.end();
+        assert!(!cmdline.right());
+        cmdline.delete();
+        assert_eq!(cmdline.as_str(), ">echo ld");
+        assert_eq!(cmdline.take(), ">echo ld");
+        assert_eq!(cmdline.cursor(), 0);
+    }
+
+    #[test]
+    fn editing_steps_over_characters() {
+        let mut cmdline = Cmdline::new();
+        cmdline.set("a\u{e9}\u{20ac}b");
+        assert!(cmdline.left());
+        assert_eq!(cmdline.cursor(), 6);
+        assert!(cmdline.left());
+        assert_eq!(cmdline.cursor(), 3);
+        cmdline.delete();
+        assert_eq!(cmdline.as_str(), "a\u{e9}b");
+        cmdline.pop();
+        assert_eq!(cmdline.as_str(), "ab");
+        assert!(cmdline.right());
+        assert_eq!(cmdline.cursor(), 2);
+    }
+
+    #[test]
+    fn full_buffer() {
+        let mut cmdline = Cmdline::new();
+        cmdline.set(&"x".repeat(126));
+        cmdline.home();
+        // A character longer than the room left is refused whole.
+        assert!(!cmdline.push('\u{20ac}'));
+        assert!(cmdline.push('\u{e9}'));
+        assert_eq!(cmdline.as_str().len(), 128);
+        assert!(cmdline.as_str().starts_with("\u{e9}x"));
+        assert!(!cmdline.push('y'));
+    }
+
+    #[test]
+    fn history_skips_blanks_and_repeats() {
+        let history = history(&["a", "", "  ", "b", "b", "a"]);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(0), Some("a"));
+        assert_eq!(history.get(1), Some("b"));
+        assert_eq!(history.get(2), Some("a"));
+        assert_eq!(history.get(3), None);
+    }
+
+    #[test]
+    fn history_wraps() {
+        let mut history = History::new();
+        for n in 0..HISTORY_LEN + 5 {
+            history.push(&n.to_string());
+        }
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.get(0), Some("36"));
+        assert_eq!(history.get(HISTORY_LEN - 1), Some("5"));
+    }
+
+    #[test]
+    fn numbers() {
+        let mut history = History::new();
+        for n in 1..=HISTORY_LEN + 2 {
+            history.push(&format!("echo {n}"));
+        }
+        history.push("!!");
+        history.push("!3 again");
+        let numbered: Vec<_> = history.numbered().collect();
+        assert_eq!(numbered.len(), HISTORY_LEN);
+        assert_eq!(numbered[0], (3, "echo 3"));
+        assert_eq!(numbered[HISTORY_LEN - 1], (HISTORY_LEN + 2, "echo 34"));
+        assert_eq!(history.by_number(3), Some("echo 3"));
+        assert_eq!(history.by_number(2), None);
+        assert_eq!(history.by_number(0), None);
+        assert_eq!(history.by_number(HISTORY_LEN + 3), None);
+    }
+
+    #[test]
+    fn recall() {
+        let mut history = History::new();
+        assert_eq!(history.expand("!!"), Err(RecallError::Empty));
+        assert_eq!(history.expand("!1"), Err(RecallError::Empty));
+        history.push("echo one");
+        history.push("echo !!");
+        history.push("help");
+
+        assert_eq!(history.expand("!!"), Ok(Some(("help", ""))));
+        assert_eq!(history.expand(" !1 two"), Ok(Some(("echo one", " two"))));
+        // The recalled line is returned as is, without being expanded again.
+        assert_eq!(history.expand("!2"), Ok(Some(("echo !!", ""))));
+        assert_eq!(
+            history.expand("!4"),
+            Err(RecallError::OutOfRange { first: 1, last: 3 })
+        );
+        assert_eq!(
+            history.expand("!0"),
+            Err(RecallError::OutOfRange { first: 1, last: 3 })
+        );
+        assert_eq!(
+            history.expand("!99999999999999999999999"),
+            Err(RecallError::OutOfRange { first: 1, last: 3 })
+        );
+        for line in ["help !!", "!", "!x", "!1x", "!!!", ""] {
+            assert_eq!(history.expand(line), Ok(None), "{line}");
+        }
+    }
+
+    #[test]
+    fn browsing() {
+        let mut history = history(&["first", "second"]);
+        let mut cmdline = Cmdline::new();
+        cmdline.set("typed");
+
+        assert!(!history.next(&mut cmdline));
+        assert!(history.previous(&mut cmdline));
+        assert_eq!(cmdline.as_str(), "second");
+        assert!(history.previous(&mut cmdline));
+        assert_eq!(cmdline.as_str(), "first");
+        // The oldest line stays.
+        assert!(!history.previous(&mut cmdline));
+        assert_eq!(cmdline.as_str(), "first");
+
+        assert!(history.next(&mut cmdline));
+        assert_eq!(cmdline.as_str(), "second");
+        // Past the newest line, what was typed comes back.
+        assert!(history.next(&mut cmdline));
+        assert_eq!(cmdline.as_str(), "typed");
+        assert!(!history.next(&mut cmdline));
+
+        // Entering a line starts over from the newest one.
+        history.previous(&mut cmdline);
+        history.push("third");
+        cmdline.set("");
+        history.previous(&mut cmdline);
+        assert_eq!(cmdline.as_str(), "third");
+    }
+
+    #[test]
+    fn browsing_an_empty_history() {
+        let mut history = History::new();
+        let mut cmdline = Cmdline::new();
+        cmdline.set("typed");
+        assert!(!history.previous(&mut cmdline));
+        assert!(!history.next(&mut cmdline));
+        assert_eq!(cmdline.as_str(), "typed");
+    }
+}
@@ -0,0 +1,236 @@
+//! Decoding of what the `cpuid` instruction returns.
+
+use core::fmt;
+
+/// The registers returned by `cpuid` for a leaf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuidResult {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+/// The first extended leaf, which returns the highest extended leaf.
+pub const EXTENDED_LEAF: u32 = 0x8000_0000;
+
+/// The extended leaves returning the brand string, 16 bytes each.
+pub const BRAND_LEAVES: [u32; 3] = [0x8000_0002, 0x8000_0003, 0x8000_0004];
+
+/// The names of the feature bits of EDX for leaf 1, by bit.
+pub const EDX_FEATURES: [(u32, &str); 27] = [
+    (0, "fpu"),
+    (1, "vme"),
+    (2, "de"),
+    (3, "pse"),
+    (4, "tsc"),
+    (5, "msr"),
+    (6, "pae"),
+    (7, "mce"),
+    (8, "cx8"),
+    (9, "apic"),
+    (11, "sep"),
+    (12, "mtrr"),
+    (13, "pge"),
+    (14, "mca"),
+    (15, "cmov"),
+    (16, "pat"),
+    (17, "pse36"),
+    (19, "clflush"),
+    (21, "ds"),
+    (22, "acpi"),
+    (23, "mmx"),
+    (24, "fxsr"),
+    (25, "sse"),
+    (26, "sse2"),
+    (27, "ss"),
+    (28, "htt"),
+    (29, "tm"),
+];
+
+/// The names of the feature bits of ECX for leaf 1, by bit.
+pub const ECX_FEATURES: [(u32, &str); 24] = [
+    (0, "sse3"),
+    (1, "pclmulqdq"),
+    (3, "monitor"),
+    (4, "ds_cpl"),
+    (5, "vmx"),
+    (6, "smx"),
+    (7, "est"),
+    (8, "tm2"),
+    (9, "ssse3"),
+    (12, "fma"),
+    (13, "cx16"),
+    (17, "pcid"),
+    (19, "sse4_1"),
+    (20, "sse4_2"),
+    (21, "x2apic"),
+    (22, "movbe"),
+    (23, "popcnt"),
+    (24, "tsc_deadline"),
+    (25, "aes"),
+    (26, "xsave"),
+    (27, "osxsave"),
+    (28, "avx"),
+    (30, "rdrand"),
+    (31, "hypervisor"),
+];
+
+/// Returns the vendor string of leaf 0, such as `GenuineIntel`.
+pub fn vendor(leaf0: &CpuidResult) -> [u8; 12] {
+    let mut vendor = [0; 12];
+    vendor[..4].copy_from_slice(&leaf0.ebx.to_le_bytes());
+    vendor[4..8].copy_from_slice(&leaf0.edx.to_le_bytes());
+    vendor[8..].copy_from_slice(&leaf0.ecx.to_le_bytes());
+    vendor
+}
+
+/// The family, model and stepping of the processor, from EAX for leaf 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+}
+
+impl Signature {
+    /// Decodes EAX for leaf 1, with the extended family and model applied.
+    pub fn new(eax: u32) -> Self {
+        let base_family = eax >> 8 & 0xF;
+        let mut family = base_family;
+        let mut model = eax >> 4 & 0xF;
+        if base_family == 0xF {
+            family += eax >> 20 & 0xFF;
+        }
+        if base_family == 0x6 || base_family == 0xF {
+            model |= (eax >> 16 & 0xF) << 4;
+        }
+        Signature {
+            family,
+            model,
+            stepping: eax & 0xF,
+        }
+    }
+}
+
+/// Returns the names of the features set in EDX and ECX for leaf 1.
+pub fn features(edx: u32, ecx: u32) -> impl Iterator<Item = &'static str> {
+    let set = |value: u32| {
+        move |&(bit, name): &(u32, &'static str)| (value >> bit & 1 != 0).then_some(name)
+    };
+    EDX_FEATURES
+        .iter()
+        .filter_map(set(edx))
+        .chain(ECX_FEATURES.iter().filter_map(set(ecx)))
+}
+
+/// The brand string of [`BRAND_LEAVES`], such as `Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz`.
+///
+/// It is displayed without its NUL terminator and the spaces it is padded with, and with
+/// anything but printable ASCII replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brand(pub [u8; 48]);
+
+impl Brand {
+    /// Gathers the brand string from what the brand leaves returned.
+    pub fn new(leaves: &[CpuidResult; 3]) -> Self {
+        let mut brand = [0; 48];
+        let registers = leaves
+            .iter()
+            .flat_map(|leaf| [leaf.eax, leaf.ebx, leaf.ecx, leaf.edx]);
+        for (chunk, register) in brand.chunks_exact_mut(4).zip(registers) {
+            chunk.copy_from_slice(&register.to_le_bytes());
+        }
+        Brand(brand)
+    }
+}
+
+impl fmt::Display for Brand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = self.0.iter().position(|&b| b == 0).unwrap_or(self.0.len());
+        for &b in self.0[..end].trim_ascii() {
+            let c = if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '?'
+            };
+            fmt::Write::write_char(f, c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs `s` into registers the way `cpuid` returns strings.
+    fn register(s: &[u8]) -> u32 {
+        u32::from_le_bytes(s.try_into().unwrap())
+    }
+
+    #[test]
+    fn vendor_string() {
+        let leaf0 = CpuidResult {
+            eax: 0x16,
+            ebx: register(b"Genu"),
+            edx: register(b"ineI"),
+            ecx: register(b"ntel"),
+        };
+        assert_eq!(&vendor(&leaf0), b"GenuineIntel");
+    }
+
+    #[test]
+    fn signatures() {
+        // A Coffee Lake: family 6, extended model 9, model 14, stepping 10.
+        let signature = Signature::new(0x000906EA);
+        assert_eq!(
+            signature,
+            Signature {
+                family: 6,
+                model: 0x9E,
+                stepping: 10
+            }
+        );
+        // A Zen 2: family 15 plus extended family 8, extended model 7, model 1.
+        let signature = Signature::new(0x00870F10);
+        assert_eq!((signature.family, signature.model), (0x17, 0x71));
+        // A 486: no extension applies.
+        let signature = Signature::new(0x000F0435);
+        assert_eq!((signature.family, signature.model), (4, 3));
+    }
+
+    #[test]
+    fn feature_names() {
+        let edx = 1 << 0 | 1 << 4 | 1 << 25 | 1 << 26;
+        let ecx = 1 << 0 | 1 << 31;
+        let names: Vec<_> = features(edx, ecx).collect();
+        assert_eq!(names, ["fpu", "tsc", "sse", "sse2", "sse3", "hypervisor"]);
+        assert_eq!(features(0, 0).count(), 0);
+        // Reserved bits have no name.
+        assert_eq!(features(1 << 10, 1 << 2).count(), 0);
+    }
+
+    #[test]
+    fn brand_string() {
+        let mut bytes = [0; 48];
+        let text = b"       Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz";
+        bytes[..text.len()].copy_from_slice(text);
+        let leaves = core::array::from_fn(|i| {
+            let r = |j: usize| register(&bytes[i * 16 + j * 4..][..4]);
+            CpuidResult {
+                eax: r(0),
+                ebx: r(1),
+                ecx: r(2),
+                edx: r(3),
+            }
+        });
+        let brand = Brand::new(&leaves);
+        assert_eq!(brand.0, bytes);
+        assert_eq!(brand.to_string(), "Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz");
+
+        let mut odd = [b' '; 48];
+        odd[..3].copy_from_slice(b"a\x01b");
+        assert_eq!(Brand(odd).to_string(), "a?b");
+    }
+}
@@ -0,0 +1,259 @@
+//! Decoding of the segment descriptors of the GDT and the gate descriptors of the IDT.
+//!
+//! A segment descriptor is 8 bytes, with its base and limit scattered around the access byte
+//! and the flags:
+//!
+//! ```text
+//! 63    56 55   52 51   48 47    40 39       16 15      0
+//! base 31:24 flags limit 19:16 access base 23:0  limit 15:0
+//! ```
+//!
+//! A gate descriptor is 8 bytes too, with the address of its handler split in two:
+//!
+//! ```text
+//! 63      48 47    40 39    32 31      16 15      0
+//! offset 31:16 access reserved selector   offset 15:0
+//! ```
+
+use core::fmt;
+
+/// Access byte: the segment was accessed.
+const ACCESS_ACCESSED: u8 = 1 << 0;
+/// Access byte: the code segment is readable, or the data segment writable.
+const ACCESS_READ_WRITE: u8 = 1 << 1;
+/// Access byte: the code segment is conforming, or the data segment expands down.
+const ACCESS_DIRECTION: u8 = 1 << 2;
+/// Access byte: the segment is a code segment.
+const ACCESS_EXECUTABLE: u8 = 1 << 3;
+/// Access byte: the segment is a code or data segment, rather than a system one.
+const ACCESS_S: u8 = 1 << 4;
+/// Access byte: the descriptor is valid.
+const ACCESS_PRESENT: u8 = 1 << 7;
+
+/// Flags: the segment is a 64-bit code segment.
+const FLAG_LONG: u8 = 1 << 1;
+/// Flags: the segment is 32-bit rather than 16-bit.
+const FLAG_SIZE: u8 = 1 << 2;
+/// Flags: the limit is in pages of 4 KiB rather than in bytes.
+const FLAG_GRANULARITY: u8 = 1 << 3;
+
+/// Returns the name of the type `kind` of a system descriptor.
+const fn system_type_name(kind: u8) -> &'static str {
+    match kind {
+        0x1 => "16-bit TSS, available",
+        0x2 => "LDT",
+        0x3 => "16-bit TSS, busy",
+        0x4 => "16-bit call gate",
+        0x5 => "task gate",
+        0x6 => "16-bit interrupt gate",
+        0x7 => "16-bit trap gate",
+        0x9 => "32-bit TSS, available",
+        0xB => "32-bit TSS, busy",
+        0xC => "32-bit call gate",
+        0xE => "32-bit interrupt gate",
+        0xF => "32-bit trap gate",
+        _ => "reserved system type",
+    }
+}
+
+/// A segment descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentDescriptor(pub u64);
+
+impl SegmentDescriptor {
+    /// Returns the address the segment starts at.
+    pub const fn base(&self) -> u32 {
+        (self.0 >> 16 & 0xFF_FFFF | self.0 >> 32 & 0xFF00_0000) as u32
+    }
+
+    /// Returns the offset of the last byte of the segment, with the granularity applied.
+    pub const fn limit(&self) -> u32 {
+        let limit = (self.0 & 0xFFFF | self.0 >> 32 & 0xF_0000) as u32;
+        if self.flags() & FLAG_GRANULARITY != 0 {
+            limit << 12 | 0xFFF
+        } else {
+            limit
+        }
+    }
+
+    /// Returns the access byte.
+    pub const fn access(&self) -> u8 {
+        (self.0 >> 40) as u8
+    }
+
+    /// Returns the 4 bits of flags.
+    pub const fn flags(&self) -> u8 {
+        (self.0 >> 52) as u8 & 0xF
+    }
+
+    /// Returns the privilege level required to use the segment.
+    pub const fn dpl(&self) -> u8 {
+        self.access() >> 5 & 0b11
+    }
+
+    /// Returns whether this is a code or data segment, rather than a system one.
+    pub const fn s(&self) -> bool {
+        self.access() & ACCESS_S != 0
+    }
+
+    /// Returns the 4 bits of type of the access byte.
+    pub const fn kind(&self) -> u8 {
+        self.access() & 0xF
+    }
+
+    /// Returns whether the descriptor is valid.
+    pub const fn present(&self) -> bool {
+        self.access() & ACCESS_PRESENT != 0
+    }
+
+    /// Returns the size of the operands of the segment, in bits.
+    pub const fn bits(&self) -> u8 {
+        if self.flags() & FLAG_LONG != 0 {
+            64
+        } else if self.flags() & FLAG_SIZE != 0 {
+            32
+        } else {
+            16
+        }
+    }
+}
+
+/// Describes the segment in words, such as `32-bit code, readable`.
+impl fmt::Display for SegmentDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("null");
+        }
+        if !self.s() {
+            return f.write_str(system_type_name(self.kind()));
+        }
+        let access = self.access();
+        let (kind, read_write, direction) = if access & ACCESS_EXECUTABLE != 0 {
+            ("code", ["execute-only", "readable"], "conforming")
+        } else {
+            ("data", ["read-only", "writable"], "expand-down")
+        };
+        write!(f, "{}-bit {kind}, ", self.bits())?;
+        f.write_str(read_write[usize::from(access & ACCESS_READ_WRITE != 0)])?;
+        if access & ACCESS_DIRECTION != 0 {
+            write!(f, ", {direction}")?;
+        }
+        if access & ACCESS_ACCESSED != 0 {
+            f.write_str(", accessed")?;
+        }
+        Ok(())
+    }
+}
+
+/// A gate descriptor of the IDT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GateDescriptor(pub u64);
+
+impl GateDescriptor {
+    /// Returns whether the descriptor is all zeros, as the vectors nothing was set up for are.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the address of the handler.
+    pub const fn offset(&self) -> u32 {
+        (self.0 & 0xFFFF | self.0 >> 32 & 0xFFFF_0000) as u32
+    }
+
+    /// Returns the selector of the code segment of the handler.
+    pub const fn selector(&self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    /// Returns the 4 bits of type of the gate.
+    pub const fn kind(&self) -> u8 {
+        (self.0 >> 40) as u8 & 0xF
+    }
+
+    /// Returns the privilege level required to reach the gate with `int`.
+    pub const fn dpl(&self) -> u8 {
+        (self.0 >> 45) as u8 & 0b11
+    }
+
+    /// Returns whether the descriptor is valid.
+    pub const fn present(&self) -> bool {
+        (self.0 >> 40) as u8 & ACCESS_PRESENT != 0
+    }
+}
+
+/// Names the type of the gate, such as `32-bit interrupt gate`.
+impl fmt::Display for GateDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.kind() {
+            0x5 | 0x6 | 0x7 | 0xE | 0xF => system_type_name(self.kind()),
+            _ => "not a gate",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_segments() {
+        let code = SegmentDescriptor(0x00cf9b000000ffff);
+        assert_eq!(code.base(), 0);
+        assert_eq!(code.limit(), 0xFFFF_FFFF);
+        assert_eq!(code.dpl(), 0);
+        assert!(code.s());
+        assert_eq!(code.kind(), 0xB);
+        assert!(code.present());
+        assert_eq!(code.to_string(), "32-bit code, readable, accessed");
+
+        let data = SegmentDescriptor(0x00cff3000000ffff);
+        assert_eq!(data.dpl(), 3);
+        assert_eq!(data.to_string(), "32-bit data, writable, accessed");
+    }
+
+    #[test]
+    fn base_and_limit() {
+        // Base 0x12345678, limit 0xABCDE in bytes, 16-bit read-only data.
+        let descriptor = SegmentDescriptor(0x120a_9034_5678_bcde);
+        assert_eq!(descriptor.base(), 0x1234_5678);
+        assert_eq!(descriptor.limit(), 0xA_BCDE);
+        assert_eq!(descriptor.to_string(), "16-bit data, read-only");
+    }
+
+    #[test]
+    fn labels() {
+        let label = |access: u64, flags: u64| {
+            SegmentDescriptor(flags << 52 | access << 40 | 0xFFFF).to_string()
+        };
+        assert_eq!(SegmentDescriptor(0).to_string(), "null");
+        assert_eq!(label(0x98, 0x4), "32-bit code, execute-only");
+        assert_eq!(label(0x9E, 0x4), "32-bit code, readable, conforming");
+        assert_eq!(label(0x96, 0x0), "16-bit data, writable, expand-down");
+        assert_eq!(label(0x9A, 0x2), "64-bit code, readable");
+        assert_eq!(label(0x89, 0x0), "32-bit TSS, available");
+        assert_eq!(label(0x82, 0x0), "LDT");
+        assert_eq!(label(0x80, 0x0), "reserved system type");
+    }
+
+    #[test]
+    fn gates() {
+        // A 32-bit interrupt gate to 0x00101234 in the kernel code segment, reachable from ring 3.
+        let gate = GateDescriptor(0x0010_ee00_0008_1234);
+        assert!(!gate.is_empty());
+        assert_eq!(gate.offset(), 0x0010_1234);
+        assert_eq!(gate.selector(), 0x08);
+        assert_eq!(gate.kind(), 0xE);
+        assert_eq!(gate.dpl(), 3);
+        assert!(gate.present());
+        assert_eq!(gate.to_string(), "32-bit interrupt gate");
+
+        let trap = GateDescriptor(0x0000_0f00_0008_0000);
+        assert!(!trap.present());
+        assert_eq!(trap.to_string(), "32-bit trap gate");
+        assert_eq!(
+            GateDescriptor(0x0000_8900_0000_0000).to_string(),
+            "not a gate"
+        );
+        assert!(GateDescriptor(0).is_empty());
+    }
+}
@@ -0,0 +1,389 @@
+//! A bitmap allocator of physical memory frames, with low-memory watermarks and allocation
+//! failures injected on demand.
+
+use core::{fmt, num::NonZeroU32, ops::Range};
+
+/// The size of a frame, in bytes.
+pub const FRAME_SIZE: usize = 4096;
+
+/// Why an allocation failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocError {
+    /// No frame is free.
+    OutOfMemory,
+    /// The failure was injected, see [`FrameAllocator::set_fail_every`].
+    Injected,
+}
+
+/// How low free memory is, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pressure {
+    /// Free memory is at or above the low watermark.
+    Normal,
+    /// Free memory is below the low watermark.
+    Low,
+    /// Free memory is below the min watermark.
+    Min,
+}
+
+impl Pressure {
+    /// Returns the name of the level, as shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pressure::Normal => "normal",
+            Pressure::Low => "low",
+            Pressure::Min => "min",
+        }
+    }
+}
+
+/// The numbers of free frames under which memory is considered low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermarks {
+    /// Below this many free frames, memory is low.
+    pub low: usize,
+    /// Below this many free frames, memory is critically low. It should not be above `low`.
+    pub min: usize,
+}
+
+/// Which allocations fail on purpose: one in every `n`, or none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FailEvery(pub Option<NonZeroU32>);
+
+impl FailEvery {
+    /// Parses `off`, `0`, or `1/<n>`.
+    pub fn parse(s: &str) -> Option<Self> {
+        if s == "off" || s == "0" {
+            return Some(FailEvery(None));
+        }
+        let n = s.strip_prefix("1/")?.parse().ok()?;
+        Some(FailEvery(Some(NonZeroU32::new(n)?)))
+    }
+}
+
+impl fmt::Display for FailEvery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(n) => write!(f, "1/{n}"),
+            None => f.write_str("off"),
+        }
+    }
+}
+
+/// The counters of a [`FrameAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of frames managed.
+    pub total: usize,
+    /// The number of frames free.
+    pub free: usize,
+    /// The number of allocations that succeeded.
+    pub allocations: u32,
+    /// The number of allocations that failed for lack of memory.
+    pub out_of_memory: u32,
+    /// The number of allocations that failed on purpose.
+    pub injected: u32,
+}
+
+/// An allocator of the frames of `32 * WORDS` frames of memory, starting at address 0.
+///
+/// It starts with no frame free: the available memory is given with [`add`](Self::add), and
+/// what is in use within it is taken back with [`reserve`](Self::reserve).
+pub struct FrameAllocator<const WORDS: usize> {
+    /// One bit per frame, set if the frame is free.
+    bitmap: [u32; WORDS],
+    /// The index of the word where the next search starts.
+    next: usize,
+    stats: Stats,
+    watermarks: Watermarks,
+    /// The pressure last reported, which must get worse to be reported again.
+    reported: Pressure,
+    /// A pressure worse than the one reported before, not taken yet.
+    crossing: Option<Pressure>,
+    fail_every: FailEvery,
+    /// The number of allocations attempted since `fail_every` was set.
+    attempts: u32,
+}
+
+impl<const WORDS: usize> FrameAllocator<WORDS> {
+    pub const fn new() -> Self {
+        FrameAllocator {
+            bitmap: [0; WORDS],
+            next: 0,
+            stats: Stats {
+                total: 0,
+                free: 0,
+                allocations: 0,
+                out_of_memory: 0,
+                injected: 0,
+            },
+            watermarks: Watermarks { low: 0, min: 0 },
+            reported: Pressure::Normal,
+            crossing: None,
+            fail_every: FailEvery(None),
+            attempts: 0,
+        }
+    }
+
+    /// Returns the frames of `range` that the allocator manages, the ones partially in it
+    /// included if `partial` is set.
+    fn frames(range: Range<u64>, partial: bool) -> Range<usize> {
+        let size = FRAME_SIZE as u64;
+        let limit = (WORDS * 32) as u64;
+        let start = if partial {
+            range.start / size
+        } else {
+            range.start.div_ceil(size)
+        };
+        let end = if partial {
+            range.end.div_ceil(size)
+        } else {
+            range.end / size
+        };
+        start.min(limit) as usize..end.min(limit) as usize
+    }
+
+    /// Sets whether `frame` is free, and returns whether that changed anything.
+    fn set_free(&mut self, frame: usize, free: bool) -> bool {
+        let (word, bit) = (frame / 32, 1 << (frame % 32));
+        let was_free = self.bitmap[word] & bit != 0;
+        if free {
+            self.bitmap[word] |= bit;
+        } else {
+            self.bitmap[word] &= !bit;
+        }
+        was_free != free
+    }
+
+    /// Makes the frames entirely in the addresses of `range` available.
+    pub fn add(&mut self, range: Range<u64>) {
+        for frame in Self::frames(range, false) {
+            if self.set_free(frame, true) {
+                self.stats.total += 1;
+                self.stats.free += 1;
+            }
+        }
+        self.update_pressure();
+    }
+
+    /// Takes back the free frames even partially in the addresses of `range`, for good.
+    pub fn reserve(&mut self, range: Range<u64>) {
+        for frame in Self::frames(range, true) {
+            if self.set_free(frame, false) {
+                self.stats.total -= 1;
+                self.stats.free -= 1;
+            }
+        }
+        self.update_pressure();
+    }
+
+    /// Allocates a frame, and returns its address.
+    pub fn alloc(&mut self) -> Result<usize, AllocError> {
+        self.attempts = self.attempts.wrapping_add(1);
+        if let Some(n) = self.fail_every.0
+            && self.attempts % n == 0
+        {
+            self.stats.injected += 1;
+            return Err(AllocError::Injected);
+        }
+        let Some(word) = (self.next..WORDS)
+            .chain(0..self.next)
+            .find(|&word| self.bitmap[word] != 0)
+        else {
+            self.stats.out_of_memory += 1;
+            return Err(AllocError::OutOfMemory);
+        };
+        let frame = word * 32 + self.bitmap[word].trailing_zeros() as usize;
+        self.set_free(frame, false);
+        self.next = word;
+        self.stats.free -= 1;
+        self.stats.allocations += 1;
+        self.update_pressure();
+        Ok(frame * FRAME_SIZE)
+    }
+
+    /// Frees the frame at `addr`, which must come from [`alloc`](Self::alloc).
+    ///
+    /// Returns `false`, and does nothing, if the frame is free already.
+    pub fn free(&mut self, addr: usize) -> bool {
+        let frame = addr / FRAME_SIZE;
+        if frame >= WORDS * 32 || !self.set_free(frame, true) {
+            return false;
+        }
+        self.stats.free += 1;
+        self.update_pressure();
+        true
+    }
+
+    /// Returns the counters of the allocator.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Returns how low free memory is.
+    pub fn pressure(&self) -> Pressure {
+        let free = self.stats.free;
+        if free < self.watermarks.min {
+            Pressure::Min
+        } else if free < self.watermarks.low {
+            Pressure::Low
+        } else {
+            Pressure::Normal
+        }
+    }
+
+    /// Notes a pressure worse than the one reported, or lowers the one reported so that it
+    /// can be reported again.
+    fn update_pressure(&mut self) {
+        let pressure = self.pressure();
+        if pressure > self.reported {
+            self.crossing = Some(pressure);
+        } else if pressure < self.reported {
+            self.crossing = None;
+        }
+        self.reported = pressure;
+    }
+
+    /// Returns the pressure free memory dropped to, once per drop below a watermark.
+    pub fn take_crossing(&mut self) -> Option<Pressure> {
+        self.crossing.take()
+    }
+
+    /// Returns the watermarks.
+    pub fn watermarks(&self) -> Watermarks {
+        self.watermarks
+    }
+
+    /// Sets the watermarks. Free memory already below them counts as a drop.
+    pub fn set_watermarks(&mut self, watermarks: Watermarks) {
+        self.watermarks = watermarks;
+        self.update_pressure();
+    }
+
+    /// Returns which allocations fail on purpose.
+    pub fn fail_every(&self) -> FailEvery {
+        self.fail_every
+    }
+
+    /// Makes one in every `n` allocations fail from now on, starting with the `n`th one.
+    pub fn set_fail_every(&mut self, fail_every: FailEvery) {
+        self.fail_every = fail_every;
+        self.attempts = 0;
+    }
+}
+
+impl<const WORDS: usize> Default for FrameAllocator<WORDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: u64 = FRAME_SIZE as u64;
+
+    /// Returns an allocator of 64 frames, with the frames `start..end` available.
+    fn allocator(frames: Range<u64>) -> FrameAllocator<2> {
+        let mut allocator = FrameAllocator::new();
+        allocator.add(frames.start * FRAME..frames.end * FRAME);
+        allocator
+    }
+
+    #[test]
+    fn add_and_reserve() {
+        let mut allocator = FrameAllocator::<2>::new();
+        // Only whole frames are added, and only the ones managed.
+        allocator.add(FRAME / 2..10 * FRAME + 1);
+        allocator.add(60 * FRAME..100 * FRAME);
+        assert_eq!(allocator.stats().total, 9 + 4);
+        // Frames partially reserved are reserved.
+        allocator.reserve(2 * FRAME - 1..3 * FRAME + 1);
+        assert_eq!(allocator.stats().total, 13 - 3);
+        assert_eq!(allocator.stats().free, 10);
+    }
+
+    #[test]
+    fn alloc_and_free() {
+        let mut allocator = allocator(1..3);
+        let a = allocator.alloc().unwrap();
+        let b = allocator.alloc().unwrap();
+        assert_eq!((a, b), (FRAME_SIZE, 2 * FRAME_SIZE));
+        assert_eq!(allocator.alloc(), Err(AllocError::OutOfMemory));
+        assert!(allocator.free(a));
+        assert!(!allocator.free(a));
+        assert_eq!(allocator.alloc(), Ok(a));
+        let stats = allocator.stats();
+        assert_eq!(
+            (stats.free, stats.allocations, stats.out_of_memory),
+            (0, 3, 1)
+        );
+    }
+
+    #[test]
+    fn alloc_searches_past_the_last_word() {
+        let mut allocator = allocator(0..64);
+        allocator.reserve(40 * FRAME..64 * FRAME);
+        for _ in 0..40 {
+            allocator.alloc().unwrap();
+        }
+        allocator.free(3 * FRAME_SIZE);
+        assert_eq!(allocator.alloc(), Ok(3 * FRAME_SIZE));
+    }
+
+    #[test]
+    fn watermarks_are_reported_once_per_crossing() {
+        let mut allocator = allocator(0..8);
+        allocator.set_watermarks(Watermarks { low: 4, min: 2 });
+        let mut frames = Vec::new();
+        let mut crossings = Vec::new();
+        for _ in 0..8 {
+            frames.push(allocator.alloc().unwrap());
+            crossings.push(allocator.take_crossing());
+        }
+        let (low, min) = (Some(Pressure::Low), Some(Pressure::Min));
+        assert_eq!(crossings, [None, None, None, None, low, None, min, None]);
+
+        // Going back above a watermark allows it to be reported again.
+        for frame in frames.drain(4..) {
+            allocator.free(frame);
+        }
+        assert_eq!(allocator.take_crossing(), None);
+        assert_eq!(allocator.pressure(), Pressure::Normal);
+        allocator.alloc().unwrap();
+        assert_eq!(allocator.take_crossing(), Some(Pressure::Low));
+    }
+
+    #[test]
+    fn raising_the_watermarks_counts_as_a_drop() {
+        let mut allocator = allocator(0..8);
+        allocator.set_watermarks(Watermarks { low: 16, min: 0 });
+        assert_eq!(allocator.take_crossing(), Some(Pressure::Low));
+    }
+
+    #[test]
+    fn injected_failures() {
+        let mut allocator = allocator(0..64);
+        allocator.set_fail_every(FailEvery::parse("1/3").unwrap());
+        let results = (0..6)
+            .map(|_| allocator.alloc().is_ok())
+            .collect::<Vec<_>>();
+        assert_eq!(results, [true, true, false, true, true, false]);
+        assert_eq!(allocator.alloc(), Ok(4 * FRAME_SIZE));
+        allocator.set_fail_every(FailEvery(None));
+        assert!((0..6).all(|_| allocator.alloc().is_ok()));
+        let stats = allocator.stats();
+        assert_eq!((stats.allocations, stats.injected), (11, 2));
+    }
+
+    #[test]
+    fn parse_fail_every() {
+        assert_eq!(FailEvery::parse("off"), Some(FailEvery(None)));
+        assert_eq!(FailEvery::parse("0"), Some(FailEvery(None)));
+        assert_eq!(FailEvery::parse("1/64").unwrap().to_string(), "1/64");
+        assert_eq!(FailEvery::parse("1/0"), None);
+        assert_eq!(FailEvery::parse("2/64"), None);
+        assert_eq!(FailEvery::parse("64"), None);
+    }
+}
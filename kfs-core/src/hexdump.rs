@@ -0,0 +1,174 @@
+//! Formatting of memory as hexdump lines: an address, the bytes in hexadecimal, and the bytes
+//! as text.
+//!
+//! ```text
+//! 00001000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|
+//! ```
+//!
+//! Lines start at multiples of their width, so the same byte always lands in the same column.
+//! The cells before an unaligned start and after the end of the dump are left blank.
+
+use core::fmt::{self, Write};
+
+/// The number of bytes between the extra spaces splitting the hexadecimal columns.
+const GROUP: usize = 8;
+
+/// Returns the number of columns of a line of `width` bytes, without the newline.
+pub const fn line_len(width: usize) -> usize {
+    // The address and two spaces, 3 columns per byte but the last one, the spaces between
+    // groups, two spaces, and the text between bars.
+    8 + 2 + width * 3 - 1 + (width.div_ceil(GROUP) - 1) + 2 + width + 2
+}
+
+/// A line of a dump: the address of its first column, the column its first byte goes to, and
+/// the number of bytes in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    pub addr: usize,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Line {
+    /// Returns the address of the first byte of the line.
+    pub fn start(&self) -> usize {
+        self.addr + self.offset
+    }
+}
+
+/// Splits the `len` bytes from the address `start` in lines of `width` bytes.
+///
+/// The dump stops at the end of the address space.
+///
+/// # Panics
+///
+/// Panics if `width` is 0.
+pub fn lines(start: usize, len: usize, width: usize) -> impl Iterator<Item = Line> {
+    assert!(width > 0, "hexdump width must not be 0");
+    let end = start.saturating_add(len);
+    // Nothing is dumped without bytes, even from an unaligned start.
+    let first = if len == 0 { end } else { start - start % width };
+    (first..end).step_by(width).map(move |addr| {
+        let from = addr.max(start);
+        let to = addr.saturating_add(width).min(end);
+        Line {
+            addr,
+            offset: from - addr,
+            len: to - from,
+        }
+    })
+}
+
+/// Writes one line of `width` bytes, with `bytes` going to the columns from `offset` on, and a
+/// newline.
+///
+/// Only printable ASCII is written as text, so that the line shows the same on any terminal.
+///
+/// # Panics
+///
+/// Panics if the bytes do not fit on the line.
+pub fn write_line(out: &mut dyn Write, line: Line, bytes: &[u8], width: usize) -> fmt::Result {
+    assert!(line.offset + bytes.len() <= width);
+    let byte = |column: usize| column.checked_sub(line.offset).and_then(|i| bytes.get(i));
+
+    write!(out, "{:08x} ", line.addr)?;
+    for column in 0..width {
+        if column % GROUP == 0 {
+            out.write_char(' ')?;
+        }
+        match byte(column) {
+            Some(byte) => write!(out, "{byte:02x}")?,
+            None => out.write_str("  ")?,
+        }
+        if column + 1 < width {
+            out.write_char(' ')?;
+        }
+    }
+    out.write_str("  |")?;
+    for column in 0..width {
+        out.write_char(match byte(column) {
+            Some(&byte) if (b' '..=b'~').contains(&byte) => byte as char,
+            Some(_) => '.',
+            None => ' ',
+        })?;
+    }
+    out.write_str("|\n")
+}
+
+/// Writes `bytes`, which start at the address `addr`, in lines of `width` bytes.
+///
+/// # Panics
+///
+/// Panics if `width` is 0.
+pub fn write(out: &mut dyn Write, addr: usize, bytes: &[u8], width: usize) -> fmt::Result {
+    for line in lines(addr, bytes.len(), width) {
+        let from = line.start() - addr;
+        write_line(out, line, &bytes[from..][..line.len], width)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the dump of `bytes` at the address `addr`.
+    fn dump(addr: usize, bytes: &[u8], width: usize) -> String {
+        let mut out = String::new();
+        write(&mut out, addr, bytes, width).unwrap();
+        out
+    }
+
+    #[test]
+    fn full_lines() {
+        assert_eq!(
+            dump(0x1000, b"Hello, world!\n\0\xff", 16),
+            "00001000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|\n"
+        );
+        assert_eq!(
+            dump(0x20, b"abcdefghijkl", 8),
+            "00000020  61 62 63 64 65 66 67 68  |abcdefgh|\n\
+             00000028  69 6a 6b 6c              |ijkl    |\n"
+        );
+    }
+
+    #[test]
+    fn unaligned_start() {
+        assert_eq!(
+            dump(0x1003, b"abc", 8),
+            "00001000           61 62 63        |   abc  |\n"
+        );
+        assert_eq!(
+            dump(0xffe, b"xyz", 4),
+            "00000ffc        78 79  |  xy|\n00001000  7a           |z   |\n"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(dump(0x1000, b"", 16), "");
+        assert_eq!(dump(0x1003, b"", 16), "");
+    }
+
+    #[test]
+    fn line_lengths() {
+        for width in [4, 8, 16, 32] {
+            let line = dump(0, &[b'.'; 32][..width], width);
+            assert_eq!(line.len(), line_len(width) + 1, "{width}");
+        }
+        assert_eq!(line_len(16), 78);
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_address_space() {
+        let lines: Vec<_> = lines(usize::MAX - 2, 16, 4).collect();
+        assert_eq!(
+            lines,
+            [Line {
+                addr: usize::MAX - 3,
+                offset: 1,
+                len: 2,
+            }]
+        );
+    }
+}
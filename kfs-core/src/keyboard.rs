@@ -0,0 +1,1534 @@
+use core::{fmt, ops::RangeInclusive};
+
+/// The characters produced for keys that have no character of their own.
+///
+/// They are taken from the Unicode private use area, so that they never collide with text: the
+/// character for a key is `U+E000` plus the scancode of the key, in set 1.
+pub mod keys {
+    /// The **F1** key.
+    pub const F1: char = '\u{E03B}';
+    /// The **F2** key.
+    pub const F2: char = '\u{E03C}';
+    /// The **F3** key.
+    pub const F3: char = '\u{E03D}';
+    /// The **F4** key.
+    pub const F4: char = '\u{E03E}';
+    /// The **F5** key.
+    pub const F5: char = '\u{E03F}';
+    /// The **F6** key.
+    pub const F6: char = '\u{E040}';
+    /// The **F7** key.
+    pub const F7: char = '\u{E041}';
+    /// The **F8** key.
+    pub const F8: char = '\u{E042}';
+    /// The **F9** key.
+    pub const F9: char = '\u{E043}';
+    /// The **F10** key.
+    pub const F10: char = '\u{E044}';
+    /// The **F11** key.
+    pub const F11: char = '\u{E057}';
+    /// The **F12** key.
+    pub const F12: char = '\u{E058}';
+    /// The **PAGE UP** key.
+    pub const PAGE_UP: char = '\u{E049}';
+    /// The **PAGE DOWN** key.
+    pub const PAGE_DOWN: char = '\u{E051}';
+    /// The **HOME** key.
+    pub const HOME: char = '\u{E047}';
+    /// The **END** key.
+    pub const END: char = '\u{E04F}';
+    /// The **UP** arrow key.
+    pub const UP: char = '\u{E048}';
+    /// The **DOWN** arrow key.
+    pub const DOWN: char = '\u{E050}';
+    /// The **LEFT** arrow key.
+    pub const LEFT: char = '\u{E04B}';
+    /// The **RIGHT** arrow key.
+    pub const RIGHT: char = '\u{E04D}';
+    /// The **INSERT** key.
+    pub const INSERT: char = '\u{E052}';
+    /// The **DELETE** key.
+    pub const DELETE: char = '\u{E053}';
+
+    /// The function keys, from **F1** to **F12**.
+    pub const FUNCTION: [char; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    /// Returns the number of the function key whose character is `c`, from 1 to 12.
+    pub fn function(c: char) -> Option<u8> {
+        let n = FUNCTION.iter().position(|&key| key == c)?;
+        Some(n as u8 + 1)
+    }
+
+    /// Returns whether `c` is the character of a key, rather than text.
+    pub fn is_key(c: char) -> bool {
+        ('\u{E000}'..='\u{E0FF}').contains(&c)
+    }
+}
+
+/// The current state of the state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The state machine is in the neutral state. No sequence of scancode has been
+    /// generated yet.
+    Neutral,
+    /// The E0 escape code has been received.
+    E0,
+    /// The first bytes of [`PAUSE_SEQUENCE`] have been received, as many as the field says.
+    E1(usize),
+}
+
+/// The scancodes sent for a press of the **PAUSE** key, which has no release.
+const PAUSE_SEQUENCE: [u8; 6] = [0xE1, 0x1D, 0x45, 0xE1, 0x9D, 0xC5];
+
+/// The characters a printable key produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutEntry {
+    /// The scancode of the key, in set 1.
+    pub scancode: u8,
+    /// The character produced without modifiers.
+    pub normal: char,
+    /// The character produced with shift, if any.
+    pub shifted: Option<char>,
+    /// The character produced with AltGr, the right alt key, if any.
+    pub alt_gr: Option<char>,
+}
+
+impl LayoutEntry {
+    /// Returns the character the key produces with `modifiers`, if any.
+    ///
+    /// Caps lock only acts on the keys whose shifted character is the uppercase version of
+    /// their normal one. Without a character of its own for AltGr, a key produces what it
+    /// would without it, so that alt combinations still work.
+    fn char(&self, modifiers: Modifiers) -> Option<char> {
+        if modifiers.right_alt()
+            && let Some(c) = self.alt_gr
+        {
+            return Some(c);
+        }
+        let letter = self.normal.is_lowercase() && self.shifted.is_some_and(char::is_uppercase);
+        let shifted = if letter {
+            modifiers.shifted()
+        } else {
+            modifiers.shift()
+        };
+        if shifted {
+            self.shifted
+        } else {
+            Some(self.normal)
+        }
+    }
+}
+
+/// The characters of the printable keys of a keyboard.
+#[derive(Debug)]
+pub struct Layout {
+    /// The name of the layout, as accepted by [`Layout::from_name`].
+    pub name: &'static str,
+    /// The printable keys. The other keys are the same on every layout.
+    ///
+    /// No two keys may produce the same character without modifiers, as keys are told apart
+    /// by that character in [`Key::Char`].
+    pub entries: &'static [LayoutEntry],
+}
+
+impl Layout {
+    /// Returns the layout called `name`.
+    pub fn from_name(name: &str) -> Option<&'static Layout> {
+        LAYOUTS.into_iter().find(|layout| layout.name == name)
+    }
+
+    /// Returns the entry of the key with the scancode `scancode`, if it is printable.
+    fn entry(&self, scancode: u8) -> Option<&LayoutEntry> {
+        self.entries.iter().find(|entry| entry.scancode == scancode)
+    }
+
+    /// Returns the entry of the key producing `c` without modifiers, if any.
+    fn entry_of(&self, c: char) -> Option<&LayoutEntry> {
+        self.entries.iter().find(|entry| entry.normal == c)
+    }
+}
+
+/// Builds the entries of a layout from `scancode => normal shifted [alt_gr]` lines, where `_`
+/// stands for no character.
+macro_rules! layout {
+    ($($scancode:literal => $normal:literal $shifted:tt $($alt_gr:literal)?;)*) => {
+        &[$(LayoutEntry {
+            scancode: $scancode,
+            normal: $normal,
+            shifted: layout!(@char $shifted),
+            alt_gr: layout!(@char $($alt_gr)?),
+        }),*]
+    };
+    (@char) => { None };
+    (@char _) => { None };
+    (@char $c:literal) => { Some($c) };
+}
+
+/// The US QWERTY layout.
+pub const US: Layout = Layout {
+    name: "us",
+    entries: layout! {
+        0x02 => '1' '!';
+        0x03 => '2' '@';
+        0x04 => '3' '#';
+        0x05 => '4' '$';
+        0x06 => '5' '%';
+        0x07 => '6' '^';
+        0x08 => '7' '&';
+        0x09 => '8' '*';
+        0x0A => '9' '(';
+        0x0B => '0' ')';
+        0x0C => '-' '_';
+        0x0D => '=' '+';
+        0x10 => 'q' 'Q';
+        0x11 => 'w' 'W';
+        0x12 => 'e' 'E';
+        0x13 => 'r' 'R';
+        0x14 => 't' 'T';
+        0x15 => 'y' 'Y';
+        0x16 => 'u' 'U';
+        0x17 => 'i' 'I';
+        0x18 => 'o' 'O';
+        0x19 => 'p' 'P';
+        0x1A => '[' '{';
+        0x1B => ']' '}';
+        0x2B => '\\' '|';
+        0x1E => 'a' 'A';
+        0x1F => 's' 'S';
+        0x20 => 'd' 'D';
+        0x21 => 'f' 'F';
+        0x22 => 'g' 'G';
+        0x23 => 'h' 'H';
+        0x24 => 'j' 'J';
+        0x25 => 'k' 'K';
+        0x26 => 'l' 'L';
+        0x27 => ';' ':';
+        0x28 => '\'' '"';
+        0x29 => '`' '~';
+        0x2C => 'z' 'Z';
+        0x2D => 'x' 'X';
+        0x2E => 'c' 'C';
+        0x2F => 'v' 'V';
+        0x30 => 'b' 'B';
+        0x31 => 'n' 'N';
+        0x32 => 'm' 'M';
+        0x33 => ',' '<';
+        0x34 => '.' '>';
+        0x35 => '/' '?';
+    },
+};
+
+/// The French AZERTY layout.
+///
+/// The dead keys produce their character right away, and the characters missing from code page
+/// 437, such as `€`, are left out.
+pub const FR: Layout = Layout {
+    name: "fr",
+    entries: layout! {
+        0x29 => '²' _;
+        0x02 => '&' '1';
+        0x03 => 'é' '2' '~';
+        0x04 => '"' '3' '#';
+        0x05 => '\'' '4' '{';
+        0x06 => '(' '5' '[';
+        0x07 => '-' '6' '|';
+        0x08 => 'è' '7' '`';
+        0x09 => '_' '8' '\\';
+        0x0A => 'ç' '9' '^';
+        0x0B => 'à' '0' '@';
+        0x0C => ')' '°' ']';
+        0x0D => '=' '+' '}';
+        0x10 => 'a' 'A';
+        0x11 => 'z' 'Z';
+        0x12 => 'e' 'E';
+        0x13 => 'r' 'R';
+        0x14 => 't' 'T';
+        0x15 => 'y' 'Y';
+        0x16 => 'u' 'U';
+        0x17 => 'i' 'I';
+        0x18 => 'o' 'O';
+        0x19 => 'p' 'P';
+        0x1A => '^' _;
+        0x1B => '$' '£';
+        0x2B => '*' 'µ';
+        0x1E => 'q' 'Q';
+        0x1F => 's' 'S';
+        0x20 => 'd' 'D';
+        0x21 => 'f' 'F';
+        0x22 => 'g' 'G';
+        0x23 => 'h' 'H';
+        0x24 => 'j' 'J';
+        0x25 => 'k' 'K';
+        0x26 => 'l' 'L';
+        0x27 => 'm' 'M';
+        0x28 => 'ù' '%';
+        0x56 => '<' '>';
+        0x2C => 'w' 'W';
+        0x2D => 'x' 'X';
+        0x2E => 'c' 'C';
+        0x2F => 'v' 'V';
+        0x30 => 'b' 'B';
+        0x31 => 'n' 'N';
+        0x32 => ',' '?';
+        0x33 => ';' '.';
+        0x34 => ':' '/';
+        0x35 => '!' '§';
+    },
+};
+
+/// The US Dvorak layout.
+pub const DVORAK: Layout = Layout {
+    name: "dvorak",
+    entries: layout! {
+        0x29 => '`' '~';
+        0x02 => '1' '!';
+        0x03 => '2' '@';
+        0x04 => '3' '#';
+        0x05 => '4' '$';
+        0x06 => '5' '%';
+        0x07 => '6' '^';
+        0x08 => '7' '&';
+        0x09 => '8' '*';
+        0x0A => '9' '(';
+        0x0B => '0' ')';
+        0x0C => '[' '{';
+        0x0D => ']' '}';
+        0x10 => '\'' '"';
+        0x11 => ',' '<';
+        0x12 => '.' '>';
+        0x13 => 'p' 'P';
+        0x14 => 'y' 'Y';
+        0x15 => 'f' 'F';
+        0x16 => 'g' 'G';
+        0x17 => 'c' 'C';
+        0x18 => 'r' 'R';
+        0x19 => 'l' 'L';
+        0x1A => '/' '?';
+        0x1B => '=' '+';
+        0x2B => '\\' '|';
+        0x1E => 'a' 'A';
+        0x1F => 'o' 'O';
+        0x20 => 'e' 'E';
+        0x21 => 'u' 'U';
+        0x22 => 'i' 'I';
+        0x23 => 'd' 'D';
+        0x24 => 'h' 'H';
+        0x25 => 't' 'T';
+        0x26 => 'n' 'N';
+        0x27 => 's' 'S';
+        0x28 => '-' '_';
+        0x2C => ';' ':';
+        0x2D => 'q' 'Q';
+        0x2E => 'j' 'J';
+        0x2F => 'k' 'K';
+        0x30 => 'x' 'X';
+        0x31 => 'b' 'B';
+        0x32 => 'm' 'M';
+        0x33 => 'w' 'W';
+        0x34 => 'v' 'V';
+        0x35 => 'z' 'Z';
+    },
+};
+
+/// Every layout, the default one first.
+pub const LAYOUTS: [&Layout; 3] = [&US, &FR, &DVORAK];
+
+/// A key of the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A key of the [`Layout`], letters, digits and punctuation, by the character it produces
+    /// without modifiers.
+    Char(char),
+    /// A key of the keypad producing a character, by that character.
+    ///
+    /// The digits and the dot are only reported this way with **NUM LOCK** on, and as the
+    /// navigation keys they stand for otherwise. The center key stays `Keypad('5')`.
+    Keypad(char),
+    /// A function key, from 1 to 12.
+    F(u8),
+    Escape,
+    Backspace,
+    Tab,
+    Enter,
+    Space,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    LeftShift,
+    RightShift,
+    LeftControl,
+    RightControl,
+    LeftAlt,
+    RightAlt,
+    LeftSuper,
+    RightSuper,
+    Menu,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    /// The **PAUSE** key, which is only ever pressed: no release is reported for it.
+    Pause,
+    /// The **SYSRQ** key, which is **PRINT SCREEN** with alt held.
+    SysRq,
+}
+
+/// A key being pressed or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The key.
+    pub key: Key,
+    /// Whether the key was pressed, rather than released. Keys held down repeat their press.
+    pub pressed: bool,
+    /// The modifiers, once updated for this event.
+    pub modifiers: Modifiers,
+}
+
+/// Contains the state required to convert scan-codes into text.
+///
+/// The printable keys are looked up in a [`Layout`], which can be changed at any time. The
+/// modifiers, the keypad and the keys without a character are handled the same for every
+/// layout.
+///
+/// Control does not change the character produced, so a control shortcut follows the layout:
+/// **CTRL+C** is the key producing `c`, wherever it is.
+pub struct Decoder {
+    /// The layout of the printable keys.
+    layout: &'static Layout,
+    /// The state of key modifiers.
+    modifiers: Modifiers,
+    /// The current state of the state machine.
+    state: State,
+}
+
+impl Decoder {
+    /// Returns a new decoder for the layout `layout`.
+    pub const fn new(layout: &'static Layout) -> Self {
+        Self {
+            layout,
+            modifiers: Modifiers::EMPTY,
+            state: State::Neutral,
+        }
+    }
+
+    /// Returns the layout of the printable keys.
+    pub fn layout(&self) -> &'static Layout {
+        self.layout
+    }
+
+    /// Changes the layout of the printable keys. The modifiers are kept.
+    pub fn set_layout(&mut self, layout: &'static Layout) {
+        self.layout = layout;
+    }
+
+    /// Returns the current state of the modifiers.
+    #[inline(always)]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Forgets the keys held and any sequence under way, as after scancodes were read without
+    /// going through the decoder. The lock keys stay as they were.
+    pub fn reset(&mut self) {
+        let mut modifiers = Modifiers::EMPTY;
+        if self.modifiers.caps_lock() {
+            modifiers.toggle_caps_lock();
+        }
+        if self.modifiers.num_lock() {
+            modifiers.toggle_num_lock();
+        }
+        if self.modifiers.scroll_lock() {
+            modifiers.toggle_scroll_lock();
+        }
+        self.modifiers = modifiers;
+        self.state = State::Neutral;
+    }
+
+    /// Advances the state of the state machine with a new scan-code. If a character can
+    /// be produced, it is returned in a [`Some(_)`] variant.
+    ///
+    /// If no character could be produced, [`None`] is returned instead.
+    pub fn advance(&mut self, scancode: u8) -> Option<char> {
+        self.advance_event(scancode)
+            .and_then(|event| self.char(event))
+    }
+
+    /// Advances the state of the state machine with a new scan-code, and returns the key event
+    /// it completes, if any.
+    ///
+    /// [`None`] is returned for prefixes, and for the keys this decoder does not know.
+    pub fn advance_event(&mut self, scancode: u8) -> Option<KeyEvent> {
+        use State::*;
+
+        let mut st = self.state;
+
+        // A Pause sequence goes on while the scancodes match it. Otherwise, it is dropped, and
+        // the scancode is decoded on its own.
+        if let E1(len) = st {
+            if scancode == PAUSE_SEQUENCE[len] {
+                self.state = if len + 1 == PAUSE_SEQUENCE.len() {
+                    Neutral
+                } else {
+                    E1(len + 1)
+                };
+                return (self.state == Neutral).then_some(KeyEvent {
+                    key: Key::Pause,
+                    pressed: true,
+                    modifiers: self.modifiers,
+                });
+            }
+            st = Neutral;
+        }
+
+        // Parse the current escape sequence.
+        self.state = match (st, scancode) {
+            (Neutral, 0xE0) => E0,
+            (Neutral, 0xE1) => E1(1),
+            _ => Neutral,
+        };
+        if self.state != Neutral {
+            return None;
+        }
+
+        // Bit 7 is set on releases.
+        let pressed = scancode & 0x80 == 0;
+        let key = self.key(st == E0, scancode & 0x7F)?;
+        self.update_modifiers(key, pressed);
+        Some(KeyEvent {
+            key,
+            pressed,
+            modifiers: self.modifiers,
+        })
+    }
+
+    /// Returns the key with the make code `code`, extended if `e0`.
+    fn key(&self, e0: bool, code: u8) -> Option<Key> {
+        let num_lock = self.modifiers.num_lock();
+        let key = match (e0, code) {
+            (false, 0x01) => Key::Escape,
+            (false, 0x0E) => Key::Backspace,
+            (false, 0x0F) => Key::Tab,
+            (_, 0x1C) => Key::Enter,
+            (false, 0x1D) => Key::LeftControl,
+            (true, 0x1D) => Key::RightControl,
+            (false, 0x2A) => Key::LeftShift,
+            (false, 0x36) => Key::RightShift,
+            (false, 0x38) => Key::LeftAlt,
+            (true, 0x38) => Key::RightAlt,
+            (false, 0x39) => Key::Space,
+            (false, 0x3A) => Key::CapsLock,
+            (false, 0x3B..=0x44) => Key::F(code - 0x3A),
+            (false, 0x57 | 0x58) => Key::F(code - 0x4C),
+            (false, 0x45) => Key::NumLock,
+            (false, 0x46) => Key::ScrollLock,
+            (true, 0x5B) => Key::LeftSuper,
+            (true, 0x5C) => Key::RightSuper,
+            (true, 0x5D) => Key::Menu,
+            (false, 0x54) => Key::SysRq,
+            // The keypad.
+            (true, 0x35) => Key::Keypad('/'),
+            (false, 0x37) => Key::Keypad('*'),
+            (false, 0x4A) => Key::Keypad('-'),
+            (false, 0x4E) => Key::Keypad('+'),
+            (false, 0x4C) => Key::Keypad('5'),
+            (false, 0x47..=0x53) if num_lock => Key::Keypad(match code {
+                0x47 => '7',
+                0x48 => '8',
+                0x49 => '9',
+                0x4B => '4',
+                0x4D => '6',
+                0x4F => '1',
+                0x50 => '2',
+                0x51 => '3',
+                0x52 => '0',
+                _ => '.',
+            }),
+            // The navigation keys, which the keypad doubles without NUM LOCK.
+            (_, 0x47) => Key::Home,
+            (_, 0x48) => Key::Up,
+            (_, 0x49) => Key::PageUp,
+            (_, 0x4B) => Key::Left,
+            (_, 0x4D) => Key::Right,
+            (_, 0x4F) => Key::End,
+            (_, 0x50) => Key::Down,
+            (_, 0x51) => Key::PageDown,
+            (_, 0x52) => Key::Insert,
+            (_, 0x53) => Key::Delete,
+            // Some keyboards wrap the gray navigation keys in fake presses or releases of the
+            // shift keys, so that they are not read as the keypad. These are not real keys,
+            // and must not touch the shift state, lest it stay stuck.
+            (true, 0x2A | 0x36) => return None,
+            (false, _) => Key::Char(self.layout.entry(code)?.normal),
+            (true, _) => return None,
+        };
+        Some(key)
+    }
+
+    /// Updates the modifiers for `key` being pressed or released.
+    fn update_modifiers(&mut self, key: Key, pressed: bool) {
+        let m = &mut self.modifiers;
+        match (key, pressed) {
+            (Key::LeftShift, true) => m.set_left_shift(),
+            (Key::LeftShift, false) => m.clear_left_shift(),
+            (Key::RightShift, true) => m.set_right_shift(),
+            (Key::RightShift, false) => m.clear_right_shift(),
+            (Key::LeftControl, true) => m.set_left_control(),
+            (Key::LeftControl, false) => m.clear_left_control(),
+            (Key::RightControl, true) => m.set_right_control(),
+            (Key::RightControl, false) => m.clear_right_control(),
+            (Key::LeftAlt, true) => m.set_left_alt(),
+            (Key::LeftAlt, false) => m.clear_left_alt(),
+            (Key::RightAlt, true) => m.set_right_alt(),
+            (Key::RightAlt, false) => m.clear_right_alt(),
+            (Key::LeftSuper, true) => m.set_left_super(),
+            (Key::LeftSuper, false) => m.clear_left_super(),
+            (Key::RightSuper, true) => m.set_right_super(),
+            (Key::RightSuper, false) => m.clear_right_super(),
+            // The lock keys toggle once per press, however long they are held.
+            (Key::CapsLock, true) if !m.caps_lock_pressed() => {
+                m.set_caps_lock_pressed();
+                m.toggle_caps_lock();
+            }
+            (Key::CapsLock, false) => m.clear_caps_lock_pressed(),
+            (Key::NumLock, true) if !m.num_lock_pressed() => {
+                m.set_num_lock_pressed();
+                m.toggle_num_lock();
+            }
+            (Key::NumLock, false) => m.clear_num_lock_pressed(),
+            (Key::ScrollLock, true) if !m.scroll_lock_pressed() => {
+                m.set_scroll_lock_pressed();
+                m.toggle_scroll_lock();
+            }
+            (Key::ScrollLock, false) => m.clear_scroll_lock_pressed(),
+            _ => (),
+        }
+    }
+
+    /// Returns the character `event` produces, if any, as returned by [`Decoder::advance`].
+    ///
+    /// Only presses produce characters. The keys without a character of their own produce
+    /// the ones in [`keys`], when there is one.
+    pub fn char(&self, event: KeyEvent) -> Option<char> {
+        if !event.pressed {
+            return None;
+        }
+        match event.key {
+            Key::Char(c) => self.layout.entry_of(c)?.char(event.modifiers),
+            Key::Keypad(c) => Some(c),
+            Key::Space => Some(' '),
+            Key::Enter => Some('\n'),
+            Key::Backspace => Some('\x08'),
+            Key::Tab => Some('\t'),
+            Key::Escape => Some('\x1b'),
+            Key::F(n) => keys::FUNCTION.get(usize::from(n).checked_sub(1)?).copied(),
+            Key::PageUp => Some(keys::PAGE_UP),
+            Key::PageDown => Some(keys::PAGE_DOWN),
+            Key::Home => Some(keys::HOME),
+            Key::End => Some(keys::END),
+            Key::Up => Some(keys::UP),
+            Key::Down => Some(keys::DOWN),
+            Key::Left => Some(keys::LEFT),
+            Key::Right => Some(keys::RIGHT),
+            Key::Insert => Some(keys::INSERT),
+            Key::Delete => Some(keys::DELETE),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new(&US)
+    }
+}
+
+/// The set 1 make code of each set 2 make code, as translated by the keyboard controller, or 0
+/// for the codes of no key.
+#[rustfmt::skip]
+const SET2_TO_SET1: [u8; 0x84] = [
+    0x00, 0x43, 0x00, 0x3F, 0x3D, 0x3B, 0x3C, 0x58,
+    0x00, 0x44, 0x42, 0x40, 0x3E, 0x0F, 0x29, 0x00,
+    0x00, 0x38, 0x2A, 0x00, 0x1D, 0x10, 0x02, 0x00,
+    0x00, 0x00, 0x2C, 0x1F, 0x1E, 0x11, 0x03, 0x5B,
+    0x00, 0x2E, 0x2D, 0x20, 0x12, 0x05, 0x04, 0x5C,
+    0x00, 0x39, 0x2F, 0x21, 0x14, 0x13, 0x06, 0x5D,
+    0x00, 0x31, 0x30, 0x23, 0x22, 0x15, 0x07, 0x00,
+    0x00, 0x00, 0x32, 0x24, 0x16, 0x08, 0x09, 0x00,
+    0x00, 0x33, 0x25, 0x17, 0x18, 0x0B, 0x0A, 0x00,
+    0x00, 0x34, 0x35, 0x26, 0x27, 0x19, 0x0C, 0x00,
+    0x00, 0x00, 0x28, 0x00, 0x1A, 0x0D, 0x00, 0x00,
+    0x3A, 0x36, 0x1C, 0x1B, 0x00, 0x2B, 0x00, 0x00,
+    0x00, 0x56, 0x00, 0x00, 0x00, 0x00, 0x0E, 0x00,
+    0x00, 0x4F, 0x00, 0x4B, 0x47, 0x00, 0x00, 0x00,
+    0x52, 0x53, 0x50, 0x4C, 0x4D, 0x48, 0x01, 0x45,
+    0x57, 0x4E, 0x51, 0x4A, 0x37, 0x49, 0x46, 0x00,
+    0x00, 0x00, 0x00, 0x41,
+];
+
+/// Converts scancodes of set 2 into the scancodes of set 1 the keyboard controller produces
+/// when it translates them, for the controllers that do not.
+///
+/// The prefixes are kept, and the `F0` prefix of releases turns into bit 7 of the code that
+/// follows it, so that `E0 F0 75` becomes `E0 C8`.
+#[derive(Debug, Default)]
+pub struct Set2 {
+    /// Whether the next code is a release.
+    release: bool,
+}
+
+impl Set2 {
+    /// Returns a new converter, at the start of a scancode.
+    pub const fn new() -> Self {
+        Self { release: false }
+    }
+
+    /// Converts the next byte `byte` of set 2. Returns the byte of set 1 it stands for, if any.
+    pub fn translate(&mut self, byte: u8) -> Option<u8> {
+        match byte {
+            0xF0 => {
+                self.release = true;
+                None
+            }
+            0xE0 | 0xE1 => Some(byte),
+            _ => {
+                let release = core::mem::take(&mut self.release);
+                let code = *SET2_TO_SET1.get(byte as usize).filter(|&&code| code != 0)?;
+                Some(if release { code | 0x80 } else { code })
+            }
+        }
+    }
+}
+
+/// The typematic delays the keyboard supports, in milliseconds.
+pub const TYPEMATIC_DELAYS: RangeInclusive<u32> = 250..=1000;
+
+/// The typematic rates the keyboard supports, in characters per second.
+pub const TYPEMATIC_RATES: RangeInclusive<u32> = 2..=30;
+
+/// Returns the period between repeats of the rate bits `rate`, in microseconds.
+const fn typematic_period_us(rate: u8) -> u32 {
+    let mantissa = (rate & 0x07) as u32;
+    let exponent = (rate >> 3 & 0x03) as u32;
+    (8 + mantissa) * (1 << exponent) * 4170
+}
+
+/// Returns the byte of the **SET TYPEMATIC** keyboard command (0xF3) closest to a delay of
+/// `delay_ms` milliseconds before a held key repeats, then `rate_cps` repeats per second.
+///
+/// The keyboard supports delays of 250 to 1000 ms by steps of 250 ms, and 32 rates from 2 to
+/// 30 characters per second. Values out of these ranges get the closest supported one.
+pub fn typematic_byte(delay_ms: u32, rate_cps: u32) -> u8 {
+    let delay =
+        (delay_ms.clamp(*TYPEMATIC_DELAYS.start(), *TYPEMATIC_DELAYS.end()) + 125) / 250 - 1;
+    let target = rate_cps.saturating_mul(1000);
+    let rate = (0..32)
+        .min_by_key(|&rate| typematic_rate_mcps(rate).abs_diff(target))
+        .unwrap();
+    (delay as u8) << 5 | rate
+}
+
+/// Returns the rate of the typematic byte `byte`, in thousandths of characters per second.
+pub fn typematic_rate_mcps(byte: u8) -> u32 {
+    1_000_000_000 / typematic_period_us(byte & 0x1F)
+}
+
+/// Returns the delay of the typematic byte `byte`, in milliseconds.
+pub fn typematic_delay_ms(byte: u8) -> u32 {
+    ((byte >> 5 & 0x03) as u32 + 1) * 250
+}
+
+/// Keyboard modifiers.
+///
+/// They are displayed as the names of the modifiers held and the locks on, such as
+/// `LSHIFT RCTRL CAPS NUM`, or `none`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers(u16);
+
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = Self::NAMES
+            .into_iter()
+            .filter(|&(bit, _)| self.is_bit_set(bit))
+            .map(|(_, name)| name);
+        let Some(first) = names.next() else {
+            return f.write_str("none");
+        };
+        f.write_str(first)?;
+        names.try_for_each(|name| write!(f, " {name}"))
+    }
+}
+
+impl fmt::Debug for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Modifiers({:#06x} {self})", self.0)
+    }
+}
+
+impl Modifiers {
+    /// The empty set of modifiers.
+    pub const EMPTY: Self = Self(0);
+
+    const LEFT_CONTROL_BIT: u32 = 0;
+    const RIGHT_CONTROL_BIT: u32 = 1;
+    const LEFT_SHIFT_BIT: u32 = 2;
+    const RIGHT_SHIFT_BIT: u32 = 3;
+    const LEFT_ALT_BIT: u32 = 4;
+    const RIGHT_ALT_BIT: u32 = 5;
+    const LEFT_SUPER_BIT: u32 = 6;
+    const RIGHT_SUPER_BIT: u32 = 7;
+    const NUM_LOCK_BIT: u32 = 8;
+    const CAPS_LOCK_BIT: u32 = 9;
+    const SCROLL_LOCK_BIT: u32 = 10;
+    const NUM_LOCK_PRESSED_BIT: u32 = 11;
+    const CAPS_LOCK_PRESSED_BIT: u32 = 12;
+    const SCROLL_LOCK_PRESSED_BIT: u32 = 13;
+
+    /// The bits shown by [`Display`](fmt::Display), and their names.
+    const NAMES: [(u32, &str); 11] = [
+        (Self::LEFT_SHIFT_BIT, "LSHIFT"),
+        (Self::RIGHT_SHIFT_BIT, "RSHIFT"),
+        (Self::LEFT_CONTROL_BIT, "LCTRL"),
+        (Self::RIGHT_CONTROL_BIT, "RCTRL"),
+        (Self::LEFT_ALT_BIT, "LALT"),
+        (Self::RIGHT_ALT_BIT, "RALT"),
+        (Self::LEFT_SUPER_BIT, "LSUPER"),
+        (Self::RIGHT_SUPER_BIT, "RSUPER"),
+        (Self::CAPS_LOCK_BIT, "CAPS"),
+        (Self::NUM_LOCK_BIT, "NUM"),
+        (Self::SCROLL_LOCK_BIT, "SCROLL"),
+    ];
+
+    /// Returns the raw bits of the modifiers.
+    #[inline]
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Returns the modifiers with the raw bits `bits`, as returned by [`Modifiers::bits`].
+    #[inline]
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    /// Returns whether the specified bit is set.
+    #[inline]
+    fn is_bit_set(self, bit: u32) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    /// Toggles the specified bit.
+    #[inline]
+    fn toggle_bit(&mut self, bit: u32) {
+        self.0 ^= 1 << bit;
+    }
+
+    /// Sets the specified bit to the provided value.
+    #[inline]
+    fn set_bit(&mut self, bit: u32) {
+        self.0 |= 1 << bit;
+    }
+
+    /// Clears the specified bit.
+    #[inline]
+    fn clear_bit(&mut self, bit: u32) {
+        self.0 &= !(1 << bit);
+    }
+
+    /// Returns whether the left **CONTROL** key is currently pressed.
+    #[inline]
+    pub fn left_control(self) -> bool {
+        self.is_bit_set(Self::LEFT_CONTROL_BIT)
+    }
+
+    /// Returns whether the right **CONTROL** key is currently pressed.
+    #[inline]
+    pub fn right_control(self) -> bool {
+        self.is_bit_set(Self::RIGHT_CONTROL_BIT)
+    }
+
+    /// Returns whether the left **SHIFT** key is currently pressed.
+    #[inline]
+    pub fn left_shift(self) -> bool {
+        self.is_bit_set(Self::LEFT_SHIFT_BIT)
+    }
+
+    /// Returns whether the right **SHIFT** key is currently pressed.
+    #[inline]
+    pub fn right_shift(self) -> bool {
+        self.is_bit_set(Self::RIGHT_SHIFT_BIT)
+    }
+
+    /// Returns whether the left **ALT** key is currently pressed.
+    #[inline]
+    pub fn left_alt(self) -> bool {
+        self.is_bit_set(Self::LEFT_ALT_BIT)
+    }
+
+    /// Returns whether the right **ALT** key is currently pressed.
+    #[inline]
+    pub fn right_alt(self) -> bool {
+        self.is_bit_set(Self::RIGHT_ALT_BIT)
+    }
+
+    /// Returns whether the left **SUPER** key is currently pressed.
+    #[inline]
+    pub fn left_super(self) -> bool {
+        self.is_bit_set(Self::LEFT_SUPER_BIT)
+    }
+
+    /// Returns whether the right **SUPER** key is currently pressed.
+    #[inline]
+    pub fn right_super(self) -> bool {
+        self.is_bit_set(Self::RIGHT_SUPER_BIT)
+    }
+
+    /// Returns whether the **CAPS LOCK** key is currently active.
+    #[inline]
+    pub fn caps_lock(self) -> bool {
+        self.is_bit_set(Self::CAPS_LOCK_BIT)
+    }
+
+    /// Returns whether the **NUM LOCK** key is currently active.
+    #[inline]
+    pub fn num_lock(self) -> bool {
+        self.is_bit_set(Self::NUM_LOCK_BIT)
+    }
+
+    /// Returns whether the **SCROLL LOCK** key is currently active.
+    #[inline]
+    pub fn scroll_lock(self) -> bool {
+        self.is_bit_set(Self::SCROLL_LOCK_BIT)
+    }
+
+    /// Returns whether the **CAPS LOCK** key is currently pressed.
+    #[inline]
+    pub fn caps_lock_pressed(self) -> bool {
+        self.is_bit_set(Self::CAPS_LOCK_PRESSED_BIT)
+    }
+
+    /// Returns whether the **NUM LOCK** key is currently pressed.
+    #[inline]
+    pub fn num_lock_pressed(self) -> bool {
+        self.is_bit_set(Self::NUM_LOCK_PRESSED_BIT)
+    }
+
+    /// Returns whether the **SCROLL LOCK** key is currently pressed.
+    #[inline]
+    pub fn scroll_lock_pressed(self) -> bool {
+        self.is_bit_set(Self::SCROLL_LOCK_PRESSED_BIT)
+    }
+
+    /// Returns whether any shift key is used.
+    #[inline]
+    pub fn shift(self) -> bool {
+        self.left_shift() || self.right_shift()
+    }
+
+    /// Returns whether any control key is used.
+    #[inline]
+    pub fn control(self) -> bool {
+        self.left_control() || self.right_control()
+    }
+
+    /// Returns whether any alt key is used.
+    #[inline]
+    pub fn alt(self) -> bool {
+        self.left_alt() || self.right_alt()
+    }
+
+    /// Returns whether any super key is used.
+    #[inline]
+    pub fn super_key(self) -> bool {
+        self.left_super() || self.right_super()
+    }
+
+    /// Returns whether the keyboard is shifted.
+    #[inline]
+    pub fn shifted(self) -> bool {
+        self.shift() ^ self.caps_lock()
+    }
+
+    /// Toggles the **NUM LOCK** key.
+    #[inline]
+    pub fn toggle_num_lock(&mut self) {
+        self.toggle_bit(Self::NUM_LOCK_BIT);
+    }
+
+    /// Toggles the **SCROLL LOCK** key.
+    #[inline]
+    pub fn toggle_scroll_lock(&mut self) {
+        self.toggle_bit(Self::SCROLL_LOCK_BIT);
+    }
+
+    /// Toggles the **CAPS LOCK** key.
+    #[inline]
+    pub fn toggle_caps_lock(&mut self) {
+        self.toggle_bit(Self::CAPS_LOCK_BIT);
+    }
+
+    /// Set the left shift key.
+    pub fn set_left_shift(&mut self) {
+        self.set_bit(Self::LEFT_SHIFT_BIT);
+    }
+
+    /// Clears the left shift key.
+    pub fn clear_left_shift(&mut self) {
+        self.clear_bit(Self::LEFT_SHIFT_BIT);
+    }
+
+    /// Set the right shift key.
+    pub fn set_right_shift(&mut self) {
+        self.set_bit(Self::RIGHT_SHIFT_BIT);
+    }
+
+    /// Clears the right shift key.
+    pub fn clear_right_shift(&mut self) {
+        self.clear_bit(Self::RIGHT_SHIFT_BIT);
+    }
+
+    /// Set the left control key.
+    pub fn set_left_control(&mut self) {
+        self.set_bit(Self::LEFT_CONTROL_BIT);
+    }
+
+    /// Clears the left control key.
+    pub fn clear_left_control(&mut self) {
+        self.clear_bit(Self::LEFT_CONTROL_BIT);
+    }
+
+    /// Set the right control key.
+    pub fn set_right_control(&mut self) {
+        self.set_bit(Self::RIGHT_CONTROL_BIT);
+    }
+
+    /// Clears the right control key.
+    pub fn clear_right_control(&mut self) {
+        self.clear_bit(Self::RIGHT_CONTROL_BIT);
+    }
+
+    /// Set the left alt key.
+    pub fn set_left_alt(&mut self) {
+        self.set_bit(Self::LEFT_ALT_BIT);
+    }
+
+    /// Clears the left alt key.
+    pub fn clear_left_alt(&mut self) {
+        self.clear_bit(Self::LEFT_ALT_BIT);
+    }
+
+    /// Set the right alt key.
+    pub fn set_right_alt(&mut self) {
+        self.set_bit(Self::RIGHT_ALT_BIT);
+    }
+
+    /// Clears the right alt key.
+    pub fn clear_right_alt(&mut self) {
+        self.clear_bit(Self::RIGHT_ALT_BIT);
+    }
+
+    /// Sets the left super key.
+    pub fn set_left_super(&mut self) {
+        self.set_bit(Self::LEFT_SUPER_BIT);
+    }
+
+    /// Clears the left super key.
+    pub fn clear_left_super(&mut self) {
+        self.clear_bit(Self::LEFT_SUPER_BIT);
+    }
+
+    /// Sets the right super key.
+    pub fn set_right_super(&mut self) {
+        self.set_bit(Self::RIGHT_SUPER_BIT);
+    }
+
+    /// Clears the right super key.
+    pub fn clear_right_super(&mut self) {
+        self.clear_bit(Self::RIGHT_SUPER_BIT);
+    }
+
+    /// Sets the state of the **CAPS LOCK** key.
+    pub fn set_caps_lock_pressed(&mut self) {
+        self.set_bit(Self::CAPS_LOCK_PRESSED_BIT);
+    }
+
+    /// Clears the state of the **CAPS LOCK** key.
+    pub fn clear_caps_lock_pressed(&mut self) {
+        self.clear_bit(Self::CAPS_LOCK_PRESSED_BIT);
+    }
+
+    /// Sets the state of the **NUM LOCK** key.
+    pub fn set_num_lock_pressed(&mut self) {
+        self.set_bit(Self::NUM_LOCK_PRESSED_BIT);
+    }
+
+    /// Clears the state of the **NUM LOCK** key.
+    pub fn clear_num_lock_pressed(&mut self) {
+        self.clear_bit(Self::NUM_LOCK_PRESSED_BIT);
+    }
+
+    /// Sets the state of the **SCROLL LOCK** key.
+    pub fn set_scroll_lock_pressed(&mut self) {
+        self.set_bit(Self::SCROLL_LOCK_PRESSED_BIT);
+    }
+
+    /// Clears the state of the **SCROLL LOCK** key.
+    pub fn clear_scroll_lock_pressed(&mut self) {
+        self.clear_bit(Self::SCROLL_LOCK_PRESSED_BIT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `scancodes` to `decoder`, and returns the characters produced.
+    fn decode(decoder: &mut Decoder, scancodes: &[u8]) -> String {
+        scancodes
+            .iter()
+            .filter_map(|&s| decoder.advance(s))
+            .collect()
+    }
+
+    #[test]
+    fn layouts_share_the_modifiers() {
+        let mut decoder = Decoder::default();
+        // Q, shift down, Q, 1, shift up.
+        let keys = [0x10, 0x90, 0x2A, 0x10, 0x90, 0x02, 0x82, 0xAA];
+        assert_eq!(decode(&mut decoder, &keys), "qQ!");
+        decoder.set_layout(&FR);
+        assert_eq!(decode(&mut decoder, &keys), "aA1");
+        assert_eq!(decoder.layout().name, "fr");
+    }
+
+    #[test]
+    fn alt_gr() {
+        let mut decoder = Decoder::new(&FR);
+        // AltGr down, 0, E, AltGr up, 0.
+        let keys = [0xE0, 0x38, 0x0B, 0x8B, 0x12, 0x92, 0xE0, 0xB8, 0x0B];
+        assert_eq!(decode(&mut decoder, &keys), "@eà");
+    }
+
+    #[test]
+    fn caps_lock_only_shifts_letters() {
+        let mut decoder = Decoder::new(&US);
+        assert_eq!(decode(&mut decoder, &[0x3A, 0xBA, 0x10, 0x02]), "Q1");
+        // With shift, letters go back to lowercase.
+        assert_eq!(decode(&mut decoder, &[0x2A, 0x10, 0x02, 0xAA]), "q!");
+        decoder.set_layout(&FR);
+        assert_eq!(decode(&mut decoder, &[0x03, 0x28]), "éù");
+    }
+
+    #[test]
+    fn layouts() {
+        let scancodes = [
+            0x02, 0x0C, 0x10, 0x11, 0x17, 0x1A, 0x1E, 0x1F, 0x27, 0x28, 0x2C, 0x35,
+        ];
+        let cases: [(&Layout, &str); 3] = [
+            (&US, "1-qwi[as;'z/"),
+            (&FR, "&)azi^qsmùw!"),
+            (&DVORAK, "1[',c/aos-;z"),
+        ];
+        for (layout, expected) in cases {
+            let mut decoder = Decoder::new(layout);
+            assert_eq!(
+                decode(&mut decoder, &scancodes),
+                expected,
+                "{}",
+                layout.name
+            );
+        }
+    }
+
+    #[test]
+    fn dvorak_shifted() {
+        let mut decoder = Decoder::new(&DVORAK);
+        let keys = [0x2A, 0x0C, 0x0D, 0x10, 0x1A, 0x1B, 0x24, 0x28, 0x2C, 0xAA];
+        assert_eq!(decode(&mut decoder, &keys), "{}\"?+H_:");
+    }
+
+    /// Returns the events `decoder` produces for `scancodes`, as keys and whether pressed.
+    fn events(decoder: &mut Decoder, scancodes: &[u8]) -> Vec<(Key, bool)> {
+        scancodes
+            .iter()
+            .filter_map(|&s| decoder.advance_event(s))
+            .map(|event| (event.key, event.pressed))
+            .collect()
+    }
+
+    #[test]
+    fn fake_shifts() {
+        let mut decoder = Decoder::default();
+        // The gray up arrow with NUM LOCK on, wrapped in a fake left shift press.
+        decode(&mut decoder, &[0x45, 0xC5]);
+        let keys = [0xE0, 0x2A, 0xE0, 0x48, 0xE0, 0xC8, 0xE0, 0xAA];
+        assert_eq!(
+            events(&mut decoder, &keys),
+            [(Key::Up, true), (Key::Up, false)]
+        );
+        assert!(!decoder.modifiers().shift());
+        // With shift held, the same key is wrapped in a fake release instead.
+        decode(&mut decoder, &[0x45, 0xC5, 0x2A]);
+        let keys = [0xE0, 0xAA, 0xE0, 0x4B, 0xE0, 0xCB, 0xE0, 0x2A];
+        assert_eq!(
+            events(&mut decoder, &keys),
+            [(Key::Left, true), (Key::Left, false)]
+        );
+        assert!(decoder.modifiers().shift());
+        // The right shift gets the same treatment.
+        decode(&mut decoder, &[0xAA, 0xE0, 0x36, 0xE0, 0x4F, 0xE0, 0xB6]);
+        assert!(!decoder.modifiers().shift());
+    }
+
+    #[test]
+    fn key_events() {
+        let mut decoder = Decoder::default();
+        // Escape, F1, F12, the up arrow, and keypad 8 with and without NUM LOCK.
+        let keys = [
+            0x01, 0x81, 0x3B, 0x58, 0xE0, 0x48, 0xE0, 0xC8, 0x48, 0x45, 0xC5, 0x48,
+        ];
+        assert_eq!(
+            events(&mut decoder, &keys),
+            [
+                (Key::Escape, true),
+                (Key::Escape, false),
+                (Key::F(1), true),
+                (Key::F(12), true),
+                (Key::Up, true),
+                (Key::Up, false),
+                (Key::Up, true),
+                (Key::NumLock, true),
+                (Key::NumLock, false),
+                (Key::Keypad('8'), true),
+            ]
+        );
+        // Alt+SysRq, which produces no character.
+        assert_eq!(
+            events(&mut decoder, &[0x38, 0x54, 0xD4, 0xB8]),
+            [
+                (Key::LeftAlt, true),
+                (Key::SysRq, true),
+                (Key::SysRq, false),
+                (Key::LeftAlt, false),
+            ]
+        );
+        assert_eq!(decode(&mut decoder, &[0x54, 0xD4]), "");
+    }
+
+    #[test]
+    fn navigation_keys() {
+        let mut decoder = Decoder::default();
+        // The extended arrows, Home, End, Delete and the pages, pressed then released.
+        let extended = [0x48, 0x50, 0x4B, 0x4D, 0x47, 0x4F, 0x53, 0x49, 0x51];
+        let chars = [
+            keys::UP,
+            keys::DOWN,
+            keys::LEFT,
+            keys::RIGHT,
+            keys::HOME,
+            keys::END,
+            keys::DELETE,
+            keys::PAGE_UP,
+            keys::PAGE_DOWN,
+        ];
+        for (code, c) in extended.into_iter().zip(chars) {
+            assert_eq!(
+                decode(&mut decoder, &[0xE0, code, 0xE0, code | 0x80]),
+                c.to_string()
+            );
+        }
+        // The keypad does the same without NUM LOCK, and its releases produce nothing either.
+        for (code, c) in extended.into_iter().zip(chars) {
+            assert_eq!(decode(&mut decoder, &[code, code | 0x80]), c.to_string());
+        }
+        // With NUM LOCK, it types digits again, and the extended keys do not change.
+        assert_eq!(decode(&mut decoder, &[0x45, 0xC5, 0x48, 0xC8]), "8");
+        assert_eq!(
+            decode(&mut decoder, &[0xE0, 0x48, 0xE0, 0xC8]),
+            keys::UP.to_string()
+        );
+    }
+
+    #[test]
+    fn function_keys() {
+        let mut decoder = Decoder::default();
+        let codes = [
+            0x3B, 0x3C, 0x3D, 0x3E, 0x3F, 0x40, 0x41, 0x42, 0x43, 0x44, 0x57, 0x58,
+        ];
+        for (n, code) in (1..=12).zip(codes) {
+            let c = keys::FUNCTION[usize::from(n) - 1];
+            assert_eq!(decode(&mut decoder, &[code, code | 0x80]), c.to_string());
+            assert_eq!(keys::function(c), Some(n));
+        }
+        assert_eq!(keys::function('a'), None);
+        assert_eq!(keys::function(keys::UP), None);
+    }
+
+    #[test]
+    fn pause() {
+        let mut decoder = Decoder::default();
+        assert_eq!(events(&mut decoder, &PAUSE_SEQUENCE), [(Key::Pause, true)]);
+        assert!(!decoder.modifiers().num_lock());
+        assert!(!decoder.modifiers().control());
+        assert_eq!(decode(&mut decoder, &PAUSE_SEQUENCE), "");
+        // With NUM LOCK on, it stays on.
+        decode(&mut decoder, &[0x45, 0xC5]);
+        assert_eq!(events(&mut decoder, &PAUSE_SEQUENCE), [(Key::Pause, true)]);
+        assert!(decoder.modifiers().num_lock());
+    }
+
+    #[test]
+    fn garbled_pause_resynchronizes() {
+        let mut decoder = Decoder::default();
+        // Cut short by Q, which is still decoded.
+        assert_eq!(
+            events(&mut decoder, &[0xE1, 0x1D, 0x10, 0x90]),
+            [(Key::Char('q'), true), (Key::Char('q'), false)]
+        );
+        // Cut short by an extended key.
+        assert_eq!(
+            events(&mut decoder, &[0xE1, 0x1D, 0x45, 0xE0, 0x48]),
+            [(Key::Up, true)]
+        );
+        // Then a complete sequence works again.
+        assert_eq!(events(&mut decoder, &PAUSE_SEQUENCE), [(Key::Pause, true)]);
+        assert_eq!(decoder.modifiers(), Modifiers::EMPTY);
+    }
+
+    /// Feeds each `(scancode, latched)` step to a new decoder, and checks whether the lock read
+    /// by `lock` is on after it.
+    fn replay_lock(lock: fn(Modifiers) -> bool, steps: &[(u8, bool)]) {
+        let mut decoder = Decoder::default();
+        for (i, &(scancode, latched)) in steps.iter().enumerate() {
+            decoder.advance(scancode);
+            assert_eq!(
+                lock(decoder.modifiers()),
+                latched,
+                "step {i}: {scancode:#04x}"
+            );
+        }
+    }
+
+    #[test]
+    fn lock_keys() {
+        for (make, lock) in [
+            (0x3A, Modifiers::caps_lock as fn(Modifiers) -> bool),
+            (0x45, Modifiers::num_lock),
+            (0x46, Modifiers::scroll_lock),
+        ] {
+            let brk = make | 0x80;
+            replay_lock(
+                lock,
+                &[
+                    // A press toggles, and the release keeps it.
+                    (make, true),
+                    (brk, true),
+                    (make, false),
+                    (brk, false),
+                    // Held down, the make code repeats without toggling again.
+                    (make, true),
+                    (make, true),
+                    (make, true),
+                    (brk, true),
+                    // Two quick presses toggle twice.
+                    (make, false),
+                    (brk, false),
+                    (make, true),
+                    (brk, true),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn locks_are_independent() {
+        let mut decoder = Decoder::default();
+        decode(&mut decoder, &[0x3A, 0xBA, 0x46, 0xC6]);
+        let modifiers = decoder.modifiers();
+        assert!(modifiers.caps_lock() && modifiers.scroll_lock() && !modifiers.num_lock());
+        // Caps lock is undone by shift, on letters only.
+        assert!(modifiers.shifted());
+        assert_eq!(decode(&mut decoder, &[0x1E, 0x2A, 0x1E, 0x02, 0xAA]), "Aa!");
+    }
+
+    #[test]
+    fn modifier_releases() {
+        let mut decoder = Decoder::default();
+        // Left and right shift, control and alt, then super, as make and break codes.
+        let keys: [&[u8]; 6] = [
+            &[0x2A, 0xAA],
+            &[0x36, 0xB6],
+            &[0x1D, 0x9D],
+            &[0xE0, 0x1D, 0xE0, 0x9D],
+            &[0x38, 0xB8],
+            &[0xE0, 0x38, 0xE0, 0xB8],
+        ];
+        for key in keys {
+            let (make, brk) = key.split_at(key.len() / 2);
+            decode(&mut decoder, make);
+            assert_ne!(decoder.modifiers(), Modifiers::EMPTY, "{key:02x?}");
+            decode(&mut decoder, brk);
+            assert_eq!(decoder.modifiers(), Modifiers::EMPTY, "{key:02x?}");
+        }
+        decode(&mut decoder, &[0xE0, 0x5B, 0xE0, 0x5C]);
+        assert!(decoder.modifiers().left_super() && decoder.modifiers().right_super());
+        decode(&mut decoder, &[0xE0, 0xDB, 0xE0, 0xDC]);
+        assert_eq!(decoder.modifiers(), Modifiers::EMPTY);
+        // Both shifts held: releasing one keeps the keyboard shifted.
+        assert_eq!(
+            decode(&mut decoder, &[0x2A, 0x36, 0xAA, 0x10, 0xB6, 0x10]),
+            "Qq"
+        );
+    }
+
+    #[test]
+    fn garbage() {
+        let mut decoder = Decoder::default();
+        // Codes of no key, releases of keys never pressed, repeated prefixes, and the keyboard
+        // answers to commands.
+        let garbage = [
+            0x00, 0x55, 0x7F, 0xFF, 0x90, 0xAA, 0xB6, 0xE0, 0xE0, 0x10, 0xE0, 0x00, 0xFA, 0xFE,
+        ];
+        decode(&mut decoder, &garbage);
+        assert_eq!(decoder.modifiers(), Modifiers::EMPTY);
+        // Decoding goes on normally afterwards.
+        assert_eq!(decode(&mut decoder, &[0x23, 0xA3, 0x17, 0x97]), "hi");
+        let mut decoder = Decoder::default();
+        for byte in 0..=u8::MAX {
+            decoder.advance(byte);
+            decoder.advance(byte);
+        }
+        decoder.reset();
+        // Caps lock was pressed once along the way, and stays on.
+        assert!(decoder.modifiers().caps_lock());
+        assert_eq!(decode(&mut decoder, &[0x10]), "Q");
+    }
+
+    #[test]
+    fn modifiers_display() {
+        assert_eq!(Modifiers::EMPTY.to_string(), "none");
+        let mut decoder = Decoder::default();
+        decode(&mut decoder, &[0x2A, 0xE0, 0x1D, 0x3A, 0xBA, 0x45, 0xC5]);
+        let modifiers = decoder.modifiers();
+        assert_eq!(modifiers.to_string(), "LSHIFT RCTRL CAPS NUM");
+        assert_eq!(
+            format!("{modifiers:?}"),
+            "Modifiers(0x0306 LSHIFT RCTRL CAPS NUM)"
+        );
+        assert_eq!(Modifiers::from_bits(modifiers.bits()), modifiers);
+        assert_eq!(Modifiers::from_bits(1 << 10).to_string(), "SCROLL");
+    }
+
+    #[test]
+    fn reset() {
+        let mut decoder = Decoder::default();
+        // Caps lock, then shift and control held, and half an extended key.
+        decode(&mut decoder, &[0x3A, 0xBA, 0x2A, 0x1D, 0xE0]);
+        decoder.reset();
+        let modifiers = decoder.modifiers();
+        assert!(modifiers.caps_lock() && !modifiers.shift() && !modifiers.control());
+        assert_eq!(decode(&mut decoder, &[0x10]), "Q");
+    }
+
+    #[test]
+    fn char_keys_ignore_modifiers() {
+        let mut decoder = Decoder::new(&DVORAK);
+        // Left control down, the key producing `c`, left control up.
+        let event = decoder.advance_event(0x1D).unwrap();
+        assert!(event.pressed && event.modifiers.control());
+        let event = decoder.advance_event(0x17).unwrap();
+        assert_eq!(event.key, Key::Char('c'));
+        assert!(event.modifiers.control());
+        assert_eq!(decoder.char(event), Some('c'));
+        let event = decoder.advance_event(0x9D).unwrap();
+        assert_eq!((event.key, event.pressed), (Key::LeftControl, false));
+        assert!(!event.modifiers.control());
+        // Shift changes the character, not the key.
+        decoder.advance_event(0x2A);
+        let event = decoder.advance_event(0x17).unwrap();
+        assert_eq!(event.key, Key::Char('c'));
+        assert_eq!(decoder.char(event), Some('C'));
+    }
+
+    #[test]
+    fn keys_are_unique_in_layouts() {
+        for layout in LAYOUTS {
+            for (i, entry) in layout.entries.iter().enumerate() {
+                assert!(
+                    layout.entries[i + 1..]
+                        .iter()
+                        .all(|other| other.normal != entry.normal
+                            && other.scancode != entry.scancode),
+                    "{}: {:?}",
+                    layout.name,
+                    entry.normal,
+                );
+            }
+        }
+    }
+
+    /// Returns the set 1 scancodes for the set 2 scancodes `bytes`.
+    fn translate(bytes: &[u8]) -> Vec<u8> {
+        let mut set2 = Set2::new();
+        bytes.iter().filter_map(|&b| set2.translate(b)).collect()
+    }
+
+    #[test]
+    fn set2() {
+        // A, then Escape pressed and released.
+        assert_eq!(
+            translate(&[0x1C, 0xF0, 0x1C, 0x76, 0xF0, 0x76]),
+            [0x1E, 0x9E, 0x01, 0x81]
+        );
+        // The up arrow and the right control key.
+        assert_eq!(
+            translate(&[0xE0, 0x75, 0xE0, 0xF0, 0x75, 0xE0, 0x14, 0xE0, 0xF0, 0x14]),
+            [0xE0, 0x48, 0xE0, 0xC8, 0xE0, 0x1D, 0xE0, 0x9D]
+        );
+        // Pause.
+        assert_eq!(
+            translate(&[0xE1, 0x14, 0x77, 0xE1, 0xF0, 0x14, 0xF0, 0x77]),
+            [0xE1, 0x1D, 0x45, 0xE1, 0x9D, 0xC5]
+        );
+        // F7, the only code past 0x7F, and codes of no key.
+        assert_eq!(
+            translate(&[0x83, 0x00, 0x02, 0xF0, 0x90, 0x83]),
+            [0x41, 0x41]
+        );
+        let mut decoder = Decoder::default();
+        let scancodes = translate(&[0x12, 0x15, 0xF0, 0x15, 0xF0, 0x12, 0x16, 0xF0, 0x16]);
+        assert_eq!(decode(&mut decoder, &scancodes), "Q1");
+    }
+
+    #[test]
+    fn typematic() {
+        assert_eq!(typematic_byte(250, 30), 0x00);
+        assert_eq!(typematic_byte(1000, 2), 0x7F);
+        assert_eq!(typematic_byte(500, 10), 0x2C);
+        assert_eq!(typematic_rate_mcps(0x2C), 9_992);
+        // The closest delay and rate are picked.
+        assert_eq!(typematic_byte(380, 25), 0x22);
+        assert_eq!(typematic_byte(0, 100), 0x00);
+        assert_eq!(typematic_byte(5000, 0), 0x7F);
+        for byte in [0x00, 0x20, 0x40, 0x60] {
+            assert_eq!(typematic_byte(typematic_delay_ms(byte), 30), byte);
+        }
+        for byte in 0..0x20 {
+            let rate = typematic_rate_mcps(byte);
+            assert!((1_990..=30_000).contains(&rate), "{byte:#x}: {rate}");
+        }
+    }
+
+    #[test]
+    fn layout_names() {
+        assert_eq!(Layout::from_name("us").map(|l| l.name), Some("us"));
+        assert_eq!(Layout::from_name("fr").map(|l| l.name), Some("fr"));
+        assert_eq!(Layout::from_name("dvorak").map(|l| l.name), Some("dvorak"));
+        assert!(Layout::from_name("de").is_none());
+    }
+}
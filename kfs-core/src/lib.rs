@@ -0,0 +1,27 @@
+//! Hardware-independent building blocks of the kernel.
+//!
+//! Nothing in this crate touches I/O ports or fixed memory addresses, which makes it usable
+//! from experiment binaries and testable on the host with `cargo test`.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod alias;
+pub mod ansi;
+pub mod calc;
+pub mod cmdline;
+pub mod cpuid;
+pub mod descriptor;
+pub mod frame;
+pub mod hexdump;
+pub mod keyboard;
+pub mod log_ring;
+pub mod mutex;
+pub mod notes;
+pub mod once;
+pub mod registers;
+pub mod rtc;
+pub mod serial_keys;
+pub mod shell;
+pub mod util;
+pub mod vga_chars;
+pub mod wrap;
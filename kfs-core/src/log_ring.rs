@@ -0,0 +1,182 @@
+//! A ring buffer of text, for the kernel log.
+
+/// A ring buffer of `N` bytes of text, which drops its oldest lines to make room for new text.
+///
+/// Every byte ever pushed has a sequence number, counting from 0. Readers use them to follow
+/// the ring while it is being written to, and to notice what was dropped in the meantime.
+pub struct LogRing<const N: usize> {
+    buffer: [u8; N],
+    /// The index in `buffer` of the oldest byte.
+    start: usize,
+    /// The number of bytes in the ring.
+    len: usize,
+    /// The sequence number of the oldest byte.
+    first: u64,
+}
+
+impl<const N: usize> LogRing<N> {
+    pub const fn new() -> Self {
+        LogRing {
+            buffer: [0; N],
+            start: 0,
+            len: 0,
+            first: 0,
+        }
+    }
+
+    /// Returns the number of bytes in the ring.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the ring is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the sequence number of the oldest byte in the ring.
+    pub fn first(&self) -> u64 {
+        self.first
+    }
+
+    /// Returns the sequence number the next byte pushed will get.
+    pub fn end(&self) -> u64 {
+        self.first + self.len as u64
+    }
+
+    /// Appends `bytes`, dropping the oldest bytes to make room for them.
+    ///
+    /// Whole lines are dropped: the rest of a partially dropped line goes too, unless that
+    /// line is the last one in the ring. Only the end of text longer than the ring is kept.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if bytes.len() >= N {
+            let skipped = bytes.len() - N;
+            self.first = self.end() + skipped as u64;
+            self.start = 0;
+            self.len = N;
+            self.buffer.copy_from_slice(&bytes[skipped..]);
+            return;
+        }
+        let free = N - self.len;
+        if bytes.len() > free {
+            self.drop_oldest(bytes.len() - free);
+        }
+        let end = (self.start + self.len) % N;
+        let (head, tail) = bytes.split_at(bytes.len().min(N - end));
+        self.buffer[end..][..head.len()].copy_from_slice(head);
+        self.buffer[..tail.len()].copy_from_slice(tail);
+        self.len += bytes.len();
+    }
+
+    /// Drops at least `count` of the oldest bytes, and up to the end of the line they stop in.
+    fn drop_oldest(&mut self, count: usize) {
+        let count = if count == 0 || self.byte(count - 1) == b'\n' {
+            count
+        } else {
+            (count..self.len)
+                .find(|&i| self.byte(i) == b'\n')
+                .map_or(count, |newline| newline + 1)
+        };
+        self.start = (self.start + count) % N;
+        self.len -= count;
+        self.first += count as u64;
+    }
+
+    /// Returns the `i`-th oldest byte.
+    fn byte(&self, i: usize) -> u8 {
+        self.buffer[(self.start + i) % N]
+    }
+
+    /// Copies the bytes starting at the sequence number `seq` to `out`, as many as fit.
+    ///
+    /// If the byte `seq` was dropped already, the copy starts at the oldest byte instead.
+    /// Returns the sequence number of the first byte copied, and the number of bytes copied.
+    pub fn read(&self, seq: u64, out: &mut [u8]) -> (u64, usize) {
+        let seq = seq.clamp(self.first, self.end());
+        let offset = (seq - self.first) as usize;
+        let count = out.len().min(self.len - offset);
+        for (i, byte) in out[..count].iter_mut().enumerate() {
+            *byte = self.byte(offset + i);
+        }
+        (seq, count)
+    }
+
+    /// Returns the contents of the ring, oldest first, split in two where it wraps around.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        if self.start + self.len <= N {
+            (&self.buffer[self.start..][..self.len], &[])
+        } else {
+            let (wrapped, head) = self.buffer.split_at(self.start);
+            (head, &wrapped[..self.start + self.len - N])
+        }
+    }
+}
+
+impl<const N: usize> Default for LogRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the contents of `ring`, oldest first.
+    fn contents<const N: usize>(ring: &LogRing<N>) -> String {
+        let (head, tail) = ring.as_slices();
+        String::from_utf8([head, tail].concat()).unwrap()
+    }
+
+    #[test]
+    fn push() {
+        let mut ring = LogRing::<16>::new();
+        ring.push(b"one\n");
+        ring.push(b"two\n");
+        assert_eq!(contents(&ring), "one\ntwo\n");
+        assert_eq!((ring.first(), ring.end()), (0, 8));
+    }
+
+    #[test]
+    fn drops_whole_lines() {
+        let mut ring = LogRing::<16>::new();
+        ring.push(b"first\nsecond\n");
+        ring.push(b"third\n");
+        assert_eq!(contents(&ring), "second\nthird\n");
+        assert_eq!(ring.first(), 6);
+        ring.push(b"fourth\n");
+        assert_eq!(contents(&ring), "third\nfourth\n");
+    }
+
+    #[test]
+    fn drops_part_of_a_single_line() {
+        let mut ring = LogRing::<8>::new();
+        ring.push(b"abcdef");
+        ring.push(b"ghij");
+        assert_eq!(contents(&ring), "cdefghij");
+        assert_eq!(ring.first(), 2);
+    }
+
+    #[test]
+    fn keeps_the_end_of_long_text() {
+        let mut ring = LogRing::<4>::new();
+        ring.push(b"ab");
+        ring.push(b"cdefgh");
+        assert_eq!(contents(&ring), "efgh");
+        assert_eq!((ring.first(), ring.end()), (4, 8));
+    }
+
+    #[test]
+    fn read_follows_the_ring() {
+        let mut ring = LogRing::<8>::new();
+        ring.push(b"aaa\nbbb\n");
+        let mut out = [0; 3];
+        assert_eq!(ring.read(0, &mut out), (0, 3));
+        assert_eq!(&out, b"aaa");
+        ring.push(b"cc\n");
+        // The first line was dropped while reading it.
+        assert_eq!(ring.read(3, &mut out), (4, 3));
+        assert_eq!(&out, b"bbb");
+        assert_eq!(ring.read(ring.end(), &mut out), (11, 0));
+    }
+}
@@ -32,6 +32,14 @@ impl<T: ?Sized> Mutex<T> {
         MutexGuard { mutex: self }
     }
 
+    /// Locks the mutex, or returns `None` if it is already locked.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+            .then(|| MutexGuard { mutex: self })
+    }
+
     /// # Safety
     ///
     /// Fait gaffe.
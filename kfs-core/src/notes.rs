@@ -0,0 +1,174 @@
+//! Parsing of notes for the speaker, such as `c4 e g c5:400 r:100`.
+//!
+//! A note is a letter from `a` to `g`, optionally followed by `#` or `b` and an octave from 1 to
+//! 8, and then by `:` and a length in milliseconds. `r` is a rest, which only takes a length. The
+//! octave and the length carry over to the next notes until changed, starting from the fourth
+//! octave and [`DEFAULT_MS`].
+
+/// The length of a note that does not give one, before any other note does.
+pub const DEFAULT_MS: u32 = 200;
+
+/// The octave of a note that does not give one, before any other note does.
+const DEFAULT_OCTAVE: u32 = 4;
+
+/// The frequencies of the notes of the fourth octave from C to B, in hundredths of Hz.
+const OCTAVE_4: [u32; 12] = [
+    26_163, 27_718, 29_366, 31_113, 32_963, 34_923, 36_999, 39_200, 41_530, 44_000, 46_616, 49_388,
+];
+
+/// A note to play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note {
+    /// The frequency of the note in Hz, or `None` for a rest.
+    pub freq: Option<u32>,
+    /// How long the note lasts, in milliseconds.
+    pub ms: u32,
+}
+
+/// Parses notes one at a time, remembering the octave and the length of the previous ones.
+#[derive(Debug, Clone)]
+pub struct NoteParser {
+    octave: u32,
+    ms: u32,
+}
+
+impl NoteParser {
+    pub const fn new() -> Self {
+        NoteParser {
+            octave: DEFAULT_OCTAVE,
+            ms: DEFAULT_MS,
+        }
+    }
+
+    /// Parses a single note, or returns `None` if it is invalid.
+    ///
+    /// A length of 0 is invalid. Nothing carries over from an invalid note.
+    pub fn parse(&mut self, s: &str) -> Option<Note> {
+        let (pitch, ms) = match s.split_once(':') {
+            Some((pitch, ms)) => (pitch, ms.parse().ok().filter(|&ms| ms > 0)?),
+            None => (s, self.ms),
+        };
+        let mut chars = pitch.chars();
+        let semitone: i32 = match chars.next()?.to_ascii_lowercase() {
+            'r' if chars.as_str().is_empty() => {
+                self.ms = ms;
+                return Some(Note { freq: None, ms });
+            }
+            'c' => 0,
+            'd' => 2,
+            'e' => 4,
+            'f' => 5,
+            'g' => 7,
+            'a' => 9,
+            'b' => 11,
+            _ => return None,
+        };
+        let rest = chars.as_str();
+        let (semitone, rest) = if let Some(rest) = rest.strip_prefix('#') {
+            (semitone + 1, rest)
+        } else if let Some(rest) = rest.strip_prefix('b') {
+            (semitone - 1, rest)
+        } else {
+            (semitone, rest)
+        };
+        let octave = match rest {
+            "" => self.octave,
+            octave => octave
+                .parse()
+                .ok()
+                .filter(|octave| (1..=8).contains(octave))?,
+        };
+        // A sharp B and a flat C belong to the next and the previous octaves.
+        let (octave, semitone) = match semitone {
+            -1 => (octave - 1, 11),
+            12 => (octave + 1, 0),
+            semitone => (octave, semitone as u32),
+        };
+        let centihertz = OCTAVE_4[semitone as usize] << octave >> DEFAULT_OCTAVE;
+        self.octave = octave;
+        self.ms = ms;
+        Some(Note {
+            freq: Some((centihertz + 50) / 100),
+            ms,
+        })
+    }
+}
+
+impl Default for NoteParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freqs(notes: &str) -> Vec<Option<u32>> {
+        let mut parser = NoteParser::new();
+        notes
+            .split_whitespace()
+            .map(|note| parser.parse(note).unwrap().freq)
+            .collect()
+    }
+
+    #[test]
+    fn pitches() {
+        assert_eq!(
+            freqs("a c4 c#4 db4 e5 b3 A1 g8"),
+            [440, 262, 277, 277, 659, 247, 55, 6272].map(Some)
+        );
+        assert_eq!(freqs("b#3 cb5 r"), [Some(262), Some(494), None]);
+    }
+
+    #[test]
+    fn octave_and_length_carry_over() {
+        let mut parser = NoteParser::new();
+        assert_eq!(
+            parser.parse("c5:400"),
+            Some(Note {
+                freq: Some(523),
+                ms: 400
+            })
+        );
+        assert_eq!(
+            parser.parse("e"),
+            Some(Note {
+                freq: Some(659),
+                ms: 400
+            })
+        );
+        assert_eq!(
+            parser.parse("r:100"),
+            Some(Note {
+                freq: None,
+                ms: 100
+            })
+        );
+        assert_eq!(
+            parser.parse("g3"),
+            Some(Note {
+                freq: Some(196),
+                ms: 100
+            })
+        );
+        assert_eq!(parser.parse("a0:50"), None);
+        assert_eq!(
+            parser.parse("a").unwrap(),
+            Note {
+                freq: Some(220),
+                ms: 100
+            }
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let mut parser = NoteParser::new();
+        for note in [
+            "", "h", "c9", "c0", "c##", "c4:", "c4:0", "c4:x", "r4", "cx", ":100",
+        ] {
+            assert_eq!(parser.parse(note), None, "{note}");
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const UNINIT: u8 = 0;
+const INITIALIZING: u8 = 1;
+const INIT: u8 = 2;
+
+/// A global that is initialized exactly once, at runtime.
+///
+/// Dereferencing an uninitialized [`Once`] panics. Code that may run before initialization
+/// (such as the panic handler) should use [`Once::get`] instead.
+pub struct Once<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send> Send for Once<T> {}
+unsafe impl<T: Send + Sync> Sync for Once<T> {}
+
+impl<T> Once<T> {
+    pub const fn new() -> Self {
+        Once {
+            state: AtomicU8::new(UNINIT),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Initializes the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value has already been initialized.
+    #[track_caller]
+    pub fn init(&self, value: T) {
+        assert!(
+            self.state
+                .compare_exchange(UNINIT, INITIALIZING, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok(),
+            "Once already initialized"
+        );
+        // Safety: the state machine above guarantees that nobody else accesses the value.
+        unsafe { (*self.value.get()).write(value) };
+        self.state.store(INIT, Ordering::Release);
+    }
+
+    /// Returns the value, or `None` if it has not been initialized yet.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) != INIT {
+            return None;
+        }
+        // Safety: the value is initialized and never mutated again.
+        Some(unsafe { (*self.value.get()).assume_init_ref() })
+    }
+}
+
+impl<T> Default for Once<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for Once<T> {
+    type Target = T;
+
+    #[inline]
+    #[track_caller]
+    fn deref(&self) -> &Self::Target {
+        self.get().expect("Once used before initialization")
+    }
+}
@@ -0,0 +1,171 @@
+//! Formatting of a snapshot of the registers of the CPU.
+//!
+//! ```text
+//! eax 00000001  ebx 0010a000  ecx 00000000  edx 000003d5
+//! esi 00000000  edi 00000000  ebp 00117f58  esp 00117f30
+//! eip 00102c4e
+//! eflags 00200246  PF ZF IF ID  IOPL 0
+//! cs 0008  ds 0010  es 0010  fs 0010  gs 0010  ss 0018
+//! cr0 00000011  cr2 00000000  cr3 00000000  cr4 00000000
+//! ```
+
+use core::fmt;
+
+/// The names of the flags of EFLAGS, by bit. IOPL takes bits 12 and 13.
+pub const EFLAGS_NAMES: [(u32, &str); 16] = [
+    (0, "CF"),
+    (2, "PF"),
+    (4, "AF"),
+    (6, "ZF"),
+    (7, "SF"),
+    (8, "TF"),
+    (9, "IF"),
+    (10, "DF"),
+    (11, "OF"),
+    (14, "NT"),
+    (16, "RF"),
+    (17, "VM"),
+    (18, "AC"),
+    (19, "VIF"),
+    (20, "VIP"),
+    (21, "ID"),
+];
+
+/// The value of EFLAGS, displayed with the names of the flags set and the I/O privilege level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eflags(pub u32);
+
+impl fmt::Display for Eflags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08x} ", self.0)?;
+        for (bit, name) in EFLAGS_NAMES {
+            if self.0 >> bit & 1 != 0 {
+                write!(f, " {name}")?;
+            }
+        }
+        write!(f, "  IOPL {}", self.0 >> 12 & 0b11)
+    }
+}
+
+/// A snapshot of the registers of the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Registers {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+    pub esi: u32,
+    pub edi: u32,
+    pub ebp: u32,
+    pub esp: u32,
+    pub eip: u32,
+    pub eflags: u32,
+    pub cs: u16,
+    pub ds: u16,
+    pub es: u16,
+    pub fs: u16,
+    pub gs: u16,
+    pub ss: u16,
+    pub cr0: u32,
+    pub cr2: u32,
+    pub cr3: u32,
+    /// CR4, unless the CPU is too old to have it.
+    pub cr4: Option<u32>,
+}
+
+/// Writes the registers in aligned columns, one group per line.
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            [
+                ("eax", self.eax),
+                ("ebx", self.ebx),
+                ("ecx", self.ecx),
+                ("edx", self.edx),
+            ],
+            [
+                ("esi", self.esi),
+                ("edi", self.edi),
+                ("ebp", self.ebp),
+                ("esp", self.esp),
+            ],
+        ];
+        for row in rows {
+            let [first, rest @ ..] = row;
+            write!(f, "{} {:08x}", first.0, first.1)?;
+            for (name, value) in rest {
+                write!(f, "  {name} {value:08x}")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "eip {:08x}", self.eip)?;
+        writeln!(f, "eflags {}", Eflags(self.eflags))?;
+        writeln!(
+            f,
+            "cs {:04x}  ds {:04x}  es {:04x}  fs {:04x}  gs {:04x}  ss {:04x}",
+            self.cs, self.ds, self.es, self.fs, self.gs, self.ss
+        )?;
+        write!(
+            f,
+            "cr0 {:08x}  cr2 {:08x}  cr3 {:08x}  cr4 ",
+            self.cr0, self.cr2, self.cr3
+        )?;
+        match self.cr4 {
+            Some(cr4) => write!(f, "{cr4:08x}"),
+            None => f.write_str("--------"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eflags() {
+        assert_eq!(
+            Eflags(0x0020_0246).to_string(),
+            "00200246  PF ZF IF ID  IOPL 0"
+        );
+        assert_eq!(Eflags(0x0000_3002).to_string(), "00003002   IOPL 3");
+        assert_eq!(
+            Eflags(0x0000_0ed5).to_string(),
+            "00000ed5  CF PF AF ZF SF IF DF OF  IOPL 0"
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        let registers = Registers {
+            eax: 1,
+            ebx: 0x10a000,
+            edx: 0x3d5,
+            ebp: 0x117f58,
+            esp: 0x117f30,
+            eip: 0x102c4e,
+            eflags: 0x200246,
+            cs: 0x08,
+            ds: 0x10,
+            es: 0x10,
+            fs: 0x10,
+            gs: 0x10,
+            ss: 0x18,
+            cr0: 0x11,
+            ..Registers::default()
+        };
+        assert_eq!(
+            registers.to_string(),
+            "eax 00000001  ebx 0010a000  ecx 00000000  edx 000003d5\n\
+             esi 00000000  edi 00000000  ebp 00117f58  esp 00117f30\n\
+             eip 00102c4e\n\
+             eflags 00200246  PF ZF IF ID  IOPL 0\n\
+             cs 0008  ds 0010  es 0010  fs 0010  gs 0010  ss 0018\n\
+             cr0 00000011  cr2 00000000  cr3 00000000  cr4 --------"
+        );
+        let registers = Registers {
+            cr4: Some(0x20),
+            ..registers
+        };
+        assert!(registers.to_string().ends_with("cr4 00000020"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Decoding of the date and time kept by the CMOS real-time clock.
+//!
+//! The clock stores each field in a register of its own, either in binary or in BCD, and the
+//! hour either on 24 hours or on 12 hours with a PM flag, as register B says.
+
+use core::fmt;
+
+/// Register B: the hour is on 24 hours rather than 12.
+pub const STATUS_B_24_HOUR: u8 = 1 << 1;
+/// Register B: the fields are in binary rather than BCD.
+pub const STATUS_B_BINARY: u8 = 1 << 2;
+
+/// The flag of the hour register marking the afternoon, on 12 hours.
+const HOUR_PM: u8 = 0x80;
+
+/// The century assumed when the clock has no century register.
+const DEFAULT_CENTURY: u16 = 20;
+
+/// The registers of the clock holding the date and time, as read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub second: u8,
+    pub minute: u8,
+    pub hour: u8,
+    pub day: u8,
+    pub month: u8,
+    /// The year within the century.
+    pub year: u8,
+    /// The century, if the clock has a register for it.
+    pub century: Option<u8>,
+    /// Register B, which tells how the other ones are encoded.
+    pub status_b: u8,
+}
+
+/// A date and time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// Returns the binary value of the BCD `value`.
+const fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+impl Registers {
+    /// Returns the date and time the registers hold.
+    ///
+    /// A century outside of 19 to 99 is taken for a register the clock does not really have,
+    /// and the 21st century is assumed instead.
+    pub fn decode(&self) -> DateTime {
+        let binary = self.status_b & STATUS_B_BINARY != 0;
+        let decode = |value: u8| if binary { value } else { from_bcd(value) };
+
+        let pm = self.hour & HOUR_PM != 0;
+        let mut hour = decode(self.hour & !HOUR_PM);
+        if self.status_b & STATUS_B_24_HOUR == 0 {
+            // 12 AM is midnight, and 12 PM is noon.
+            hour = hour % 12 + if pm { 12 } else { 0 };
+        }
+
+        let century = self
+            .century
+            .map(|century| decode(century) as u16)
+            .filter(|century| (19..=99).contains(century))
+            .unwrap_or(DEFAULT_CENTURY);
+        DateTime {
+            year: century * 100 + decode(self.year) as u16,
+            month: decode(self.month),
+            day: decode(self.day),
+            hour,
+            minute: decode(self.minute),
+            second: decode(self.second),
+        }
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registers(status_b: u8) -> Registers {
+        Registers {
+            second: 0x59,
+            minute: 0x07,
+            hour: 0x13,
+            day: 0x31,
+            month: 0x12,
+            year: 0x99,
+            century: Some(0x19),
+            status_b,
+        }
+    }
+
+    #[test]
+    fn bcd() {
+        let date = registers(STATUS_B_24_HOUR).decode();
+        assert_eq!(date.to_string(), "1999-12-31 13:07:59");
+    }
+
+    #[test]
+    fn binary() {
+        let registers = Registers {
+            second: 5,
+            minute: 30,
+            hour: 23,
+            day: 2,
+            month: 1,
+            year: 24,
+            century: Some(20),
+            status_b: STATUS_B_24_HOUR | STATUS_B_BINARY,
+        };
+        assert_eq!(registers.decode().to_string(), "2024-01-02 23:30:05");
+    }
+
+    #[test]
+    fn twelve_hours() {
+        let at = |hour| {
+            let registers = Registers {
+                hour,
+                ..registers(0)
+            };
+            registers.decode().hour
+        };
+        assert_eq!(at(0x12), 0);
+        assert_eq!(at(0x01), 1);
+        assert_eq!(at(0x11), 11);
+        assert_eq!(at(0x12 | HOUR_PM), 12);
+        assert_eq!(at(0x01 | HOUR_PM), 13);
+        assert_eq!(at(0x11 | HOUR_PM), 23);
+
+        let registers = Registers {
+            hour: 7 | HOUR_PM,
+            ..registers(STATUS_B_BINARY)
+        };
+        assert_eq!(registers.decode().hour, 19);
+    }
+
+    #[test]
+    fn missing_century() {
+        for century in [None, Some(0x00), Some(0xFF)] {
+            let registers = Registers {
+                century,
+                year: 0x26,
+                ..registers(STATUS_B_24_HOUR)
+            };
+            assert_eq!(registers.decode().year, 2026);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! Decoding of the bytes a host terminal sends over a serial line into key events.
+//!
+//! Text arrives as ASCII, with the control keys as C0 characters, and the other keys as escape
+//! sequences: `ESC [ A` for the up arrow, `ESC [ 3 ~` for delete, `ESC O P` for F1, and so on. A
+//! byte following `ESC` on its own is that key with alt held. Since the **ESCAPE** key sends a
+//! lone `ESC`, it is only reported once nothing followed it for [`ESCAPE_TIMEOUT_NS`].
+//!
+//! The events are the ones the PS/2 keyboard would produce for the same keys, as presses only:
+//! a printable key is found in the [`Layout`] the events are for, along with the modifiers
+//! producing its character.
+
+use crate::keyboard::{Key, KeyEvent, Layout, Modifiers};
+
+/// How long a lone `ESC` waits for the rest of a sequence before it is the **ESCAPE** key, in
+/// nanoseconds. Terminals send sequences in one write, which arrives well within this.
+pub const ESCAPE_TIMEOUT_NS: u64 = 50_000_000;
+
+/// The most bytes kept from an escape sequence after `ESC`. Longer sequences are dropped.
+const MAX_SEQUENCE_LEN: usize = 16;
+
+/// An escape sequence the decoder does not know, which was dropped.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Sequence {
+    bytes: [u8; MAX_SEQUENCE_LEN],
+    len: usize,
+}
+
+impl Sequence {
+    const fn new() -> Self {
+        Sequence {
+            bytes: [0; MAX_SEQUENCE_LEN],
+            len: 0,
+        }
+    }
+
+    /// Returns the bytes of the sequence after `ESC`, up to [`MAX_SEQUENCE_LEN`].
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Appends `byte`, and returns whether it fit.
+    fn push(&mut self, byte: u8) -> bool {
+        let Some(slot) = self.bytes.get_mut(self.len) else {
+            return false;
+        };
+        *slot = byte;
+        self.len += 1;
+        true
+    }
+}
+
+/// Shows the sequence as `ESC` followed by its bytes, with the control characters escaped.
+impl core::fmt::Debug for Sequence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("ESC")?;
+        for &byte in self.as_bytes() {
+            write!(f, " {}", char::from(byte).escape_debug())?;
+        }
+        Ok(())
+    }
+}
+
+/// What the decoder makes of the bytes received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// A key was pressed.
+    Key(KeyEvent),
+    /// An escape sequence the decoder does not know was dropped.
+    Unknown(Sequence),
+}
+
+/// The current state of the state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// No sequence is under way.
+    Ground,
+    /// `ESC` was received, at the time in the field.
+    Escape(u64),
+    /// `ESC [` or `ESC O` was received, at the time in the field, possibly followed by
+    /// parameters.
+    Sequence(u64),
+}
+
+/// Turns the bytes received from a host terminal into key events.
+#[derive(Debug)]
+pub struct Decoder {
+    state: State,
+    /// The bytes of the sequence under way, after `ESC`.
+    sequence: Sequence,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Decoder {
+            state: State::Ground,
+            sequence: Sequence::new(),
+        }
+    }
+
+    /// Decodes `byte`, received at `now_ns`, and passes what it completes to `emit`, with the
+    /// printable keys looked up in `layout`.
+    ///
+    /// Bytes above ASCII, and the characters `layout` has no key for, are dropped.
+    pub fn advance(
+        &mut self,
+        byte: u8,
+        now_ns: u64,
+        layout: &Layout,
+        emit: &mut impl FnMut(Input),
+    ) {
+        self.poll(now_ns, emit);
+        match self.state {
+            State::Ground if byte == 0x1B => {
+                self.state = State::Escape(now_ns);
+                self.sequence = Sequence::new();
+            }
+            State::Ground => {
+                if let Some(event) = byte_event(byte, Modifiers::EMPTY, layout) {
+                    emit(Input::Key(event));
+                }
+            }
+            State::Escape(since) => match byte {
+                b'[' | b'O' => {
+                    self.sequence.push(byte);
+                    self.state = State::Sequence(since);
+                }
+                // Alt with the escape key sends it twice: the first one is a key of its own.
+                0x1B => {
+                    emit(Input::Key(press(Key::Escape, Modifiers::EMPTY)));
+                    self.state = State::Escape(now_ns);
+                }
+                _ => {
+                    self.state = State::Ground;
+                    let mut alt = Modifiers::EMPTY;
+                    alt.set_left_alt();
+                    if let Some(event) = byte_event(byte, alt, layout) {
+                        emit(Input::Key(event));
+                    }
+                }
+            },
+            State::Sequence(_) => {
+                if !self.sequence.push(byte) {
+                    self.state = State::Ground;
+                    emit(Input::Unknown(self.sequence));
+                    return;
+                }
+                // Parameters and intermediate bytes come before the final byte.
+                if (0x20..0x40).contains(&byte) {
+                    return;
+                }
+                self.state = State::Ground;
+                emit(match sequence_event(self.sequence.as_bytes()) {
+                    Some(event) => Input::Key(event),
+                    None => Input::Unknown(self.sequence),
+                });
+            }
+        }
+    }
+
+    /// Completes what has waited for more bytes for [`ESCAPE_TIMEOUT_NS`] at `now_ns`: a lone
+    /// `ESC` is the **ESCAPE** key, and a sequence cut short is dropped.
+    pub fn poll(&mut self, now_ns: u64, emit: &mut impl FnMut(Input)) {
+        match self.state {
+            State::Escape(since) if now_ns.saturating_sub(since) >= ESCAPE_TIMEOUT_NS => {
+                self.state = State::Ground;
+                emit(Input::Key(press(Key::Escape, Modifiers::EMPTY)));
+            }
+            State::Sequence(since) if now_ns.saturating_sub(since) >= ESCAPE_TIMEOUT_NS => {
+                self.state = State::Ground;
+                emit(Input::Unknown(self.sequence));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the press of `key` with `modifiers`.
+fn press(key: Key, modifiers: Modifiers) -> KeyEvent {
+    KeyEvent {
+        key,
+        pressed: true,
+        modifiers,
+    }
+}
+
+/// Returns the press sending the byte `byte` outside of a sequence, with `modifiers` held on
+/// top of the ones it needs.
+fn byte_event(byte: u8, mut modifiers: Modifiers, layout: &Layout) -> Option<KeyEvent> {
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        b' ' => Key::Space,
+        0x08 | 0x7F => Key::Backspace,
+        0x1B => Key::Escape,
+        // CTRL with a letter sends the letter minus 0x60.
+        0x01..=0x1A => {
+            modifiers.set_left_control();
+            let letter = char::from(byte + 0x60);
+            layout.entries.iter().find(|entry| entry.normal == letter)?;
+            Key::Char(letter)
+        }
+        0x21..=0x7E => {
+            let c = char::from(byte);
+            let entry = layout.entries.iter().find(|entry| {
+                entry.normal == c || entry.shifted == Some(c) || entry.alt_gr == Some(c)
+            })?;
+            if entry.normal != c {
+                // Caps lock is not known here, so letters are told apart by shift alone.
+                if entry.shifted == Some(c) {
+                    modifiers.set_left_shift();
+                } else {
+                    modifiers.set_right_alt();
+                }
+            }
+            Key::Char(entry.normal)
+        }
+        _ => return None,
+    };
+    Some(press(key, modifiers))
+}
+
+/// Returns the press sending the escape sequence `sequence`, given without its `ESC`, if it is
+/// known.
+///
+/// Both the `ESC [` and the `ESC O` forms of the cursor keys are known, as terminals switch
+/// between them, along with the modifiers xterm passes as a second parameter, such as
+/// `ESC [ 1 ; 5 A` for **CTRL+UP**.
+fn sequence_event(sequence: &[u8]) -> Option<KeyEvent> {
+    let (&introducer, rest) = sequence.split_first()?;
+    let (&last, params) = rest.split_last()?;
+    let mut numbers = [0u16; 2];
+    let mut count = 0;
+    if !params.is_empty() {
+        for param in params.split(|&byte| byte == b';') {
+            let slot = numbers.get_mut(count)?;
+            *slot = core::str::from_utf8(param).ok()?.parse().ok()?;
+            count += 1;
+        }
+    }
+    let [first, modifier_param] = numbers;
+    let key = match (introducer, last, first) {
+        (b'[' | b'O', b'A', _) => Key::Up,
+        (b'[' | b'O', b'B', _) => Key::Down,
+        (b'[' | b'O', b'C', _) => Key::Right,
+        (b'[' | b'O', b'D', _) => Key::Left,
+        (b'[' | b'O', b'H', _) => Key::Home,
+        (b'[' | b'O', b'F', _) => Key::End,
+        (b'[' | b'O', b'P'..=b'S', _) => Key::F(last - b'P' + 1),
+        (b'[', b'~', 1 | 7) => Key::Home,
+        (b'[', b'~', 2) => Key::Insert,
+        (b'[', b'~', 3) => Key::Delete,
+        (b'[', b'~', 4 | 8) => Key::End,
+        (b'[', b'~', 5) => Key::PageUp,
+        (b'[', b'~', 6) => Key::PageDown,
+        (b'[', b'~', 11..=15) => Key::F((first - 10) as u8),
+        (b'[', b'~', 17..=21) => Key::F((first - 11) as u8),
+        (b'[', b'~', 23 | 24) => Key::F((first - 12) as u8),
+        _ => return None,
+    };
+    // The parameter is 1 plus a bit for shift, one for alt and one for control.
+    let mut modifiers = Modifiers::EMPTY;
+    let bits = modifier_param.saturating_sub(1);
+    if bits & !0b111 != 0 {
+        return None;
+    }
+    if bits & 0b001 != 0 {
+        modifiers.set_left_shift();
+    }
+    if bits & 0b010 != 0 {
+        modifiers.set_left_alt();
+    }
+    if bits & 0b100 != 0 {
+        modifiers.set_left_control();
+    }
+    Some(press(key, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::{FR, US};
+
+    /// Feeds `bytes` to a decoder for `layout`, each received `gap_ns` after the previous
+    /// one, and returns the keys pressed, as the characters they produce or `key:` followed by
+    /// their name, with `^` for control and `~` for alt, along with the unknown sequences.
+    fn replay(layout: &'static Layout, bytes: &[u8], gap_ns: u64) -> Vec<String> {
+        let mut decoder = Decoder::new();
+        let chars = crate::keyboard::Decoder::new(layout);
+        let mut inputs = Vec::new();
+        let mut now = 0;
+        for &byte in bytes {
+            decoder.advance(byte, now, layout, &mut |input| inputs.push(input));
+            now += gap_ns;
+        }
+        decoder.poll(now + ESCAPE_TIMEOUT_NS, &mut |input| inputs.push(input));
+        inputs
+            .into_iter()
+            .map(|input| match input {
+                Input::Key(event) => {
+                    let mut name = String::new();
+                    if event.modifiers.control() {
+                        name.push('^');
+                    }
+                    if event.modifiers.left_alt() {
+                        name.push('~');
+                    }
+                    match event.key {
+                        Key::Char(_) => name.push(chars.char(event).unwrap()),
+                        key => name.push_str(&format!("key:{key:?}")),
+                    }
+                    name
+                }
+                Input::Unknown(sequence) => format!("unknown:{sequence:?}"),
+            })
+            .collect()
+    }
+
+    /// What xterm sends for `ls`, the arrows, home and end, delete, F1 and F5, and enter.
+    #[test]
+    fn xterm() {
+        let bytes = b"ls\x1b[A\x1b[B\x1b[C\x1b[D\x1b[H\x1b[F\x1b[3~\x1bOP\x1b[15~\r";
+        assert_eq!(
+            replay(&US, bytes, 100_000),
+            [
+                "l",
+                "s",
+                "key:Up",
+                "key:Down",
+                "key:Right",
+                "key:Left",
+                "key:Home",
+                "key:End",
+                "key:Delete",
+                "key:F(1)",
+                "key:F(5)",
+                "key:Enter",
+            ]
+        );
+    }
+
+    /// What picocom passes on from a Linux console: the VT220 forms of home and end, the
+    /// application cursor keys, CTRL+C and a backspace sent as DEL.
+    #[test]
+    fn picocom() {
+        let bytes = b"\x1b[1~\x1b[4~\x1bOA\x1b[5~\x1b[6~\x03ab\x7f\n";
+        assert_eq!(
+            replay(&US, bytes, 100_000),
+            [
+                "key:Home",
+                "key:End",
+                "key:Up",
+                "key:PageUp",
+                "key:PageDown",
+                "^c",
+                "a",
+                "b",
+                "key:Backspace",
+                "key:Enter",
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_escape() {
+        // Pressed on its own, then twice in a row faster than the timeout.
+        assert_eq!(replay(&US, b"\x1b", 0), ["key:Escape"]);
+        assert_eq!(
+            replay(&US, b"\x1b\x1b", ESCAPE_TIMEOUT_NS / 2),
+            ["key:Escape", "key:Escape"]
+        );
+        // Typed slowly, ESC and [ are two keys.
+        assert_eq!(
+            replay(&US, b"\x1b[A", ESCAPE_TIMEOUT_NS),
+            ["key:Escape", "[", "A"]
+        );
+    }
+
+    #[test]
+    fn modifiers() {
+        // ALT+X, CTRL+UP, SHIFT+F1 and ALT+LEFT, as sent by xterm.
+        assert_eq!(
+            replay(&US, b"\x1bx\x1b[1;5A\x1b[1;2P\x1b[1;3D", 0),
+            ["~x", "^key:Up", "key:F(1)", "~key:Left"]
+        );
+    }
+
+    #[test]
+    fn layout() {
+        // The keys of the layout, shifted or with AltGr where needed.
+        assert_eq!(replay(&US, b"A!", 0), ["A", "!"]);
+        assert_eq!(replay(&FR, b"a1@", 0), ["a", "1", "@"]);
+    }
+
+    #[test]
+    fn unknown_sequences_are_dropped() {
+        assert_eq!(
+            replay(&US, b"\x1b[99~a\x1b[1;2;3Ab", 0),
+            ["unknown:ESC [ 9 9 ~", "a", "unknown:ESC [ 1 ; 2 ; 3 A", "b"]
+        );
+        // Cut short, or too long.
+        assert_eq!(replay(&US, b"\x1b[1;", 0), ["unknown:ESC [ 1 ;"]);
+        let long = [&b"\x1b["[..], &[b'1'; MAX_SEQUENCE_LEN], b"~x"].concat();
+        let decoded = replay(&US, &long, 0);
+        assert!(decoded[0].starts_with("unknown:"));
+        assert_eq!(decoded[1..], ["~", "x"]);
+    }
+}
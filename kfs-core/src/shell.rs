@@ -0,0 +1,571 @@
+//! Argument parsing for shell commands.
+
+/// An error produced by a shell command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellError {
+    /// A required argument is missing.
+    MissingArgument {
+        /// The index of the missing argument, starting at 1.
+        index: usize,
+    },
+    /// An argument could not be parsed.
+    InvalidArgument {
+        /// The index of the invalid argument, starting at 1.
+        index: usize,
+        /// What was expected instead.
+        expected: &'static str,
+    },
+    /// More arguments were given than the command accepts.
+    UnexpectedArgument {
+        /// The index of the first unexpected argument, starting at 1.
+        index: usize,
+    },
+    /// The command failed.
+    Failed(&'static str),
+    /// The command was stopped by **CTRL+C** before it was done.
+    Interrupted,
+    /// A quote opening an argument is never closed.
+    UnterminatedQuote {
+        /// The index of the argument, starting at 1.
+        index: usize,
+    },
+}
+
+impl core::fmt::Display for ShellError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ShellError::MissingArgument { index } => write!(f, "missing argument {index}"),
+            ShellError::InvalidArgument { index, expected } => {
+                write!(f, "argument {index}: expected {expected}")
+            }
+            ShellError::UnexpectedArgument { index } => write!(f, "unexpected argument {index}"),
+            ShellError::Failed(message) => f.write_str(message),
+            ShellError::Interrupted => f.write_str("interrupted"),
+            ShellError::UnterminatedQuote { index } => {
+                write!(f, "argument {index}: unterminated quote")
+            }
+        }
+    }
+}
+
+/// Parses an unsigned number, either decimal or hexadecimal with a `0x` prefix.
+pub fn parse_u32(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Parses a size, which is a number optionally followed by a `K`, `M` or `G` suffix.
+pub fn parse_size(s: &str) -> Option<usize> {
+    let (number, shift) = match s.as_bytes().last()? {
+        b'k' | b'K' => (&s[..s.len() - 1], 10),
+        b'm' | b'M' => (&s[..s.len() - 1], 20),
+        b'g' | b'G' => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    let number = parse_u32(number)? as usize;
+    number.checked_mul(1 << shift)
+}
+
+/// A command of the shell.
+pub struct Command {
+    /// The name the command is run with.
+    pub name: &'static str,
+    /// What the command does, in one line.
+    pub help: &'static str,
+    /// How to run the command, such as `tabs [1-80]`.
+    pub usage: &'static str,
+    /// Whether the command can change the state of the machine enough to be refused while the
+    /// kernel is in lockdown.
+    pub dangerous: bool,
+    /// Runs the command with its arguments, the name being already consumed.
+    pub run: fn(&mut Args) -> Result<(), ShellError>,
+}
+
+/// Returns the command named `name` among `commands`.
+pub fn find<'a>(commands: &'a [Command], name: &str) -> Option<&'a Command> {
+    commands.iter().find(|command| command.name == name)
+}
+
+/// Returns the names among `names` starting with `prefix`.
+pub fn completions<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    prefix: &str,
+) -> impl Iterator<Item = &'a str> {
+    names
+        .into_iter()
+        .filter(move |name| name.starts_with(prefix))
+}
+
+/// Returns the word being completed in `line` if the cursor is at the end of its first word.
+///
+/// `cursor` is a byte index in `line`.
+pub fn first_word_before(line: &str, cursor: usize) -> Option<&str> {
+    let word = line[..cursor].trim_start();
+    let at_end = line[cursor..].starts_with(char::is_whitespace) || cursor == line.len();
+    (at_end && !word.contains(char::is_whitespace)).then_some(word)
+}
+
+/// An argument found in a command line.
+struct Token {
+    /// The byte offset of its text, without the opening quote.
+    start: usize,
+    /// The byte offset right after its text, before the closing quote.
+    end: usize,
+    /// The byte offset right after the argument, closing quote included.
+    next: usize,
+    /// Whether it is quoted.
+    quoted: bool,
+    /// Whether it is quoted, and the quote is never closed.
+    unterminated: bool,
+}
+
+/// Returns the byte offset of the first `quote` in `s` that is not escaped by a backslash.
+///
+/// Backslashes do not escape anything between single quotes.
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the byte offset of the first whitespace in `s` that is not escaped by a backslash.
+fn unescaped_whitespace(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c.is_whitespace() => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns an error for the first argument of `line` whose quote is never closed, if any.
+pub fn check_quotes(line: &str) -> Result<(), ShellError> {
+    let args = Args::new(line);
+    let mut pos = 0;
+    let mut index = 0;
+    while let Some(token) = args.token_at(pos) {
+        if token.unterminated {
+            return Err(ShellError::UnterminatedQuote { index });
+        }
+        pos = token.next;
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Interprets the backslash escapes of `s`, and displays the result.
+///
+/// `\n` is a newline, `\t` a tab, and `\\`, `\"` and `\'` the character escaped. Any other
+/// backslash is kept as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unescaped<'a>(pub &'a str);
+
+impl core::fmt::Display for Unescaped<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use core::fmt::Write;
+
+        let mut chars = self.0.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                f.write_char(c)?;
+                continue;
+            }
+            match chars.clone().next() {
+                Some(escaped @ ('n' | 't' | '\\' | '"' | '\'')) => {
+                    chars.next();
+                    f.write_char(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        c => c,
+                    })?;
+                }
+                _ => f.write_char('\\')?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The arguments of a command line.
+///
+/// The command name itself is argument 0, so that the arguments of the command are numbered
+/// from 1 in error messages. Arguments are separated by whitespace, unless quoted with `"` or
+/// `'`, or escaped with a backslash. The quotes are not part of the argument. A backslash keeps
+/// the next character from ending a double-quoted argument, and is left in the argument for
+/// [`Unescaped`] to interpret. An unterminated quote extends to the end of the line: see
+/// [`check_quotes`].
+///
+/// Flags can appear anywhere on the line: once matched by [`Args::flag`], they are skipped by
+/// every other method.
+pub struct Args<'a> {
+    /// The command line.
+    line: &'a str,
+    /// The byte offset of the next argument in `line`.
+    pos: usize,
+    /// The index of the next argument.
+    index: usize,
+    /// The indices of the arguments consumed as flags.
+    flags: u64,
+}
+
+impl<'a> Args<'a> {
+    /// Splits `line` into arguments.
+    pub fn new(line: &'a str) -> Self {
+        Args {
+            line,
+            pos: 0,
+            index: 0,
+            flags: 0,
+        }
+    }
+
+    /// Finds the argument starting at or after the byte offset `pos`.
+    fn token_at(&self, pos: usize) -> Option<Token> {
+        let rest = &self.line[pos..];
+        let start = pos + rest.find(|c: char| !c.is_whitespace())?;
+        let quote = self.line[start..].chars().next()?;
+        if quote == '"' || quote == '\'' {
+            let start = start + 1;
+            return Some(match closing_quote(&self.line[start..], quote) {
+                Some(len) => Token {
+                    start,
+                    end: start + len,
+                    next: start + len + 1,
+                    quoted: true,
+                    unterminated: false,
+                },
+                None => Token {
+                    start,
+                    end: self.line.len(),
+                    next: self.line.len(),
+                    quoted: true,
+                    unterminated: true,
+                },
+            });
+        }
+        let end =
+            unescaped_whitespace(&self.line[start..]).map_or(self.line.len(), |len| start + len);
+        Some(Token {
+            start,
+            end,
+            next: end,
+            quoted: false,
+            unterminated: false,
+        })
+    }
+
+    /// Returns whether the argument at `index` was consumed as a flag.
+    fn is_flag(&self, index: usize) -> bool {
+        index < 64 && self.flags & (1 << index) != 0
+    }
+
+    /// Skips the arguments consumed as flags.
+    fn skip_flags(&mut self) {
+        while self.is_flag(self.index) {
+            let Some(token) = self.token_at(self.pos) else {
+                return;
+            };
+            self.pos = token.next;
+            self.index += 1;
+        }
+    }
+
+    /// Returns the index of the last argument returned.
+    pub fn index(&self) -> usize {
+        self.index.saturating_sub(1)
+    }
+
+    /// Returns an error reporting that the last argument returned is not `expected`.
+    pub fn invalid(&self, expected: &'static str) -> ShellError {
+        ShellError::InvalidArgument {
+            index: self.index(),
+            expected,
+        }
+    }
+
+    /// Returns whether the flag `name` appears among the remaining arguments, and consumes it.
+    pub fn flag(&mut self, name: &str) -> bool {
+        let mut pos = self.pos;
+        let mut index = self.index;
+        while let Some(token) = self.token_at(pos) {
+            if !self.is_flag(index) && &self.line[token.start..token.end] == name && index < 64 {
+                self.flags |= 1 << index;
+                return true;
+            }
+            pos = token.next;
+            index += 1;
+        }
+        false
+    }
+
+    /// Consumes the next argument if it is one of `names` and not quoted, and returns it.
+    ///
+    /// Unlike [`Args::flag`], only the next argument is looked at, for commands whose options
+    /// must come before anything else, like those of `echo`.
+    pub fn leading_flag(&mut self, names: &[&str]) -> Option<&'a str> {
+        self.skip_flags();
+        let token = self.token_at(self.pos).filter(|token| !token.quoted)?;
+        let text = &self.line[token.start..token.end];
+        if !names.contains(&text) {
+            return None;
+        }
+        self.pos = token.next;
+        self.index += 1;
+        Some(text)
+    }
+
+    /// Returns the next argument.
+    pub fn next_str(&mut self) -> Result<&'a str, ShellError> {
+        let index = self.index;
+        self.next().ok_or(ShellError::MissingArgument { index })
+    }
+
+    /// Returns the next argument, parsed as a decimal or `0x`-prefixed hexadecimal number.
+    pub fn next_u32(&mut self) -> Result<u32, ShellError> {
+        parse_u32(self.next_str()?).ok_or(self.invalid("a number"))
+    }
+
+    /// Returns the next argument, parsed as a size with an optional `K`, `M` or `G` suffix.
+    pub fn next_size(&mut self) -> Result<usize, ShellError> {
+        parse_size(self.next_str()?).ok_or(self.invalid("a size"))
+    }
+
+    /// Returns the rest of the line as it was typed, without leading whitespace.
+    ///
+    /// Flags that were already consumed are skipped if they come first, but are otherwise part
+    /// of the returned text.
+    pub fn rest_raw(&mut self) -> &'a str {
+        self.skip_flags();
+        let rest = self.line[self.pos..].trim_start();
+        self.pos = self.line.len();
+        rest
+    }
+
+    /// Fails if any argument is left.
+    pub fn expect_end(&mut self) -> Result<(), ShellError> {
+        let index = self.index;
+        match self.next() {
+            Some(_) => Err(ShellError::UnexpectedArgument { index }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.skip_flags();
+        let token = self.token_at(self.pos)?;
+        self.pos = token.next;
+        self.index += 1;
+        Some(&self.line[token.start..token.end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        let commands = [
+            Command {
+                name: "ok",
+                help: "Succeeds",
+                usage: "ok",
+                dangerous: false,
+                run: |_| Ok(()),
+            },
+            Command {
+                name: "fail",
+                help: "Fails",
+                usage: "fail",
+                dangerous: false,
+                run: |args| Err(args.invalid("nothing")),
+            },
+        ];
+        let mut args = Args::new("fail now");
+        let name = args.next().unwrap();
+        let command = find(&commands, name).unwrap();
+        assert_eq!(command.usage, "fail");
+        assert_eq!(
+            (command.run)(&mut args),
+            Err(ShellError::InvalidArgument {
+                index: 0,
+                expected: "nothing"
+            })
+        );
+        assert!((find(&commands, "ok").unwrap().run)(&mut args).is_ok());
+        assert!(find(&commands, "o").is_none());
+    }
+
+    #[test]
+    fn completion() {
+        let names = ["echo", "halt", "help", "hwscroll"];
+        let found: Vec<_> = completions(names, "h").collect();
+        assert_eq!(found, ["halt", "help", "hwscroll"]);
+        assert_eq!(completions(names, "he").collect::<Vec<_>>(), ["help"]);
+        assert_eq!(completions(names, "x").count(), 0);
+        assert_eq!(completions(names, "").count(), 4);
+    }
+
+    #[test]
+    fn first_word() {
+        assert_eq!(first_word_before("ec", 2), Some("ec"));
+        assert_eq!(first_word_before("  ec", 4), Some("ec"));
+        assert_eq!(first_word_before("ec hi", 2), Some("ec"));
+        assert_eq!(first_word_before("", 0), Some(""));
+        // Not at the end of the word, or past the first one.
+        assert_eq!(first_word_before("echo", 2), None);
+        assert_eq!(first_word_before("echo h", 6), None);
+    }
+
+    #[test]
+    fn positionals() {
+        let mut args = Args::new("  cmd  12 0x1f   word ");
+        assert_eq!(args.next_str(), Ok("cmd"));
+        assert_eq!(args.next_u32(), Ok(12));
+        assert_eq!(args.next_u32(), Ok(0x1f));
+        assert_eq!(args.next_str(), Ok("word"));
+        assert_eq!(args.expect_end(), Ok(()));
+        assert_eq!(
+            args.next_str(),
+            Err(ShellError::MissingArgument { index: 4 })
+        );
+    }
+
+    #[test]
+    fn flags_anywhere() {
+        let mut args = Args::new("cmd a -v b -w");
+        assert_eq!(args.next(), Some("cmd"));
+        assert!(args.flag("-v"));
+        assert!(!args.flag("-v"));
+        assert!(!args.flag("-x"));
+        assert_eq!(args.next(), Some("a"));
+        assert_eq!(args.next(), Some("b"));
+        assert!(args.flag("-w"));
+        assert_eq!(args.expect_end(), Ok(()));
+    }
+
+    #[test]
+    fn errors_carry_the_index() {
+        let mut args = Args::new("cmd 1 two 3");
+        args.next();
+        assert_eq!(args.next_u32(), Ok(1));
+        assert_eq!(
+            args.next_u32(),
+            Err(ShellError::InvalidArgument {
+                index: 2,
+                expected: "a number"
+            })
+        );
+        assert_eq!(
+            args.expect_end(),
+            Err(ShellError::UnexpectedArgument { index: 3 })
+        );
+
+        // Flags count, wherever they are.
+        let mut args = Args::new("cmd -f a b");
+        assert!(args.flag("-f"));
+        args.next();
+        assert_eq!(args.next(), Some("a"));
+        assert_eq!(args.index(), 2);
+    }
+
+    #[test]
+    fn quotes() {
+        let mut args = Args::new(r#"cmd "a b" -v '"c"'  "" 'open"#);
+        args.next();
+        assert!(args.flag("-v"));
+        assert_eq!(args.next(), Some("a b"));
+        assert_eq!(args.next(), Some("\"c\""));
+        assert_eq!(args.next(), Some(""));
+        assert_eq!(args.next(), Some("open"));
+        assert_eq!(args.next(), None);
+    }
+
+    #[test]
+    fn escapes() {
+        let mut args = Args::new(r#"cmd "say \"hi\"" 'a\' b\ c \"d"#);
+        args.next();
+        assert_eq!(args.next(), Some(r#"say \"hi\""#));
+        assert_eq!(args.next(), Some(r"a\"));
+        assert_eq!(args.next(), Some(r"b\ c"));
+        assert_eq!(args.next(), Some(r#"\"d"#));
+        assert_eq!(args.next(), None);
+
+        let unescaped = |s| Unescaped(s).to_string();
+        assert_eq!(unescaped(r#"say \"hi\""#), r#"say "hi""#);
+        assert_eq!(unescaped(r"a\tb\nc\\n"), "a\tb\nc\\n");
+        assert_eq!(unescaped(r"\x \"), r"\x \");
+        assert_eq!(unescaped("é\\'"), "é'");
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        assert_eq!(check_quotes(r#"echo "a b" 'c d' e"#), Ok(()));
+        assert_eq!(check_quotes(""), Ok(()));
+        assert_eq!(
+            check_quotes(r#"echo a "b c"#),
+            Err(ShellError::UnterminatedQuote { index: 2 })
+        );
+        // A backslash does not escape a single quote.
+        assert_eq!(check_quotes(r#"echo 'a\' 'b\'"#), Ok(()));
+        assert_eq!(
+            check_quotes(r#"echo 'a' 'b"#),
+            Err(ShellError::UnterminatedQuote { index: 2 })
+        );
+        assert_eq!(
+            check_quotes(r#"echo "\""#),
+            Err(ShellError::UnterminatedQuote { index: 1 })
+        );
+    }
+
+    #[test]
+    fn leading_flags() {
+        let mut args = Args::new("echo -n -E a -n \"-e\"");
+        args.next();
+        assert_eq!(args.leading_flag(&["-n", "-e", "-E"]), Some("-n"));
+        assert_eq!(args.leading_flag(&["-n", "-e", "-E"]), Some("-E"));
+        assert_eq!(args.leading_flag(&["-n", "-e", "-E"]), None);
+        assert_eq!(args.collect::<Vec<_>>(), ["a", "-n", "-e"]);
+
+        // A quoted flag is an operand.
+        let mut args = Args::new("echo '-n' b");
+        args.next();
+        assert_eq!(args.leading_flag(&["-n"]), None);
+        assert_eq!(args.next(), Some("-n"));
+    }
+
+    #[test]
+    fn rest_raw_keeps_spacing() {
+        let mut args = Args::new("echo -n  hello   world ");
+        args.next();
+        assert!(args.flag("-n"));
+        assert_eq!(args.rest_raw(), "hello   world ");
+        assert_eq!(args.next(), None);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4K"), Some(4096));
+        assert_eq!(parse_size("0x10"), Some(16));
+        assert_eq!(parse_size("2m"), Some(2 << 20));
+        assert_eq!(parse_size("K"), None);
+        assert_eq!(parse_size(""), None);
+    }
+}
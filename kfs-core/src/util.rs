@@ -0,0 +1,3 @@
+pub mod cstr;
+pub mod duration;
+pub mod kernel_args;
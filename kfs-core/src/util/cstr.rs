@@ -0,0 +1,65 @@
+//! Helpers for NUL-terminated strings handed to us by the outside world.
+
+/// An error returned when a C string could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CStrError {
+    /// The pointer is null.
+    Null,
+    /// Reading the string would wrap around the address space.
+    OutOfRange,
+    /// No NUL terminator was found within the allowed length.
+    Unterminated,
+}
+
+impl core::fmt::Display for CStrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CStrError::Null => f.write_str("null pointer"),
+            CStrError::OutOfRange => f.write_str("pointer out of range"),
+            CStrError::Unterminated => f.write_str("missing NUL terminator"),
+        }
+    }
+}
+
+/// Copies the NUL-terminated string at `ptr` into `buf`, and returns it as a `&str`.
+///
+/// At most `buf.len()` bytes are scanned looking for the terminator, which is not copied.
+/// Bytes that are not part of valid UTF-8 sequences are replaced with `?`.
+///
+/// # Safety
+///
+/// Every address in `ptr..ptr + buf.len()` up to the terminator must be readable. No fault
+/// handler is installed yet, so a bogus pointer cannot be recovered from.
+pub unsafe fn read_bounded(ptr: *const u8, buf: &mut [u8]) -> Result<&str, CStrError> {
+    if ptr.is_null() {
+        return Err(CStrError::Null);
+    }
+    if ptr.addr().checked_add(buf.len()).is_none() {
+        return Err(CStrError::OutOfRange);
+    }
+
+    let mut len = 0;
+    loop {
+        if len == buf.len() {
+            return Err(CStrError::Unterminated);
+        }
+        // Safety: the caller guarantees that the string is readable.
+        let byte = unsafe { ptr.add(len).read_volatile() };
+        if byte == 0 {
+            break;
+        }
+        buf[len] = byte;
+        len += 1;
+    }
+
+    let mut rest = &mut buf[..len];
+    while let Err(err) = core::str::from_utf8(rest) {
+        let valid = err.valid_up_to();
+        let invalid = err.error_len().unwrap_or(rest.len() - valid);
+        rest[valid..valid + invalid].fill(b'?');
+        rest = &mut rest[valid + invalid..];
+    }
+
+    // Safety: every invalid sequence has been replaced above.
+    Ok(unsafe { core::str::from_utf8_unchecked(&buf[..len]) })
+}
@@ -0,0 +1,42 @@
+//! Formatting of durations for humans.
+
+use core::fmt;
+
+/// A number of seconds, displayed in days, hours, minutes and seconds, such as `3 min 27 s`.
+///
+/// Only the units from the largest non-zero one down are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanDuration(pub u64);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = [
+            (self.0 / 86_400, "d"),
+            (self.0 / 3_600 % 24, "h"),
+            (self.0 / 60 % 60, "min"),
+        ];
+        let mut shown = false;
+        for (value, unit) in units {
+            shown |= value != 0;
+            if shown {
+                write!(f, "{value} {unit} ")?;
+            }
+        }
+        write!(f, "{} s", self.0 % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units() {
+        assert_eq!(HumanDuration(0).to_string(), "0 s");
+        assert_eq!(HumanDuration(59).to_string(), "59 s");
+        assert_eq!(HumanDuration(207).to_string(), "3 min 27 s");
+        assert_eq!(HumanDuration(3_600).to_string(), "1 h 0 min 0 s");
+        assert_eq!(HumanDuration(90_061).to_string(), "1 d 1 h 1 min 1 s");
+        assert_eq!(HumanDuration(86_400 + 5).to_string(), "1 d 0 h 0 min 5 s");
+    }
+}
@@ -0,0 +1,46 @@
+//! Parsing of the kernel command line.
+
+/// An iterator over the options of a kernel command line.
+///
+/// Options are separated by whitespace and have the form `key` or `key=value`. A value may be
+/// enclosed in double quotes to contain whitespace; an unterminated quote extends to the end of
+/// the command line.
+pub struct Options<'a> {
+    rest: &'a str,
+}
+
+/// Returns an iterator over the options of `cmdline`.
+pub fn options(cmdline: &str) -> Options<'_> {
+    Options { rest: cmdline }
+}
+
+impl<'a> Iterator for Options<'a> {
+    type Item = (&'a str, Option<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        let Some(value) = rest[key_end..].strip_prefix('=') else {
+            self.rest = &rest[key_end..];
+            return Some((key, None));
+        };
+
+        let (value, rest) = match value.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, rest)) => (value, rest),
+                None => (quoted, ""),
+            },
+            None => value.split_at(value.find(char::is_whitespace).unwrap_or(value.len())),
+        };
+        self.rest = rest;
+        Some((key, Some(value)))
+    }
+}
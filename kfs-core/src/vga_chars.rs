@@ -1,6 +1,14 @@
 /// Declares a set of VGA character constants for the [`VgaChar`] type.
+///
+/// The characters listed under `aliases` are only recognized by [`from_char`]: they look like
+/// a VGA character without being the character [`to_char`] returns for it.
 macro_rules! declare_vga_chars {
-    ( $( $character:literal => $value:literal; )* ) => {
+    (
+        $( $character:literal => $value:literal; )*
+        aliases {
+            $( $alias:literal => $alias_value:literal; )*
+        }
+    ) => {
         /// Returns the [`VgaChar`] associated with the provided character.
         ///
         /// If the character does not map to any known VGA character, this function returns
@@ -8,13 +16,32 @@ macro_rules! declare_vga_chars {
         pub const fn from_char(c: char) -> Option<u8> {
             match c {
                 $( $character => Some($value), )*
+                $( $alias => Some($alias_value), )*
                 _ => None,
             }
         }
+
+        /// Returns the character drawn for the VGA character `byte`.
+        ///
+        /// This is the inverse of [`from_char`]: every byte has exactly one character.
+        pub const fn to_char(byte: u8) -> char {
+            match byte {
+                $( $value => $character, )*
+            }
+        }
     };
 }
 
+/// Returns whether `byte` is a printable character rather than an ASCII control code.
+///
+/// Control codes still have a glyph on screen, but they are not meant to be shown as is, for
+/// instance in the text column of a hex dump.
+pub const fn is_printable(byte: u8) -> bool {
+    byte >= 0x20 && byte != 0x7F
+}
+
 declare_vga_chars! {
+    '\0' => 0x00;
     '☺' => 0x01;
     '☻' => 0x02;
     '♥' => 0x03;
@@ -269,4 +296,95 @@ declare_vga_chars! {
     'ⁿ' => 0xFC;
     '²' => 0xFD;
     '■' => 0xFE;
+    '\u{A0}' => 0xFF;
+
+    aliases {
+        // Typographic punctuation.
+        '\u{2018}' => 0x27; // ‘
+        '\u{2019}' => 0x27; // ’
+        '\u{201C}' => 0x22; // “
+        '\u{201D}' => 0x22; // ”
+        '\u{2010}' => 0x2D; // ‐
+        '\u{2013}' => 0x2D; // –
+        '\u{2014}' => 0x2D; // —
+        '\u{2212}' => 0x2D; // −
+        // Lookalikes of the letters and symbols of the table.
+        '\u{03B2}' => 0xE1; // β
+        '\u{2211}' => 0xE4; // ∑
+        '\u{03BC}' => 0xE6; // μ
+        '\u{2126}' => 0xEA; // Ω
+        '\u{00F0}' => 0xEB; // ð
+        '\u{03D5}' => 0xED; // ϕ
+        '\u{00F8}' => 0xED; // ø
+        '\u{2205}' => 0xED; // ∅
+        '\u{2208}' => 0xEE; // ∈
+        '\u{2713}' => 0xFB; // ✓
+        '\u{2717}' => 0x78; // ✗
+        // Rounded corners, drawn square.
+        '\u{256D}' => 0xDA; // ╭
+        '\u{256E}' => 0xBF; // ╮
+        '\u{256F}' => 0xD9; // ╯
+        '\u{2570}' => 0xC0; // ╰
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(from_char(to_char(byte)), Some(byte), "{byte:#04X}");
+        }
+    }
+
+    #[test]
+    fn notable_characters() {
+        let expected = [
+            ('é', 0x82),
+            ('ß', 0xE1),
+            ('±', 0xF1),
+            ('÷', 0xF6),
+            ('°', 0xF8),
+            ('π', 0xE3),
+            ('Σ', 0xE4),
+            ('µ', 0xE6),
+            ('Ω', 0xEA),
+            ('∞', 0xEC),
+            ('→', 0x1A),
+            ('░', 0xB0),
+            ('▒', 0xB1),
+            ('▓', 0xB2),
+            ('█', 0xDB),
+            ('┌', 0xDA),
+            ('╬', 0xCE),
+            ('■', 0xFE),
+        ];
+        for (c, byte) in expected {
+            assert_eq!(from_char(c), Some(byte), "{c}");
+        }
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(from_char('β'), from_char('ß'));
+        assert_eq!(from_char('μ'), from_char('µ'));
+        assert_eq!(from_char('—'), Some(b'-'));
+        assert_eq!(from_char('’'), Some(b'\''));
+        assert_eq!(from_char('╭'), from_char('┌'));
+        assert_eq!(from_char('€'), None);
+    }
+
+    #[test]
+    fn printable() {
+        assert!(!is_printable(0x00));
+        assert!(!is_printable(b'\n'));
+        assert!(!is_printable(0x1F));
+        assert!(is_printable(b' '));
+        assert!(is_printable(b'~'));
+        assert!(!is_printable(0x7F));
+        assert!(is_printable(0xB0));
+        assert!(is_printable(0xFF));
+    }
 }
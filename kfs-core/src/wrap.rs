@@ -0,0 +1,197 @@
+//! Line wrapping policies for text output.
+
+/// What happens to the characters written past the end of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Continue on the next row, possibly in the middle of a word.
+    #[default]
+    Hard,
+    /// Move the last word of the row to the next row, indented by [`WORD_WRAP_INDENT`].
+    Word,
+    /// Drop the characters up to the next newline, and mark the row as truncated.
+    Truncate,
+}
+
+/// The indentation of the rows continuing a word-wrapped line.
+pub const WORD_WRAP_INDENT: usize = 2;
+
+/// The default distance between two tab stops.
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+impl WrapMode {
+    /// Returns the mode called `name`: `hard`, `word` or `trunc`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hard" => Some(WrapMode::Hard),
+            "word" => Some(WrapMode::Word),
+            "trunc" => Some(WrapMode::Truncate),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the mode, as accepted by [`from_name`](Self::from_name).
+    pub fn name(self) -> &'static str {
+        match self {
+            WrapMode::Hard => "hard",
+            WrapMode::Word => "word",
+            WrapMode::Truncate => "trunc",
+        }
+    }
+}
+
+/// Finds where to break a full row in [`WrapMode::Word`].
+///
+/// `row` holds the characters of the row, and `start` is the column at which its text starts,
+/// after any indentation. Returns the column of the first character to move to the next row,
+/// which is `row.len()` when the row ends with a space. Returns `None` when the row has no space
+/// to break at, or when the last word would not fit on the next row either: the row should then
+/// be broken in the middle of the word.
+pub fn word_break(row: &[u8], start: usize) -> Option<usize> {
+    let space = row.iter().rposition(|&c| c == b' ')?;
+    let word = space + 1;
+    (word > start && row.len() - word + WORD_WRAP_INDENT < row.len()).then_some(word)
+}
+
+/// Returns the column a tab written at column `x` moves to: the next multiple of `width`, or
+/// `row_width` when that stop is past the end of the row.
+///
+/// A tab always moves at least one column, unless the row is already full.
+pub fn tab_stop(x: usize, width: usize, row_width: usize) -> usize {
+    ((x / width + 1) * width).min(row_width.max(x))
+}
+
+/// Returns the cell each character of `s` goes to when it is written from the column `x` of the
+/// row `y`, on rows of `row_width` cells, with no wrapping.
+///
+/// A newline moves to the column `x` of the next row. The characters past the end of a row are
+/// dropped.
+pub fn place(
+    x: usize,
+    y: usize,
+    s: &str,
+    row_width: usize,
+) -> impl Iterator<Item = (usize, usize, char)> {
+    s.split('\n').enumerate().flat_map(move |(row, line)| {
+        (x..row_width)
+            .zip(line.chars())
+            .map(move |(column, c)| (column, y + row, c))
+    })
+}
+
+/// Returns the cell a backspace written at the column `x` of the row `y` moves to, on rows of
+/// `row_width` cells.
+///
+/// The cursor moves one column to the left. At the start of a row, it moves to the end of the
+/// previous row if `wrapped` tells that the output wrapped from there, and stays put otherwise,
+/// in which case `None` is returned. A cursor past the end of a full row moves to its last
+/// column.
+pub fn backspace(x: usize, y: usize, wrapped: bool, row_width: usize) -> Option<(usize, usize)> {
+    match x {
+        0 if wrapped && y > 0 => Some((row_width - 1, y - 1)),
+        0 => None,
+        x => Some((x.min(row_width) - 1, y)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for mode in [WrapMode::Hard, WrapMode::Word, WrapMode::Truncate] {
+            assert_eq!(WrapMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(WrapMode::from_name("soft"), None);
+    }
+
+    #[test]
+    fn breaks_before_the_last_word() {
+        assert_eq!(word_break(b"hello worl", 0), Some(6));
+        assert_eq!(word_break(b"a b c defg", 0), Some(6));
+        assert_eq!(word_break(b"hello wor ", 0), Some(10));
+    }
+
+    #[test]
+    fn tab_stops() {
+        assert_eq!(tab_stop(0, 8, 80), 8);
+        assert_eq!(tab_stop(7, 8, 80), 8);
+        assert_eq!(tab_stop(8, 8, 80), 16);
+        assert_eq!(tab_stop(3, 4, 80), 4);
+    }
+
+    #[test]
+    fn tab_stops_at_the_end_of_the_row() {
+        assert_eq!(tab_stop(75, 8, 80), 80);
+        assert_eq!(tab_stop(79, 8, 80), 80);
+        assert_eq!(tab_stop(80, 8, 80), 80);
+        assert_eq!(tab_stop(79, 1, 80), 80);
+    }
+
+    #[test]
+    fn tab_width_one() {
+        assert_eq!(tab_stop(0, 1, 80), 1);
+        assert_eq!(tab_stop(41, 1, 80), 42);
+    }
+
+    #[test]
+    fn falls_back_to_hard_wrap() {
+        // No space at all.
+        assert_eq!(word_break(b"helloworld", 0), None);
+        // The only spaces are the indentation of a continuation row.
+        assert_eq!(word_break(b"  abcdefgh", WORD_WRAP_INDENT), None);
+        // The word would not fit on the next row either.
+        assert_eq!(word_break(b" abcdefghi", 0), None);
+    }
+
+    /// Returns the cells `s` is placed at, on rows of 10 cells.
+    fn cells(x: usize, y: usize, s: &str) -> Vec<(usize, usize, char)> {
+        place(x, y, s, 10).collect()
+    }
+
+    #[test]
+    fn places_text() {
+        assert_eq!(cells(2, 1, "ab"), [(2, 1, 'a'), (3, 1, 'b')]);
+        assert_eq!(cells(0, 0, ""), []);
+    }
+
+    #[test]
+    fn clips_at_the_end_of_the_row() {
+        assert_eq!(
+            cells(7, 0, "abcdef"),
+            [(7, 0, 'a'), (8, 0, 'b'), (9, 0, 'c')]
+        );
+        assert_eq!(cells(9, 0, "ab"), [(9, 0, 'a')]);
+        assert_eq!(cells(10, 0, "ab"), []);
+        assert_eq!(cells(12, 0, "ab"), []);
+    }
+
+    #[test]
+    fn newlines_go_back_to_the_start_column() {
+        assert_eq!(
+            cells(8, 3, "abc\nd"),
+            [(8, 3, 'a'), (9, 3, 'b'), (8, 4, 'd')]
+        );
+        assert_eq!(cells(1, 0, "\n\nx"), [(1, 2, 'x')]);
+    }
+
+    #[test]
+    fn backspaces() {
+        assert_eq!(backspace(5, 2, false, 10), Some((4, 2)));
+        assert_eq!(backspace(9, 2, true, 10), Some((8, 2)));
+        // Past the end of a full row.
+        assert_eq!(backspace(10, 2, false, 10), Some((9, 2)));
+    }
+
+    #[test]
+    fn backspaces_at_the_start_of_a_row() {
+        assert_eq!(backspace(0, 2, false, 10), None);
+        assert_eq!(backspace(0, 2, true, 10), Some((9, 1)));
+        assert_eq!(backspace(0, 0, true, 10), None);
+    }
+
+    #[test]
+    fn breaks_continuation_rows() {
+        assert_eq!(word_break(b"  abc defg", WORD_WRAP_INDENT), Some(6));
+    }
+}
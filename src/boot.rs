@@ -0,0 +1,46 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Proof that the boot sequence is running, and that the hardware it hands out is not owned by
+/// anyone else yet.
+///
+/// Only one [`BootToken`] can ever be created. It is split into one token per piece of
+/// hardware, each of which must be consumed to construct the driver that owns it.
+pub struct BootToken {
+    /// Grants ownership of the VGA text buffer and of the CRT controller.
+    pub vga: VgaToken,
+    /// Grants ownership of the PS/2 keyboard controller.
+    pub keyboard: KeyboardToken,
+    /// Grants ownership of the COM1 serial port.
+    pub serial: SerialToken,
+}
+
+/// Grants ownership of the VGA text buffer and of the CRT controller.
+pub struct VgaToken(());
+
+/// Grants ownership of the PS/2 keyboard controller.
+pub struct KeyboardToken(());
+
+/// Grants ownership of the COM1 serial port.
+pub struct SerialToken(());
+
+impl BootToken {
+    /// Returns the boot token.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token has already been taken.
+    #[track_caller]
+    pub fn take() -> Self {
+        static TAKEN: AtomicBool = AtomicBool::new(false);
+
+        assert!(
+            !TAKEN.swap(true, Ordering::Relaxed),
+            "BootToken already taken"
+        );
+        BootToken {
+            vga: VgaToken(()),
+            keyboard: KeyboardToken(()),
+            serial: SerialToken(()),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Identification of the processor.
+
+use {
+    core::arch::{asm, naked_asm},
+    kfs_core::{cpuid::CpuidResult, registers::Registers},
+};
+
+/// The ID flag of EFLAGS, which can only be toggled on processors with `cpuid`.
+const EFLAGS_ID: usize = 1 << 21;
+
+/// The features of leaf 1 EDX that CR4 controls: VME, DE, PSE, TSC, PAE, MCE and PGE.
+const CR4_FEATURES: u32 = 1 << 1 | 1 << 2 | 1 << 3 | 1 << 4 | 1 << 6 | 1 << 7 | 1 << 13;
+
+/// Returns whether the processor has the `cpuid` instruction.
+pub fn has_cpuid() -> bool {
+    let toggled: usize;
+    // Safety: EFLAGS is put back as it was.
+    unsafe {
+        asm!(
+            "pushfd",
+            "pop {saved}",
+            "mov {toggled}, {saved}",
+            "xor {toggled}, {id}",
+            "push {toggled}",
+            "popfd",
+            "pushfd",
+            "pop {toggled}",
+            "push {saved}",
+            "popfd",
+            "xor {toggled}, {saved}",
+            saved = out(reg) _,
+            toggled = out(reg) toggled,
+            id = const EFLAGS_ID,
+        );
+    }
+    toggled & EFLAGS_ID != 0
+}
+
+/// Runs `cpuid` for `leaf` and `subleaf`, or returns `None` if the processor does not have it.
+///
+/// Leaves above the highest one the processor supports return unspecified values.
+pub fn cpuid(leaf: u32, subleaf: u32) -> Option<CpuidResult> {
+    if !has_cpuid() {
+        return None;
+    }
+    let (eax, ebx, ecx, edx);
+    // Safety: the processor has `cpuid`, which only writes the registers. EBX may be reserved by
+    // the compiler, so it is saved in another register.
+    unsafe {
+        asm!(
+            "mov {ebx:e}, ebx",
+            "cpuid",
+            "xchg {ebx:e}, ebx",
+            ebx = out(reg) ebx,
+            inout("eax") leaf => eax,
+            inout("ecx") subleaf => ecx,
+            out("edx") edx,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+    Some(CpuidResult { eax, ebx, ecx, edx })
+}
+
+/// Returns the names and values of the segment registers.
+pub fn segment_registers() -> [(&'static str, u16); 6] {
+    let (cs, ds, es, fs, gs, ss): (u16, u16, u16, u16, u16, u16);
+    // Safety: the registers are only read.
+    unsafe {
+        asm!(
+            "mov {:x}, cs",
+            "mov {:x}, ds",
+            "mov {:x}, es",
+            "mov {:x}, fs",
+            "mov {:x}, gs",
+            "mov {:x}, ss",
+            out(reg) cs,
+            out(reg) ds,
+            out(reg) es,
+            out(reg) fs,
+            out(reg) gs,
+            out(reg) ss,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+    [
+        ("cs", cs),
+        ("ds", ds),
+        ("es", es),
+        ("fs", fs),
+        ("gs", gs),
+        ("ss", ss),
+    ]
+}
+
+/// Returns whether the processor has CR4, which reading on an older one faults.
+///
+/// CR4 came with the Pentium, which has `cpuid` and some of the features it controls.
+pub fn has_cr4() -> bool {
+    cpuid(1, 0).is_some_and(|leaf1| leaf1.edx & CR4_FEATURES != 0)
+}
+
+/// The general-purpose registers, EIP and EFLAGS, in the order [`snapshot`] stores them.
+#[repr(C)]
+#[derive(Default)]
+struct Snapshot {
+    eax: u32,
+    ebx: u32,
+    ecx: u32,
+    edx: u32,
+    esi: u32,
+    edi: u32,
+    ebp: u32,
+    esp: u32,
+    eip: u32,
+    eflags: u32,
+}
+
+/// Stores the registers into `out`, as they are when the function is called.
+///
+/// ESP is the value it has in the caller once the call returns, and EIP the return address.
+#[unsafe(naked)]
+extern "C" fn snapshot(out: &mut Snapshot) {
+    naked_asm!(
+        "
+        push eax
+        mov eax, [esp + 8]
+        mov [eax + 4], ebx
+        mov [eax + 8], ecx
+        mov [eax + 12], edx
+        mov [eax + 16], esi
+        mov [eax + 20], edi
+        mov [eax + 24], ebp
+        pop ecx
+        mov [eax], ecx
+        lea ecx, [esp + 4]
+        mov [eax + 28], ecx
+        mov ecx, [esp]
+        mov [eax + 32], ecx
+        pushfd
+        pop ecx
+        mov [eax + 36], ecx
+        ret
+        ",
+    )
+}
+
+/// Returns a snapshot of the registers.
+///
+/// The general-purpose registers, EIP and EFLAGS are those of the call to a shim early in this
+/// function: EIP points into it, and the other registers hold whatever the caller and the
+/// prologue left there, not the state of whoever typed a command. The segment and control
+/// registers are read right after.
+#[inline(never)]
+pub fn registers() -> Registers {
+    let mut gprs = Snapshot::default();
+    snapshot(&mut gprs);
+    let (cr0, cr2, cr3): (usize, usize, usize);
+    // Safety: the registers are only read.
+    unsafe {
+        asm!("mov {}, cr0", out(reg) cr0, options(nomem, nostack, preserves_flags));
+        asm!("mov {}, cr2", out(reg) cr2, options(nomem, nostack, preserves_flags));
+        asm!("mov {}, cr3", out(reg) cr3, options(nomem, nostack, preserves_flags));
+    }
+    let cr4 = has_cr4().then(|| {
+        let cr4: usize;
+        // Safety: the processor has CR4, which is only read.
+        unsafe { asm!("mov {}, cr4", out(reg) cr4, options(nomem, nostack, preserves_flags)) };
+        cr4 as u32
+    });
+    let [cs, ds, es, fs, gs, ss] = segment_registers().map(|(_, selector)| selector);
+    Registers {
+        eax: gprs.eax,
+        ebx: gprs.ebx,
+        ecx: gprs.ecx,
+        edx: gprs.edx,
+        esi: gprs.esi,
+        edi: gprs.edi,
+        ebp: gprs.ebp,
+        esp: gprs.esp,
+        eip: gprs.eip,
+        eflags: gprs.eflags,
+        cs,
+        ds,
+        es,
+        fs,
+        gs,
+        ss,
+        cr0: cr0 as u32,
+        cr2: cr2 as u32,
+        cr3: cr3 as u32,
+        cr4,
+    }
+}
@@ -1,253 +1,1073 @@
 use core::arch::asm;
+use core::fmt::Write;
 use core::hint::unreachable_unchecked;
+use core::ops::{Deref, DerefMut};
 
-mod keyboard;
-mod vga_chars;
+use kfs_core::{
+    cmdline::{Cmdline, History},
+    hexdump,
+    keyboard::{Modifiers, keys},
+    mutex::{Mutex, MutexGuard},
+    wrap::WrapMode,
+};
+
+use crate::{boot::VgaToken, time};
+
+pub mod draw;
+mod input;
+pub mod progress;
+pub mod rtc;
+mod serial;
+pub mod speaker;
+mod vga;
+pub mod vga_font;
+pub mod vga_palette;
+
+pub use self::{
+    input::{Keyboard, Translation},
+    serial::SerialPort,
+    vga::{OutOfBounds, TextMode, VgaBuffer},
+};
 
 const VGA_BUFFER_ADDRESS: usize = 0xb8000;
 const VGA_BUFFER_WIDTH: usize = 80;
-const VGA_BUFFER_HEIGHT: usize = 25;
+/// The number of rows of the screen in the tallest text mode.
+const VGA_BUFFER_MAX_HEIGHT: usize = 50;
 
-const TAB_SIZE: usize = 4;
+/// The 16 colors of text mode.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Blue,
+    Green,
+    Cyan,
+    Red,
+    Magenta,
+    Brown,
+    LightGray,
+    DarkGray,
+    LightBlue,
+    LightGreen,
+    LightCyan,
+    LightRed,
+    Pink,
+    Yellow,
+    White,
+}
 
-pub struct Cmdline {
-    buffer: [u8; 128],
-    len: usize,
+impl Color {
+    /// Every color, in the order of their values.
+    const ALL: [Color; 16] = [
+        Color::Black,
+        Color::Blue,
+        Color::Green,
+        Color::Cyan,
+        Color::Red,
+        Color::Magenta,
+        Color::Brown,
+        Color::LightGray,
+        Color::DarkGray,
+        Color::LightBlue,
+        Color::LightGreen,
+        Color::LightCyan,
+        Color::LightRed,
+        Color::Pink,
+        Color::Yellow,
+        Color::White,
+    ];
+
+    /// What the `color` command accepts, for error messages.
+    pub const EXPECTED: &str = "a hexadecimal attribute or a color name: black, blue, green, \
+        cyan, red, magenta, brown, lightgray, darkgray, lightblue, lightgreen, lightcyan, \
+        lightred, pink, yellow or white, where light may be spelled bright and gray grey";
+
+    /// Returns the color with the value `value`, ignoring the bits above the lowest 4.
+    pub fn from_u8(value: u8) -> Self {
+        Self::ALL[(value & 0x0F) as usize]
+    }
+
+    /// Parses a color from the name returned by [`name`](Self::name), or a synonym.
+    ///
+    /// `light` may be spelled `bright` and `gray` `grey`. Plain `gray` is light gray, and the
+    /// bright versions of black, magenta, brown and white are dark gray, pink, yellow and white.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (light, base) = match name
+            .strip_prefix("light")
+            .or_else(|| name.strip_prefix("bright"))
+        {
+            Some(base) => (true, base),
+            None => (false, name),
+        };
+        let base = if base.ends_with("grey") {
+            match base {
+                "grey" => "gray",
+                "darkgrey" => "darkgray",
+                _ => return None,
+            }
+        } else {
+            base
+        };
+        let color = match (light, base) {
+            (_, "gray") => Color::LightGray,
+            (false, "darkgray") | (true, "black") => Color::DarkGray,
+            (true, "blue") => Color::LightBlue,
+            (true, "green") => Color::LightGreen,
+            (true, "cyan") => Color::LightCyan,
+            (true, "red") => Color::LightRed,
+            (true, "magenta") => Color::Pink,
+            (true, "brown" | "yellow") => Color::Yellow,
+            (true, "white") => Color::White,
+            (true, _) => return None,
+            (false, base) => return Self::ALL.into_iter().find(|color| color.name() == base),
+        };
+        Some(color)
+    }
+
+    /// Returns the name of the color, in lowercase and without spaces.
+    pub fn name(self) -> &'static str {
+        match self {
+            Color::Black => "black",
+            Color::Blue => "blue",
+            Color::Green => "green",
+            Color::Cyan => "cyan",
+            Color::Red => "red",
+            Color::Magenta => "magenta",
+            Color::Brown => "brown",
+            Color::LightGray => "lightgray",
+            Color::DarkGray => "darkgray",
+            Color::LightBlue => "lightblue",
+            Color::LightGreen => "lightgreen",
+            Color::LightCyan => "lightcyan",
+            Color::LightRed => "lightred",
+            Color::Pink => "pink",
+            Color::Yellow => "yellow",
+            Color::White => "white",
+        }
+    }
 }
 
-impl Cmdline {
-    pub const fn new() -> Self {
-        Cmdline {
-            buffer: [0; 128],
-            len: 0,
+/// The number of virtual consoles.
+pub const CONSOLE_COUNT: usize = 4;
+
+/// The keys switching to each console when pressed along with `Alt`.
+const CONSOLE_KEYS: [char; CONSOLE_COUNT] = [keys::F1, keys::F2, keys::F3, keys::F4];
+
+/// How long a `Ctrl+X` chord waits for its second key, in nanoseconds.
+const CHORD_TIMEOUT_NS: u64 = 1_000_000_000;
+
+/// An action of a `Ctrl+X` chord handled by the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChordAction {
+    /// Clears the screen, keeping the command line.
+    Clear,
+    /// Switches to the console `n`.
+    Console(usize),
+}
+
+/// What shows that a `Ctrl+X` chord is pending.
+const CHORD_INDICATOR: &str = "C-x-";
+
+/// What shows that output is waiting for the `flush` command.
+const PENDING_INDICATOR: &str = "*";
+
+/// The second keys of the `Ctrl+X` chords handled by the terminal, along with their actions.
+/// The other chords are left to the caller of [`Terminal::get_line`].
+const CHORDS: &[(char, ChordAction)] = &[
+    ('c', ChordAction::Clear),
+    ('1', ChordAction::Console(0)),
+    ('2', ChordAction::Console(1)),
+    ('3', ChordAction::Console(2)),
+    ('4', ChordAction::Console(3)),
+];
+
+/// Returns whether the chord `Ctrl+X` `c` is handled by the terminal, and cannot be bound.
+pub fn builtin_chord(c: char) -> bool {
+    CHORDS.iter().any(|&(key, _)| key == c)
+}
+
+/// What [`Terminal::get_line`] read from the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input<'a> {
+    /// A line of input was entered.
+    Line(&'a str),
+    /// The function key `F<n>` was pressed, leaving the command line as it is.
+    Function(u8),
+    /// Tab was pressed, asking for the command line to be completed.
+    Complete,
+    /// `Ctrl+X` was followed by a key the terminal does not handle itself.
+    Chord(char),
+}
+
+/// The prompt shown before the command line.
+const PS1: &str = "kernel@kfs$ ";
+
+/// The number of terminal snapshots that can be held at once.
+const SNAPSHOT_SLOTS: usize = 2;
+
+/// The state saved by a [`TerminalSnapshot`].
+struct SnapshotData {
+    /// The contents of the VGA buffer.
+    cells: [u16; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT],
+    /// The number of rows saved.
+    height: usize,
+    /// The cells of the status bar, if it was on.
+    status_cells: Option<[u16; VGA_BUFFER_WIDTH]>,
+    /// What the status bar was last drawn with.
+    status: Option<Status>,
+    /// The position of the output cursor.
+    cursor: (usize, usize),
+    /// The shape of the cursor.
+    cursor_shape: (u8, u8),
+    /// Whether the cursor is hidden.
+    cursor_hidden: bool,
+    /// The current color.
+    color: u8,
+    /// The scrolling region, if any.
+    scroll_region: Option<(usize, usize)>,
+    /// Whether bit 7 of the color makes characters blink.
+    blink: bool,
+    /// Where the command line of the console was last drawn.
+    cmdline_area: Option<CmdlineArea>,
+    /// The cell of the console drawn in reverse video.
+    highlight: Option<Highlight>,
+}
+
+impl SnapshotData {
+    const fn new() -> Self {
+        SnapshotData {
+            cells: [0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT],
+            height: 0,
+            status_cells: None,
+            status: None,
+            cursor: (0, 0),
+            cursor_shape: (0, 0),
+            cursor_hidden: false,
+            color: 0,
+            scroll_region: None,
+            blink: false,
+            cmdline_area: None,
+            highlight: None,
         }
     }
+}
 
-    pub fn as_str(&self) -> &str {
-        unsafe { core::str::from_utf8_unchecked(self.buffer.get_unchecked(..self.len)) }
+/// The storage behind [`TerminalSnapshot`]s. A slot is in use while its mutex is locked.
+static SNAPSHOTS: [Mutex<SnapshotData>; SNAPSHOT_SLOTS] =
+    [const { Mutex::new(SnapshotData::new()) }; SNAPSHOT_SLOTS];
+
+/// The full state of the terminal, as saved by [`Terminal::snapshot`]: the cells of the console
+/// output goes to, the status bar, the cursor, the current color, the scrolling region and the
+/// blink mode.
+///
+/// Features that take over the screen save it beforehand and put it back with
+/// [`Terminal::restore`] once done. Snapshots can be nested, up to [`SNAPSHOT_SLOTS`] at once.
+/// Dropping a snapshot frees its slot.
+pub struct TerminalSnapshot(MutexGuard<'static, SnapshotData>);
+
+impl TerminalSnapshot {
+    /// Returns the number of rows saved, without the status bar.
+    pub fn height(&self) -> usize {
+        self.0.height
     }
 
-    pub fn take(&mut self) -> &str {
-        let result =
-            unsafe { core::str::from_utf8_unchecked(self.buffer.get_unchecked(..self.len)) };
-        self.len = 0;
-        result
+    /// Returns the cells of the row `y`: the character of each in the low byte, and its color
+    /// in the high byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is not below [`height`](Self::height).
+    pub fn row(&self, y: usize) -> &[u16; VGA_BUFFER_WIDTH] {
+        assert!(y < self.0.height);
+        self.0.cells[y * VGA_BUFFER_WIDTH..][..VGA_BUFFER_WIDTH]
+            .try_into()
+            .unwrap()
     }
+}
+
+/// Where a command line was last drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CmdlineArea {
+    /// The row of the prompt.
+    row: usize,
+    /// The position of the output cursor after drawing.
+    end: (usize, usize),
+    /// The scroll count of the VGA buffer after drawing.
+    scrolls: usize,
+}
 
-    pub fn push(&mut self, c: char) -> bool {
-        let rem = unsafe { self.buffer.get_unchecked_mut(self.len..) };
-        let len = c.len_utf8();
-        if rem.len() < len {
-            return false;
+/// The cell of a command line drawn in reverse video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Highlight {
+    x: usize,
+    y: usize,
+    /// The scroll count of the VGA buffer after drawing, unless the row does not scroll.
+    scrolls: Option<usize>,
+    /// The cell, as it was drawn.
+    cell: u16,
+}
+
+/// The colors of the terminal, by role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// The name of the theme, as accepted by the `theme` command.
+    pub name: &'static str,
+    /// The color of text.
+    pub normal: u8,
+    /// The color of the prompt.
+    pub prompt: u8,
+    /// The color of error messages.
+    pub error: u8,
+    /// The color of the status bar.
+    pub status: u8,
+    /// Whether messages are told apart by more than their color: the leveled ones start with a
+    /// glyph and their level, and the panic screen is framed in reverse video.
+    pub accessible: bool,
+}
+
+impl Theme {
+    /// Returns the theme called `name`.
+    pub fn from_name(name: &str) -> Option<&'static Theme> {
+        THEMES.iter().find(|theme| theme.name == name)
+    }
+
+    /// Returns the accessible variant of this theme, which does not rely on colors alone:
+    /// errors are drawn in reverse video, and [`accessible`](Self::accessible) is set.
+    pub const fn accessible(self) -> Theme {
+        Theme {
+            error: self.normal.rotate_left(4),
+            accessible: true,
+            ..self
         }
-        c.encode_utf8(rem);
-        self.len += len;
-        true
     }
 
-    pub fn pop(&mut self) {
-        match self.as_str().chars().next_back() {
-            Some(c) => self.len -= c.len_utf8(),
-            None => self.len = 0,
+    /// Returns the color that replaces `color` when switching from this theme to `to`.
+    ///
+    /// The colors of the roles are swapped for the ones of `to`, and the others are kept.
+    fn translate(&self, to: &Theme, color: u8) -> u8 {
+        match color {
+            _ if color == self.normal => to.normal,
+            _ if color == self.prompt => to.prompt,
+            _ if color == self.error => to.error,
+            _ => color,
         }
     }
+}
 
-    pub fn pop_word(&mut self) {
-        match self
-            .as_str()
-            .char_indices()
-            .rev()
-            .skip_while(|(_, x)| x.is_whitespace())
-            .find(|(_, x)| x.is_whitespace())
-        {
-            Some((index, c)) => self.len = index + c.len_utf8(),
-            None => self.len = 0,
+/// The themes the terminal can be switched to. The first one is used at boot.
+pub const THEMES: &[Theme] = &[
+    // White on black, with a black on light gray status bar.
+    Theme {
+        name: "default",
+        normal: 0x0F,
+        prompt: 0x0F,
+        error: 0x0C,
+        status: 0x70,
+        accessible: false,
+    },
+    // Light gray on blue, with a yellow prompt.
+    Theme {
+        name: "solarized",
+        normal: 0x17,
+        prompt: 0x1E,
+        error: 0x1C,
+        status: 0x30,
+        accessible: false,
+    },
+    // Shades of brown, like an amber monochrome monitor.
+    Theme {
+        name: "amber",
+        normal: 0x06,
+        prompt: 0x0E,
+        error: 0x60,
+        status: 0x60,
+        accessible: false,
+    },
+    // Shades of green on black.
+    Theme {
+        name: "matrix",
+        normal: 0x02,
+        prompt: 0x0A,
+        error: 0x0F,
+        status: 0x20,
+        accessible: false,
+    },
+];
+
+/// What the status bar shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Status {
+    console: usize,
+    cursor: (usize, usize),
+    color: u8,
+    modifiers: Modifiers,
+    /// Whether a `Ctrl+X` chord is pending.
+    chord: bool,
+    /// Whether output is waiting to be flushed to the terminal.
+    pending: bool,
+}
+
+impl Status {
+    /// Returns the text of the status bar.
+    fn render(&self) -> RowText {
+        let mut text = RowText::new();
+        let (x, y) = self.cursor;
+        _ = write!(
+            text,
+            " tty{}  {}:{}  color {:02X} ",
+            self.console + 1,
+            y + 1,
+            x + 1,
+            self.color
+        );
+        let modifiers = self.modifiers;
+        let names = [
+            (modifiers.caps_lock(), "CAPS"),
+            (modifiers.num_lock(), "NUM"),
+            (modifiers.scroll_lock(), "SCRL"),
+            (modifiers.shift(), "shift"),
+            (modifiers.control(), "ctrl"),
+            (modifiers.alt(), "alt"),
+            (modifiers.super_key(), "super"),
+        ];
+        for (_, name) in names.iter().filter(|&&(on, _)| on) {
+            _ = write!(text, " {name}");
+        }
+        if self.chord {
+            _ = write!(text, "  {CHORD_INDICATOR}");
         }
+        if self.pending {
+            _ = write!(text, "  {PENDING_INDICATOR}");
+        }
+        text
     }
 }
 
+/// A row of text, truncated to the width of the screen.
+pub struct RowText {
+    bytes: [u8; VGA_BUFFER_WIDTH],
+    len: usize,
+}
+
+impl RowText {
+    pub const fn new() -> Self {
+        RowText {
+            bytes: [0; VGA_BUFFER_WIDTH],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the text, up to the last complete character.
+    pub fn as_str(&self) -> &str {
+        match core::str::from_utf8(self.as_bytes()) {
+            Ok(s) => s,
+            Err(err) => core::str::from_utf8(&self.bytes[..err.valid_up_to()]).unwrap(),
+        }
+    }
+}
+
+impl core::fmt::Write for RowText {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            let Some(cell) = self.bytes.get_mut(self.len) else {
+                break;
+            };
+            *cell = byte;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The terminal used by the REPL: a VGA buffer along with command line editing.
 pub struct Terminal {
-    cursor_x: usize,
-    cursor_y: usize,
-    current_color: u8,
-    keyboard: keyboard::Qwerty,
+    vga: VgaBuffer,
+    /// When a `Ctrl+X` chord is pending in each console, the time at which it expires.
+    chord_deadlines: [Option<u64>; CONSOLE_COUNT],
+    /// Where the command line of each console was last drawn.
+    cmdline_areas: [Option<CmdlineArea>; CONSOLE_COUNT],
+    /// What the status bar was last drawn with.
+    status: Option<Status>,
+    /// The colors of the terminal.
+    theme: Theme,
+    /// Whether the cell at the edit position of the command line is drawn in reverse video.
+    cmdline_highlight: bool,
+    /// The cell drawn in reverse video in each console.
+    highlights: [Option<Highlight>; CONSOLE_COUNT],
 }
 
 impl Terminal {
-    /// Creates the VGA buffer interface.
-    ///
-    /// # Safety
-    /// This function is unsafe because it allows mutable access to the VGA buffer, and Text Mode cursor,
-    /// and keyboard controller ports, which may lead to data races if multiple mutable references exist.
-    /// As such, the caller must ensure that they have exclusive access to these resources.
-    pub const unsafe fn new() -> Self {
-        // SAFETY: The caller must ensure that they have exclusive access to the Text Mode cursor.
-        let current_color = 0x0F; // White on black
-
+    /// Creates the terminal.
+    pub const fn new(token: VgaToken) -> Self {
         Terminal {
-            cursor_x: 0,
-            cursor_y: 0,
-            current_color,
-            keyboard: keyboard::Qwerty::new(),
+            vga: VgaBuffer::new(token),
+            chord_deadlines: [None; CONSOLE_COUNT],
+            cmdline_areas: [None; CONSOLE_COUNT],
+            status: None,
+            theme: THEMES[0],
+            cmdline_highlight: false,
+            highlights: [None; CONSOLE_COUNT],
         }
     }
 
-    pub fn buffer_mut(&mut self) -> &mut [u16] {
-        const VGA_BUFFER: *mut [u16] = core::ptr::slice_from_raw_parts_mut(
-            core::ptr::without_provenance_mut(VGA_BUFFER_ADDRESS),
-            VGA_BUFFER_WIDTH * VGA_BUFFER_HEIGHT,
-        );
+    /// Saves the state of the terminal, or returns `None` if every snapshot slot is in use.
+    pub fn snapshot(&mut self) -> Option<TerminalSnapshot> {
+        let mut data = SNAPSHOTS.iter().find_map(Mutex::try_lock)?;
+        let cells = self.vga.buffer_mut();
+        data.cells[..cells.len()].copy_from_slice(cells);
+        data.height = self.vga.height();
+        data.status_cells = self.vga.status_cells().copied();
+        data.status = self.status;
+        data.cursor = self.vga.output_cursor();
+        data.cursor_shape = self.vga.cursor_shape();
+        data.cursor_hidden = self.vga.cursor_hidden();
+        data.color = self.vga.get_color();
+        data.scroll_region = self.vga.scroll_region();
+        data.blink = self.vga.blink();
+        let console = self.vga.output_console();
+        data.cmdline_area = self.cmdline_areas[console];
+        data.highlight = self.highlights[console];
+        Some(TerminalSnapshot(data))
+    }
+
+    /// Puts the terminal back in the state saved in `snapshot`, hardware cursor included.
+    ///
+    /// If the text mode changed since, as many rows as fit are put back.
+    pub fn restore(&mut self, snapshot: &TerminalSnapshot) {
+        let data = &snapshot.0;
+        // The status bar goes first, since it changes the number of rows.
+        self.vga.set_status_bar(data.status_cells.is_some());
+        if let Some(status_cells) = &data.status_cells {
+            self.vga.set_status_cells(status_cells);
+        }
+        self.status = data.status;
+        let cells = self.vga.buffer_mut();
+        let len = cells.len().min(data.height * VGA_BUFFER_WIDTH);
+        cells[..len].copy_from_slice(&data.cells[..len]);
+        let region = data
+            .scroll_region
+            .and_then(|(top, bottom)| self.vga.set_scroll_region(top, bottom).ok());
+        if region.is_none() {
+            self.vga.reset_scroll_region();
+        }
+        let (x, y) = data.cursor;
+        let y = y.min(self.vga.height() - 1);
+        self.vga.set_output_cursor(x, y);
+        let (cursor_start, cursor_end) = data.cursor_shape;
+        self.vga.set_cursor_shape(cursor_start, cursor_end);
+        if data.cursor_hidden {
+            self.vga.cursor_hide();
+        } else {
+            self.vga.cursor_show();
+        }
+        self.vga.set_color(data.color);
+        if self.vga.blink() != data.blink {
+            self.vga.set_blink(data.blink);
+        }
+        // What the command line was drawn over is back, but the screen may have scrolled since.
+        let console = self.vga.output_console();
+        let scrolls = self.vga.scroll_count();
+        self.cmdline_areas[console] = data
+            .cmdline_area
+            .map(|area| CmdlineArea { scrolls, ..area });
+        self.highlights[console] = data.highlight.map(|highlight| Highlight {
+            scrolls: highlight.scrolls.map(|_| scrolls),
+            ..highlight
+        });
+    }
 
-        // SAFETY: We have an exclusive reference to vga buffer object, which means we own
-        // the memory buffer.
-        unsafe { &mut *VGA_BUFFER }
+    /// Sets the current color from a foreground and a background color.
+    ///
+    /// Bright backgrounds make characters blink instead while [`blink`](VgaBuffer::blink) is on.
+    pub fn set_colors(&mut self, fg: Color, bg: Color) {
+        self.vga.set_color((bg as u8) << 4 | fg as u8);
     }
 
-    /// Clears the VGA buffer by filling it with spaces and default colors.
-    pub fn clear(&mut self) {
-        let color = self.current_color as u16;
-        self.buffer_mut().fill(color << 8 | (b' ' as u16));
+    /// Returns the foreground and background of the current color.
+    pub fn colors(&self) -> (Color, Color) {
+        let color = self.vga.get_color();
+        (Color::from_u8(color), Color::from_u8(color >> 4))
     }
 
-    /// Writes a byte to the VGA buffer at the specified coordinates with the given color.
-    #[inline]
-    pub fn write_byte(&mut self, x: usize, y: usize, byte: u8, color: u8) {
-        assert!(x < VGA_BUFFER_WIDTH);
-        assert!(y < VGA_BUFFER_HEIGHT);
-        self.buffer_mut()[x + y * VGA_BUFFER_WIDTH] = (color as u16) << 8 | (byte as u16);
+    /// Turns line wrapping on or off. When it is off, the characters written past the end of a
+    /// row are dropped until the next newline.
+    ///
+    /// The command line wraps either way.
+    pub fn set_wrap(&mut self, on: bool) {
+        self.vga.set_wrap_mode(if on {
+            WrapMode::Hard
+        } else {
+            WrapMode::Truncate
+        });
+    }
+
+    /// Returns the current theme.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Switches to the accessible variant of the current theme, or back to the theme itself.
+    pub fn set_accessible(&mut self, on: bool) {
+        let Some(base) = Theme::from_name(self.theme.name) else {
+            return;
+        };
+        self.set_theme(if on { base.accessible() } else { *base });
+    }
+
+    /// Switches to the colors of `theme`.
+    ///
+    /// What is already on screen is repainted in place: the text drawn in the colors of a role
+    /// of the previous theme takes the color of that role in `theme`.
+    ///
+    /// The accessible variant of `theme` is used while the current theme is accessible.
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        self.set_theme(if self.theme.accessible {
+            theme.accessible()
+        } else {
+            *theme
+        });
     }
 
-    /// Writes a byte to the VGA buffer at the specified coordinates using the current color.
-    pub fn write_at(&mut self, x: usize, y: usize, byte: u8) {
-        self.write_byte(x, y, byte, self.current_color);
+    /// Switches to the colors of `theme`, as is.
+    fn set_theme(&mut self, theme: Theme) {
+        let previous = self.theme;
+        self.vga.recolor(|color| previous.translate(&theme, color));
+        self.theme = theme;
+        self.vga.set_color(theme.normal);
+        // Redraw the status bar with its new color.
+        self.status = None;
     }
 
-    fn newline(&mut self) {
-        self.cursor_x = 0;
-        self.cursor_y += 1;
-        if self.cursor_y == VGA_BUFFER_HEIGHT {
-            self.buffer_mut().copy_within(VGA_BUFFER_WIDTH.., 0);
-            let color = self.current_color as u16;
-            self.buffer_mut()[VGA_BUFFER_WIDTH * (VGA_BUFFER_HEIGHT - 1)..].fill(color << 8);
-            self.cursor_y -= 1;
-        } else if self.cursor_y > VGA_BUFFER_HEIGHT {
-            unreachable!();
+    /// Turns the status bar on or off.
+    pub fn set_status_bar(&mut self, on: bool) {
+        self.vga.set_status_bar(on);
+        self.status = None;
+    }
+
+    /// Redraws the status bar if what it shows changed since it was last drawn.
+    fn refresh_status(&mut self, modifiers: Modifiers) {
+        if !self.vga.status_bar() {
+            return;
+        }
+        let status = Status {
+            console: self.vga.output_console(),
+            cursor: self.vga.output_cursor(),
+            color: self.vga.get_color(),
+            modifiers,
+            chord: self.chord_deadlines[self.vga.shown_console()].is_some(),
+            pending: crate::klog::pending() != 0,
+        };
+        if self.status == Some(status) {
+            return;
         }
+        self.status = Some(status);
+        let text = status.render();
+        self.vga.set_status(text.as_bytes(), self.theme.status);
     }
 
-    pub fn putchar(&mut self, c: char) {
-        match c {
-            '\n' => {
-                self.newline();
-            }
-            '\r' => {
-                self.cursor_x = 0;
-            }
-            '\t' => {
-                self.cursor_x = (self.cursor_x + 1).next_multiple_of(TAB_SIZE);
-            }
-            _ => {
-                const REPLACEMENT_CHARACTER: u8 = vga_chars::from_char('■').unwrap();
-                let b = vga_chars::from_char(c).unwrap_or(REPLACEMENT_CHARACTER);
-                self.write_at(self.cursor_x, self.cursor_y, b);
-                self.cursor_x += 1;
+    /// Returns whether the terminal is in the state saved in `snapshot`, as after
+    /// [`restore`](Self::restore).
+    pub fn matches(&mut self, snapshot: &TerminalSnapshot) -> bool {
+        let data = &*snapshot.0;
+        let cells = self.vga.buffer_mut();
+        cells.len() == data.height * VGA_BUFFER_WIDTH
+            && *cells == data.cells[..cells.len()]
+            && self.vga.status_cells() == data.status_cells.as_ref()
+            && self.vga.output_cursor() == data.cursor
+            && self.vga.cursor_shape() == data.cursor_shape
+            && self.vga.cursor_hidden() == data.cursor_hidden
+            && self.vga.get_color() == data.color
+            && self.vga.scroll_region() == data.scroll_region
+            && self.vga.blink() == data.blink
+    }
+
+    /// Switches the screen to the geometry `mode`, clearing every console.
+    pub fn set_text_mode(&mut self, mode: TextMode) {
+        self.vga.set_text_mode(mode);
+        self.cmdline_areas = [None; CONSOLE_COUNT];
+    }
+
+    /// Restricts scrolling to the rows from `top` to `bottom`, excluded, as with
+    /// [`VgaBuffer::set_scroll_region`].
+    ///
+    /// While the region ends above the last row, the command line is drawn on the last row.
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) -> Result<(), OutOfBounds> {
+        self.vga.set_scroll_region(top, bottom)?;
+        self.cmdline_areas = [None; CONSOLE_COUNT];
+        Ok(())
+    }
+
+    /// Lets the whole screen scroll again.
+    pub fn reset_scroll_region(&mut self) {
+        self.vga.reset_scroll_region();
+        self.cmdline_areas = [None; CONSOLE_COUNT];
+    }
+
+    /// Returns the row the command line is kept on, if the scrolling region leaves room for it
+    /// below.
+    fn prompt_row(&self) -> Option<usize> {
+        let (_, bottom) = self.vga.scroll_region()?;
+        let last = self.vga.height() - 1;
+        (bottom <= last).then_some(last)
+    }
+
+    /// Refreshes the command line.
+    ///
+    /// If nothing was written since the command line was last drawn, it is redrawn in place,
+    /// and the rows it no longer needs are cleared. Otherwise, it is drawn at the current row.
+    ///
+    /// Below a scrolling region, it is drawn on the last row instead, where output cannot reach
+    /// it. Only its end is shown if it does not fit, or the part around the edit position.
+    ///
+    /// The cursor goes to the edit position of `cmdline`.
+    pub fn refresh_cmdline(&mut self, cmdline: &Cmdline) {
+        self.vga.begin_frame();
+        self.clear_highlight();
+        if let Some(row) = self.prompt_row() {
+            self.refresh_pinned_cmdline(row, cmdline);
+            self.vga.end_frame();
+            return;
+        }
+        let console = self.vga.output_console();
+        let cursor = self.vga.output_cursor();
+        let scrolls = self.vga.scroll_count();
+        let row = match self.cmdline_areas[console] {
+            Some(area) if area.end == cursor && area.scrolls == scrolls => {
+                let (_, end_row) = cursor;
+                // The rows are between the start of the command line and the cursor.
+                _ = self
+                    .vga
+                    .clear_region(0, area.row + 1, VGA_BUFFER_WIDTH, end_row - area.row);
+                area.row
             }
+            _ => cursor.1,
+        };
+        self.vga.set_output_cursor(0, row);
+
+        // Write the command line, and clear what remains of the previous one. It always wraps,
+        // so that all of it can be edited.
+        let wrap = self.vga.wrap_mode();
+        self.vga.set_wrap_mode(WrapMode::Hard);
+        let color = self.vga.get_color();
+        self.vga.set_color(self.prompt_color());
+        for c in PS1.chars() {
+            self.vga.putchar(c);
         }
-        if self.cursor_x >= VGA_BUFFER_WIDTH {
-            self.newline();
+        self.vga.set_color(color);
+        let s = cmdline.as_str();
+        for c in s.chars() {
+            self.vga.putchar(c);
         }
-        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+        self.vga.clear_to_eol();
+        self.vga.set_wrap_mode(wrap);
+        let row = row.saturating_sub(self.vga.scroll_count().wrapping_sub(scrolls));
+        let (x, y) = if cmdline.cursor() == s.len() {
+            self.vga.output_cursor()
+        } else {
+            let edit = PS1.len() + s[..cmdline.cursor()].chars().count();
+            (edit % VGA_BUFFER_WIDTH, row + edit / VGA_BUFFER_WIDTH)
+        };
+        self.highlight(x, y);
+        self.vga.set_visual_cursor_pos(x, y);
+
+        self.draw_chord_indicator(row);
+        self.cmdline_areas[console] = Some(CmdlineArea {
+            row,
+            end: self.vga.output_cursor(),
+            scrolls: self.vga.scroll_count(),
+        });
+        self.vga.end_frame();
     }
 
-    #[inline]
-    pub fn set_color(&mut self, color: u8) {
-        self.current_color = color;
+    /// Draws the command line on the row `row`, leaving the output cursor where it is.
+    fn refresh_pinned_cmdline(&mut self, row: usize, cmdline: &Cmdline) {
+        _ = self.vga.clear_line(row);
+        let s = cmdline.as_str();
+        let len = PS1.len() + s.chars().count();
+        let edit = PS1.len() + s[..cmdline.cursor()].chars().count();
+        // Only the end is shown if it does not fit, keeping the last column free for the
+        // cursor, unless the edit position would then be out of sight.
+        let skip = (len + 1).saturating_sub(VGA_BUFFER_WIDTH).min(edit);
+        let prompt = PS1.get(skip..).unwrap_or("");
+        let x = self
+            .vga
+            .write_str_at_color(0, row, prompt, self.prompt_color());
+        let start = s
+            .char_indices()
+            .nth(skip.saturating_sub(PS1.len()))
+            .map_or(s.len(), |(i, _)| i);
+        let end = s[start..]
+            .char_indices()
+            .nth(VGA_BUFFER_WIDTH - 1 - x)
+            .map_or(s.len(), |(i, _)| start + i);
+        self.vga.write_str_at(x, row, &s[start..end]);
+        let x = edit - skip;
+        self.highlight(x, row);
+        self.draw_chord_indicator(row);
+        self.vga.set_visual_cursor_pos(x, row);
+    }
+
+    /// Returns the color of the prompt: the one of the theme, unless the current color is not
+    /// the normal color of the theme anymore.
+    fn prompt_color(&self) -> u8 {
+        let color = self.vga.get_color();
+        if color == self.theme.normal {
+            self.theme.prompt
+        } else {
+            color
+        }
     }
 
-    pub fn get_color(&self) -> u8 {
-        self.current_color
+    /// Returns whether the cell at the edit position of the command line is drawn in reverse
+    /// video.
+    pub fn cmdline_highlight(&self) -> bool {
+        self.cmdline_highlight
     }
 
-    pub fn set_visual_cursor_pos(&mut self, x: usize, y: usize) {
-        let pos = y * 80 + x;
-        unsafe {
-            outb(0x3D4, 0x0F);
-            outb(0x3D5, (pos & 0xFF) as u8);
+    /// Draws the cell at the edit position of the command line in reverse video, in addition to
+    /// placing the cursor there, or stops doing so.
+    pub fn set_cmdline_highlight(&mut self, on: bool) {
+        self.cmdline_highlight = on;
+        if !on {
+            self.clear_highlight();
+        }
+    }
 
-            outb(0x3D4, 0x0E);
-            outb(0x3D5, ((pos >> 8) & 0xFF) as u8);
+    /// Draws the cell at `x`, `y` in reverse video, if the edit position is highlighted.
+    fn highlight(&mut self, x: usize, y: usize) {
+        if !self.cmdline_highlight {
+            return;
         }
-        self.cursor_x = x;
-        self.cursor_y = y;
+        let Ok(cell) = self.vga.read_cell(x, y) else {
+            return;
+        };
+        let [byte, color] = cell.to_le_bytes();
+        let reversed = color.rotate_left(4);
+        _ = self.vga.write_byte(x, y, byte, reversed);
+        let scrolls = match self.vga.scroll_region() {
+            Some((top, bottom)) if !(top..bottom).contains(&y) => None,
+            _ => Some(self.vga.scroll_count()),
+        };
+        self.highlights[self.vga.output_console()] = Some(Highlight {
+            x,
+            y,
+            scrolls,
+            cell: u16::from_le_bytes([byte, reversed]),
+        });
     }
 
-    pub fn set_cursor_shape(&mut self, cursor_start: u8, cursor_end: u8) {
-        unsafe {
-            outb(0x3D4, 0x0A);
-            outb(0x3D5, (inb(0x3D5) & 0xC0) | cursor_start);
+    /// Puts back the colors of the cell drawn in reverse video in the console output goes to,
+    /// unless something else was written there since.
+    fn clear_highlight(&mut self) {
+        let Some(highlight) = self.highlights[self.vga.output_console()].take() else {
+            return;
+        };
+        // The cell moved up as the screen scrolled.
+        let scrolled = highlight
+            .scrolls
+            .map_or(0, |scrolls| self.vga.scroll_count().wrapping_sub(scrolls));
+        let Some(y) = highlight.y.checked_sub(scrolled) else {
+            return;
+        };
+        if self.vga.read_cell(highlight.x, y) == Ok(highlight.cell) {
+            let [byte, color] = highlight.cell.to_le_bytes();
+            _ = self
+                .vga
+                .write_byte(highlight.x, y, byte, color.rotate_left(4));
+        }
+    }
 
-            outb(0x3D4, 0x0B);
-            outb(0x3D5, (inb(0x3D5) & 0xE0) | cursor_end);
+    /// Shows that a chord is pending in the console output goes to at the end of the row `row`,
+    /// unless the status bar shows it.
+    fn draw_chord_indicator(&mut self, row: usize) {
+        if self.chord_deadlines[self.vga.output_console()].is_some() && !self.vga.status_bar() {
+            self.vga.write_str_at(
+                VGA_BUFFER_WIDTH - CHORD_INDICATOR.len(),
+                row,
+                CHORD_INDICATOR,
+            );
         }
     }
 
-    pub fn get_kb_data(&mut self) -> Option<u8> {
-        let status = unsafe { inb(0x64) };
-        if status & 0x01 == 0 {
-            return None;
+    /// Shows the console `n`, sends output to it, and redraws its command line.
+    fn switch_console(&mut self, n: usize, cmdline: &Cmdline) {
+        self.vga.show_console(n);
+        self.vga.set_output_console(n);
+        self.refresh_cmdline(cmdline);
+    }
+
+    /// Runs the `Ctrl+X` chord ending with `c` in the console shown, or returns it if the
+    /// terminal does not handle it.
+    fn run_chord<'a>(
+        &mut self,
+        c: char,
+        cmdlines: &'a mut [Cmdline; CONSOLE_COUNT],
+    ) -> Option<Input<'a>> {
+        let shown = self.vga.shown_console();
+        let action = CHORDS
+            .iter()
+            .find(|&&(key, _)| key == c)
+            .map(|&(_, action)| action);
+        match action {
+            Some(ChordAction::Clear) => {
+                self.vga.clear();
+                self.refresh_cmdline(&cmdlines[shown]);
+                None
+            }
+            Some(ChordAction::Console(n)) => {
+                // Without the indicator of the chord.
+                self.refresh_cmdline(&cmdlines[shown]);
+                self.switch_console(n, &cmdlines[n]);
+                None
+            }
+            // Escape cancels the chord.
+            None if c == '\x1b' => {
+                self.refresh_cmdline(&cmdlines[shown]);
+                None
+            }
+            None => {
+                self.refresh_cmdline(&cmdlines[shown]);
+                Some(Input::Chord(c))
+            }
         }
-        let scancode = unsafe { inb(0x60) };
-        Some(scancode)
     }
 
-    /// Returns the next key press event.
-    pub fn get_char(&mut self) -> Option<char> {
-        self.get_kb_data()
-            .and_then(|scancode| self.keyboard.advance(scancode))
+    /// Moves the command line to the output, as entered, before a command runs.
+    ///
+    /// The prompt stays in the output, ready for the command to be echoed after it.
+    pub fn submit_cmdline(&mut self) {
+        // The command is echoed after the prompt, which must then go to the output.
+        if self.prompt_row().is_some() {
+            self.vga.write_str(PS1).unwrap();
+        }
+        self.refresh_cmdline(&Cmdline::new());
+        self.clear_highlight();
     }
 
-    /// Refreshes the command line at the current row.
-    pub fn refresh_cmdline(&mut self, s: &str) {
-        const PS1: &str = "kernel@kfs$ ";
+    /// Returns the next line of input, or the function key pressed.
+    ///
+    /// `cmdlines` holds the command line of each console, and `histories` the lines entered in
+    /// each. Input goes to the ones of the console shown on screen.
+    pub fn get_line<'a>(
+        &mut self,
+        keyboard: &mut Keyboard,
+        cmdlines: &'a mut [Cmdline; CONSOLE_COUNT],
+        histories: &mut [History; CONSOLE_COUNT],
+    ) -> Option<Input<'a>> {
+        let shown = self.vga.shown_console();
+        let c = keyboard.get_char();
+        self.refresh_status(keyboard.modifiers());
+        let Some(c) = c else {
+            let now = time::monotonic_ns();
+            for n in 0..CONSOLE_COUNT {
+                if self.chord_deadlines[n].is_some_and(|deadline| now >= deadline) {
+                    self.chord_deadlines[n] = None;
+                    // The indicator of the other consoles goes when they are drawn again.
+                    if n == shown {
+                        self.refresh_cmdline(&cmdlines[shown]);
+                    }
+                }
+            }
+            return None;
+        };
 
-        self.cursor_x = 0;
-        let cursor_y = self.cursor_y;
+        if keyboard.modifiers().shift() {
+            let page = (self.vga.height() - 1) as isize;
+            let rows = match c {
+                keys::PAGE_UP => Some(page),
+                keys::PAGE_DOWN => Some(-page),
+                keys::UP => Some(1),
+                keys::DOWN => Some(-1),
+                keys::HOME => Some(isize::MAX),
+                keys::END => Some(isize::MIN),
+                _ => None,
+            };
+            if let Some(rows) = rows {
+                self.vga.scroll_view(rows);
+                return None;
+            }
+        }
+        // Any other key brings the live screen back.
+        self.vga.reset_view();
 
-        // Clear the line.
-        let clear_color = (self.current_color as u16) << 8;
-        self.buffer_mut()[cursor_y * VGA_BUFFER_WIDTH..(cursor_y + 1) * VGA_BUFFER_WIDTH]
-            .fill(clear_color);
+        if keyboard.modifiers().alt()
+            && let Some(n) = CONSOLE_KEYS.iter().position(|&key| key == c)
+        {
+            self.switch_console(n, &cmdlines[n]);
+            return None;
+        }
 
-        // Write the command line.
-        for c in PS1.chars().chain(s.chars()) {
-            self.putchar(c);
+        // Clearing the screen keeps what was typed, and cancels a pending chord.
+        if matches!(c, 'l' | 'L') && keyboard.modifiers().control() {
+            self.chord_deadlines[shown] = None;
+            self.vga.clear();
+            self.refresh_cmdline(&cmdlines[shown]);
+            return None;
         }
-    }
 
-    /// Returns the next line of input.
-    pub fn get_line<'a>(&mut self, cmdline: &'a mut Cmdline) -> Option<&'a str> {
-        let c = self.get_char()?;
+        // The key completing a chord never goes to the command line.
+        if self.chord_deadlines[shown].take().is_some() {
+            return self.run_chord(c, cmdlines);
+        }
+        let cmdline = &mut cmdlines[shown];
+        let history = &mut histories[shown];
+        if let Some(n) = keys::function(c) {
+            return Some(Input::Function(n));
+        }
 
         match c {
+            'x' | 'X' if keyboard.modifiers().control() => {
+                self.chord_deadlines[shown] = Some(time::monotonic_ns() + CHORD_TIMEOUT_NS);
+                self.refresh_cmdline(cmdline);
+                None
+            }
             '\n' => {
-                self.refresh_cmdline("");
-                Some(cmdline.take())
+                self.submit_cmdline();
+                history.push(cmdline.as_str());
+                Some(Input::Line(cmdline.take()))
+            }
+            keys::UP | keys::DOWN => {
+                let changed = if c == keys::UP {
+                    history.previous(cmdline)
+                } else {
+                    history.next(cmdline)
+                };
+                if changed {
+                    self.refresh_cmdline(cmdline);
+                }
+                None
             }
             '\x08' => {
-                if self.keyboard.modifiers().control() {
+                if keyboard.modifiers().control() {
                     cmdline.pop_word();
                 } else {
                     cmdline.pop();
                 }
 
-                self.refresh_cmdline(cmdline.as_str());
+                self.refresh_cmdline(cmdline);
 
                 None
             }
-            c if c.is_control() => None,
+            '\t' => Some(Input::Complete),
+            keys::LEFT | keys::RIGHT | keys::HOME | keys::END | keys::DELETE => {
+                match c {
+                    keys::LEFT => _ = cmdline.left(),
+                    keys::RIGHT => _ = cmdline.right(),
+                    keys::HOME => cmdline.home(),
+                    keys::END => cmdline.end(),
+                    _ => cmdline.delete(),
+                }
+                self.refresh_cmdline(cmdline);
+                None
+            }
+            c if c.is_control() || keys::is_key(c) => None,
             c => {
                 if cmdline.push(c) {
-                    self.refresh_cmdline(cmdline.as_str());
+                    self.refresh_cmdline(cmdline);
                 }
                 None
             }
@@ -255,31 +1075,82 @@ impl Terminal {
     }
 }
 
+impl Deref for Terminal {
+    type Target = VgaBuffer;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.vga
+    }
+}
+
+impl DerefMut for Terminal {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vga
+    }
+}
+
 impl core::fmt::Write for Terminal {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        for c in s.chars() {
-            self.putchar(c);
-        }
-        Ok(())
+        self.vga.write_str(s)
     }
 
     fn write_char(&mut self, c: char) -> core::fmt::Result {
-        self.putchar(c);
+        self.vga.write_char(c)
+    }
+}
+
+/// QEMU's `isa-debugcon` device: every byte written to port 0xE9 shows up on the host.
+///
+/// Writing to it takes no lock, which makes it usable anywhere, including while the terminal is
+/// locked. Without the `debugcon` feature, writes are discarded.
+pub struct DebugCon;
+
+impl core::fmt::Write for DebugCon {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if cfg!(feature = "debugcon") {
+            for byte in s.bytes() {
+                unsafe { outb(0xE9, byte) };
+            }
+        }
         Ok(())
     }
 }
 
-// unsafe fn get_cursor_pos() -> (usize, usize) {
-//     let mut pos: usize;
-//     unsafe {
-//         outb(0x3D4, 0x0F);
-//         pos = inb(0x3D5) as usize;
-//
-//         outb(0x3D4, 0x0E);
-//         pos |= (inb(0x3D5) as usize) << 8;
-//     }
-//     (pos % VGA_BUFFER_WIDTH, pos / VGA_BUFFER_WIDTH)
-// }
+/// The widest line [`hexdump`] writes, in bytes.
+pub const HEXDUMP_MAX_WIDTH: usize = 32;
+
+/// Writes the `len` bytes from `start` to `out` as a hexdump, with `width` bytes per line.
+///
+/// The memory is read a line at a time with [volatile reads](read_memory), so that memory-mapped
+/// devices show their live contents. See [`kfs_core::hexdump`] for the layout.
+///
+/// # Safety
+///
+/// Every byte from `start` to `start + len` must be readable.
+///
+/// # Panics
+///
+/// Panics if `width` is 0 or above [`HEXDUMP_MAX_WIDTH`].
+pub unsafe fn hexdump(
+    out: &mut dyn Write,
+    start: *const u8,
+    len: usize,
+    width: usize,
+) -> core::fmt::Result {
+    assert!(width <= HEXDUMP_MAX_WIDTH, "hexdump width too large");
+    let mut buffer = [0; HEXDUMP_MAX_WIDTH];
+    for line in hexdump::lines(start.addr(), len, width) {
+        let bytes = &mut buffer[..line.len];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = unsafe { read_memory(line.start() + i, Width::Byte) } as u8;
+        }
+        hexdump::write_line(out, line, bytes, width)?;
+    }
+    Ok(())
+}
 
 pub fn qemu_shutdown() -> ! {
     unsafe {
@@ -295,11 +1166,115 @@ pub fn qemu_reboot() -> ! {
     }
 }
 
+/// The width of a memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    /// 8 bits.
+    Byte,
+    /// 16 bits.
+    Word,
+    /// 32 bits.
+    Dword,
+}
+
+impl Width {
+    /// Parses the width named `name`: `b`, `w` or `d`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "b" => Some(Width::Byte),
+            "w" => Some(Width::Word),
+            "d" => Some(Width::Dword),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes accessed.
+    pub const fn bytes(self) -> usize {
+        match self {
+            Width::Byte => 1,
+            Width::Word => 2,
+            Width::Dword => 4,
+        }
+    }
+
+    /// Returns the largest value that fits.
+    pub const fn max(self) -> u32 {
+        u32::MAX >> (32 - 8 * self.bytes())
+    }
+}
+
+/// Reads the value of `width` at `addr` with a volatile read.
+///
+/// Unlike with [`read_volatile`](core::ptr::read_volatile), `addr` may be unaligned, or 0,
+/// which is where the real mode interrupt table lies.
+///
+/// # Safety
+///
+/// The memory at `addr` must be readable.
+pub unsafe fn read_memory(addr: usize, width: Width) -> u32 {
+    let ret: u32;
+    unsafe {
+        match width {
+            Width::Byte => asm!(
+                "movzx {:e}, byte ptr [{}]",
+                out(reg) ret,
+                in(reg) addr,
+                options(nostack, readonly, preserves_flags),
+            ),
+            Width::Word => asm!(
+                "movzx {:e}, word ptr [{}]",
+                out(reg) ret,
+                in(reg) addr,
+                options(nostack, readonly, preserves_flags),
+            ),
+            Width::Dword => asm!(
+                "mov {:e}, dword ptr [{}]",
+                out(reg) ret,
+                in(reg) addr,
+                options(nostack, readonly, preserves_flags),
+            ),
+        }
+    }
+    ret
+}
+
+/// Writes the low `width` of `value` at `addr` with a volatile write.
+///
+/// As with [`read_memory`], `addr` may be unaligned or 0.
+///
+/// # Safety
+///
+/// The memory at `addr` must be writable, and nothing may rely on what it held.
+pub unsafe fn write_memory(addr: usize, width: Width, value: u32) {
+    unsafe {
+        match width {
+            Width::Byte => asm!(
+                "mov byte ptr [{}], {}",
+                in(reg) addr,
+                in(reg_byte) value as u8,
+                options(nostack, preserves_flags),
+            ),
+            Width::Word => asm!(
+                "mov word ptr [{}], {:x}",
+                in(reg) addr,
+                in(reg) value as u16,
+                options(nostack, preserves_flags),
+            ),
+            Width::Dword => asm!(
+                "mov dword ptr [{}], {:e}",
+                in(reg) addr,
+                in(reg) value,
+                options(nostack, preserves_flags),
+            ),
+        }
+    }
+}
+
 /// Read a byte from the specified port.
 /// # Safety
 /// This function is unsafe because some accesses to certain ports may have
 /// side effects that can compromise memory safety.
-unsafe fn inb(port: u16) -> u8 {
+pub(crate) unsafe fn inb(port: u16) -> u8 {
     let ret: u8;
     unsafe {
         asm!(
@@ -316,7 +1291,7 @@ unsafe fn inb(port: u16) -> u8 {
 /// # Safety
 /// This function is unsafe because some accesses to certain ports may have
 /// side effects that can compromise memory safety.
-unsafe fn outb(port: u16, val: u8) {
+pub(crate) unsafe fn outb(port: u16, val: u8) {
     unsafe {
         asm!(
             "out dx, al",
@@ -327,11 +1302,28 @@ unsafe fn outb(port: u16, val: u8) {
     }
 }
 
+/// Read a word from the specified port.
+/// # Safety
+/// This function is unsafe because some accesses to certain ports may have
+/// side effects that can compromise memory safety.
+pub(crate) unsafe fn inw(port: u16) -> u16 {
+    let ret: u16;
+    unsafe {
+        asm!(
+            "in ax, dx",
+            out("ax") ret,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags),
+        )
+    }
+    ret
+}
+
 /// Write a word to the specified port.
 /// # Safety
 /// This function is unsafe because some accesses to certain ports may have
 /// side effects that can compromise memory safety.
-unsafe fn outw(port: u16, val: u16) {
+pub(crate) unsafe fn outw(port: u16, val: u16) {
     unsafe {
         asm!(
             "out dx, ax",
@@ -341,3 +1333,65 @@ unsafe fn outw(port: u16, val: u16) {
         )
     }
 }
+
+/// Read a dword from the specified port.
+/// # Safety
+/// This function is unsafe because some accesses to certain ports may have
+/// side effects that can compromise memory safety.
+pub(crate) unsafe fn inl(port: u16) -> u32 {
+    let ret: u32;
+    unsafe {
+        asm!(
+            "in eax, dx",
+            out("eax") ret,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags),
+        )
+    }
+    ret
+}
+
+/// Write a dword to the specified port.
+/// # Safety
+/// This function is unsafe because some accesses to certain ports may have
+/// side effects that can compromise memory safety.
+pub(crate) unsafe fn outl(port: u16, val: u32) {
+    unsafe {
+        asm!(
+            "out dx, eax",
+            in("eax") val,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags),
+        )
+    }
+}
+
+/// Reads a value of `width` from `port`.
+///
+/// # Safety
+///
+/// See [`inb`].
+pub unsafe fn read_port(port: u16, width: Width) -> u32 {
+    unsafe {
+        match width {
+            Width::Byte => inb(port).into(),
+            Width::Word => inw(port).into(),
+            Width::Dword => inl(port),
+        }
+    }
+}
+
+/// Writes the low `width` of `value` to `port`.
+///
+/// # Safety
+///
+/// See [`outb`].
+pub unsafe fn write_port(port: u16, width: Width, value: u32) {
+    unsafe {
+        match width {
+            Width::Byte => outb(port, value as u8),
+            Width::Word => outw(port, value as u16),
+            Width::Dword => outl(port, value),
+        }
+    }
+}
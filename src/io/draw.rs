@@ -0,0 +1,115 @@
+//! Line drawing with the box characters of code page 437, for text user interfaces.
+//!
+//! Everything is drawn with the current color, and clipped against the edges of the screen.
+
+use kfs_core::vga_chars;
+
+use super::{VGA_BUFFER_WIDTH, VgaBuffer};
+
+/// The kind of lines boxes are drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoxStyle {
+    /// `┌─┐`
+    #[default]
+    Single,
+    /// `╔═╗`
+    Double,
+}
+
+/// The characters a [`BoxStyle`] is drawn with.
+struct Glyphs {
+    horizontal: u8,
+    vertical: u8,
+    top_left: u8,
+    top_right: u8,
+    bottom_left: u8,
+    bottom_right: u8,
+}
+
+/// Returns the VGA character for `c`, at compile time.
+const fn glyph(c: char) -> u8 {
+    vga_chars::from_char(c).unwrap()
+}
+
+impl BoxStyle {
+    /// Returns the characters the style is drawn with.
+    const fn glyphs(self) -> Glyphs {
+        match self {
+            BoxStyle::Single => Glyphs {
+                horizontal: glyph('─'),
+                vertical: glyph('│'),
+                top_left: glyph('┌'),
+                top_right: glyph('┐'),
+                bottom_left: glyph('└'),
+                bottom_right: glyph('┘'),
+            },
+            BoxStyle::Double => Glyphs {
+                horizontal: glyph('═'),
+                vertical: glyph('║'),
+                top_left: glyph('╔'),
+                top_right: glyph('╗'),
+                bottom_left: glyph('╚'),
+                bottom_right: glyph('╝'),
+            },
+        }
+    }
+}
+
+/// Writes `byte` at `x`, `y`, unless that is off the screen.
+fn put(vga: &mut VgaBuffer, x: usize, y: usize, byte: u8) {
+    _ = vga.write_at(x, y, byte);
+}
+
+/// Draws a horizontal line of `len` cells, starting at `x`, `y`.
+pub fn draw_hline(vga: &mut VgaBuffer, x: usize, y: usize, len: usize, style: BoxStyle) {
+    let horizontal = style.glyphs().horizontal;
+    for x in x..x.saturating_add(len).min(VGA_BUFFER_WIDTH) {
+        put(vga, x, y, horizontal);
+    }
+}
+
+/// Draws a vertical line of `len` cells, starting at `x`, `y`.
+pub fn draw_vline(vga: &mut VgaBuffer, x: usize, y: usize, len: usize, style: BoxStyle) {
+    let vertical = style.glyphs().vertical;
+    for y in y..y.saturating_add(len).min(vga.height()) {
+        put(vga, x, y, vertical);
+    }
+}
+
+/// Draws the frame of a box of `w` by `h` cells, with its top left corner at `x`, `y`. The
+/// inside of the box is left untouched.
+///
+/// A box one cell high or wide is drawn as a line, and an empty box is not drawn at all.
+pub fn draw_box(vga: &mut VgaBuffer, x: usize, y: usize, w: usize, h: usize, style: BoxStyle) {
+    match (w, h) {
+        (0, _) | (_, 0) => {}
+        (_, 1) => draw_hline(vga, x, y, w, style),
+        (1, _) => draw_vline(vga, x, y, h, style),
+        _ => {
+            let glyphs = style.glyphs();
+            let (right, bottom) = (x.saturating_add(w - 1), y.saturating_add(h - 1));
+            let (inside_x, inside_y) = (x.saturating_add(1), y.saturating_add(1));
+            draw_hline(vga, inside_x, y, w - 2, style);
+            draw_hline(vga, inside_x, bottom, w - 2, style);
+            draw_vline(vga, x, inside_y, h - 2, style);
+            draw_vline(vga, right, inside_y, h - 2, style);
+            put(vga, x, y, glyphs.top_left);
+            put(vga, right, y, glyphs.top_right);
+            put(vga, x, bottom, glyphs.bottom_left);
+            put(vga, right, bottom, glyphs.bottom_right);
+        }
+    }
+}
+
+/// Writes `s` centered within the `w` cells starting at `x`, `y`. Only the beginning of `s` is
+/// written if it does not fit.
+///
+/// Characters without a VGA equivalent are shown as the
+/// [replacement character](VgaBuffer::replacement).
+pub fn draw_text_centered(vga: &mut VgaBuffer, x: usize, y: usize, w: usize, s: &str) {
+    let (len, s) = match s.char_indices().nth(w) {
+        Some((end, _)) => (w, &s[..end]),
+        None => (s.chars().count(), s),
+    };
+    vga.write_str_at(x.saturating_add((w - len) / 2), y, s);
+}
@@ -0,0 +1,403 @@
+use kfs_core::keyboard;
+
+use crate::{boot::KeyboardToken, time};
+
+use super::{inb, outb};
+
+/// How long the controller may take to accept or answer a command, in nanoseconds.
+const CONTROLLER_TIMEOUT_NS: u64 = 10_000_000;
+
+/// How many times a command is sent to the keyboard when it keeps asking for it again.
+const KEYBOARD_COMMAND_TRIES: usize = 3;
+
+/// The number of scancodes that can be buffered before the oldest ones are dropped.
+const SCANCODE_RING_SIZE: usize = 16;
+
+/// The number of injected key events that can be buffered before the newest ones are dropped.
+const INJECTED_RING_SIZE: usize = 16;
+
+/// How the scancodes of the keyboard become the scancodes of set 1 the decoder reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Translation {
+    /// The controller translates them, as set up by the BIOS on most machines.
+    Controller,
+    /// The keyboard sends set 1 itself.
+    None,
+    /// The keyboard sends set 2, translated with [`keyboard::Set2`].
+    Kernel,
+}
+
+/// The PS/2 keyboard input device.
+///
+/// Owns the scancode decoder along with a small ring of scancodes that have been read from
+/// the controller but not consumed yet.
+pub struct Keyboard {
+    /// The scancode decoder, which also tracks the state of the modifiers.
+    decoder: keyboard::Decoder,
+    /// How the scancodes read become scancodes of set 1.
+    translation: Translation,
+    /// The state of the translation of set 2, when done by the kernel.
+    set2: keyboard::Set2,
+    /// A decoder of its own for the scancodes as they are read, watching for **CTRL+ALT+DEL**
+    /// and **CTRL+C** even when nothing consumes the input.
+    watcher: keyboard::Decoder,
+    /// Whether **CTRL+ALT+DEL** reboots.
+    ctrl_alt_del: bool,
+    /// Whether the debug keys are enabled.
+    sysrq: bool,
+    /// Whether **ALT+SYSRQ** was pressed, so that the next letter is a debug key.
+    sysrq_pending: bool,
+    /// Scancodes waiting to be consumed.
+    ring: [u8; SCANCODE_RING_SIZE],
+    /// The index of the oldest scancode in `ring`.
+    head: usize,
+    /// The number of scancodes in `ring`.
+    len: usize,
+    /// The time at which the last scancode was read from the controller, in nanoseconds.
+    last_input_ns: u64,
+    /// Key events from other devices, waiting to be consumed before the scancodes.
+    injected: [Option<keyboard::KeyEvent>; INJECTED_RING_SIZE],
+    /// The modifiers of the last event returned by [`Keyboard::get_event`], if it was injected.
+    injected_modifiers: Option<keyboard::Modifiers>,
+}
+
+impl Keyboard {
+    /// Creates the keyboard interface.
+    ///
+    /// Consumes the [`KeyboardToken`], which guarantees exclusive access to the keyboard
+    /// controller ports.
+    pub const fn new(_token: KeyboardToken) -> Self {
+        Keyboard {
+            decoder: keyboard::Decoder::new(&keyboard::US),
+            translation: Translation::Controller,
+            set2: keyboard::Set2::new(),
+            watcher: keyboard::Decoder::new(&keyboard::US),
+            ctrl_alt_del: true,
+            sysrq: false,
+            sysrq_pending: false,
+            ring: [0; SCANCODE_RING_SIZE],
+            head: 0,
+            len: 0,
+            last_input_ns: 0,
+            injected: [None; INJECTED_RING_SIZE],
+            injected_modifiers: None,
+        }
+    }
+
+    /// Returns the current state of the modifiers, or the ones of the last event returned by
+    /// [`Keyboard::get_event`] if it was injected.
+    #[inline]
+    pub fn modifiers(&self) -> keyboard::Modifiers {
+        self.injected_modifiers
+            .unwrap_or_else(|| self.decoder.modifiers())
+    }
+
+    /// Forgets the keys held, after scancodes were read with [`Keyboard::get_kb_data`] without
+    /// going through the decoder.
+    pub fn reset_decoder(&mut self) {
+        self.decoder.reset();
+    }
+
+    /// Returns the layout the scancodes are decoded with.
+    #[inline]
+    pub fn layout(&self) -> &'static keyboard::Layout {
+        self.decoder.layout()
+    }
+
+    /// Changes the layout the scancodes are decoded with.
+    #[inline]
+    pub fn set_layout(&mut self, layout: &'static keyboard::Layout) {
+        self.decoder.set_layout(layout);
+        self.watcher.set_layout(layout);
+    }
+
+    /// Returns how the scancodes of the keyboard are translated to set 1.
+    pub fn translation(&self) -> Translation {
+        self.translation
+    }
+
+    /// Finds out how the scancodes of the keyboard must be translated to set 1, and returns it.
+    ///
+    /// Without translation by the controller, the keyboard is asked which set it sends, and
+    /// set 2 is assumed if it does not say. When the controller does not answer, it is assumed
+    /// to translate, as it does on most machines.
+    pub fn detect_translation(&mut self) -> Translation {
+        // Bit 6 of the command byte enables the translation.
+        let translated = self
+            .controller_read(0x20)
+            .is_none_or(|command_byte| command_byte & 0x40 != 0);
+        self.translation = if translated {
+            Translation::Controller
+        } else if self.scancode_set() == Some(1) {
+            Translation::None
+        } else {
+            Translation::Kernel
+        };
+        self.set2 = keyboard::Set2::new();
+        self.translation
+    }
+
+    /// Returns whether **CTRL+ALT+DEL** reboots.
+    pub fn ctrl_alt_del(&self) -> bool {
+        self.ctrl_alt_del
+    }
+
+    /// Sets whether **CTRL+ALT+DEL** reboots.
+    pub fn set_ctrl_alt_del(&mut self, enabled: bool) {
+        self.ctrl_alt_del = enabled;
+    }
+
+    /// Returns whether the debug keys are enabled.
+    pub fn sysrq(&self) -> bool {
+        self.sysrq
+    }
+
+    /// Sets whether the debug keys are enabled: a letter after **ALT+SYSRQ**, or with
+    /// **CTRL+ALT** held, runs the debug action of that letter.
+    pub fn set_sysrq(&mut self, enabled: bool) {
+        self.sysrq = enabled;
+        self.sysrq_pending = false;
+    }
+
+    /// Handles the key combinations that act as soon as they are read, whatever the kernel is
+    /// doing. Returns whether the event was used, and must not be read as input.
+    fn watch(&mut self, event: keyboard::KeyEvent) -> bool {
+        use keyboard::Key;
+
+        let modifiers = event.modifiers;
+        if !event.pressed {
+            return false;
+        }
+        if self.ctrl_alt_del && event.key == Key::Delete && modifiers.control() && modifiers.alt() {
+            crate::ctrl_alt_del();
+        }
+        if matches!(event.key, Key::Char('c' | 'C')) && modifiers.control() && !modifiers.alt() {
+            crate::shell::cancel();
+            return true;
+        }
+        if !self.sysrq {
+            return false;
+        }
+        let pending = core::mem::take(&mut self.sysrq_pending);
+        match event.key {
+            Key::SysRq => {
+                self.sysrq_pending = true;
+                true
+            }
+            Key::Char(c) if pending => {
+                // Anything but a debug key gets the list of them.
+                if !crate::sysrq(c) {
+                    crate::sysrq('h');
+                }
+                true
+            }
+            Key::Char(c) if modifiers.control() && modifiers.alt() => crate::sysrq(c),
+            // Modifiers may be pressed between ALT+SYSRQ and the letter.
+            Key::LeftShift
+            | Key::RightShift
+            | Key::LeftControl
+            | Key::RightControl
+            | Key::LeftAlt
+            | Key::RightAlt => {
+                self.sysrq_pending = pending;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves every scancode available from the controller into the ring, translated to set 1.
+    ///
+    /// When the ring is full, the oldest scancode is dropped. The key combinations of
+    /// [`Keyboard::watch`] act right away, and do not reach the ring.
+    pub fn poll(&mut self) {
+        while unsafe { inb(0x64) } & 0x01 != 0 {
+            let scancode = unsafe { inb(0x60) };
+            self.last_input_ns = time::monotonic_ns();
+            let scancode = match self.translation {
+                Translation::Kernel => match self.set2.translate(scancode) {
+                    Some(scancode) => scancode,
+                    None => continue,
+                },
+                _ => scancode,
+            };
+            if let Some(event) = self.watcher.advance_event(scancode)
+                && self.watch(event)
+            {
+                continue;
+            }
+            if self.len == SCANCODE_RING_SIZE {
+                self.head = (self.head + 1) % SCANCODE_RING_SIZE;
+                self.len -= 1;
+            }
+            self.ring[(self.head + self.len) % SCANCODE_RING_SIZE] = scancode;
+            self.len += 1;
+        }
+    }
+
+    /// Returns the next raw scancode, if any.
+    pub fn get_kb_data(&mut self) -> Option<u8> {
+        self.poll();
+        if self.len == 0 {
+            return None;
+        }
+        let scancode = self.ring[self.head];
+        self.head = (self.head + 1) % SCANCODE_RING_SIZE;
+        self.len -= 1;
+        Some(scancode)
+    }
+
+    /// Busy-waits until the controller status matches `mask`, giving up after
+    /// [`CONTROLLER_TIMEOUT_NS`].
+    fn wait_status(&self, mask: u8, set: bool) -> bool {
+        let deadline = time::monotonic_ns() + CONTROLLER_TIMEOUT_NS;
+        while (unsafe { inb(0x64) } & mask != 0) != set {
+            if time::monotonic_ns() >= deadline {
+                return false;
+            }
+            core::hint::spin_loop();
+        }
+        true
+    }
+
+    /// Writes `byte` to the controller port `port` once it accepts input.
+    fn send(&mut self, port: u16, byte: u8) -> bool {
+        // Anything the keyboard sent so far is a scancode: move it out of the way, so that it is
+        // not mistaken for a response.
+        self.poll();
+        if !self.wait_status(0x02, false) {
+            return false;
+        }
+        unsafe { outb(port, byte) };
+        true
+    }
+
+    /// Reads the response to a command.
+    fn receive(&mut self) -> Option<u8> {
+        self.wait_status(0x01, true).then(|| unsafe { inb(0x60) })
+    }
+
+    /// Returns the controller status register.
+    pub fn controller_status(&mut self) -> u8 {
+        unsafe { inb(0x64) }
+    }
+
+    /// Sends `command` to the controller, and returns its one-byte response.
+    pub fn controller_read(&mut self, command: u8) -> Option<u8> {
+        if !self.send(0x64, command) {
+            return None;
+        }
+        self.receive()
+    }
+
+    /// Sends `command` to the controller, followed by its parameter `value`.
+    pub fn controller_write(&mut self, command: u8, value: u8) -> bool {
+        self.send(0x64, command) && self.send(0x60, value)
+    }
+
+    /// Sends `command` to the keyboard itself, and returns whether it was acknowledged.
+    ///
+    /// The command is sent again while the keyboard asks for it, up to
+    /// [`KEYBOARD_COMMAND_TRIES`] times.
+    fn keyboard_command(&mut self, command: u8) -> bool {
+        const ACK: u8 = 0xFA;
+        const RESEND: u8 = 0xFE;
+        for _ in 0..KEYBOARD_COMMAND_TRIES {
+            if !self.send(0x60, command) {
+                return false;
+            }
+            match self.receive() {
+                Some(ACK) => return true,
+                Some(RESEND) => continue,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Sets how long a key must be held before it repeats, and how fast it then repeats, to
+    /// the supported values closest to `delay_ms` milliseconds and `rate_cps` characters per
+    /// second.
+    ///
+    /// Returns the typematic byte sent, from which the values used can be found, or [`None`] if
+    /// the keyboard did not acknowledge it.
+    pub fn set_typematic(&mut self, delay_ms: u32, rate_cps: u32) -> Option<u8> {
+        let byte = keyboard::typematic_byte(delay_ms, rate_cps);
+        (self.keyboard_command(0xF3) && self.keyboard_command(byte)).then_some(byte)
+    }
+
+    /// Asks the keyboard which scancode set it uses.
+    ///
+    /// This is the set the keyboard sends, before any translation by the controller.
+    pub fn scancode_set(&mut self) -> Option<u8> {
+        if !self.keyboard_command(0xF0) || !self.keyboard_command(0x00) {
+            return None;
+        }
+        // With translation enabled, the answer itself is translated.
+        match self.receive()? {
+            1 | 0x43 => Some(1),
+            2 | 0x41 => Some(2),
+            3 | 0x3F => Some(3),
+            _ => None,
+        }
+    }
+
+    /// Queues `event` from another device, such as a terminal on the serial port, to be read
+    /// like the keys of this keyboard. It is dropped if too many are waiting.
+    ///
+    /// The key combinations of [`Keyboard::watch`] act right away, and are not queued.
+    pub fn inject(&mut self, event: keyboard::KeyEvent) {
+        self.last_input_ns = time::monotonic_ns();
+        if self.watch(event) {
+            return;
+        }
+        if let Some(slot) = self.injected.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(event);
+        }
+    }
+
+    /// Returns the next key event, presses and releases alike, the injected ones first.
+    pub fn get_event(&mut self) -> Option<keyboard::KeyEvent> {
+        self.injected_modifiers = None;
+        if let Some(event) = self.injected[0].take() {
+            self.injected.rotate_left(1);
+            self.injected_modifiers = Some(event.modifiers);
+            return Some(event);
+        }
+        while let Some(scancode) = self.get_kb_data() {
+            if let Some(event) = self.decoder.advance_event(scancode) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// Returns the character of the next key press producing one.
+    ///
+    /// The events without a character are consumed along the way.
+    pub fn get_char(&mut self) -> Option<char> {
+        while let Some(event) = self.get_event() {
+            if let Some(c) = self.decoder.char(event) {
+                return Some(c);
+            }
+        }
+        None
+    }
+
+    /// Consumes every available scancode, and returns whether any key was pressed, including
+    /// the modifiers.
+    ///
+    /// The scancodes still go through the decoder, so that the modifiers stay up to date.
+    pub fn key_pressed(&mut self) -> bool {
+        let mut pressed = false;
+        while let Some(event) = self.get_event() {
+            pressed |= event.pressed;
+        }
+        pressed
+    }
+
+    /// Returns how long ago the last scancode was received, in nanoseconds.
+    pub fn idle_ns(&self) -> u64 {
+        time::monotonic_ns().saturating_sub(self.last_input_ns)
+    }
+}
@@ -0,0 +1,129 @@
+//! Progress indicators for long operations, redrawn in place rather than printed line after
+//! line.
+//!
+//! They are drawn on fixed cells, and never move the cursor. Output scrolling the screen takes
+//! what they drew along with it, and the next update draws on whatever came in its place.
+//! Callers printing while progress is shown should either keep the indicator out of a
+//! [scrolling region](VgaBuffer::set_scroll_region), or accept that it moves.
+
+use core::fmt::Write;
+
+use super::{RowText, VGA_BUFFER_WIDTH, VgaBuffer};
+
+/// The number of cells between the brackets of a [`ProgressBar`], which leaves room for the
+/// percentage and keeps the last column free.
+const BAR_WIDTH: usize = VGA_BUFFER_WIDTH - 8;
+
+/// The frames of a [`Spinner`].
+const SPINNER_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// Copies the cells from `x`, `y` on to `cells`. The cells off the screen read as 0.
+fn save_cells(vga: &mut VgaBuffer, x: usize, y: usize, cells: &mut [u16]) {
+    for (i, cell) in cells.iter_mut().enumerate() {
+        *cell = vga.read_cell(x + i, y).unwrap_or(0);
+    }
+}
+
+/// Writes `cells` back from `x`, `y` on, as saved by [`save_cells`].
+fn restore_cells(vga: &mut VgaBuffer, x: usize, y: usize, cells: &[u16]) {
+    for (i, &cell) in cells.iter().enumerate() {
+        let [byte, color] = cell.to_le_bytes();
+        _ = vga.write_byte(x + i, y, byte, color);
+    }
+}
+
+/// A bar filling a row as the steps of an operation are done: `[#####.....]  50%`.
+pub struct ProgressBar {
+    row: usize,
+    total: u64,
+    /// The row as it was before the bar was drawn.
+    saved: [u16; VGA_BUFFER_WIDTH],
+}
+
+impl ProgressBar {
+    /// Draws an empty bar on the row `row`, for an operation of `total` steps.
+    ///
+    /// With no steps at all, the bar is full right away.
+    pub fn new(vga: &mut VgaBuffer, row: usize, total: u64) -> Self {
+        let mut bar = ProgressBar {
+            row,
+            total,
+            saved: [0; VGA_BUFFER_WIDTH],
+        };
+        save_cells(vga, 0, row, &mut bar.saved);
+        bar.set(vga, 0);
+        bar
+    }
+
+    /// Redraws the bar with `value` steps done. Values past the total count as the total.
+    pub fn set(&mut self, vga: &mut VgaBuffer, value: u64) {
+        let (filled, percent) = match self.total {
+            0 => (BAR_WIDTH, 100),
+            total => {
+                let value = value.min(total);
+                (
+                    (value * BAR_WIDTH as u64 / total) as usize,
+                    value * 100 / total,
+                )
+            }
+        };
+        let mut text = RowText::new();
+        _ = text.write_char('[');
+        for i in 0..BAR_WIDTH {
+            _ = text.write_char(if i < filled { '#' } else { '.' });
+        }
+        _ = write!(text, "] {percent:3}%");
+        vga.write_str_at(0, self.row, text.as_str());
+    }
+
+    /// Stops showing progress. The row is put back as it was before the bar if `restore`, and
+    /// the bar is left as it was last drawn otherwise.
+    pub fn finish(self, vga: &mut VgaBuffer, restore: bool) {
+        if restore {
+            restore_cells(vga, 0, self.row, &self.saved);
+        }
+    }
+}
+
+/// A cell cycling through `|/-\`, showing that an operation of unknown length goes on.
+pub struct Spinner {
+    x: usize,
+    y: usize,
+    frame: usize,
+    /// The cell as it was before the spinner was drawn.
+    saved: u16,
+}
+
+impl Spinner {
+    /// Draws the first frame of a spinner at `x`, `y`.
+    pub fn new(vga: &mut VgaBuffer, x: usize, y: usize) -> Self {
+        let mut saved = [0];
+        save_cells(vga, x, y, &mut saved);
+        let spinner = Spinner {
+            x,
+            y,
+            frame: 0,
+            saved: saved[0],
+        };
+        spinner.draw(vga);
+        spinner
+    }
+
+    /// Draws the next frame.
+    pub fn tick(&mut self, vga: &mut VgaBuffer) {
+        self.frame = (self.frame + 1) % SPINNER_FRAMES.len();
+        self.draw(vga);
+    }
+
+    fn draw(&self, vga: &mut VgaBuffer) {
+        vga.write_str_at(self.x, self.y, SPINNER_FRAMES[self.frame]);
+    }
+
+    /// Stops spinning. The cell is put back as it was before the spinner if `restore`, and the
+    /// last frame is left otherwise.
+    pub fn finish(self, vga: &mut VgaBuffer, restore: bool) {
+        if restore {
+            restore_cells(vga, self.x, self.y, &[self.saved]);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! The CMOS real-time clock, read through ports 0x70 and 0x71.
+//!
+//! The clock updates its registers once a second, and a read racing with an update can mix
+//! values from before and after it. [`now`] waits for no update to be in progress, and reads
+//! everything until two reads in a row agree.
+
+use {
+    super::{inb, outb},
+    kfs_core::rtc::{DateTime, Registers},
+};
+
+/// The port selecting the CMOS register to access.
+const CMOS_ADDRESS: u16 = 0x70;
+/// The port accessing the selected CMOS register.
+const CMOS_DATA: u16 = 0x71;
+
+/// Register A: an update of the date and time is in progress.
+const STATUS_A_UPDATING: u8 = 1 << 7;
+
+/// The register holding the century on most machines, where ACPI would say so.
+const CENTURY_REGISTER: u8 = 0x32;
+
+/// The registers of the clock, along with their names.
+pub const REGISTERS: [(u8, &str); 15] = [
+    (0x00, "second"),
+    (0x01, "second alarm"),
+    (0x02, "minute"),
+    (0x03, "minute alarm"),
+    (0x04, "hour"),
+    (0x05, "hour alarm"),
+    (0x06, "weekday"),
+    (0x07, "day"),
+    (0x08, "month"),
+    (0x09, "year"),
+    (0x0A, "status A"),
+    (0x0B, "status B"),
+    (0x0C, "status C"),
+    (0x0D, "status D"),
+    (CENTURY_REGISTER, "century"),
+];
+
+/// Reads the CMOS register `register`.
+pub fn read(register: u8) -> u8 {
+    unsafe {
+        // The top bit of the address would disable NMIs.
+        outb(CMOS_ADDRESS, register & 0x7F);
+        inb(CMOS_DATA)
+    }
+}
+
+/// Writes `value` to the CMOS register `register`.
+///
+/// The registers of the clock are best left alone: this is meant for the NVRAM bytes past them.
+pub fn write(register: u8, value: u8) {
+    unsafe {
+        // The top bit of the address would disable NMIs.
+        outb(CMOS_ADDRESS, register & 0x7F);
+        outb(CMOS_DATA, value);
+    }
+}
+
+/// Reads the registers holding the date and time once no update is in progress.
+fn read_registers() -> Registers {
+    while read(0x0A) & STATUS_A_UPDATING != 0 {
+        core::hint::spin_loop();
+    }
+    Registers {
+        second: read(0x00),
+        minute: read(0x02),
+        hour: read(0x04),
+        day: read(0x07),
+        month: read(0x08),
+        year: read(0x09),
+        century: Some(read(CENTURY_REGISTER)),
+        status_b: read(0x0B),
+    }
+}
+
+/// Returns the date and time of the clock.
+pub fn now() -> DateTime {
+    let mut registers = read_registers();
+    loop {
+        let again = read_registers();
+        if again == registers {
+            return registers.decode();
+        }
+        registers = again;
+    }
+}
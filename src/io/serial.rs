@@ -0,0 +1,89 @@
+use kfs_core::{keyboard::Layout, serial_keys};
+
+use crate::{boot::SerialToken, time};
+
+use super::{inb, outb};
+
+/// The base I/O port of COM1.
+const COM1: u16 = 0x3F8;
+
+/// The divisor of the 115200 Hz UART clock giving the baud rate, 38400.
+const BAUD_DIVISOR: u16 = 3;
+
+/// The number of times the line status is polled before a byte is dropped. This keeps output
+/// from hanging when the port is missing or stuck.
+const TRANSMIT_SPINS: u32 = 100_000;
+
+/// The COM1 serial port.
+pub struct SerialPort {
+    /// The decoder of the keys sent by the terminal on the other end.
+    keys: serial_keys::Decoder,
+}
+
+impl SerialPort {
+    /// Initializes COM1 as 8N1 with FIFOs enabled, and returns its interface.
+    ///
+    /// Consumes the [`SerialToken`], which guarantees exclusive access to the COM1 ports.
+    pub fn new(_token: SerialToken) -> Self {
+        unsafe {
+            // Disable interrupts.
+            outb(COM1 + 1, 0x00);
+            // Set the baud rate divisor, with DLAB set.
+            outb(COM1 + 3, 0x80);
+            outb(COM1, BAUD_DIVISOR as u8);
+            outb(COM1 + 1, (BAUD_DIVISOR >> 8) as u8);
+            // 8 bits, no parity, one stop bit, and DLAB clear.
+            outb(COM1 + 3, 0x03);
+            // Enable and clear the FIFOs, with a 14-byte threshold.
+            outb(COM1 + 2, 0xC7);
+            // Assert DTR and RTS, and enable OUT2.
+            outb(COM1 + 4, 0x0B);
+        }
+        SerialPort {
+            keys: serial_keys::Decoder::new(),
+        }
+    }
+
+    /// Sends a byte once the transmitter is ready for it.
+    ///
+    /// The byte is dropped if the transmitter stays busy.
+    pub fn write_byte(&mut self, byte: u8) {
+        for _ in 0..TRANSMIT_SPINS {
+            // Bit 5 of the line status is set when the transmit buffer is empty.
+            if unsafe { inb(COM1 + 5) } & 0x20 != 0 {
+                unsafe { outb(COM1, byte) };
+                return;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Returns the next byte received, if any.
+    pub fn read_byte(&mut self) -> Option<u8> {
+        // Bit 0 of the line status is set when a byte was received.
+        (unsafe { inb(COM1 + 5) } & 0x01 != 0).then(|| unsafe { inb(COM1) })
+    }
+
+    /// Decodes the keys sent by the terminal on the other end since the last call, and passes
+    /// them to `emit`, with the printable keys looked up in `layout`.
+    pub fn poll_keys(&mut self, layout: &Layout, mut emit: impl FnMut(serial_keys::Input)) {
+        while let Some(byte) = self.read_byte() {
+            self.keys
+                .advance(byte, time::monotonic_ns(), layout, &mut emit);
+        }
+        self.keys.poll(time::monotonic_ns(), &mut emit);
+    }
+}
+
+impl core::fmt::Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            // Host terminals expect a carriage return before each line feed.
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! The PC speaker, driven by channel 2 of the PIT.
+//!
+//! There is no timer interrupt to stop a beep on time, so [`beep`] only records when it should
+//! end, and whoever waits for input calls [`poll`] to stop it once that time has come. [`tone`]
+//! instead waits for the sound to end, for callers that can afford to.
+//!
+//! Bits 0 and 1 of port 0x61, which gate channel 2 and connect the speaker to it, are put back as
+//! they were before the sound started once it stops.
+
+use {
+    super::{inb, outb},
+    crate::time::{self, PIT_FREQUENCY},
+    kfs_core::mutex::Mutex,
+};
+
+/// The frequency of the bell, in Hz.
+pub const BELL_FREQUENCY: u32 = 750;
+
+/// How long the bell rings, in milliseconds.
+pub const BELL_MS: u32 = 100;
+
+/// The frequency of `beep` without arguments, in Hz.
+pub const BEEP_FREQUENCY: u32 = 880;
+
+/// How long `beep` plays without a length, in milliseconds.
+pub const BEEP_MS: u32 = 200;
+
+/// The longest a single sound may be asked to play, in milliseconds.
+pub const MAX_MS: u32 = 10_000;
+
+/// The lowest frequency the PIT can produce, in Hz.
+pub const MIN_FREQUENCY: u32 = PIT_FREQUENCY / 0xFFFF + 1;
+
+/// When the sound playing should stop, if any.
+static DEADLINE: Mutex<Option<u64>> = Mutex::new(None);
+
+/// The speaker bits of port 0x61 from before the sound playing started, if any.
+static SAVED_GATE: Mutex<Option<u8>> = Mutex::new(None);
+
+/// Starts playing a square wave of frequency `freq` in Hz, until [`stop`] is called.
+///
+/// # Panics
+///
+/// Panics if `freq` is below [`MIN_FREQUENCY`] or above [`PIT_FREQUENCY`].
+pub fn start(freq: u32) {
+    assert!(
+        (MIN_FREQUENCY..=PIT_FREQUENCY).contains(&freq),
+        "frequency out of range"
+    );
+    let divisor = PIT_FREQUENCY / freq;
+    unsafe {
+        SAVED_GATE.lock().get_or_insert(inb(0x61) & 0x03);
+        // Channel 2, lobyte/hibyte, mode 3 (square wave generator).
+        outb(0x43, 0b1011_0110);
+        outb(0x42, divisor as u8);
+        outb(0x42, (divisor >> 8) as u8);
+        // Enable the channel 2 gate and connect the speaker to its output.
+        outb(0x61, inb(0x61) | 0x03);
+    }
+}
+
+/// Silences the speaker, putting port 0x61 back as it was before the sound started.
+pub fn stop() {
+    *DEADLINE.lock() = None;
+    let saved = SAVED_GATE.lock().take().unwrap_or(0);
+    unsafe { outb(0x61, inb(0x61) & !0x03 | saved) };
+}
+
+/// Plays a sound of frequency `freq` in Hz for `ms` milliseconds, without waiting for it to end.
+///
+/// The sound goes on until [`poll`] is called after `ms` milliseconds.
+///
+/// # Panics
+///
+/// Panics if `freq` is below [`MIN_FREQUENCY`] or above [`PIT_FREQUENCY`].
+pub fn beep(freq: u32, ms: u32) {
+    start(freq);
+    *DEADLINE.lock() = Some(time::monotonic_ns() + ms as u64 * 1_000_000);
+}
+
+/// Stops the sound started by [`beep`] if it has played long enough.
+///
+/// Returns whether it is still playing.
+pub fn poll() -> bool {
+    let deadline = *DEADLINE.lock();
+    match deadline {
+        Some(deadline) if time::monotonic_ns() >= deadline => {
+            stop();
+            false
+        }
+        deadline => deadline.is_some(),
+    }
+}
+
+/// Plays a sound of frequency `freq` in Hz for `ms` milliseconds, and returns once it ended.
+///
+/// A sound started by [`beep`] is cut short. **CTRL+C** stops the sound early.
+///
+/// # Panics
+///
+/// Panics if `freq` is below [`MIN_FREQUENCY`] or above [`PIT_FREQUENCY`].
+pub fn tone(freq: u32, ms: u32) -> Result<(), time::Interrupted> {
+    start(freq);
+    *DEADLINE.lock() = None;
+    let result = time::msleep(ms);
+    stop();
+    result
+}
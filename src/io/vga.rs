@@ -0,0 +1,1493 @@
+use core::cell::UnsafeCell;
+
+use kfs_core::{
+    ansi::{self, Control},
+    vga_chars,
+    wrap::{self, WrapMode},
+};
+
+use crate::{boot::VgaToken, time};
+
+use super::{
+    CONSOLE_COUNT, VGA_BUFFER_ADDRESS, VGA_BUFFER_MAX_HEIGHT, VGA_BUFFER_WIDTH, inb, outb, speaker,
+    vga_font,
+};
+
+/// The number of rows that fit in the 32 KiB of VGA text memory.
+const VGA_MEMORY_ROWS: usize = 0x8000 / 2 / VGA_BUFFER_WIDTH;
+
+/// The number of rows kept once they scroll off the top of the screen.
+const SCROLLBACK_ROWS: usize = 200;
+
+/// The error returned when writing to a cell off the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds;
+
+/// Memory used by the [`VgaBuffer`] but kept out of it, being too large to be moved around
+/// along with it.
+///
+/// Only the VGA buffer object accesses the memory, which is why it can be shared.
+struct Storage<T>(UnsafeCell<T>);
+
+unsafe impl<T> Sync for Storage<T> {}
+
+impl<T> Storage<T> {
+    const fn new(value: T) -> Self {
+        Storage(UnsafeCell::new(value))
+    }
+
+    /// Returns the memory, for the VGA buffer object to access.
+    fn get(&self) -> *mut T {
+        self.0.get()
+    }
+}
+
+/// The rows that scrolled off the top of the screen.
+struct Scrollback {
+    /// A ring of rows.
+    rows: [[u16; VGA_BUFFER_WIDTH]; SCROLLBACK_ROWS],
+    /// The index of the oldest row in `rows`.
+    head: usize,
+    /// The number of rows in `rows`.
+    len: usize,
+    /// The live screen, while the view is scrolled back.
+    live: [u16; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT],
+}
+
+impl Scrollback {
+    /// Appends a row, dropping the oldest one when full.
+    fn push(&mut self, row: [u16; VGA_BUFFER_WIDTH]) {
+        if self.len == SCROLLBACK_ROWS {
+            self.head = (self.head + 1) % SCROLLBACK_ROWS;
+            self.len -= 1;
+        }
+        self.rows[(self.head + self.len) % SCROLLBACK_ROWS] = row;
+        self.len += 1;
+    }
+
+    /// Returns the `i`-th row, starting from the oldest.
+    fn row(&self, i: usize) -> [u16; VGA_BUFFER_WIDTH] {
+        self.rows[(self.head + i) % SCROLLBACK_ROWS]
+    }
+}
+
+/// The saved state of a console.
+struct Console {
+    /// The cells of the console, unless it is shown on screen.
+    cells: [u16; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT],
+    /// The output cursor of the console, unless output goes to it.
+    cursor: (usize, usize),
+    /// The current color of the console, unless output goes to it.
+    color: u8,
+}
+
+/// The storage behind [`VgaBuffer::consoles`].
+static CONSOLES: Storage<[Console; CONSOLE_COUNT]> = Storage::new(
+    [const {
+        Console {
+            cells: [0x0F << 8 | b' ' as u16; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT],
+            cursor: (0, 0),
+            color: 0x0F,
+        }
+    }; CONSOLE_COUNT],
+);
+
+/// The geometries of the VGA text mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextMode {
+    /// 25 rows of 16-scanline characters, as set up by the BIOS.
+    #[default]
+    Mode80x25,
+    /// 50 rows of 8-scanline characters.
+    Mode80x50,
+}
+
+impl TextMode {
+    /// Parses a mode from the name returned by [`name`](Self::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "80x25" => Some(TextMode::Mode80x25),
+            "80x50" => Some(TextMode::Mode80x50),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the mode, as shown by the `mode` command.
+    pub fn name(self) -> &'static str {
+        match self {
+            TextMode::Mode80x25 => "80x25",
+            TextMode::Mode80x50 => "80x50",
+        }
+    }
+
+    /// Returns the number of rows of the screen.
+    pub const fn height(self) -> usize {
+        match self {
+            TextMode::Mode80x25 => 25,
+            TextMode::Mode80x50 => VGA_BUFFER_MAX_HEIGHT,
+        }
+    }
+
+    /// Returns the number of scanlines of a character.
+    pub const fn char_height(self) -> u8 {
+        match self {
+            TextMode::Mode80x25 => 16,
+            TextMode::Mode80x50 => 8,
+        }
+    }
+
+    /// Returns the first and last scanlines of an underline cursor, as the BIOS sets up.
+    pub const fn underline_cursor(self) -> (u8, u8) {
+        (self.char_height() - 3, self.char_height() - 2)
+    }
+
+    /// Returns the first and last scanlines of a cursor filling the whole cell.
+    pub const fn block_cursor(self) -> (u8, u8) {
+        (0, self.char_height() - 1)
+    }
+
+    /// Returns the value of the Character Map Select register: both character sets use font
+    /// map 0 for the BIOS font, or font map 1 for the font derived from it.
+    const fn font_maps(self) -> u8 {
+        match self {
+            TextMode::Mode80x25 => 0x00,
+            TextMode::Mode80x50 => 0x05,
+        }
+    }
+}
+
+/// The VGA sequencer index port. The data port follows it.
+const SEQUENCER: u16 = 0x3C4;
+
+/// The VGA graphics controller index port. The data port follows it.
+const GRAPHICS_CONTROLLER: u16 = 0x3CE;
+
+/// The CRT controller index port. The data port follows it.
+const CRT_CONTROLLER: u16 = 0x3D4;
+
+/// The address of the font plane while it is mapped for [`VgaBuffer::with_font_plane`].
+const FONT_PLANE_ADDRESS: usize = 0xA0000;
+
+/// The storage behind [`VgaBuffer::shadow`].
+static SHADOW: Storage<[u16; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT]> =
+    Storage::new([0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT]);
+
+/// The dirty row mask with every row of the screen set.
+const ALL_ROWS: u64 = (1 << VGA_BUFFER_MAX_HEIGHT) - 1;
+
+/// The storage behind [`VgaBuffer::scrollback`].
+static SCROLLBACK: Storage<Scrollback> = Storage::new(Scrollback {
+    rows: [[0; VGA_BUFFER_WIDTH]; SCROLLBACK_ROWS],
+    head: 0,
+    len: 0,
+    live: [0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT],
+});
+
+/// The VGA text buffer, along with the Text Mode cursor.
+///
+/// This type is only concerned with cells and cursors. Input handling lives in
+/// [`Keyboard`](super::Keyboard).
+///
+/// There are two cursors. The output cursor is where the next character written through
+/// [`putchar`](Self::putchar) or [`core::fmt::Write`] lands, and the visual cursor is the
+/// hardware cursor shown on screen. Stream output keeps them in sync. Direct cell writes such
+/// as [`write_at`](Self::write_at) never move either of them, so code that draws directly and
+/// then wants stream output to resume elsewhere must call
+/// [`set_output_cursor`](Self::set_output_cursor).
+///
+/// What happens past the end of a row depends on the [`WrapMode`]. In [`WrapMode::Hard`], the
+/// cursor moves to the next row as soon as the last column is written. In the other modes, it
+/// stays past the last column until the next character shows whether the row must be broken.
+///
+/// Rows scrolling off the top of the screen are kept in a scrollback. While the view is
+/// scrolled back, [`buffer_mut`](Self::buffer_mut) and everything built on it keep working on
+/// the live screen, which is shown again once the view is scrolled all the way forward.
+///
+/// There are [`CONSOLE_COUNT`] consoles. Output goes to one of them, which need not be the one
+/// shown on screen: the cursor and color always belong to the console output goes to. Only the
+/// rows of the console shown on screen are kept in the scrollback.
+///
+/// Stream output understands ANSI Select Graphic Rendition sequences such as `"\x1b[31m"`. They
+/// change the current color, and `"\x1b[0m"` goes back to the color last set with
+/// [`set_color`](Self::set_color). It also understands the VT100 sequences moving the cursor
+/// (`CUP`, `CUU`, `CUD`, `CUF` and `CUB`), erasing the screen or the line (`ED` and `EL`) and
+/// setting the [scrolling region](Self::set_scroll_region) (`DECSTBM`).
+///
+/// Between [`begin_frame`](Self::begin_frame) and [`end_frame`](Self::end_frame), the screen is
+/// drawn to a shadow buffer in kernel memory, and only the rows that changed are copied to VGA
+/// memory when the frame ends. Outside of a frame, writes go straight to VGA memory.
+pub struct VgaBuffer {
+    cursor_x: usize,
+    cursor_y: usize,
+    current_color: u8,
+    /// The color restored by ANSI sequences.
+    default_color: u8,
+    /// The parser for the escape sequences in stream output.
+    ansi: ansi::Parser,
+    wrap: WrapMode,
+    /// Whether the current row continues a word-wrapped line.
+    continuation: bool,
+    /// Whether the output wrapped onto the current row from the end of the previous one, which
+    /// a backspace at the start of the row goes back to.
+    wrapped: bool,
+    /// Whether characters were dropped from the current row in [`WrapMode::Truncate`].
+    truncated: bool,
+    /// The number of rows the view is scrolled back, or 0 when showing the live screen.
+    scrolled: usize,
+    /// The console output goes to.
+    output: usize,
+    /// The console shown on screen.
+    shown: usize,
+    /// The number of times output scrolled, wrapping around.
+    scrolls: usize,
+    /// The row of VGA memory shown at the top of the screen.
+    start_row: usize,
+    /// Whether scrolling moves the window of VGA memory shown on screen, rather than the cells.
+    hardware_scroll: bool,
+    /// Whether the screen is drawn to the shadow buffer.
+    framing: bool,
+    /// The rows written since the last flush, one bit per row.
+    dirty: u64,
+    /// The geometry of the screen.
+    mode: TextMode,
+    /// The cells of the status bar, when it is on.
+    status: Option<[u16; VGA_BUFFER_WIDTH]>,
+    /// The distance between two tab stops.
+    tab_width: usize,
+    /// The VGA character shown for the characters that have none.
+    replacement: u8,
+    /// Whether `\x07` rings the bell.
+    bell: bool,
+    /// The frequency of the bell, in Hz.
+    bell_frequency: u32,
+    /// Whether bit 7 of the color makes characters blink, rather than brightening the background.
+    blink: bool,
+    /// The first and last scanlines of the cursor, once known.
+    cursor_shape: Option<(u8, u8)>,
+    /// Whether the cursor was hidden with [`cursor_hide`](Self::cursor_hide).
+    cursor_hidden: bool,
+    /// The first row of the scrolling region and the row past its end, unless the whole screen
+    /// scrolls.
+    scroll_region: Option<(usize, usize)>,
+}
+
+impl VgaBuffer {
+    /// Creates the VGA buffer interface.
+    ///
+    /// Consumes the [`VgaToken`], which guarantees exclusive access to the VGA buffer and to the
+    /// Text Mode cursor.
+    pub const fn new(_token: VgaToken) -> Self {
+        let current_color = 0x0F; // White on black
+
+        VgaBuffer {
+            cursor_x: 0,
+            cursor_y: 0,
+            current_color,
+            default_color: current_color,
+            ansi: ansi::Parser::new(),
+            wrap: WrapMode::Hard,
+            continuation: false,
+            wrapped: false,
+            truncated: false,
+            scrolled: 0,
+            output: 0,
+            shown: 0,
+            scrolls: 0,
+            start_row: 0,
+            hardware_scroll: true,
+            framing: false,
+            dirty: 0,
+            mode: TextMode::Mode80x25,
+            status: None,
+            tab_width: wrap::DEFAULT_TAB_WIDTH,
+            replacement: vga_chars::from_char('■').unwrap(),
+            bell: true,
+            bell_frequency: speaker::BELL_FREQUENCY,
+            // This is what the BIOS sets up.
+            blink: true,
+            cursor_shape: None,
+            cursor_hidden: false,
+            scroll_region: None,
+        }
+    }
+
+    /// Returns the cells of the console output goes to.
+    ///
+    /// Every row is considered written.
+    pub fn buffer_mut(&mut self) -> &mut [u16] {
+        self.dirty = ALL_ROWS;
+        self.cells_mut()
+    }
+
+    /// Returns the cells of the rows `rows` of the console output goes to, starting from the
+    /// first cell of the first row.
+    fn rows_mut(&mut self, rows: core::ops::Range<usize>) -> &mut [u16] {
+        self.dirty |= (1 << rows.end) - (1 << rows.start);
+        &mut self.cells_mut()[rows.start * VGA_BUFFER_WIDTH..rows.end * VGA_BUFFER_WIDTH]
+    }
+
+    /// Returns the cells of the console output goes to, without marking anything dirty.
+    fn cells_mut(&mut self) -> &mut [u16] {
+        let len = self.cell_count();
+        if self.output != self.shown {
+            let output = self.output;
+            return &mut self.consoles()[output].cells[..len];
+        }
+        if self.scrolled != 0 {
+            return &mut self.scrollback().live[..len];
+        }
+        &mut self.screen_mut()[..len]
+    }
+
+    /// Returns the whole VGA text memory, of which only a window is shown on screen.
+    fn memory_mut(&mut self) -> &mut [u16] {
+        const VGA_MEMORY: *mut [u16] = core::ptr::slice_from_raw_parts_mut(
+            core::ptr::without_provenance_mut(VGA_BUFFER_ADDRESS),
+            VGA_BUFFER_WIDTH * VGA_MEMORY_ROWS,
+        );
+
+        // SAFETY: We have an exclusive reference to vga buffer object, which means we own
+        // the memory buffer.
+        unsafe { &mut *VGA_MEMORY }
+    }
+
+    /// Returns the cells of the screen, which are the shadow buffer while drawing a frame.
+    fn screen_mut(&mut self) -> &mut [u16] {
+        if self.framing {
+            let len = self.screen_cell_count();
+            return &mut self.shadow()[..len];
+        }
+        self.window_mut()
+    }
+
+    /// Returns the shadow buffer the screen is drawn to while drawing a frame.
+    fn shadow(&mut self) -> &mut [u16; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT] {
+        // SAFETY: Only the VGA buffer object accesses the shadow buffer, and we have an
+        // exclusive reference to it.
+        unsafe { &mut *SHADOW.get() }
+    }
+
+    /// Starts drawing a frame: until [`end_frame`](Self::end_frame), the screen is drawn to the
+    /// shadow buffer.
+    ///
+    /// Frames do not nest. Starting a frame while drawing one does nothing.
+    pub fn begin_frame(&mut self) {
+        if self.framing {
+            return;
+        }
+        let len = self.screen_cell_count();
+        let mut cells = [0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT];
+        cells[..len].copy_from_slice(self.window_mut());
+        *self.shadow() = cells;
+        self.framing = true;
+        self.dirty = 0;
+    }
+
+    /// Copies the rows of the shadow buffer written since the last flush to VGA memory.
+    pub fn flush(&mut self) {
+        if !self.framing {
+            return;
+        }
+        let dirty = core::mem::take(&mut self.dirty);
+        for y in (0..self.mode.height()).filter(|y| dirty & 1 << y != 0) {
+            let row = y * VGA_BUFFER_WIDTH..(y + 1) * VGA_BUFFER_WIDTH;
+            let cells: [u16; VGA_BUFFER_WIDTH] = self.shadow()[row.clone()].try_into().unwrap();
+            self.window_mut()[row].copy_from_slice(&cells);
+        }
+    }
+
+    /// Ends the frame started with [`begin_frame`](Self::begin_frame), and shows it.
+    pub fn end_frame(&mut self) {
+        self.flush();
+        self.framing = false;
+    }
+
+    /// Returns the cells of VGA memory shown on screen.
+    fn window_mut(&mut self) -> &mut [u16] {
+        let start = self.start_row * VGA_BUFFER_WIDTH;
+        let len = self.screen_cell_count();
+        &mut self.memory_mut()[start..][..len]
+    }
+
+    /// Scrolls the screen up by one row by moving the window of VGA memory shown on screen down.
+    ///
+    /// Once the window reaches the end of VGA memory, it is copied back to the start. The last
+    /// row is left as it is.
+    fn scroll_window(&mut self) {
+        if self.start_row + self.mode.height() < VGA_MEMORY_ROWS {
+            self.start_row += 1;
+        } else {
+            let start = (self.start_row + 1) * VGA_BUFFER_WIDTH;
+            let end = (self.start_row + self.mode.height()) * VGA_BUFFER_WIDTH;
+            self.memory_mut().copy_within(start..end, 0);
+            self.start_row = 0;
+        }
+        self.write_start_address();
+        self.draw_status();
+    }
+
+    /// Tells the CRT controller which row of VGA memory to show at the top of the screen.
+    fn write_start_address(&mut self) {
+        let start = self.start_row * VGA_BUFFER_WIDTH;
+        unsafe {
+            outb(0x3D4, 0x0C);
+            outb(0x3D5, (start >> 8) as u8);
+            outb(0x3D4, 0x0D);
+            outb(0x3D5, start as u8);
+        }
+    }
+
+    /// Returns whether scrolling is done by moving the window of VGA memory shown on screen.
+    pub fn hardware_scroll(&self) -> bool {
+        self.hardware_scroll
+    }
+
+    /// Sets whether scrolling is done by moving the window of VGA memory shown on screen, or by
+    /// copying the cells of the screen.
+    pub fn set_hardware_scroll(&mut self, on: bool) {
+        self.hardware_scroll = on;
+        if !on && self.start_row != 0 {
+            let start = self.start_row * VGA_BUFFER_WIDTH;
+            let len = self.screen_cell_count();
+            self.memory_mut().copy_within(start..start + len, 0);
+            self.start_row = 0;
+            self.write_start_address();
+            self.sync_hardware_cursor();
+        }
+    }
+
+    /// Moves the hardware cursor where it belongs: at the output cursor, at the saved cursor of
+    /// the console shown on screen, or parked off screen while the view is scrolled back.
+    fn sync_hardware_cursor(&mut self) {
+        let (x, y) = if self.scrolled != 0 {
+            (0, self.mode.height())
+        } else if self.output == self.shown {
+            (self.cursor_x, self.cursor_y)
+        } else {
+            let shown = self.shown;
+            self.consoles()[shown].cursor
+        };
+        self.write_cursor_pos(y * VGA_BUFFER_WIDTH + x);
+    }
+
+    /// Returns the index of the cell of the screen the hardware cursor is on.
+    fn read_cursor_pos(&mut self) -> usize {
+        let high = read_register(CRT_CONTROLLER, 0x0E) as usize;
+        let low = read_register(CRT_CONTROLLER, 0x0F) as usize;
+        (high << 8 | low).wrapping_sub(self.start_row * VGA_BUFFER_WIDTH)
+    }
+
+    /// Moves the output cursor to where the hardware cursor is, such as where the boot loader
+    /// left it. A position out of the screen is clamped to its edges.
+    pub fn sync_cursor_from_hw(&mut self) {
+        let pos = self.read_cursor_pos();
+        self.cursor_x = pos % VGA_BUFFER_WIDTH;
+        self.cursor_y = (pos / VGA_BUFFER_WIDTH).min(self.height() - 1);
+        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+    }
+
+    /// Moves the hardware cursor to the cell at index `pos` of the screen.
+    fn write_cursor_pos(&mut self, pos: usize) {
+        let pos = self.start_row * VGA_BUFFER_WIDTH + pos;
+        unsafe {
+            outb(0x3D4, 0x0F);
+            outb(0x3D5, (pos & 0xFF) as u8);
+
+            outb(0x3D4, 0x0E);
+            outb(0x3D5, ((pos >> 8) & 0xFF) as u8);
+        }
+    }
+
+    /// Returns the saved state of the consoles.
+    fn consoles(&mut self) -> &mut [Console; CONSOLE_COUNT] {
+        // SAFETY: Only the VGA buffer object accesses the consoles, and we have an exclusive
+        // reference to it.
+        unsafe { &mut *CONSOLES.get() }
+    }
+
+    /// Returns the console output goes to.
+    #[inline]
+    pub fn output_console(&self) -> usize {
+        self.output
+    }
+
+    /// Returns the console shown on screen.
+    #[inline]
+    pub fn shown_console(&self) -> usize {
+        self.shown
+    }
+
+    /// Sends output to the console `n` from now on.
+    pub fn set_output_console(&mut self, n: usize) {
+        assert!(n < CONSOLE_COUNT);
+        if n == self.output {
+            return;
+        }
+        let (x, y, color) = (self.cursor_x, self.cursor_y, self.current_color);
+        let output = self.output;
+        let consoles = self.consoles();
+        consoles[output].cursor = (x, y);
+        consoles[output].color = color;
+        let (cursor, color) = (consoles[n].cursor, consoles[n].color);
+        (self.cursor_x, self.cursor_y) = cursor;
+        self.current_color = color;
+        self.continuation = false;
+        self.wrapped = false;
+        self.truncated = false;
+        self.output = n;
+        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+    }
+
+    /// Shows the console `n` on screen.
+    pub fn show_console(&mut self, n: usize) {
+        assert!(n < CONSOLE_COUNT);
+        if n == self.shown {
+            return;
+        }
+        self.reset_view();
+        let len = self.cell_count();
+        let mut cells = [0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT];
+        cells[..len].copy_from_slice(&self.screen_mut()[..len]);
+        let shown = self.shown;
+        self.consoles()[shown].cells = cells;
+        cells = self.consoles()[n].cells;
+        self.screen_mut()[..len].copy_from_slice(&cells[..len]);
+        self.dirty = ALL_ROWS;
+        self.shown = n;
+
+        self.sync_hardware_cursor();
+    }
+
+    /// Returns the rows that scrolled off the top of the screen.
+    fn scrollback(&mut self) -> &mut Scrollback {
+        // SAFETY: Only the VGA buffer object accesses the scrollback, and we have an exclusive
+        // reference to it.
+        unsafe { &mut *SCROLLBACK.get() }
+    }
+
+    /// Scrolls the view `rows` rows back into the scrollback, or forward when negative.
+    ///
+    /// While the view is scrolled back, the cursor is parked off screen.
+    pub fn scroll_view(&mut self, rows: isize) {
+        let scrolled = self
+            .scrolled
+            .saturating_add_signed(rows)
+            .min(self.scrollback().len);
+        if scrolled == self.scrolled {
+            return;
+        }
+        if self.scrolled == 0 {
+            let len = self.cell_count();
+            let mut live = [0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT];
+            live[..len].copy_from_slice(&self.screen_mut()[..len]);
+            self.scrollback().live = live;
+        }
+        let was_scrolled = self.scrolled != 0;
+        self.scrolled = scrolled;
+        self.dirty = ALL_ROWS;
+        if was_scrolled != (scrolled != 0) {
+            self.write_cursor_shape();
+        }
+
+        let live = self.scrollback().live;
+        if scrolled == 0 {
+            let len = self.cell_count();
+            self.screen_mut()[..len].copy_from_slice(&live[..len]);
+            self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+            return;
+        }
+        let history = self.scrollback().len;
+        for y in 0..self.height() {
+            let row = history + y - scrolled;
+            let cells = match row.checked_sub(history) {
+                None => self.scrollback().row(row),
+                Some(row) => live[row * VGA_BUFFER_WIDTH..][..VGA_BUFFER_WIDTH]
+                    .try_into()
+                    .unwrap(),
+            };
+            self.screen_mut()[y * VGA_BUFFER_WIDTH..][..VGA_BUFFER_WIDTH].copy_from_slice(&cells);
+        }
+        self.sync_hardware_cursor();
+    }
+
+    /// Shows the live screen again, if the view is scrolled back.
+    pub fn reset_view(&mut self) {
+        self.scroll_view(isize::MIN);
+    }
+
+    /// Rewrites the color of every cell of every console, scrollback included, as well as the
+    /// current colors, with `map`. The characters are left untouched.
+    pub fn recolor(&mut self, map: impl Fn(u8) -> u8) {
+        let recolor = |cell: &mut u16| {
+            *cell = (map((*cell >> 8) as u8) as u16) << 8 | *cell & 0xFF;
+        };
+        self.reset_view();
+        let len = self.cell_count();
+        let shown = self.shown;
+        for (n, console) in self.consoles().iter_mut().enumerate() {
+            console.color = map(console.color);
+            if n != shown {
+                console.cells[..len].iter_mut().for_each(recolor);
+            }
+        }
+        self.screen_mut()[..len].iter_mut().for_each(recolor);
+        self.scrollback()
+            .rows
+            .iter_mut()
+            .flatten()
+            .for_each(recolor);
+        self.current_color = map(self.current_color);
+        self.default_color = map(self.default_color);
+        self.dirty = ALL_ROWS;
+    }
+
+    /// Clears the console output goes to by filling it with spaces of the current color, and
+    /// moves the output cursor to the top-left corner.
+    ///
+    /// The status bar is left alone. If the console is shown with its view scrolled back, the
+    /// live screen is shown again first.
+    pub fn clear(&mut self) {
+        if self.output == self.shown {
+            self.reset_view();
+        }
+        let color = self.current_color as u16;
+        self.buffer_mut().fill(color << 8 | (b' ' as u16));
+        self.set_output_cursor(0, 0);
+        self.continuation = false;
+        self.truncated = false;
+    }
+
+    /// Fills the row `y` with blank cells of the current color.
+    ///
+    /// Nothing is cleared if the row is off the screen.
+    pub fn clear_line(&mut self, y: usize) -> Result<(), OutOfBounds> {
+        self.clear_region(0, y, VGA_BUFFER_WIDTH, 1)
+    }
+
+    /// Fills the current row with blank cells of the current color, from the output cursor to
+    /// the end of the row.
+    pub fn clear_to_eol(&mut self) {
+        let x = self.cursor_x.min(VGA_BUFFER_WIDTH);
+        // The output cursor is always on the screen.
+        _ = self.clear_region(x, self.cursor_y, VGA_BUFFER_WIDTH - x, 1);
+    }
+
+    /// Fills the rectangle of `w` by `h` cells starting at `x`, `y` with blank cells of the
+    /// current color.
+    ///
+    /// The cursors are left untouched. Nothing is cleared if the rectangle does not fit on the
+    /// screen.
+    pub fn clear_region(
+        &mut self,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<(), OutOfBounds> {
+        let fits = |start: usize, len: usize, end| start.checked_add(len).is_some_and(|e| e <= end);
+        if !fits(x, w, VGA_BUFFER_WIDTH) || !fits(y, h, self.height()) {
+            return Err(OutOfBounds);
+        }
+        let blank = (self.current_color as u16) << 8 | b' ' as u16;
+        for row in y..y + h {
+            self.rows_mut(row..row + 1)[x..][..w].fill(blank);
+        }
+        Ok(())
+    }
+
+    /// Writes a byte to the VGA buffer at the specified coordinates with the given color.
+    ///
+    /// Nothing is written if the cell is off the screen.
+    #[inline]
+    pub fn write_byte(
+        &mut self,
+        x: usize,
+        y: usize,
+        byte: u8,
+        color: u8,
+    ) -> Result<(), OutOfBounds> {
+        if x >= VGA_BUFFER_WIDTH || y >= self.height() {
+            return Err(OutOfBounds);
+        }
+        self.rows_mut(y..y + 1)[x] = (color as u16) << 8 | (byte as u16);
+        Ok(())
+    }
+
+    /// Writes a byte to the VGA buffer at the specified coordinates using the current color.
+    ///
+    /// Nothing is written if the cell is off the screen.
+    pub fn write_at(&mut self, x: usize, y: usize, byte: u8) -> Result<(), OutOfBounds> {
+        self.write_byte(x, y, byte, self.current_color)
+    }
+
+    /// Returns the cell at the specified coordinates: the character in the low byte, and its
+    /// color in the high byte.
+    pub fn read_cell(&mut self, x: usize, y: usize) -> Result<u16, OutOfBounds> {
+        if x >= VGA_BUFFER_WIDTH || y >= self.height() {
+            return Err(OutOfBounds);
+        }
+        Ok(self.cells_mut()[y * VGA_BUFFER_WIDTH + x])
+    }
+
+    /// Writes `s` from the cell at `x`, `y` with the current color, and returns the number of
+    /// cells written. See [`write_str_at_color`](Self::write_str_at_color).
+    pub fn write_str_at(&mut self, x: usize, y: usize, s: &str) -> usize {
+        self.write_str_at_color(x, y, s, self.current_color)
+    }
+
+    /// Writes `s` from the cell at `x`, `y` with the color `color`, and returns the number of
+    /// cells written.
+    ///
+    /// Each character takes one cell, and characters without a VGA equivalent are shown as
+    /// the [replacement character](Self::replacement). A newline goes on at the column `x` of
+    /// the next row. What does not fit on the screen is dropped, and the cursors are left
+    /// untouched.
+    pub fn write_str_at_color(&mut self, x: usize, y: usize, s: &str, color: u8) -> usize {
+        let mut written = 0;
+        for (x, y, c) in wrap::place(x, y, s, VGA_BUFFER_WIDTH) {
+            // The rows only go down, so the first cell off the screen is below it.
+            if self.write_byte(x, y, self.vga_char(c), color).is_err() {
+                break;
+            }
+            written += 1;
+        }
+        written
+    }
+
+    fn newline(&mut self) {
+        self.cursor_x = 0;
+        self.cursor_y += 1;
+        self.continuation = false;
+        self.wrapped = false;
+        self.truncated = false;
+        let (_, bottom) = self.scroll_bounds();
+        if self.cursor_y == bottom {
+            self.scroll_up();
+            self.cursor_y -= 1;
+        } else if self.cursor_y == self.height() {
+            // Below the scrolling region, output piles up on the last row.
+            self.cursor_y -= 1;
+        } else if self.cursor_y > self.height() {
+            unreachable!();
+        }
+    }
+
+    /// Moves the rows of the scrolling region of the console output goes to up by one, and
+    /// blanks its last row.
+    fn scroll_up(&mut self) {
+        let (top, bottom) = self.scroll_bounds();
+        if self.output == self.shown {
+            let row = self.cells_mut()[top * VGA_BUFFER_WIDTH..][..VGA_BUFFER_WIDTH]
+                .try_into()
+                .unwrap();
+            self.scrollback().push(row);
+        }
+        let on_screen = self.output == self.shown && self.scrolled == 0;
+        if self.hardware_scroll && on_screen && !self.framing && self.scroll_region.is_none() {
+            self.scroll_window();
+        } else {
+            let rows = (top + 1) * VGA_BUFFER_WIDTH..bottom * VGA_BUFFER_WIDTH;
+            self.buffer_mut().copy_within(rows, top * VGA_BUFFER_WIDTH);
+        }
+        let color = self.current_color as u16;
+        let last_row = VGA_BUFFER_WIDTH * (bottom - 1);
+        self.buffer_mut()[last_row..][..VGA_BUFFER_WIDTH].fill(color << 8);
+        self.scrolls = self.scrolls.wrapping_add(1);
+    }
+
+    /// Returns the first row of the scrolling region and the row past its end.
+    fn scroll_bounds(&self) -> (usize, usize) {
+        self.scroll_region.unwrap_or((0, self.height()))
+    }
+
+    /// Returns the first row of the scrolling region and the row past its end, unless the whole
+    /// screen scrolls.
+    pub fn scroll_region(&self) -> Option<(usize, usize)> {
+        self.scroll_region
+    }
+
+    /// Restricts scrolling to the rows from `top` to `bottom`, excluded: a newline on the last
+    /// row of the region only moves the rows of the region, and the rows around it are left
+    /// alone. Below the region, output stops at the last row of the screen.
+    ///
+    /// Direct writes such as [`write_at`](Self::write_at) can still go anywhere.
+    ///
+    /// Nothing changes if the region is empty or does not fit on the screen.
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) -> Result<(), OutOfBounds> {
+        if top >= bottom || bottom > self.height() {
+            return Err(OutOfBounds);
+        }
+        self.scroll_region = Some((top, bottom));
+        Ok(())
+    }
+
+    /// Lets the whole screen scroll again.
+    pub fn reset_scroll_region(&mut self) {
+        self.scroll_region = None;
+    }
+
+    /// Makes room for the character `c` when the current row is full, according to the wrap
+    /// mode.
+    ///
+    /// Returns whether `c` should be written.
+    fn wrap_row(&mut self, c: char) -> bool {
+        match self.wrap {
+            WrapMode::Hard => {
+                self.newline();
+                self.wrapped = true;
+            }
+            WrapMode::Word => {
+                let row_start = self.cursor_y * VGA_BUFFER_WIDTH;
+                let mut row = [0; VGA_BUFFER_WIDTH];
+                let mut cells = [0; VGA_BUFFER_WIDTH];
+                cells.copy_from_slice(&self.cells_mut()[row_start..][..VGA_BUFFER_WIDTH]);
+                for (byte, cell) in row.iter_mut().zip(cells) {
+                    *byte = cell as u8;
+                }
+                let indent = if self.continuation {
+                    wrap::WORD_WRAP_INDENT
+                } else {
+                    0
+                };
+                // A space can break the row right where it is.
+                let word = match c {
+                    ' ' => VGA_BUFFER_WIDTH,
+                    _ => wrap::word_break(&row, indent).unwrap_or(VGA_BUFFER_WIDTH),
+                };
+                let blank = (self.current_color as u16) << 8 | b' ' as u16;
+                let y = self.cursor_y;
+                self.rows_mut(y..y + 1)[word..].fill(blank);
+
+                self.newline();
+                self.continuation = true;
+                let y = self.cursor_y;
+                let moved = VGA_BUFFER_WIDTH - word;
+                let buffer = self.rows_mut(y..y + 1);
+                buffer[..wrap::WORD_WRAP_INDENT].fill(blank);
+                buffer[wrap::WORD_WRAP_INDENT..][..moved].copy_from_slice(&cells[word..]);
+                self.cursor_x = wrap::WORD_WRAP_INDENT + moved;
+                // The space the row was broken at is not carried over.
+                if c == ' ' {
+                    return false;
+                }
+            }
+            WrapMode::Truncate => {
+                if !self.truncated {
+                    const ELLIPSIS: u8 = vga_chars::from_char('»').unwrap();
+                    _ = self.write_at(VGA_BUFFER_WIDTH - 1, self.cursor_y, ELLIPSIS);
+                    self.truncated = true;
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Writes a character at the output cursor, or feeds it to the escape sequence parser.
+    pub fn putchar(&mut self, c: char) {
+        self.feed(c);
+        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+    }
+
+    /// Does what [`putchar`](Self::putchar) does, except for moving the visual cursor.
+    fn feed(&mut self, c: char) {
+        match self.ansi.advance(c) {
+            ansi::Action::None => {}
+            ansi::Action::Print(c) => self.put_glyph(c),
+            ansi::Action::Control(control) => self.control(control),
+            ansi::Action::Csi(csi) => self.control_sequence(csi),
+        }
+    }
+
+    /// Writes as many of the printable ASCII characters `bytes` as fit on the current row in one
+    /// go, and returns how many were written.
+    ///
+    /// Nothing is written when the row is full, so that the caller falls back to
+    /// [`feed`](Self::feed), which knows how to wrap.
+    fn write_ascii(&mut self, bytes: &[u8]) -> usize {
+        let x = self.cursor_x;
+        let Some(room) = VGA_BUFFER_WIDTH.checked_sub(x).filter(|&room| room > 0) else {
+            return 0;
+        };
+        let len = bytes.len().min(room);
+        let y = self.cursor_y;
+        let color = (self.current_color as u16) << 8;
+        let cells = &mut self.rows_mut(y..y + 1)[x..][..len];
+        for (cell, &byte) in cells.iter_mut().zip(bytes) {
+            *cell = color | byte as u16;
+        }
+        self.cursor_x += len;
+        if self.wrap == WrapMode::Hard && self.cursor_x >= VGA_BUFFER_WIDTH {
+            self.newline();
+        }
+        len
+    }
+
+    /// Runs an ANSI control sequence. Unsupported sequences are ignored.
+    ///
+    /// Coordinates out of the screen are clamped to its edges.
+    fn control_sequence(&mut self, csi: ansi::Csi) {
+        let (x, y) = (self.cursor_x.min(VGA_BUFFER_WIDTH - 1), self.cursor_y);
+        let count = |i| csi.param(i, 1) as usize;
+        let (x, y) = match csi.function {
+            'm' => {
+                self.current_color =
+                    ansi::apply_sgr(self.current_color, self.default_color, csi.params());
+                return;
+            }
+            // Cursor Position, with 1-based coordinates.
+            'H' | 'f' => (count(1) - 1, count(0) - 1),
+            // Cursor Up, Down, Forward and Back.
+            'A' => (x, y.saturating_sub(count(0))),
+            'B' => (x, y.saturating_add(count(0))),
+            'C' => (x.saturating_add(count(0)), y),
+            'D' => (x.saturating_sub(count(0)), y),
+            // Set Top and Bottom Margins, with 1-based rows. The cursor goes home.
+            'r' => {
+                let top = count(0) - 1;
+                let bottom = (csi.param(1, self.height() as u16) as usize).min(self.height());
+                if top == 0 && bottom == self.height() {
+                    self.reset_scroll_region();
+                } else {
+                    // An empty region is ignored, as terminals do.
+                    _ = self.set_scroll_region(top, bottom);
+                }
+                (0, 0)
+            }
+            // Erase in Display and Erase in Line, which do not move the cursor.
+            // The regions are on the screen, as the cursor is. It may be just past the end of its
+            // row, hence the bound on what is cleared up to it.
+            'J' => {
+                let through_cursor = (x + 1).min(VGA_BUFFER_WIDTH);
+                _ = match csi.param(0, 0) {
+                    0 => self
+                        .clear_region(x, y, VGA_BUFFER_WIDTH - x, 1)
+                        .and(self.clear_region(0, y + 1, VGA_BUFFER_WIDTH, self.height() - y - 1)),
+                    1 => self
+                        .clear_region(0, 0, VGA_BUFFER_WIDTH, y)
+                        .and(self.clear_region(0, y, through_cursor, 1)),
+                    2 => self.clear_region(0, 0, VGA_BUFFER_WIDTH, self.height()),
+                    _ => Ok(()),
+                };
+                return;
+            }
+            'K' => {
+                let through_cursor = (x + 1).min(VGA_BUFFER_WIDTH);
+                _ = match csi.param(0, 0) {
+                    0 => self.clear_region(x, y, VGA_BUFFER_WIDTH - x, 1),
+                    1 => self.clear_region(0, y, through_cursor, 1),
+                    2 => self.clear_line(y),
+                    _ => Ok(()),
+                };
+                return;
+            }
+            _ => return,
+        };
+        self.cursor_x = x.min(VGA_BUFFER_WIDTH - 1);
+        self.cursor_y = y.min(self.height() - 1);
+        self.continuation = false;
+        self.wrapped = false;
+        self.truncated = false;
+        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+    }
+
+    /// Writes a character at the output cursor.
+    fn put_glyph(&mut self, c: char) {
+        if self.cursor_x >= VGA_BUFFER_WIDTH && !self.wrap_row(c) {
+            return;
+        }
+        let b = vga_chars::from_char(c).unwrap_or(self.replacement);
+        _ = self.write_at(self.cursor_x, self.cursor_y, b);
+        self.cursor_x += 1;
+        self.wrap_full_row();
+    }
+
+    /// Goes on to the next row once the current one is full, in [`WrapMode::Hard`].
+    fn wrap_full_row(&mut self) {
+        if self.wrap == WrapMode::Hard && self.cursor_x >= VGA_BUFFER_WIDTH {
+            self.newline();
+            self.wrapped = true;
+        }
+    }
+
+    /// Applies a control character at the output cursor.
+    ///
+    /// A vertical tab moves down like a line feed but stays in the same column, and a form feed
+    /// clears the screen.
+    fn control(&mut self, control: Control) {
+        match control {
+            Control::Bell => self.ring_bell(),
+            Control::Backspace | Control::Delete => {
+                let (x, y) = (self.cursor_x, self.cursor_y);
+                let Some((x, y)) = wrap::backspace(x, y, self.wrapped, VGA_BUFFER_WIDTH) else {
+                    return;
+                };
+                if y != self.cursor_y {
+                    // Whether the previous row was itself wrapped onto is not known.
+                    self.wrapped = false;
+                    self.continuation = false;
+                }
+                self.truncated = false;
+                (self.cursor_x, self.cursor_y) = (x, y);
+                if control == Control::Delete {
+                    _ = self.clear_region(x, y, 1, 1);
+                }
+            }
+            Control::Tab => {
+                // The cells skipped over are blanked, so that nothing stale shows through.
+                let x = self.cursor_x;
+                let stop = wrap::tab_stop(x, self.tab_width, VGA_BUFFER_WIDTH);
+                _ = self.clear_region(x, self.cursor_y, stop - x, 1);
+                self.cursor_x = stop;
+                self.wrap_full_row();
+            }
+            Control::LineFeed => self.newline(),
+            Control::VerticalTab => {
+                let x = self.cursor_x;
+                self.newline();
+                self.cursor_x = x;
+            }
+            Control::FormFeed => self.clear(),
+            Control::CarriageReturn => self.cursor_x = 0,
+        }
+    }
+
+    /// Returns what happens to characters written past the end of a row.
+    pub fn wrap_mode(&self) -> WrapMode {
+        self.wrap
+    }
+
+    /// Sets what happens to characters written past the end of a row.
+    pub fn set_wrap_mode(&mut self, wrap: WrapMode) {
+        self.wrap = wrap;
+    }
+
+    /// Returns the distance between two tab stops.
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Sets the distance between two tab stops.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    pub fn set_tab_width(&mut self, width: usize) {
+        assert!(width > 0, "tab width must not be 0");
+        self.tab_width = width;
+    }
+
+    /// Returns the character shown for the characters without a VGA equivalent.
+    pub fn replacement(&self) -> char {
+        vga_chars::to_char(self.replacement)
+    }
+
+    /// Shows `c` for the characters without a VGA equivalent, instead of `■`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `c` has no VGA equivalent itself.
+    pub fn set_replacement(&mut self, c: char) {
+        self.replacement = vga_chars::from_char(c).expect("replacement must be a VGA character");
+    }
+
+    /// Returns whether `\x07` rings the bell, rather than being ignored.
+    pub fn bell(&self) -> bool {
+        self.bell
+    }
+
+    /// Makes `\x07` ring the bell, or be ignored.
+    pub fn set_bell(&mut self, enabled: bool) {
+        self.bell = enabled;
+    }
+
+    /// Rings the bell, unless it is turned off, without writing anything.
+    pub fn ring_bell(&self) {
+        if self.bell {
+            speaker::beep(self.bell_frequency, speaker::BELL_MS);
+        }
+    }
+
+    /// Returns the frequency of the bell, in Hz.
+    pub fn bell_frequency(&self) -> u32 {
+        self.bell_frequency
+    }
+
+    /// Sets the frequency of the bell, in Hz.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the speaker cannot play `freq`, as with [`speaker::start`].
+    pub fn set_bell_frequency(&mut self, freq: u32) {
+        assert!(
+            (speaker::MIN_FREQUENCY..=time::PIT_FREQUENCY).contains(&freq),
+            "frequency out of range"
+        );
+        self.bell_frequency = freq;
+    }
+
+    /// Returns the VGA character for `c`, or the replacement character if it has none.
+    pub fn vga_char(&self, c: char) -> u8 {
+        vga_chars::from_char(c).unwrap_or(self.replacement)
+    }
+
+    /// Sets the current color, which is also the one ANSI sequences go back to.
+    #[inline]
+    pub fn set_color(&mut self, color: u8) {
+        self.current_color = color;
+        self.default_color = color;
+    }
+
+    pub fn get_color(&self) -> u8 {
+        self.current_color
+    }
+
+    /// Returns the number of columns of the screen.
+    #[inline]
+    pub fn width(&self) -> usize {
+        VGA_BUFFER_WIDTH
+    }
+
+    /// Returns the number of rows available to text, which excludes the status bar.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.mode.height() - self.status.is_some() as usize
+    }
+
+    /// Returns the number of cells available to text.
+    #[inline]
+    fn cell_count(&self) -> usize {
+        VGA_BUFFER_WIDTH * self.height()
+    }
+
+    /// Returns the number of cells of the screen, including the status bar.
+    #[inline]
+    fn screen_cell_count(&self) -> usize {
+        VGA_BUFFER_WIDTH * self.mode.height()
+    }
+
+    /// Returns whether the last row of the screen is a status bar.
+    pub fn status_bar(&self) -> bool {
+        self.status.is_some()
+    }
+
+    /// Turns the status bar on the last row of the screen on or off. It starts out blank.
+    ///
+    /// Turning it on pushes the text of the console output goes to up by one row if the output
+    /// cursor is on the last row. The other consoles lose their last row.
+    pub fn set_status_bar(&mut self, on: bool) {
+        if on == self.status.is_some() {
+            return;
+        }
+        let blank = (self.current_color as u16) << 8 | b' ' as u16;
+        if on {
+            self.status = Some([blank; VGA_BUFFER_WIDTH]);
+            let height = self.height();
+            // The status bar may have taken the last row of the scrolling region.
+            if let Some((top, bottom)) = self.scroll_region {
+                self.scroll_region = (top < height).then_some((top, bottom.min(height)));
+            }
+            if self.cursor_y >= height {
+                let (_, bottom) = self.scroll_bounds();
+                if bottom == height {
+                    self.scroll_up();
+                }
+                self.cursor_y -= 1;
+            }
+            let last = height - 1;
+            for console in self.consoles() {
+                console.cursor.1 = console.cursor.1.min(last);
+            }
+            self.draw_status();
+        } else {
+            self.status = None;
+            let row = VGA_BUFFER_WIDTH * (self.height() - 1);
+            self.screen_mut()[row..].fill(blank);
+            self.dirty = ALL_ROWS;
+        }
+        self.sync_hardware_cursor();
+    }
+
+    /// Shows `text` in the status bar with the color `color`, if it is on. Characters that do not
+    /// fit are dropped.
+    pub fn set_status(&mut self, text: &[u8], color: u8) {
+        let Some(status) = &mut self.status else {
+            return;
+        };
+        let blank = (color as u16) << 8 | b' ' as u16;
+        status.fill(blank);
+        for (cell, &byte) in status.iter_mut().zip(text) {
+            *cell = (color as u16) << 8 | byte as u16;
+        }
+        self.draw_status();
+    }
+
+    /// Returns the cells of the status bar, if it is on.
+    pub fn status_cells(&self) -> Option<&[u16; VGA_BUFFER_WIDTH]> {
+        self.status.as_ref()
+    }
+
+    /// Puts `cells` in the status bar, if it is on.
+    pub(super) fn set_status_cells(&mut self, cells: &[u16; VGA_BUFFER_WIDTH]) {
+        let Some(status) = &mut self.status else {
+            return;
+        };
+        *status = *cells;
+        self.draw_status();
+    }
+
+    /// Draws the status bar on the last row of the screen, if it is on.
+    fn draw_status(&mut self) {
+        let Some(status) = self.status else {
+            return;
+        };
+        let y = self.height();
+        self.screen_mut()[y * VGA_BUFFER_WIDTH..][..VGA_BUFFER_WIDTH].copy_from_slice(&status);
+        self.dirty |= 1 << y;
+    }
+
+    /// Returns the number of times output scrolled the screen so far, wrapping around.
+    ///
+    /// Comparing two values tells how far the rows written in between moved up.
+    #[inline]
+    pub fn scroll_count(&self) -> usize {
+        self.scrolls
+    }
+
+    /// Returns the position of the output cursor.
+    #[inline]
+    pub fn output_cursor(&self) -> (usize, usize) {
+        (self.cursor_x, self.cursor_y)
+    }
+
+    /// Moves the output cursor, and the visual cursor along with it.
+    ///
+    /// A position off the screen is clamped to its edges. The cursor may be just past the end of
+    /// a row, where the next character wraps.
+    pub fn set_output_cursor(&mut self, x: usize, y: usize) {
+        self.cursor_x = x.min(VGA_BUFFER_WIDTH);
+        self.cursor_y = y.min(self.height() - 1);
+        self.wrapped = false;
+        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+    }
+
+    /// Moves the visual cursor, leaving the output cursor where it is.
+    ///
+    /// This has no effect while the view is scrolled back, or while output goes to a console
+    /// that is not shown.
+    pub fn set_visual_cursor_pos(&mut self, x: usize, y: usize) {
+        if self.scrolled == 0 && self.output == self.shown {
+            self.write_cursor_pos(y * VGA_BUFFER_WIDTH + x);
+        }
+    }
+
+    /// Returns the geometry of the screen.
+    pub fn text_mode(&self) -> TextMode {
+        self.mode
+    }
+
+    /// Switches the screen to the geometry `mode`.
+    ///
+    /// What every console showed is lost: they are cleared, and their cursors go back to the top
+    /// left corner.
+    pub fn set_text_mode(&mut self, mode: TextMode) {
+        self.end_frame();
+        self.reset_view();
+        if mode == TextMode::Mode80x50 {
+            vga_font::load_half_height_font(self);
+        }
+        write_register(SEQUENCER, 0x03, mode.font_maps());
+        let max_scan_line = read_register(CRT_CONTROLLER, 0x09);
+        write_register(
+            CRT_CONTROLLER,
+            0x09,
+            max_scan_line & 0xE0 | (mode.char_height() - 1),
+        );
+        self.mode = mode;
+        self.scroll_region = None;
+        let (cursor_start, cursor_end) = mode.underline_cursor();
+        self.set_cursor_shape(cursor_start, cursor_end);
+
+        for console in self.consoles() {
+            console
+                .cells
+                .fill((console.color as u16) << 8 | b' ' as u16);
+            console.cursor = (0, 0);
+        }
+        self.start_row = 0;
+        self.write_start_address();
+        self.clear();
+        self.draw_status();
+    }
+
+    /// Runs `f` with the font plane mapped at the address it is given, and restores text mode
+    /// afterwards.
+    ///
+    /// The text is neither shown nor reachable while the font plane is mapped, so the cells on
+    /// screen are saved beforehand and written back afterwards.
+    pub(super) fn with_font_plane<R>(&mut self, f: impl FnOnce(*mut u8) -> R) -> R {
+        // The map mask and memory mode of the sequencer, then the read map select, graphics
+        // mode and miscellaneous registers of the graphics controller.
+        const REGISTERS: [(u16, u8); 5] = [
+            (SEQUENCER, 0x02),
+            (SEQUENCER, 0x04),
+            (GRAPHICS_CONTROLLER, 0x04),
+            (GRAPHICS_CONTROLLER, 0x05),
+            (GRAPHICS_CONTROLLER, 0x06),
+        ];
+        // Plane 2 only, sequential addressing, and the planes mapped at 0xA0000.
+        const FONT_ACCESS: [u8; 5] = [0x04, 0x07, 0x02, 0x00, 0x04];
+
+        let len = self.screen_cell_count();
+        let mut cells = [0; VGA_BUFFER_WIDTH * VGA_BUFFER_MAX_HEIGHT];
+        cells[..len].copy_from_slice(self.window_mut());
+
+        let saved = REGISTERS.map(|(port, index)| read_register(port, index));
+        for ((port, index), value) in REGISTERS.into_iter().zip(FONT_ACCESS) {
+            write_register(port, index, value);
+        }
+        let ret = f(core::ptr::without_provenance_mut(FONT_PLANE_ADDRESS));
+        for ((port, index), value) in REGISTERS.into_iter().zip(saved) {
+            write_register(port, index, value);
+        }
+
+        self.window_mut().copy_from_slice(&cells[..len]);
+        ret
+    }
+
+    /// Returns whether bit 7 of the color makes characters blink.
+    pub fn blink(&self) -> bool {
+        self.blink
+    }
+
+    /// Sets whether bit 7 of the color makes characters blink, or selects the bright variant of
+    /// the background color.
+    pub fn set_blink(&mut self, blink: bool) {
+        // The Attribute Mode Control register.
+        update_attribute_register(0x10, |mode| mode & !0x08 | (blink as u8) << 3);
+        self.blink = blink;
+    }
+
+    /// Returns the index in the DAC of the color `color` of text mode, as set in the palette
+    /// registers of the attribute controller.
+    pub(super) fn dac_index(&mut self, color: u8) -> u8 {
+        assert!(color < 16);
+        update_attribute_register(color, |index| index) & 0x3F
+    }
+
+    /// Returns the first and last scanlines of the cursor, as passed to
+    /// [`set_cursor_shape`](Self::set_cursor_shape).
+    ///
+    /// Until a shape is set, this is the shape the BIOS left.
+    pub fn cursor_shape(&mut self) -> (u8, u8) {
+        *self.cursor_shape.get_or_insert_with(|| {
+            let cursor_start = read_register(CRT_CONTROLLER, 0x0A) & 0x1F;
+            let cursor_end = read_register(CRT_CONTROLLER, 0x0B) & 0x1F;
+            (cursor_start, cursor_end)
+        })
+    }
+
+    /// Sets the first and last scanlines of the cursor. The shape survives hiding the cursor.
+    pub fn set_cursor_shape(&mut self, cursor_start: u8, cursor_end: u8) {
+        self.cursor_shape = Some((cursor_start & 0x1F, cursor_end & 0x1F));
+        self.write_cursor_shape();
+    }
+
+    /// Returns whether the cursor was hidden with [`cursor_hide`](Self::cursor_hide).
+    pub fn cursor_hidden(&self) -> bool {
+        self.cursor_hidden
+    }
+
+    /// Hides the cursor, until [`cursor_show`](Self::cursor_show) is called.
+    pub fn cursor_hide(&mut self) {
+        self.cursor_hidden = true;
+        self.write_cursor_shape();
+    }
+
+    /// Shows the cursor again, with the shape it had before it was hidden.
+    pub fn cursor_show(&mut self) {
+        self.cursor_hidden = false;
+        self.write_cursor_shape();
+    }
+
+    /// Programs the CRT controller with the shape of the cursor, and with whether it is hidden.
+    ///
+    /// The cursor is also hidden while the view is scrolled back.
+    fn write_cursor_shape(&mut self) {
+        let (cursor_start, cursor_end) = self.cursor_shape();
+        let hidden = (self.cursor_hidden || self.scrolled != 0) as u8;
+        let start = read_register(CRT_CONTROLLER, 0x0A) & 0xC0 | hidden << 5 | cursor_start;
+        write_register(CRT_CONTROLLER, 0x0A, start);
+        let end = read_register(CRT_CONTROLLER, 0x0B) & 0xE0 | cursor_end;
+        write_register(CRT_CONTROLLER, 0x0B, end);
+    }
+}
+
+/// Reads the register `index` of the VGA register group with the index port `port`.
+fn read_register(port: u16, index: u8) -> u8 {
+    unsafe {
+        outb(port, index);
+        inb(port + 1)
+    }
+}
+
+/// Replaces the value of the register `index` of the attribute controller with what `f` returns
+/// for it, and returns its previous value.
+///
+/// The screen is blank for the time it takes.
+fn update_attribute_register(index: u8, f: impl FnOnce(u8) -> u8) -> u8 {
+    unsafe {
+        // Reading the input status register resets the flip-flop of the attribute controller,
+        // so that the next write to 0x3C0 is taken as an index.
+        inb(0x3DA);
+        // Selecting a register with bit 5 clear gives the palette to the CPU, which blanks the
+        // screen until it is given back below.
+        outb(0x3C0, index);
+        let value = inb(0x3C1);
+        outb(0x3C0, f(value));
+        // Give the palette back to the display: without this, the screen stays black.
+        inb(0x3DA);
+        outb(0x3C0, 0x20);
+        value
+    }
+}
+
+/// Writes `value` to the register `index` of the VGA register group with the index port `port`.
+fn write_register(port: u16, index: u8, value: u8) {
+    unsafe {
+        outb(port, index);
+        outb(port + 1, value);
+    }
+}
+
+impl core::fmt::Write for VgaBuffer {
+    /// Writes `s` at the output cursor, and moves the visual cursor once at the end.
+    ///
+    /// Runs of printable ASCII characters outside of escape sequences are written a row at a
+    /// time. Everything else goes through [`putchar`](Self::putchar)'s path.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut rest = s;
+        while !rest.is_empty() {
+            if self.ansi.is_ground() {
+                let run = rest.bytes().take_while(|b| (b' '..=b'~').contains(b));
+                let len = self.write_ascii(&rest.as_bytes()[..run.count()]);
+                if len > 0 {
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            let mut chars = rest.chars();
+            if let Some(c) = chars.next() {
+                self.feed(c);
+            }
+            rest = chars.as_str();
+        }
+        self.set_visual_cursor_pos(self.cursor_x, self.cursor_y);
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        self.putchar(c);
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! The VGA character generator, which holds the glyphs of the 256 characters of text mode.
+
+use kfs_core::mutex::Mutex;
+
+use super::{TextMode, VgaBuffer};
+
+/// The scanlines of a glyph, from top to bottom. Bit 7 is the leftmost pixel.
+pub type Glyph = [u8; 16];
+
+/// The glyphs of every character.
+pub type Font = [Glyph; 256];
+
+/// The number of bytes of a glyph in a font map, whatever the height of the characters.
+const GLYPH_STRIDE: usize = 32;
+
+/// The offset of font map 1 in the font plane.
+const HALF_HEIGHT_FONT_OFFSET: usize = 0x4000;
+
+/// The first character of [`LCD_GLYPHS`].
+const LCD_FIRST: usize = 0x20;
+
+/// The glyphs of the printable ASCII characters, from `' '` to `'~'`, drawn like on a character
+/// LCD.
+static LCD_GLYPHS: &[u8; 95 * 16] = include_bytes!("vga_font/lcd.bin");
+
+/// The fonts the character generator can be loaded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontKind {
+    /// The font set up by the BIOS.
+    #[default]
+    Bios,
+    /// The BIOS font with the printable ASCII characters drawn like on a character LCD.
+    Lcd,
+}
+
+impl FontKind {
+    /// Parses a font from the name returned by [`name`](Self::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bios" => Some(FontKind::Bios),
+            "lcd" => Some(FontKind::Lcd),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the font, as shown by the `font` command.
+    pub fn name(self) -> &'static str {
+        match self {
+            FontKind::Bios => "bios",
+            FontKind::Lcd => "lcd",
+        }
+    }
+}
+
+/// The state behind [`select_font`].
+struct Fonts {
+    /// The BIOS font, saved before it was first replaced.
+    bios: Option<Font>,
+    /// The font loaded last.
+    current: FontKind,
+}
+
+/// The storage behind [`select_font`] and [`current_font`].
+static FONTS: Mutex<Fonts> = Mutex::new(Fonts {
+    bios: None,
+    current: FontKind::Bios,
+});
+
+/// Reads the font the characters are drawn with.
+pub fn read_font(vga: &mut VgaBuffer) -> Font {
+    vga.with_font_plane(|plane| {
+        let mut font = [[0; 16]; 256];
+        for (c, glyph) in font.iter_mut().enumerate() {
+            for (row, scanline) in glyph.iter_mut().enumerate() {
+                // SAFETY: The font plane is mapped at `plane`, and this stays within font map 0.
+                *scanline = unsafe { plane.add(c * GLYPH_STRIDE + row).read_volatile() };
+            }
+        }
+        font
+    })
+}
+
+/// Replaces the glyphs of every character with the ones of `font`.
+pub fn upload_font(vga: &mut VgaBuffer, font: &Font) {
+    vga.with_font_plane(|plane| {
+        for (c, glyph) in font.iter().enumerate() {
+            // SAFETY: The font plane is mapped at `plane`, and this stays within font map 0.
+            unsafe { write_glyph(plane, c, glyph) };
+        }
+    });
+    if vga.text_mode() == TextMode::Mode80x50 {
+        load_half_height_font(vga);
+    }
+}
+
+/// Replaces the glyph of the character `index` with `bitmap`.
+pub fn set_glyph(vga: &mut VgaBuffer, index: u8, bitmap: &Glyph) {
+    // SAFETY: The font plane is mapped at `plane`, and this stays within font map 0.
+    vga.with_font_plane(|plane| unsafe { write_glyph(plane, index as usize, bitmap) });
+    if vga.text_mode() == TextMode::Mode80x50 {
+        load_half_height_font(vga);
+    }
+}
+
+/// Writes the glyph of the character `c` to font map 0.
+///
+/// # Safety
+///
+/// The font plane must be mapped at `plane`.
+unsafe fn write_glyph(plane: *mut u8, c: usize, glyph: &Glyph) {
+    for (row, &scanline) in glyph.iter().enumerate() {
+        unsafe { plane.add(c * GLYPH_STRIDE + row).write_volatile(scanline) };
+    }
+}
+
+/// Returns the font loaded with [`select_font`].
+pub fn current_font() -> FontKind {
+    FONTS.lock().current
+}
+
+/// Loads the font `kind`.
+pub fn select_font(vga: &mut VgaBuffer, kind: FontKind) {
+    let mut fonts = FONTS.lock();
+    let mut font = *fonts.bios.get_or_insert_with(|| read_font(vga));
+    if kind == FontKind::Lcd {
+        let lcd = font[LCD_FIRST..].iter_mut().zip(LCD_GLYPHS.as_chunks().0);
+        for (glyph, bitmap) in lcd {
+            *glyph = *bitmap;
+        }
+    }
+    upload_font(vga, &font);
+    fonts.current = kind;
+}
+
+/// Loads font map 1 with an 8-scanline font derived from the 16-scanline font in font map 0, by
+/// merging each pair of scanlines.
+pub(super) fn load_half_height_font(vga: &mut VgaBuffer) {
+    vga.with_font_plane(|plane| {
+        for glyph in (0..256).map(|c| c * GLYPH_STRIDE) {
+            for row in 0..8 {
+                // SAFETY: The font plane is mapped at `plane`, and this stays within font maps 0
+                // and 1.
+                unsafe {
+                    let top = plane.add(glyph + 2 * row).read_volatile();
+                    let bottom = plane.add(glyph + 2 * row + 1).read_volatile();
+                    let half = plane.add(HALF_HEIGHT_FONT_OFFSET + glyph + row);
+                    half.write_volatile(top | bottom);
+                }
+            }
+        }
+    });
+}
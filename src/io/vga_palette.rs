@@ -0,0 +1,82 @@
+//! The VGA DAC, which gives the 16 colors of text mode their RGB values.
+//!
+//! The attribute controller maps each color to one of the 256 entries of the DAC through its
+//! palette registers. The functions here go through that mapping, so that setting the color
+//! `n` changes what the cells with the color `n` look like.
+
+use super::{VgaBuffer, inb, outb};
+
+/// The red, green and blue components of a color, from 0 to 63.
+pub type Rgb = (u8, u8, u8);
+
+/// The colors set up by the BIOS, as in the [`Color`](super::Color) enum.
+pub const EGA_PALETTE: [Rgb; 16] = [
+    (0, 0, 0),
+    (0, 0, 42),
+    (0, 42, 0),
+    (0, 42, 42),
+    (42, 0, 0),
+    (42, 0, 42),
+    (42, 21, 0),
+    (42, 42, 42),
+    (21, 21, 21),
+    (21, 21, 63),
+    (21, 63, 21),
+    (21, 63, 63),
+    (63, 21, 21),
+    (63, 21, 63),
+    (63, 63, 21),
+    (63, 63, 63),
+];
+
+/// The port selecting the DAC entry to read.
+const DAC_READ_INDEX: u16 = 0x3C7;
+/// The port selecting the DAC entry to write.
+const DAC_WRITE_INDEX: u16 = 0x3C8;
+/// The port the components of the selected DAC entry go through, red first.
+const DAC_DATA: u16 = 0x3C9;
+
+/// Returns the RGB value of the color `index`.
+///
+/// # Panics
+///
+/// Panics if `index` is not below 16.
+pub fn color(vga: &mut VgaBuffer, index: u8) -> Rgb {
+    let entry = vga.dac_index(index);
+    unsafe {
+        outb(DAC_READ_INDEX, entry);
+        (inb(DAC_DATA), inb(DAC_DATA), inb(DAC_DATA))
+    }
+}
+
+/// Sets the RGB value of the color `index`. Only the lowest 6 bits of each component are used.
+///
+/// # Panics
+///
+/// Panics if `index` is not below 16.
+pub fn set_color(vga: &mut VgaBuffer, index: u8, r: u8, g: u8, b: u8) {
+    let entry = vga.dac_index(index);
+    unsafe {
+        outb(DAC_WRITE_INDEX, entry);
+        outb(DAC_DATA, r & 0x3F);
+        outb(DAC_DATA, g & 0x3F);
+        outb(DAC_DATA, b & 0x3F);
+    }
+}
+
+/// Returns the RGB values of the 16 colors.
+pub fn palette(vga: &mut VgaBuffer) -> [Rgb; 16] {
+    core::array::from_fn(|index| color(vga, index as u8))
+}
+
+/// Sets the RGB values of the 16 colors.
+pub fn set_palette(vga: &mut VgaBuffer, palette: &[Rgb; 16]) {
+    for (index, &(r, g, b)) in palette.iter().enumerate() {
+        set_color(vga, index as u8, r, g, b);
+    }
+}
+
+/// Puts back the colors set up by the BIOS.
+pub fn reset_palette(vga: &mut VgaBuffer) {
+    set_palette(vga, &EGA_PALETTE);
+}
@@ -0,0 +1,185 @@
+//! The `kbc` command, which shows the state of the 8042 keyboard controller.
+
+use kfs_core::shell::{Args, ShellError};
+
+use crate::{KEYBOARD, shell::confirm};
+
+/// The meaning of the bits of the status register, from bit 0 up.
+const STATUS_BITS: [&str; 8] = [
+    "output buffer full",
+    "input buffer full",
+    "system flag",
+    "last write was a command",
+    "keyboard not inhibited",
+    "output is from the mouse",
+    "timeout error",
+    "parity error",
+];
+
+/// The meaning of the bits of the command byte, from bit 0 up.
+const COMMAND_BYTE_BITS: [&str; 8] = [
+    "keyboard IRQ 1 enabled",
+    "mouse IRQ 12 enabled",
+    "system flag",
+    "reserved",
+    "keyboard clock disabled",
+    "mouse clock disabled",
+    "scancode translation",
+    "reserved",
+];
+
+/// The meaning of the bits of the output port, from bit 0 up.
+const OUTPUT_PORT_BITS: [&str; 8] = [
+    "system running (0 = reset)",
+    "A20 gate enabled",
+    "mouse clock",
+    "mouse data",
+    "keyboard output full (IRQ 1)",
+    "mouse output full (IRQ 12)",
+    "keyboard clock",
+    "keyboard data",
+];
+
+/// A register of the controller that `kbc set` can modify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    CommandByte,
+    OutputPort,
+}
+
+impl Register {
+    /// Returns the controller commands reading and writing the register.
+    fn commands(self) -> (u8, u8) {
+        match self {
+            Register::CommandByte => (0x20, 0x60),
+            Register::OutputPort => (0xD0, 0xD1),
+        }
+    }
+}
+
+/// A bit that `kbc set` can modify.
+struct Field {
+    name: &'static str,
+    register: Register,
+    bit: u8,
+    /// Why changing the bit is dangerous, if it is. Dangerous changes must be confirmed.
+    danger: Option<&'static str>,
+}
+
+/// The bits that `kbc set` can modify.
+const FIELDS: &[Field] = &[
+    Field {
+        name: "irq1",
+        register: Register::CommandByte,
+        bit: 0,
+        danger: None,
+    },
+    Field {
+        name: "irq12",
+        register: Register::CommandByte,
+        bit: 1,
+        danger: None,
+    },
+    Field {
+        name: "translation",
+        register: Register::CommandByte,
+        bit: 6,
+        danger: Some(
+            "Without translation, keys are misread unless the keyboard says which set it sends.",
+        ),
+    },
+    Field {
+        name: "a20",
+        register: Register::OutputPort,
+        bit: 1,
+        danger: Some("Without A20, every odd megabyte aliases the one below, kernel included."),
+    },
+];
+
+/// Prints the bits of `value`, two per line.
+fn print_bits(name: &str, value: Option<u8>, bits: &[&str; 8]) {
+    let Some(value) = value else {
+        printk!("{name}: no response\n");
+        return;
+    };
+    printk!("{name}: {value:#04x}\n");
+    for (i, pair) in bits.chunks(2).enumerate() {
+        for (j, meaning) in pair.iter().enumerate() {
+            let bit = i * 2 + j;
+            printk!("  {bit} {meaning:<30} {}", value >> bit & 1);
+        }
+        printk!("\n");
+    }
+}
+
+/// Implements the `kbc` command.
+pub fn command(args: &mut Args) -> Result<(), ShellError> {
+    match args.next() {
+        None => {
+            let (status, command_byte, output_port, scancode_set) = {
+                let mut keyboard = KEYBOARD.lock();
+                (
+                    keyboard.controller_status(),
+                    keyboard.controller_read(0x20),
+                    keyboard.controller_read(0xD0),
+                    keyboard.scancode_set(),
+                )
+            };
+            print_bits("status", Some(status), &STATUS_BITS);
+            print_bits("command byte", command_byte, &COMMAND_BYTE_BITS);
+            print_bits("output port", output_port, &OUTPUT_PORT_BITS);
+            match scancode_set {
+                Some(set) => printk!("scancode set: {set}\n"),
+                None => printk!("scancode set: no response\n"),
+            }
+            Ok(())
+        }
+        Some("set") => {
+            let name = args.next_str()?;
+            let field = FIELDS
+                .iter()
+                .find(|field| field.name == name)
+                .ok_or(args.invalid("irq1, irq12, translation or a20"))?;
+            let value = match args.next_str()? {
+                "0" => false,
+                "1" => true,
+                _ => return Err(args.invalid("0 or 1")),
+            };
+            args.expect_end()?;
+            set(field, value)
+        }
+        Some(_) => Err(args.invalid("`set`")),
+    }
+}
+
+/// Sets the bit `field` to `value`.
+fn set(field: &Field, value: bool) -> Result<(), ShellError> {
+    let (read, write) = field.register.commands();
+    let current = KEYBOARD
+        .lock()
+        .controller_read(read)
+        .ok_or(ShellError::Failed("the controller did not respond"))?;
+    let new = if value {
+        current | 1 << field.bit
+    } else {
+        current & !(1 << field.bit)
+    };
+    if new == current {
+        return Ok(());
+    }
+
+    if let Some(danger) = field.danger {
+        printk!("{danger}\n");
+        if !confirm("Continue?") {
+            return Ok(());
+        }
+    }
+    let mut keyboard = KEYBOARD.lock();
+    if !keyboard.controller_write(write, new) {
+        return Err(ShellError::Failed("the controller did not respond"));
+    }
+    if field.name == "translation" {
+        keyboard.detect_translation();
+    }
+    Ok(())
+}
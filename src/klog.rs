@@ -0,0 +1,147 @@
+//! The kernel log: everything written with [`printk!`], kept in memory so that it can be read
+//! back with `dmesg` after it was cleared or scrolled off the screen.
+//!
+//! It also holds the writes that could not reach the terminal yet, until they are
+//! [drained](drain).
+
+use {
+    core::{
+        fmt::{self, Write},
+        ops::Range,
+        sync::atomic::{AtomicU32, Ordering},
+    },
+    kfs_core::{log_ring::LogRing, mutex::Mutex},
+};
+
+/// The size of the kernel log, in bytes.
+const KLOG_SIZE: usize = 16 * 1024;
+
+/// The kernel log, oldest lines first.
+static KLOG: Mutex<LogRing<KLOG_SIZE>> = Mutex::new(LogRing::new());
+
+/// Appends `s` to the kernel log, and returns the sequence number of its first byte.
+pub fn append(s: &str) -> u64 {
+    let mut klog = KLOG.lock();
+    let seq = klog.end();
+    klog.push(s.as_bytes());
+    seq
+}
+
+/// The most ranges of deferred output kept. Adjacent ones are merged.
+const MAX_DEFERRED: usize = 8;
+
+/// The ranges of sequence numbers of the writes not shown on the terminal yet, oldest first.
+static DEFERRED: Mutex<[Option<Range<u64>>; MAX_DEFERRED]> =
+    Mutex::new([const { None }; MAX_DEFERRED]);
+
+/// The number of writes not shown on the terminal yet, read without taking any lock.
+static PENDING: AtomicU32 = AtomicU32::new(0);
+
+/// Records that the write of `len` bytes at the sequence number `seq` did not reach the
+/// terminal.
+///
+/// Past [`MAX_DEFERRED`] ranges, the write is only kept in the log.
+pub fn defer(seq: u64, len: usize) {
+    let range = seq..seq + len as u64;
+    let mut deferred = DEFERRED.lock();
+    let last = deferred.iter_mut().rev().find_map(Option::as_mut);
+    if let Some(last) = last.filter(|last| last.end == range.start) {
+        last.end = range.end;
+    } else if let Some(free) = deferred.iter_mut().find(|slot| slot.is_none()) {
+        *free = Some(range);
+    } else {
+        return;
+    }
+    PENDING.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the number of writes not shown on the terminal yet.
+///
+/// This never waits for a lock, so that it can be called from anywhere.
+pub fn pending() -> u32 {
+    PENDING.load(Ordering::Relaxed)
+}
+
+/// Writes the deferred output to `out`, the ranges deferred when it is called at most.
+///
+/// Returns the number of writes drained. The output dropped from the log since is skipped.
+pub fn drain(out: &mut impl Write) -> u32 {
+    let ranges = core::mem::replace(&mut *DEFERRED.lock(), [const { None }; MAX_DEFERRED]);
+    let count = PENDING.swap(0, Ordering::Relaxed);
+    for range in ranges.into_iter().flatten() {
+        let start = range.start.max(KLOG.lock().first());
+        _ = write_range(
+            |seq, buffer| KLOG.lock().read(seq, buffer),
+            start,
+            range.end,
+            out,
+        );
+    }
+    count
+}
+
+/// Writes `bytes` to `out`, with each invalid UTF-8 sequence replaced by `U+FFFD`.
+fn write_lossy(out: &mut impl Write, bytes: &[u8]) -> fmt::Result {
+    for chunk in bytes.utf8_chunks() {
+        out.write_str(chunk.valid())?;
+        if !chunk.invalid().is_empty() {
+            out.write_char(char::REPLACEMENT_CHARACTER)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the bytes from the sequence number `seq` to `end` to `out`, a piece at a time.
+///
+/// `read` copies the bytes starting at a sequence number, like [`LogRing::read`].
+fn write_range(
+    mut read: impl FnMut(u64, &mut [u8]) -> (u64, usize),
+    mut seq: u64,
+    end: u64,
+    out: &mut impl Write,
+) -> fmt::Result {
+    let mut buffer = [0; 256];
+    while seq < end {
+        let len = buffer.len().min((end - seq) as usize);
+        let (start, count) = read(seq, &mut buffer[..len]);
+        if count == 0 {
+            break;
+        }
+        // Keep a character cut at the end of the piece for the next one.
+        let count = match core::str::from_utf8(&buffer[..count]) {
+            Err(err) if err.error_len().is_none() && err.valid_up_to() > 0 => err.valid_up_to(),
+            _ => count,
+        };
+        write_lossy(out, &buffer[..count])?;
+        seq = start + count as u64;
+    }
+    Ok(())
+}
+
+/// Writes the kernel log as it is now to `out`.
+///
+/// The log is only locked while each piece is copied, so `out` may itself append to the log:
+/// what it appends is not written back.
+pub fn replay(out: &mut impl Write) -> fmt::Result {
+    let (first, end) = {
+        let klog = KLOG.lock();
+        (klog.first(), klog.end())
+    };
+    write_range(|seq, buffer| KLOG.lock().read(seq, buffer), first, end, out)
+}
+
+/// Writes the whole kernel log to `out`, without waiting for the lock.
+///
+/// # Safety
+///
+/// Nothing may be appending to the log at the same time, which is the case once the kernel
+/// panicked.
+pub unsafe fn dump(out: &mut impl Write) -> fmt::Result {
+    let klog = unsafe { KLOG.lock_unchecked() };
+    write_range(
+        |seq, buffer| klog.read(seq, buffer),
+        klog.first(),
+        klog.end(),
+        out,
+    )
+}
@@ -0,0 +1,103 @@
+//! Instrumentation of the time spent holding the terminal lock.
+//!
+//! With the `lockstat` feature enabled, [`lock`] records the longest critical section of every
+//! call site. Without it, [`lock`] is a plain [`Mutex::lock`].
+
+use kfs_core::mutex::Mutex;
+
+#[cfg(feature = "lockstat")]
+pub use self::instrumented::*;
+
+#[cfg(not(feature = "lockstat"))]
+pub use kfs_core::mutex::MutexGuard as Guard;
+
+/// Locks `mutex`.
+#[cfg(not(feature = "lockstat"))]
+#[inline(always)]
+#[track_caller]
+pub fn lock<T>(mutex: &Mutex<T>) -> Guard<'_, T> {
+    mutex.lock()
+}
+
+#[cfg(feature = "lockstat")]
+mod instrumented {
+    use {
+        super::Mutex,
+        crate::time,
+        core::{
+            ops::{Deref, DerefMut},
+            panic::Location,
+        },
+        kfs_core::mutex::MutexGuard,
+    };
+
+    /// The maximum number of call sites tracked.
+    const MAX_SITES: usize = 32;
+
+    /// A call site, along with the longest time it held the lock, in TSC cycles.
+    type Site = (&'static Location<'static>, u64);
+
+    static SITES: Mutex<[Option<Site>; MAX_SITES]> = Mutex::new([None; MAX_SITES]);
+
+    /// A guard recording how long it was held when dropped.
+    pub struct Guard<'a, T> {
+        guard: MutexGuard<'a, T>,
+        location: &'static Location<'static>,
+        start: u64,
+    }
+
+    /// Locks `mutex`, recording the time spent holding it under the caller's location.
+    #[track_caller]
+    pub fn lock<T>(mutex: &Mutex<T>) -> Guard<'_, T> {
+        Guard {
+            guard: mutex.lock(),
+            location: Location::caller(),
+            start: time::rdtsc(),
+        }
+    }
+
+    impl<T> Deref for Guard<'_, T> {
+        type Target = T;
+
+        #[inline]
+        fn deref(&self) -> &T {
+            &self.guard
+        }
+    }
+
+    impl<T> DerefMut for Guard<'_, T> {
+        #[inline]
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.guard
+        }
+    }
+
+    impl<T> Drop for Guard<'_, T> {
+        fn drop(&mut self) {
+            let cycles = time::rdtsc().wrapping_sub(self.start);
+            let mut sites = SITES.lock();
+            let slot = sites
+                .iter_mut()
+                .find(|site| site.is_none_or(|(location, _)| location == self.location));
+            if let Some(slot) = slot {
+                let max = slot.map_or(0, |(_, max)| max);
+                *slot = Some((self.location, max.max(cycles)));
+            }
+        }
+    }
+
+    /// Calls `f` with every call site recorded so far, along with the longest time it held the
+    /// lock, in microseconds.
+    pub fn for_each(mut f: impl FnMut(&'static Location<'static>, u64)) {
+        let sites = *SITES.lock();
+        let khz = time::tsc_khz().max(1);
+        for (location, cycles) in sites.into_iter().flatten() {
+            f(location, cycles * 1000 / khz);
+        }
+    }
+
+    /// Forgets every recorded call site.
+    pub fn reset() {
+        *SITES.lock() = [None; MAX_SITES];
+    }
+}
@@ -0,0 +1,103 @@
+//! Leveled kernel messages, written with [`printk!`] by the `pr_*!` macros.
+//!
+//! Messages below the level set with [`set_min_level`] are dropped. Warnings and errors are
+//! prefixed with their level and drawn in their own color: yellow for warnings, and the error
+//! color of the theme for errors. With an [accessible](crate::io::Theme::accessible) theme, every
+//! message is prefixed with its level, after a glyph telling the levels apart without color.
+
+use {
+    crate::{io::Color, terminal},
+    core::{
+        fmt,
+        sync::atomic::{AtomicU8, Ordering},
+    },
+};
+
+/// How important a kernel message is, from the least to the most.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Every level, in the order of their values.
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    /// Parses a level from its value, or from the name returned by [`name`](Self::name).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.parse::<usize>() {
+            Ok(value) => Self::ALL.get(value).copied(),
+            Err(_) => Self::ALL.into_iter().find(|level| level.name() == s),
+        }
+    }
+
+    /// Returns the name of the level, as shown by the `loglevel` command.
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "err",
+        }
+    }
+
+    /// Returns what messages of this level start with, with an accessible theme or not.
+    fn tag(self, accessible: bool) -> &'static str {
+        match (self, accessible) {
+            (Level::Debug, false) => "debug: ",
+            (Level::Info, false) => "",
+            (Level::Warn, false) => "warning: ",
+            (Level::Error, false) => "error: ",
+            (Level::Debug, true) => "· debug: ",
+            (Level::Info, true) => "· info: ",
+            (Level::Warn, true) => "! warning: ",
+            (Level::Error, true) => "✗ error: ",
+        }
+    }
+}
+
+/// The least important level of the messages that are printed.
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Returns the least important level of the messages that are printed.
+pub fn min_level() -> Level {
+    Level::ALL[MIN_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+/// Drops the messages less important than `level` from now on.
+pub fn set_min_level(level: Level) {
+    MIN_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Prints a message of level `level`, unless it is below the minimum level.
+///
+/// The color of the terminal is changed for warnings and errors, and put back afterwards.
+pub fn print(level: Level, args: fmt::Arguments) {
+    if level < min_level() {
+        return;
+    }
+    let mut lock = terminal();
+    let accessible = lock.theme().accessible;
+    let previous = lock.get_color();
+    match level {
+        Level::Error => {
+            let error = lock.theme().error;
+            lock.set_color(error);
+        }
+        Level::Warn => {
+            let (_, bg) = lock.colors();
+            lock.set_colors(Color::Yellow, bg);
+        }
+        _ => {}
+    }
+    drop(lock);
+    let previous = (level >= Level::Warn).then_some(previous);
+    printk!("{}{args}", level.tag(accessible));
+    if let Some(color) = previous {
+        terminal().set_color(color);
+    }
+}
@@ -2,32 +2,149 @@
 #![no_main]
 #![allow(clippy::needless_range_loop)]
 
-use mutex::Mutex;
 use {
-    self::io::Cmdline,
+    self::{boot::BootToken, stack::KERNEL_STACK},
     core::{
         arch::{asm, naked_asm},
-        mem::MaybeUninit,
+        fmt::Write,
+        sync::atomic::{AtomicBool, AtomicU32, Ordering},
     },
+    kfs_core::{cmdline::Cmdline, keyboard, mutex::Mutex, once::Once, util},
 };
 
+macro_rules! printk {
+    ($($arg:tt)*) => {
+        _ = core::fmt::Write::write_fmt(&mut $crate::Printk, core::format_args!($($arg)*))
+    };
+}
+
+/// Like [`printk!`], but for a debug message, which is dropped unless enabled with `loglevel`.
+macro_rules! pr_debug {
+    ($($arg:tt)*) => {
+        $crate::log::print($crate::log::Level::Debug, core::format_args!($($arg)*))
+    };
+}
+
+/// Like [`printk!`], but for an informational message.
+macro_rules! pr_info {
+    ($($arg:tt)*) => {
+        $crate::log::print($crate::log::Level::Info, core::format_args!($($arg)*))
+    };
+}
+
+/// Like [`printk!`], but for a warning, which is shown in yellow.
+macro_rules! pr_warn {
+    ($($arg:tt)*) => {
+        $crate::log::print($crate::log::Level::Warn, core::format_args!($($arg)*))
+    };
+}
+
+/// Like [`printk!`], but for an error, which is shown in red.
+macro_rules! pr_err {
+    ($($arg:tt)*) => {
+        $crate::log::print($crate::log::Level::Error, core::format_args!($($arg)*))
+    };
+}
+
+/// Like [`printk!`], but writes to the [`io::DebugCon`], without taking any lock.
+macro_rules! debugk {
+    ($($arg:tt)*) => {
+        _ = core::fmt::Write::write_fmt(&mut $crate::io::DebugCon, core::format_args!($($arg)*))
+    };
+}
+
+mod boot;
+mod cpu;
 mod io;
+mod kbc;
+mod klog;
+mod lockstat;
+mod log;
+mod memory;
 mod multiboot;
-mod mutex;
+mod shell;
+mod stack;
+mod time;
+mod version;
 
 #[used]
 #[unsafe(link_section = ".multiboot")]
 static MULTIBOOT2_HEADER: multiboot::Header = multiboot::Header::new();
 
-const KERNEL_STACK_SIZE: usize = 0x1000 * 32;
-static mut KERNEL_STACK: MaybeUninit<[u8; KERNEL_STACK_SIZE]> = MaybeUninit::uninit();
+static TERMINAL: Once<Mutex<io::Terminal>> = Once::new();
+static KEYBOARD: Once<Mutex<io::Keyboard>> = Once::new();
+static SERIAL: Once<Mutex<io::SerialPort>> = Once::new();
 
-static TERMINAL: Mutex<io::Terminal> = unsafe { Mutex::new(io::Terminal::new()) };
+/// Locks the terminal.
+#[track_caller]
+fn terminal() -> lockstat::Guard<'static, io::Terminal> {
+    lockstat::lock(&TERMINAL)
+}
 
-macro_rules! printk {
-    ($($arg:tt)*) => {
-        _ = core::fmt::Write::write_fmt(&mut *TERMINAL.lock(), core::format_args!($($arg)*))
-    };
+/// The longest time [`Printk`] may hold the terminal lock at once, in microseconds.
+static TERM_BUDGET_US: AtomicU32 = AtomicU32::new(500);
+
+/// The writer behind [`printk!`].
+///
+/// Everything is kept in the [kernel log](klog), and mirrored to the serial port once it is
+/// initialized. Large writes release the terminal lock every [`TERM_BUDGET_US`] microseconds,
+/// so that printing a long string does not keep everyone else out of the terminal.
+///
+/// Writes made while the terminal is locked are [deferred](klog::defer) rather than waiting
+/// for a lock that would never be released, until the `flush` command.
+struct Printk;
+
+impl core::fmt::Write for Printk {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // Long outputs do not read the keyboard, so give CTRL+ALT+DEL a chance here.
+        if let Some(mut keyboard) = KEYBOARD.get().and_then(Mutex::try_lock) {
+            keyboard.poll();
+        }
+        let seq = klog::append(s);
+        if let Some(serial) = SERIAL.get() {
+            _ = core::fmt::Write::write_str(&mut *serial.lock(), s);
+        }
+
+        // The terminal is held further up the stack, and waiting for it would never end: the
+        // write is left in the kernel log until it is flushed.
+        if TERMINAL.try_lock().is_none() {
+            klog::defer(seq, s.len());
+            return Ok(());
+        }
+        Printk::write_terminal(s, |lock, chunk| {
+            _ = core::fmt::Write::write_str(lock, chunk)
+        });
+        Ok(())
+    }
+}
+
+/// The most bytes [`Printk`] writes to the terminal between two checks of its time budget.
+const PRINTK_CHUNK: usize = 80;
+
+impl Printk {
+    /// Writes `s` to the terminal with `write`, in chunks of at most [`PRINTK_CHUNK`] bytes cut at
+    /// character boundaries. The lock is released once a chunk goes over [`TERM_BUDGET_US`].
+    fn write_terminal(s: &str, write: fn(&mut io::Terminal, &str)) {
+        let budget = TERM_BUDGET_US.load(Ordering::Relaxed) as u64 * time::tsc_khz() / 1000;
+        let mut rest = s;
+        while !rest.is_empty() {
+            let mut lock = terminal();
+            let start = time::rdtsc();
+            // At least one chunk is written each time the lock is taken.
+            loop {
+                let mut end = rest.len().min(PRINTK_CHUNK);
+                while !rest.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let (chunk, tail) = rest.split_at(end);
+                write(&mut lock, chunk);
+                rest = tail;
+                if rest.is_empty() || time::rdtsc().wrapping_sub(start) > budget {
+                    break;
+                }
+            }
+        }
+    }
 }
 
 #[unsafe(no_mangle)]
@@ -37,99 +154,267 @@ extern "C" fn _start() {
         "
         lea esp, [{stack_base} + {stack_size}]
         and esp, 0xfffffff0
+        sub esp, 8
+        push ebx
+        push eax
         call {main}
         ",
         main = sym main,
         stack_base = sym KERNEL_STACK,
-        stack_size = const KERNEL_STACK_SIZE,
+        stack_size = const stack::KERNEL_STACK_SIZE,
     )
 }
 
-extern "C" fn main() -> ! {
-    init_gdt();
-    funny_42();
-    TERMINAL.lock().clear();
-    repl();
+extern "C" fn main(magic: u32, info: *const multiboot::Info) -> ! {
+    // Safety: the boot loader passed these in EAX and EBX.
+    unsafe { multiboot::init(magic, info) };
+    let token = BootToken::take();
+    TERMINAL.init(Mutex::new(io::Terminal::new(token.vga)));
+    // Output goes after whatever the boot loader printed.
+    terminal().sync_cursor_from_hw();
+    // Make the whole range of colors accepted by `color` usable as backgrounds.
+    terminal().set_blink(false);
+    KEYBOARD.init(Mutex::new(io::Keyboard::new(token.keyboard)));
+    SERIAL.init(Mutex::new(io::SerialPort::new(token.serial)));
+    let mut boot = Boot {
+        time_source: time::TimeSource::None,
+        translation: None,
+        options: BootOptions::new(),
+    };
+    for step in INIT_STEPS {
+        if !(step.skippable && SAFE_MODE.load(Ordering::Relaxed)) {
+            (step.run)(&mut boot);
+        }
+    }
+    shell::repl(&mut boot.options);
 }
 
-fn repl() -> ! {
-    let mut cmdline = Cmdline::new();
+/// What the init steps find out about the machine and pass on to the following ones.
+struct Boot {
+    /// The clock the TSC was calibrated against.
+    time_source: time::TimeSource,
+    /// How the keyboard controller translates scancodes, once detected.
+    translation: Option<io::Translation>,
+    /// The options given on the kernel command line.
+    options: BootOptions,
+}
 
-    loop {
-        let line = 'line: {
-            let mut lock = TERMINAL.lock();
-            cmdline.take();
-            lock.refresh_cmdline("");
-            loop {
-                core::hint::spin_loop();
-                if let Some(line) = lock.get_line(&mut cmdline) {
-                    break 'line line;
-                }
+/// A step of the boot sequence, run by `main` once the terminal, the keyboard and the serial
+/// port are available.
+struct InitStep {
+    /// The name of the step, listed by the safe mode banner if skipped.
+    name: &'static str,
+    /// Whether the step is skipped in safe mode.
+    skippable: bool,
+    run: fn(&mut Boot),
+}
+
+/// The boot sequence, in order.
+static INIT_STEPS: &[InitStep] = &[
+    InitStep {
+        name: "GDT",
+        skippable: false,
+        run: |_| init_gdt(),
+    },
+    InitStep {
+        name: "frame allocator",
+        skippable: false,
+        run: |_| memory::init(),
+    },
+    InitStep {
+        name: "clock calibration",
+        skippable: false,
+        run: |boot| boot.time_source = time::init(),
+    },
+    InitStep {
+        // The controller timeouts need the clock.
+        name: "keyboard translation",
+        skippable: false,
+        run: |boot| boot.translation = Some(KEYBOARD.lock().detect_translation()),
+    },
+    InitStep {
+        name: "safe mode key",
+        skippable: false,
+        run: |_| SAFE_MODE.store(key_held_at_boot(), Ordering::Relaxed),
+    },
+    InitStep {
+        // Safe mode must not be a way around it.
+        name: "lockdown state",
+        skippable: false,
+        run: |_| shell::load_lockdown(),
+    },
+    InitStep {
+        name: "kernel command line options",
+        skippable: true,
+        run: |boot| boot.options = parse_boot_options(),
+    },
+    InitStep {
+        name: "boot animation",
+        skippable: true,
+        run: |boot| funny_42(boot.options.animation_step_ns),
+    },
+    InitStep {
+        name: "safe mode banner",
+        skippable: false,
+        run: |_| {
+            if !SAFE_MODE.load(Ordering::Relaxed) {
+                return;
             }
-        };
-        printk!("{line}\n");
-
-        let mut words = line.split_whitespace();
-        match words.next() {
-            Some("reboot") => io::qemu_reboot(),
-            Some("poweroff" | "shutdown") => io::qemu_shutdown(),
-            Some("halt") => unsafe { asm!("hlt") },
-            Some("stack") => print_stack(),
-            Some("echo") => {
-                for w in words {
-                    printk!("{w} ");
-                }
-                printk!("\n");
+            terminal().clear();
+            printk!("*** SAFE MODE ***\nSkipped: ");
+            let skipped = INIT_STEPS.iter().filter(|step| step.skippable);
+            for (i, step) in skipped.enumerate() {
+                printk!("{}{}", if i == 0 { "" } else { ", " }, step.name);
             }
-            Some("color") => {
-                let color = words.next().unwrap_or("0f");
-
-                let Ok(color) = u8::from_str_radix(color.strip_prefix("0x").unwrap_or(color), 16)
-                else {
-                    printk!("Invalid color\n");
-                    continue;
-                };
-
-                TERMINAL.lock().set_color(color);
-                TERMINAL.lock().refresh_cmdline("");
+            printk!("\nUse `safemode off` to reboot normally.\n");
+        },
+    },
+    InitStep {
+        name: "boot report",
+        skippable: false,
+        run: |boot| {
+            let time_source = boot.time_source;
+            if time_source != time::TimeSource::Pit {
+                pr_warn!("PIT not responding, using {time_source}\n");
             }
-            Some(cmd) => {
-                printk!("Unknown command: {}\n", cmd);
+            pr_debug!(
+                "TSC at {} kHz, calibrated against {time_source}\n",
+                time::tsc_khz()
+            );
+            if let Some(translation) = boot.translation {
+                pr_debug!("keyboard scancodes: {}\n", scancode_path(translation));
             }
-            None => {}
+        },
+    },
+];
+
+/// Whether the kernel was booted in safe mode.
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// The scancodes of the keys requesting safe mode when held during boot: Space and S.
+const SAFE_MODE_KEYS: [u8; 2] = [0x39, 0x1F];
+
+/// How long the keyboard is watched for a held key during boot, in nanoseconds. This must be
+/// longer than the interval between two typematic repeats.
+const SAFE_MODE_WINDOW_NS: u64 = 250_000_000;
+
+/// Returns whether one of [`SAFE_MODE_KEYS`] is being held down.
+///
+/// The raw scancodes are consumed without going through the decoder.
+fn key_held_at_boot() -> bool {
+    let mut keyboard = KEYBOARD.lock();
+    let deadline = time::monotonic_ns() + SAFE_MODE_WINDOW_NS;
+    let mut held = false;
+    while time::monotonic_ns() < deadline {
+        match keyboard.get_kb_data() {
+            Some(scancode) if SAFE_MODE_KEYS.contains(&scancode) => held = true,
+            Some(scancode) if SAFE_MODE_KEYS.contains(&(scancode & 0x7F)) => held = false,
+            _ => core::hint::spin_loop(),
         }
     }
+    held
 }
 
-fn print_stack() {
-    let esp: usize;
-    // Safety: nothing is touched, we only get the value of ESP
-    unsafe {
-        asm!("mov {}, esp", out (reg) esp, options(nostack, nomem, preserves_flags));
-    }
-    let mut esp = esp as *const u8;
-    printk!("Stack dump from {:p}:\n", esp);
-    const STACK_END: *const u8 = unsafe { core::ptr::addr_of!(KERNEL_STACK).add(1).cast() };
-    if !esp.addr().is_multiple_of(16) {
-        printk!("{:p}:", esp);
-        if !esp.addr().is_multiple_of(4) {
-            printk!(" ");
+/// The number of virtual terminals.
+const TTY_COUNT: usize = io::CONSOLE_COUNT;
+
+/// Options passed on the kernel command line.
+struct BootOptions {
+    /// The index of the TTY to bring to the foreground at the end of boot.
+    tty: usize,
+    /// The command to run when each TTY is first initialized.
+    init: [Cmdline; TTY_COUNT],
+    /// How long each step of the boot animation lasts, in nanoseconds.
+    animation_step_ns: u64,
+}
+
+impl BootOptions {
+    /// Returns the options used when the kernel command line is empty.
+    const fn new() -> Self {
+        BootOptions {
+            tty: 0,
+            init: [const { Cmdline::new() }; TTY_COUNT],
+            animation_step_ns: ANIMATION_STEP_NS,
         }
     }
-    while esp < STACK_END {
-        let byte = unsafe { esp.read_volatile() };
-        if esp.addr().is_multiple_of(16) {
-            printk!("{:p}: ", esp);
-        } else if esp.addr().is_multiple_of(4) {
-            printk!(" ");
+}
+
+/// Parses the kernel command line.
+///
+/// Recognized options are `loglevel=<level>`, `boot_tty=<n>`, `tty<n>.init=<command>`,
+/// `anim=fast|normal|slow`, `keymap=us|fr|dvorak` and `accessible=0|1`.
+/// Invalid options are reported and ignored.
+fn parse_boot_options() -> BootOptions {
+    let mut options = BootOptions::new();
+
+    let strings = multiboot::strings();
+    let cmdline = match strings.cmdline() {
+        None => return options,
+        Some(Err(err)) => {
+            pr_err!("invalid kernel command line: {err}\n");
+            return options;
         }
-        printk!("{:02x}", byte);
-        esp = unsafe { esp.add(1) };
-        if esp.addr().is_multiple_of(16) {
-            printk!("\n");
+        Some(Ok(cmdline)) => cmdline,
+    };
+
+    for (key, value) in util::kernel_args::options(cmdline) {
+        if key == "loglevel" {
+            match value.and_then(log::Level::parse) {
+                Some(level) => log::set_min_level(level),
+                None => pr_warn!("loglevel: no such level, ignored\n"),
+            }
+        } else if key == "boot_tty" {
+            match value.map(str::parse::<usize>) {
+                Some(Ok(n @ 1..=TTY_COUNT)) => options.tty = n - 1,
+                _ => pr_warn!("boot_tty: no such TTY, defaulting to tty1\n"),
+            }
+        } else if key == "anim" {
+            options.animation_step_ns = match value {
+                Some("fast") => ANIMATION_STEP_NS / 4,
+                Some("normal") => ANIMATION_STEP_NS,
+                Some("slow") => ANIMATION_STEP_NS * 4,
+                _ => {
+                    pr_warn!("anim: expected fast, normal or slow, ignored\n");
+                    continue;
+                }
+            };
+        } else if key == "accessible" {
+            match value {
+                Some("0") => terminal().set_accessible(false),
+                Some("1") => terminal().set_accessible(true),
+                _ => pr_warn!("accessible: expected 0 or 1, ignored\n"),
+            }
+        } else if key == "keymap" {
+            match value.and_then(keyboard::Layout::from_name) {
+                Some(layout) => KEYBOARD.lock().set_layout(layout),
+                None => pr_warn!("keymap: no such layout, ignored\n"),
+            }
+        } else if let Some(n) = key
+            .strip_prefix("tty")
+            .and_then(|k| k.strip_suffix(".init"))
+        {
+            let (Ok(n @ 1..=TTY_COUNT), Some(value)) = (n.parse::<usize>(), value) else {
+                pr_warn!("{key}: no such TTY, ignored\n");
+                continue;
+            };
+            let init = &mut options.init[n - 1];
+            init.take();
+            if !value.chars().all(|c| init.push(c)) {
+                pr_warn!("{key}: command too long, truncated\n");
+            }
         }
     }
-    // printk!("{:p}\n", esp);
+
+    options
+}
+
+/// Dumps the stack to `out`, from the current stack pointer up, with `width` bytes per row.
+fn print_stack(out: &mut dyn Write, width: usize) {
+    let esp = stack::current();
+    _ = writeln!(out, "Stack dump from {esp:p}:");
+    let len = KERNEL_STACK.top().addr() - esp.addr();
+    // Safety: the stack is mapped from the stack pointer to its top.
+    _ = unsafe { io::hexdump(out, esp, len, width) };
 }
 
 fn init_gdt() {
@@ -182,51 +467,254 @@ fn init_gdt() {
     }
 }
 
-fn funny_42() {
+/// Runs `f` on a cleared screen with the cursor hidden, then puts the terminal back as it was.
+///
+/// Nothing happens if the terminal cannot be saved.
+fn take_over_screen(f: impl FnOnce()) {
+    let mut lock = terminal();
+    let Some(snapshot) = lock.snapshot() else {
+        return;
+    };
+    lock.reset_view();
+    lock.cursor_hide();
+    lock.clear();
+    drop(lock);
+
+    f();
+
+    terminal().restore(&snapshot);
+}
+
+fn funny_42(step_ns: u64) {
+    // Put whatever the boot loader left on screen back afterwards.
+    take_over_screen(|| animate_42(step_ns));
+}
+
+/// Draws the 42, with `color` giving the color of the character at a column and a row.
+fn draw_42(lock: &mut io::Terminal, color: impl Fn(usize, usize) -> u8) {
     const ASCII_42: &str = include_str!("42.txt");
 
-    // Initialize the VGA buffer.
-    {
-        let mut lock = TERMINAL.lock();
-        lock.clear();
-        lock.set_cursor_shape(0, 16);
-        lock.set_visual_cursor_pos(0, 0);
+    let mut row = 0;
+    let mut col = 27;
+    lock.begin_frame();
+    for c in ASCII_42.trim_ascii_end().bytes() {
+        if c == b'\n' {
+            row += 1;
+            col = 27;
+            continue;
+        }
+        lock.set_color(color(col, row));
+        // The rows of a short screen are dropped.
+        _ = lock.write_at(col, row, c);
+        col += 1;
     }
+    lock.end_frame();
+}
+
+/// How long each color step of the 42 animation lasts, in nanoseconds.
+const ANIMATION_STEP_NS: u64 = 50_000_000;
 
+/// Waits for `ns` nanoseconds, and returns whether a key was pressed in the meantime.
+///
+/// The key is consumed, so that it does not go to the command line.
+fn wait_for_key(ns: u64) -> bool {
+    let deadline = time::monotonic_ns() + ns;
+    while time::monotonic_ns() < deadline {
+        if KEYBOARD.lock().key_pressed() {
+            return true;
+        }
+        core::hint::spin_loop();
+    }
+    false
+}
+
+/// Draws the color-cycling 42 on a cleared screen until a key is pressed, and consumes that key.
+///
+/// The colors move one step every `step_ns` nanoseconds.
+fn animate_42(step_ns: u64) {
     let mut d = 0;
-    'a: loop {
-        for _ in 0..5_000 {
-            let mut row = 0;
-            let mut col = 27;
-            for c in ASCII_42.trim_ascii_end().bytes() {
-                if c == b'\n' {
-                    row += 1;
-                    col = 27;
-                    continue;
-                }
-                let color = ((col / 2 + row + d) & 0xF) as u8;
-                TERMINAL.lock().set_color(color);
-                TERMINAL.lock().write_at(col, row, c);
-                col += 1;
+    loop {
+        draw_42(&mut terminal(), |col, row| {
+            ((col / 2 + row + d) & 0xF) as u8
+        });
+        if wait_for_key(step_ns) {
+            return;
+        }
+        d = d.wrapping_add(1);
+    }
+}
+
+/// Like [`animate_42`], but draws the 42 once and cycles the colors of the DAC instead.
+///
+/// The background color is left alone, and the palette is put back afterwards.
+fn animate_42_dac() {
+    /// How long each step of the cycle lasts, in nanoseconds.
+    const STEP_NS: u64 = 100_000_000;
+
+    let saved = {
+        let mut lock = terminal();
+        draw_42(&mut lock, |col, row| (1 + (col / 2 + row) % 15) as u8);
+        io::vga_palette::palette(&mut lock)
+    };
+    let mut d = 0;
+    loop {
+        {
+            let mut lock = terminal();
+            for color in 1..16 {
+                let (r, g, b) = saved[1 + (color - 1 + d) % 15];
+                io::vga_palette::set_color(&mut lock, color as u8, r, g, b);
             }
-            if TERMINAL.lock().get_char().is_some() {
-                break 'a;
+        }
+        if wait_for_key(STEP_NS) {
+            break;
+        }
+        d = (d + 1) % 15;
+    }
+    io::vga_palette::set_palette(&mut terminal(), &saved);
+}
+
+/// Describes how the scancodes of the keyboard reach the decoder.
+fn scancode_path(translation: io::Translation) -> &'static str {
+    match translation {
+        io::Translation::Controller => "set 2, translated by the controller",
+        io::Translation::None => "set 1",
+        io::Translation::Kernel => "set 2, translated by the kernel",
+    }
+}
+
+/// Writes to the serial port and to the terminal without taking their locks.
+///
+/// This is for the output of the keys acting as soon as they are read, whose caller may hold the
+/// locks. Whatever is written by the caller at the time gets mixed with this output.
+struct Unlocked;
+
+impl Write for Unlocked {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // Safety: only the caller may hold the locks, and it does not run meanwhile.
+        if let Some(serial) = SERIAL.get() {
+            _ = core::fmt::Write::write_str(&mut *unsafe { serial.lock_unchecked() }, s);
+        }
+        if let Some(terminal) = TERMINAL.get() {
+            let mut terminal = unsafe { terminal.lock_unchecked() };
+            s.chars().for_each(|c| terminal.putchar(c));
+        }
+        Ok(())
+    }
+}
+
+/// Reboots, on **CTRL+ALT+DEL**.
+///
+/// This is called while reading the keyboard, with the terminal possibly locked by the caller.
+fn ctrl_alt_del() -> ! {
+    _ = Unlocked.write_str("\nrebooting...\n");
+    io::qemu_reboot()
+}
+
+/// The debug keys, pressed after **ALT+SYSRQ** or with **CTRL+ALT** held, and what they do.
+const SYSRQ_KEYS: [(char, &str); 5] = [
+    ('h', "show this help"),
+    ('m', "show the memory"),
+    ('p', "show the registers"),
+    ('r', "reboot"),
+    ('s', "dump the stack"),
+];
+
+/// Runs the action of the debug key `c`, and returns whether it is one.
+///
+/// This is called while reading the keyboard, at any point after the keyboard is set up, so the
+/// output goes through [`Unlocked`].
+fn sysrq(c: char) -> bool {
+    let out = &mut Unlocked;
+    match c {
+        'h' => {
+            _ = writeln!(out, "\nsysrq:");
+            for (key, action) in SYSRQ_KEYS {
+                _ = writeln!(out, "  {key}  {action}");
             }
         }
-        d = d.wrapping_add(1);
+        'm' => print_memory(out),
+        'p' => print_registers(out),
+        'r' => ctrl_alt_del(),
+        's' => print_stack(out, 16),
+        _ => return false,
     }
+    true
+}
+
+/// Prints the memory reported by the boot loader, and the use of the kernel stack.
+fn print_memory(out: &mut dyn Write) {
+    match multiboot::memory() {
+        Some(memory) => {
+            _ = writeln!(
+                out,
+                "\nmemory: {} KiB lower, {} KiB upper",
+                memory.lower_kib, memory.upper_kib
+            )
+        }
+        None => _ = writeln!(out, "\nmemory: not reported by the boot loader"),
+    }
+    let used = KERNEL_STACK.top().addr() - stack::current().addr();
+    _ = writeln!(
+        out,
+        "stack: {used} of {} bytes used",
+        stack::KERNEL_STACK_SIZE
+    );
+}
+
+/// Prints a snapshot of the registers, as taken by [`cpu::registers`].
+fn print_registers(out: &mut dyn Write) {
+    _ = writeln!(out, "\n{}", cpu::registers());
 }
 
 #[panic_handler]
 fn crash_and_burn(info: &core::panic::PanicInfo) -> ! {
+    debugk!("{info}\n");
+    let note = if memory::note_panic() {
+        "The last allocation failed on purpose (FAILALLOC).\n"
+    } else {
+        ""
+    };
+    // Without a terminal or a keyboard, there is nothing we can report.
+    let (Some(terminal), Some(keyboard)) = (TERMINAL.get(), KEYBOARD.get()) else {
+        loop {
+            unsafe { asm!("cli", "hlt") };
+        }
+    };
     // Safety: At this point we're crashing down anyways.
     // Might as well try to get some insights.
-    let mut lock = unsafe { TERMINAL.lock_unchecked() };
+    if let Some(serial) = SERIAL.get() {
+        let mut serial = unsafe { serial.lock_unchecked() };
+        _ = core::fmt::Write::write_str(&mut *serial, "--- kernel log ---\n");
+        // Safety: Nothing else runs anymore.
+        _ = unsafe { klog::dump(&mut *serial) };
+        _ = core::fmt::Write::write_str(&mut *serial, "--- end of kernel log ---\n");
+        _ = core::fmt::Write::write_fmt(&mut *serial, core::format_args!("{info}\n"));
+    }
+    let mut lock = unsafe { terminal.lock_unchecked() };
+    lock.end_frame();
+    let console = lock.output_console();
+    lock.show_console(console);
+    if lock.theme().accessible {
+        // Told apart by reverse video and a frame, rather than by red alone.
+        use io::draw::{self, BoxStyle};
+
+        let reversed = lock.theme().normal.rotate_left(4);
+        lock.set_color(reversed);
+        lock.reset_scroll_region();
+        lock.clear();
+        let width = lock.width();
+        draw::draw_box(&mut lock, 0, 0, width, 3, BoxStyle::Double);
+        draw::draw_text_centered(&mut lock, 1, 1, width - 2, "KERNEL PANIC");
+        lock.set_output_cursor(0, 3);
+    } else {
+        lock.set_colors(io::Color::White, io::Color::Red);
+    }
     _ = core::fmt::Write::write_fmt(
         &mut *lock,
-        core::format_args!("{info}\nPress ESC to shutdown"),
+        core::format_args!("{info}\n{note}Press ESC to shutdown"),
     );
-    while lock.get_kb_data() != Some(0x01) {
+    let mut keyboard = unsafe { keyboard.lock_unchecked() };
+    while keyboard.get_kb_data() != Some(0x01) {
         core::hint::spin_loop();
     }
     io::qemu_shutdown()
@@ -0,0 +1,138 @@
+//! The physical memory of the machine, handed out a frame at a time.
+//!
+//! There is no paging: the address of a frame is where it can be read and written.
+
+use {
+    crate::{io, multiboot},
+    core::{
+        ops::Range,
+        sync::atomic::{AtomicBool, Ordering},
+    },
+    kfs_core::{
+        frame::{AllocError, FrameAllocator, Pressure, Watermarks},
+        mutex::{Mutex, MutexGuard},
+    },
+};
+
+/// The words of the bitmap of the frame allocator, enough for 4 GiB.
+const FRAME_WORDS: usize = 1 << 15;
+
+static FRAMES: Mutex<FrameAllocator<FRAME_WORDS>> = Mutex::new(FrameAllocator::new());
+
+/// The memory below 1 MiB, left to the BIOS, the VGA buffer and the boot loader.
+const LOW_MEMORY: Range<u64> = 0..0x10_0000;
+
+/// The CMOS NVRAM register counting the panics that followed an injected allocation failure,
+/// past the lockdown state.
+const INJECTED_PANICS_NVRAM: u8 = 0x45;
+
+/// Whether the last allocation failed on purpose.
+static LAST_INJECTED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" {
+    /// The first byte of the kernel image, placed by the linker script.
+    static __kernel_start: u8;
+    /// The end of the kernel image, including the BSS and the stack.
+    static __kernel_end: u8;
+}
+
+/// Returns the addresses of the kernel image.
+pub fn kernel_image() -> Range<usize> {
+    (&raw const __kernel_start).addr()..(&raw const __kernel_end).addr()
+}
+
+/// Gives the available memory to the frame allocator, except what is already in use, and sets
+/// the watermarks to an eighth and a thirty-second of it.
+///
+/// Without a memory map, the upper memory reported by the boot loader is used.
+pub fn init() {
+    let mut frames = FRAMES.lock();
+    let info = multiboot::info();
+    if info.memory_map_len().is_some() {
+        for region in info
+            .memory_map()
+            .iter()
+            .filter(|region| region.is_available())
+        {
+            frames.add(region.base..region.base.saturating_add(region.len));
+        }
+    } else if let Some(memory) = multiboot::memory() {
+        frames.add(LOW_MEMORY.end..LOW_MEMORY.end + memory.upper_kib as u64 * 1024);
+    }
+    frames.reserve(LOW_MEMORY);
+    let kernel = kernel_image();
+    frames.reserve(kernel.start as u64..kernel.end as u64);
+    for module in info.modules() {
+        frames.reserve(module.start as u64..module.end as u64);
+    }
+    let total = frames.stats().total;
+    frames.set_watermarks(Watermarks {
+        low: total / 8,
+        min: total / 32,
+    });
+    _ = frames.take_crossing();
+}
+
+/// Locks the frame allocator.
+pub fn frames() -> MutexGuard<'static, FrameAllocator<FRAME_WORDS>> {
+    FRAMES.lock()
+}
+
+/// Allocates a frame, and returns its address.
+///
+/// Free memory dropping below a watermark is logged as a warning, once per drop.
+pub fn alloc() -> Result<usize, AllocError> {
+    let (result, crossing) = {
+        let mut frames = FRAMES.lock();
+        (frames.alloc(), frames.take_crossing())
+    };
+    LAST_INJECTED.store(result == Err(AllocError::Injected), Ordering::Relaxed);
+    report(crossing);
+    result
+}
+
+/// Sets the watermarks. Free memory already below them is logged as a drop.
+pub fn set_watermarks(watermarks: Watermarks) {
+    let crossing = {
+        let mut frames = FRAMES.lock();
+        frames.set_watermarks(watermarks);
+        frames.take_crossing()
+    };
+    report(crossing);
+}
+
+/// Logs a drop of free memory below a watermark, if there was one.
+fn report(crossing: Option<Pressure>) {
+    if let Some(pressure) = crossing {
+        pr_warn!(
+            "memory: free memory below the {} watermark\n",
+            pressure.name()
+        );
+    }
+}
+
+/// Frees the frame at `addr`, which must come from [`alloc`]. Returns `false` if it was free
+/// already.
+pub fn free(addr: usize) -> bool {
+    FRAMES.lock().free(addr)
+}
+
+/// Returns the number of panics that followed an injected allocation failure, across reboots.
+pub fn injected_panics() -> u8 {
+    io::rtc::read(INJECTED_PANICS_NVRAM)
+}
+
+/// Resets the count of [`injected_panics`].
+pub fn reset_injected_panics() {
+    io::rtc::write(INJECTED_PANICS_NVRAM, 0);
+}
+
+/// Counts the panic under way if the last allocation failed on purpose: its caller probably
+/// did not handle the failure. Returns whether it did.
+pub fn note_panic() -> bool {
+    let injected = LAST_INJECTED.load(Ordering::Relaxed);
+    if injected {
+        io::rtc::write(INJECTED_PANICS_NVRAM, injected_panics().saturating_add(1));
+    }
+    injected
+}
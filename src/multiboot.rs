@@ -1,3 +1,9 @@
+use kfs_core::{
+    mutex::{Mutex, MutexGuard},
+    once::Once,
+    util::cstr::{self, CStrError},
+};
+
 /// The Multiboot header structure.
 ///
 /// [https://www.gnu.org/software/grub/manual/multiboot/multiboot.html#Header-layout]
@@ -45,3 +51,379 @@ impl Header {
         }
     }
 }
+
+/// The value found in EAX when the kernel is loaded by a Multiboot-compliant boot loader.
+pub const BOOTLOADER_MAGIC: u32 = 0x2BADB002;
+
+/// The Multiboot information structure, as provided by the boot loader in EBX.
+///
+/// [https://www.gnu.org/software/grub/manual/multiboot/multiboot.html#Boot-information-format]
+/// Only the fields up to `boot_loader_name` are described.
+#[repr(C)]
+pub struct Info {
+    /// Indicates which of the other fields are valid.
+    pub flags: u32,
+    /// The amount of lower memory, in KiB. Valid if flags:0 is set.
+    pub mem_lower: u32,
+    /// The amount of upper memory, in KiB. Valid if flags:0 is set.
+    pub mem_upper: u32,
+    /// The BIOS disk the image was loaded from. Valid if flags:1 is set.
+    pub boot_device: u32,
+    /// The physical address of the kernel command line. Valid if flags:2 is set.
+    pub cmdline: u32,
+    /// The number of boot modules. Valid if flags:3 is set.
+    pub mods_count: u32,
+    /// The physical address of the module structures. Valid if flags:3 is set.
+    pub mods_addr: u32,
+    /// The a.out symbol table or ELF section header table. Valid if flags:4 or flags:5 is set.
+    pub syms: [u32; 4],
+    /// The size of the memory map buffer. Valid if flags:6 is set.
+    pub mmap_length: u32,
+    /// The physical address of the memory map buffer. Valid if flags:6 is set.
+    pub mmap_addr: u32,
+    /// The size of the drive structures. Valid if flags:7 is set.
+    pub drives_length: u32,
+    /// The physical address of the drive structures. Valid if flags:7 is set.
+    pub drives_addr: u32,
+    /// The address of the ROM configuration table. Valid if flags:8 is set.
+    pub config_table: u32,
+    /// The physical address of the boot loader name. Valid if flags:9 is set.
+    pub boot_loader_name: u32,
+}
+
+impl Info {
+    /// Returns whether the specified bit of `flags` is set.
+    #[inline]
+    pub fn has(&self, bit: u32) -> bool {
+        self.flags & (1 << bit) != 0
+    }
+}
+
+/// The amounts of memory reported by the boot loader.
+#[derive(Debug, Clone, Copy)]
+pub struct Memory {
+    /// The memory below 1 MiB, in KiB.
+    pub lower_kib: u32,
+    /// The memory from 1 MiB up to the first hole, in KiB.
+    pub upper_kib: u32,
+}
+
+static MEMORY: Once<Memory> = Once::new();
+
+/// The maximum length of the strings copied out of the Multiboot information structure.
+const MAX_STRING_LEN: usize = 4096;
+
+/// The strings provided by the boot loader, copied into kernel memory.
+pub struct BootStrings {
+    cmdline: [u8; MAX_STRING_LEN],
+    cmdline_len: Option<Result<usize, CStrError>>,
+    boot_loader_name: [u8; MAX_STRING_LEN],
+    boot_loader_name_len: Option<Result<usize, CStrError>>,
+}
+
+static BOOT_STRINGS: Mutex<BootStrings> = Mutex::new(BootStrings {
+    cmdline: [0; MAX_STRING_LEN],
+    cmdline_len: None,
+    boot_loader_name: [0; MAX_STRING_LEN],
+    boot_loader_name_len: None,
+});
+
+/// Copies the string at `addr` into `buf` if `present` is true.
+fn copy_string(present: bool, addr: u32, buf: &mut [u8]) -> Option<Result<usize, CStrError>> {
+    if !present {
+        return None;
+    }
+    // Safety: the boot loader claims the string is there. Bounds are checked by `read_bounded`.
+    let s = unsafe { cstr::read_bounded(core::ptr::without_provenance(addr as usize), buf) };
+    Some(s.map(str::len))
+}
+
+/// Captures the information provided by the boot loader.
+///
+/// Must be called before anything overwrites the memory holding the information structure.
+///
+/// # Safety
+///
+/// If `magic` is [`BOOTLOADER_MAGIC`], `info` must point to a valid Multiboot information
+/// structure.
+pub unsafe fn init(magic: u32, info: *const Info) {
+    if magic != BOOTLOADER_MAGIC || info.is_null() {
+        return;
+    }
+    // Safety: the caller guarantees that the structure is valid.
+    let info = unsafe { &*info };
+
+    if info.has(0) {
+        MEMORY.init(Memory {
+            lower_kib: info.mem_lower,
+            upper_kib: info.mem_upper,
+        });
+    }
+
+    let mut boot_info = BOOT_INFO.lock();
+    boot_info.flags = Some(info.flags);
+    // Safety: the caller guarantees that the structure is valid, and so is what it points to.
+    unsafe {
+        copy_modules(info, &mut boot_info);
+        copy_memory_map(info, &mut boot_info);
+    }
+
+    let mut strings = BOOT_STRINGS.lock();
+    let strings = &mut *strings;
+    strings.cmdline_len = copy_string(info.has(2), info.cmdline, &mut strings.cmdline);
+    strings.boot_loader_name_len = copy_string(
+        info.has(9),
+        info.boot_loader_name,
+        &mut strings.boot_loader_name,
+    );
+}
+
+/// The number of boot modules whose information is kept.
+pub const MAX_MODULES: usize = 8;
+
+/// The maximum length of the strings of the modules copied out of their structures.
+const MAX_MODULE_STRING_LEN: usize = 256;
+
+/// The number of entries of the memory map that are kept.
+pub const MAX_MEMORY_MAP_ENTRIES: usize = 32;
+
+/// The names of the bits of the `flags` field of [`Info`], from bit 0 up.
+pub const FLAG_NAMES: [&str; 13] = [
+    "mem",
+    "boot_device",
+    "cmdline",
+    "mods",
+    "aout_syms",
+    "elf_shdr",
+    "mmap",
+    "drives",
+    "config_table",
+    "boot_loader_name",
+    "apm_table",
+    "vbe",
+    "framebuffer",
+];
+
+/// A module structure, as provided by the boot loader.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ModuleEntry {
+    start: u32,
+    end: u32,
+    string: u32,
+    reserved: u32,
+}
+
+/// An entry of the memory map, as provided by the boot loader, after its `size` field.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct MemoryMapEntry {
+    base: u64,
+    len: u64,
+    kind: u32,
+}
+
+/// A boot module, copied into kernel memory.
+pub struct Module {
+    /// The address of the first byte of the module.
+    pub start: u32,
+    /// The address of the byte following the module.
+    pub end: u32,
+    string: [u8; MAX_MODULE_STRING_LEN],
+    string_len: Option<Result<usize, CStrError>>,
+}
+
+impl Module {
+    const EMPTY: Module = Module {
+        start: 0,
+        end: 0,
+        string: [0; MAX_MODULE_STRING_LEN],
+        string_len: None,
+    };
+
+    /// Returns the string associated with the module, usually its command line.
+    pub fn string(&self) -> Option<Result<&str, CStrError>> {
+        stored_string(&self.string, self.string_len)
+    }
+}
+
+/// A region of the memory map.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryRegion {
+    /// The address of the first byte of the region.
+    pub base: u64,
+    /// The size of the region, in bytes.
+    pub len: u64,
+    /// The type of the region, 1 meaning available RAM.
+    pub kind: u32,
+}
+
+impl MemoryRegion {
+    const EMPTY: MemoryRegion = MemoryRegion {
+        base: 0,
+        len: 0,
+        kind: 0,
+    };
+
+    /// Returns whether the region is RAM the kernel may use.
+    pub fn is_available(&self) -> bool {
+        self.kind == 1
+    }
+
+    /// Returns the name of the type of the region.
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            1 => "available",
+            2 => "reserved",
+            3 => "ACPI reclaimable",
+            4 => "ACPI NVS",
+            5 => "bad RAM",
+            _ => "unknown",
+        }
+    }
+}
+
+/// What the boot loader provided besides the memory amounts and the strings, copied into kernel
+/// memory.
+pub struct BootInfo {
+    flags: Option<u32>,
+    modules: [Module; MAX_MODULES],
+    module_count: Option<usize>,
+    memory_map: [MemoryRegion; MAX_MEMORY_MAP_ENTRIES],
+    memory_map_len: Option<usize>,
+}
+
+static BOOT_INFO: Mutex<BootInfo> = Mutex::new(BootInfo {
+    flags: None,
+    modules: [Module::EMPTY; MAX_MODULES],
+    module_count: None,
+    memory_map: [MemoryRegion::EMPTY; MAX_MEMORY_MAP_ENTRIES],
+    memory_map_len: None,
+});
+
+impl BootInfo {
+    /// Returns the `flags` field of the information structure, or `None` if the kernel was not
+    /// loaded by a Multiboot boot loader.
+    pub fn flags(&self) -> Option<u32> {
+        self.flags
+    }
+
+    /// Returns the number of boot modules, if the boot loader provided them.
+    ///
+    /// Only the first [`MAX_MODULES`] are in [`BootInfo::modules`].
+    pub fn module_count(&self) -> Option<usize> {
+        self.module_count
+    }
+
+    /// Returns the boot modules that were kept.
+    pub fn modules(&self) -> &[Module] {
+        &self.modules[..self.module_count.unwrap_or(0).min(MAX_MODULES)]
+    }
+
+    /// Returns the number of entries of the memory map, if the boot loader provided one.
+    ///
+    /// Only the first [`MAX_MEMORY_MAP_ENTRIES`] are in [`BootInfo::memory_map`].
+    pub fn memory_map_len(&self) -> Option<usize> {
+        self.memory_map_len
+    }
+
+    /// Returns the entries of the memory map that were kept.
+    pub fn memory_map(&self) -> &[MemoryRegion] {
+        &self.memory_map[..self.memory_map_len.unwrap_or(0).min(MAX_MEMORY_MAP_ENTRIES)]
+    }
+}
+
+/// Copies the module structures described by `info`.
+///
+/// # Safety
+///
+/// If flags:3 is set in `info`, the module structures must be readable.
+unsafe fn copy_modules(info: &Info, boot_info: &mut BootInfo) {
+    if !info.has(3) {
+        return;
+    }
+    let count = if info.mods_addr == 0 {
+        0
+    } else {
+        info.mods_count as usize
+    };
+    for (i, module) in boot_info.modules.iter_mut().take(count).enumerate() {
+        let addr = info.mods_addr as usize + i * size_of::<ModuleEntry>();
+        // Safety: the caller guarantees that the structures are readable.
+        let entry = unsafe { core::ptr::without_provenance::<ModuleEntry>(addr).read_unaligned() };
+        module.start = entry.start;
+        module.end = entry.end;
+        module.string_len = copy_string(true, entry.string, &mut module.string);
+    }
+    boot_info.module_count = Some(count);
+}
+
+/// Copies the memory map described by `info`.
+///
+/// # Safety
+///
+/// If flags:6 is set in `info`, the memory map must be readable.
+unsafe fn copy_memory_map(info: &Info, boot_info: &mut BootInfo) {
+    if !info.has(6) {
+        return;
+    }
+    let mut len = 0;
+    let mut offset = 0;
+    // Each entry starts with its size, which does not count the size field itself.
+    while info.mmap_addr != 0 && offset + 4 <= info.mmap_length as usize {
+        let addr = info.mmap_addr as usize + offset;
+        // Safety: the caller guarantees that the memory map is readable.
+        let (size, entry) = unsafe {
+            (
+                core::ptr::without_provenance::<u32>(addr).read_unaligned(),
+                core::ptr::without_provenance::<MemoryMapEntry>(addr + 4).read_unaligned(),
+            )
+        };
+        if let Some(region) = boot_info.memory_map.get_mut(len) {
+            *region = MemoryRegion {
+                base: entry.base,
+                len: entry.len,
+                kind: entry.kind,
+            };
+        }
+        len += 1;
+        offset += size as usize + 4;
+    }
+    boot_info.memory_map_len = Some(len);
+}
+
+/// Returns the string stored in `buf` by [`copy_string`].
+fn stored_string(
+    buf: &[u8],
+    len: Option<Result<usize, CStrError>>,
+) -> Option<Result<&str, CStrError>> {
+    // Safety: `read_bounded` only copies valid UTF-8.
+    len.map(|len| len.map(|len| unsafe { core::str::from_utf8_unchecked(&buf[..len]) }))
+}
+
+impl BootStrings {
+    /// Returns the kernel command line, if the boot loader provided one.
+    pub fn cmdline(&self) -> Option<Result<&str, CStrError>> {
+        stored_string(&self.cmdline, self.cmdline_len)
+    }
+
+    /// Returns the name of the boot loader, if it provided one.
+    pub fn boot_loader_name(&self) -> Option<Result<&str, CStrError>> {
+        stored_string(&self.boot_loader_name, self.boot_loader_name_len)
+    }
+}
+
+/// Returns the strings captured from the boot loader.
+pub fn strings() -> MutexGuard<'static, BootStrings> {
+    BOOT_STRINGS.lock()
+}
+
+/// Returns the amounts of memory reported by the boot loader, if it did.
+pub fn memory() -> Option<&'static Memory> {
+    MEMORY.get()
+}
+
+/// Returns the rest of the information captured from the boot loader.
+pub fn info() -> MutexGuard<'static, BootInfo> {
+    BOOT_INFO.lock()
+}
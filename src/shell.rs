@@ -0,0 +1,720 @@
+//! The kernel shell: the REPL reading command lines and running them, along with the history,
+//! the key bindings, aliases, recording and lockdown built around it.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use kfs_core::{
+    alias::Aliases,
+    cmdline::{Cmdline, History},
+    keyboard,
+    mutex::Mutex,
+    serial_keys,
+    shell::{self, Args, ShellError},
+};
+
+use crate::{
+    ANIMATION_STEP_NS, BootOptions, KEYBOARD, SERIAL, TTY_COUNT, animate_42, io, take_over_screen,
+    terminal,
+};
+
+use self::commands::COMMANDS;
+
+mod commands;
+
+/// Runs the REPL, starting with the init command of each TTY, and ending on the boot TTY.
+pub fn repl(options: &mut BootOptions) -> ! {
+    let mut cmdlines = [const { Cmdline::new() }; TTY_COUNT];
+
+    for (tty, init) in options.init.iter_mut().enumerate() {
+        let init = init.take();
+        if init.is_empty() {
+            continue;
+        }
+        terminal().set_output_console(tty);
+        terminal().refresh_cmdline(&Cmdline::new());
+        printk!("{init}\n");
+        execute(init);
+    }
+    {
+        let mut lock = terminal();
+        lock.show_console(options.tty);
+        lock.set_output_console(options.tty);
+    }
+    for &(key, command) in DEFAULT_BINDINGS {
+        BINDINGS.lock()[key - 1].set(command);
+    }
+    for &(key, command) in DEFAULT_CHORD_BINDINGS {
+        BoundKey::parse(key)
+            .unwrap()
+            .with_binding(|binding| binding.set(command));
+    }
+
+    let mut bound: Cmdline;
+    loop {
+        {
+            // What was typed before a bound key is shown again after its command.
+            let mut lock = terminal();
+            let shown = lock.shown_console();
+            lock.refresh_cmdline(&cmdlines[shown]);
+        }
+        let line = loop {
+            core::hint::spin_loop();
+            poll_serial();
+            // The locks are only held while handling a single key, so that the terminal stays
+            // available to others while waiting for input.
+            let input =
+                terminal().get_line(&mut KEYBOARD.lock(), &mut cmdlines, &mut HISTORIES.lock());
+            match input {
+                Some(io::Input::Line(line)) => break line,
+                Some(io::Input::Complete) => {
+                    let shown = terminal().shown_console();
+                    complete_command(&mut cmdlines[shown]);
+                }
+                Some(io::Input::Function(n)) => {
+                    // Copied, so that the command may change the bindings.
+                    bound = BINDINGS.lock()[usize::from(n) - 1].clone();
+                    if !bound.as_str().is_empty() {
+                        terminal().submit_cmdline();
+                        break bound.as_str();
+                    }
+                }
+                Some(io::Input::Chord(c)) => {
+                    bound = BoundKey::chord(c).map_or(Cmdline::new(), |key| {
+                        key.with_binding(|binding| binding.clone())
+                    });
+                    if !bound.as_str().is_empty() {
+                        terminal().submit_cmdline();
+                        break bound.as_str();
+                    }
+                    printk!("\n");
+                    pr_warn!("C-x {c:?}: undefined chord\n");
+                    let shown = terminal().shown_console();
+                    terminal().refresh_cmdline(&cmdlines[shown]);
+                }
+                None => {}
+            }
+            io::speaker::poll();
+            let timeout = SCREENSAVER_TIMEOUT_S.load(Ordering::Relaxed) as u64;
+            if timeout != 0 && KEYBOARD.lock().idle_ns() >= timeout * 1_000_000_000 {
+                screensaver();
+                let shown = terminal().shown_console();
+                terminal().refresh_cmdline(&cmdlines[shown]);
+            }
+        };
+        printk!("{line}\n");
+        record(line);
+        execute(line);
+    }
+}
+
+/// Passes the keys sent by the terminal on the serial port to the keyboard, as if typed on it.
+fn poll_serial() {
+    let Some(serial) = SERIAL.get() else {
+        return;
+    };
+    let mut unknown = None;
+    {
+        let mut keyboard = KEYBOARD.lock();
+        let layout = keyboard.layout();
+        serial.lock().poll_keys(layout, |input| match input {
+            serial_keys::Input::Key(event) => keyboard.inject(event),
+            serial_keys::Input::Unknown(sequence) => unknown = Some(sequence),
+        });
+    }
+    if let Some(sequence) = unknown {
+        pr_debug!("serial: unknown escape sequence dropped: {sequence:?}\n");
+    }
+}
+
+/// Completes the command name before the cursor of `cmdline`, if it is in the first word.
+///
+/// A unique command is completed in place. Several are listed under the command line, which is
+/// then drawn again as it was. Without any, the bell rings.
+fn complete_command(cmdline: &mut Cmdline) {
+    let Some(word) = shell::first_word_before(cmdline.as_str(), cmdline.cursor()) else {
+        return;
+    };
+    let typed = word.len();
+    let (first, second) = {
+        let mut found = shell::completions(COMMANDS.iter().map(|command| command.name), word);
+        (found.next(), found.next())
+    };
+    match (first, second) {
+        (None, _) => terminal().ring_bell(),
+        (Some(name), None) => {
+            for c in name[typed..].chars() {
+                cmdline.push(c);
+            }
+            if !cmdline.as_str()[cmdline.cursor()..].starts_with(' ') {
+                cmdline.push(' ');
+            }
+            terminal().refresh_cmdline(cmdline);
+        }
+        (Some(_), Some(_)) => {
+            terminal().submit_cmdline();
+            printk!("{}\n", cmdline.as_str());
+            let word = &cmdline.as_str()[cmdline.cursor() - typed..cmdline.cursor()];
+            for name in shell::completions(COMMANDS.iter().map(|command| command.name), word) {
+                printk!("{name}  ");
+            }
+            printk!("\n");
+            terminal().refresh_cmdline(cmdline);
+        }
+    }
+}
+
+/// The lines entered in each TTY.
+static HISTORIES: Mutex<[History; TTY_COUNT]> = Mutex::new([const { History::new() }; TTY_COUNT]);
+
+/// The number of function keys.
+const FUNCTION_KEYS: usize = keyboard::keys::FUNCTION.len();
+
+/// The command run by each function key, or an empty line if it has none.
+static BINDINGS: Mutex<[Cmdline; FUNCTION_KEYS]> =
+    Mutex::new([const { Cmdline::new() }; FUNCTION_KEYS]);
+
+/// The function keys bound when the REPL starts, along with their commands.
+const DEFAULT_BINDINGS: &[(usize, &str)] = &[
+    (1, "help"),
+    (2, "statusbar toggle"),
+    (3, "repeat"),
+    (5, "dmesg"),
+];
+
+/// The keys that may follow `Ctrl+X` in a chord bound with `bind`, except the ones handled by the
+/// terminal.
+const CHORD_KEYS: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// The command run by each chord, or an empty line if it has none, by index in [`CHORD_KEYS`].
+static CHORD_BINDINGS: Mutex<[Cmdline; CHORD_KEYS.len()]> =
+    Mutex::new([const { Cmdline::new() }; CHORD_KEYS.len()]);
+
+/// The chords bound when the REPL starts, along with their commands.
+const DEFAULT_CHORD_BINDINGS: &[(&str, &str)] =
+    &[("C-x-l", "screensaver now"), ("C-x-r", "record")];
+
+/// A key that can be bound to a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BoundKey {
+    /// The function key `F<n + 1>`.
+    Function(usize),
+    /// `Ctrl+X` followed by the key at the index `n` of [`CHORD_KEYS`].
+    Chord(usize),
+}
+
+impl BoundKey {
+    /// What [`parse`](Self::parse) accepts, for error messages.
+    const EXPECTED: &str = "a key from f1 to f12, or C-x- and a letter or a digit other than c \
+        and 1 to 4";
+
+    /// Parses the name of a function key, from `f1` to `f12`, or of a chord, such as `C-x-l`.
+    fn parse(key: &str) -> Option<Self> {
+        if let Some(key) = key
+            .strip_prefix("C-x-")
+            .or_else(|| key.strip_prefix("c-x-"))
+        {
+            let mut chars = key.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return None;
+            };
+            return Self::chord(c);
+        }
+        let n = key.strip_prefix(['f', 'F'])?.parse::<usize>().ok()?;
+        (1..=FUNCTION_KEYS)
+            .contains(&n)
+            .then(|| BoundKey::Function(n - 1))
+    }
+
+    /// Returns the chord `Ctrl+X` `c`, unless it is handled by the terminal or cannot be bound.
+    fn chord(c: char) -> Option<Self> {
+        if io::builtin_chord(c) {
+            return None;
+        }
+        CHORD_KEYS.find(c).map(BoundKey::Chord)
+    }
+
+    /// Calls `f` with the command of this key, the bindings being locked meanwhile.
+    fn with_binding<R>(self, f: impl FnOnce(&mut Cmdline) -> R) -> R {
+        match self {
+            BoundKey::Function(n) => f(&mut BINDINGS.lock()[n]),
+            BoundKey::Chord(n) => f(&mut CHORD_BINDINGS.lock()[n]),
+        }
+    }
+}
+
+impl core::fmt::Display for BoundKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            BoundKey::Function(n) => write!(f, "f{}", n + 1),
+            BoundKey::Chord(n) => write!(f, "C-x-{}", &CHORD_KEYS[n..=n]),
+        }
+    }
+}
+
+/// The last command entered, for `repeat`.
+static LAST_COMMAND: Mutex<Cmdline> = Mutex::new(Cmdline::new());
+
+/// The most iterations `repeat` runs.
+const MAX_REPEAT: u32 = 100_000;
+
+/// Whether `repeat` is running, which it refuses to nest.
+static REPEATING: AtomicBool = AtomicBool::new(false);
+
+/// Implements the `repeat` command.
+///
+/// Neither form runs inside the other, or inside itself: each level would take more of the
+/// kernel stack, and a bare `repeat` could end up running itself through an alias.
+fn repeat(args: &mut Args) -> Result<(), ShellError> {
+    if REPEATING.swap(true, Ordering::Relaxed) {
+        return Err(ShellError::Failed("cannot be nested"));
+    }
+    let result = match args.next() {
+        None => repeat_last(),
+        Some(count) => repeat_count(args, count),
+    };
+    REPEATING.store(false, Ordering::Relaxed);
+    result
+}
+
+/// Runs the last command entered again.
+fn repeat_last() -> Result<(), ShellError> {
+    let last = LAST_COMMAND.lock().clone();
+    if last.as_str().is_empty() {
+        return Err(ShellError::Failed("no command entered yet"));
+    }
+    printk!("{}\n", last.as_str());
+    execute(last.as_str());
+    Ok(())
+}
+
+/// Runs the rest of `args` `count` times, stopping early on an error or **CTRL+C**.
+fn repeat_count(args: &mut Args, count: &str) -> Result<(), ShellError> {
+    let count = shell::parse_u32(count)
+        .filter(|count| (1..=MAX_REPEAT).contains(count))
+        .ok_or(args.invalid("a count from 1 to 100000"))?;
+    let command = args.rest_raw();
+    if command.is_empty() {
+        return Err(ShellError::MissingArgument { index: 2 });
+    }
+    for i in 1..=count {
+        printk!("--- {i}/{count}: {command}\n");
+        let succeeded = execute(command);
+        // Checked after the command, since the next one starts by clearing the flag, and before
+        // its result, since a command stopped by CTRL+C fails.
+        if cancelled() {
+            return Err(ShellError::Interrupted);
+        }
+        if !succeeded {
+            return Err(ShellError::Failed("stopped after an error"));
+        }
+    }
+    Ok(())
+}
+
+/// The most lines `record` keeps.
+const RECORD_LINES: usize = 16;
+
+/// The lines recorded by `record`, and how many there are.
+static RECORDED: Mutex<([Cmdline; RECORD_LINES], usize)> =
+    Mutex::new(([const { Cmdline::new() }; RECORD_LINES], 0));
+
+/// Whether the lines entered are being recorded.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// Whether `record play` is running, which it refuses to nest.
+static PLAYING: AtomicBool = AtomicBool::new(false);
+
+/// Records `line` while recording is on, unless it runs `record` itself.
+fn record(line: &str) {
+    if !RECORDING.load(Ordering::Relaxed) || Args::new(line).next() == Some("record") {
+        return;
+    }
+    let mut recorded = RECORDED.lock();
+    let (lines, len) = &mut *recorded;
+    if *len == RECORD_LINES || !lines[*len].set(line) {
+        RECORDING.store(false, Ordering::Relaxed);
+        pr_warn!("record: no room for this line, recording stopped\n");
+        return;
+    }
+    *len += 1;
+}
+
+/// Implements the `record` command.
+///
+/// Without arguments, it starts recording the lines entered, replacing the previous recording,
+/// or stops. `record play` runs them again, stopping early on an error or **CTRL+C**.
+fn record_command(args: &mut Args) -> Result<(), ShellError> {
+    match args.next() {
+        None => {
+            if RECORDING.swap(false, Ordering::Relaxed) {
+                printk!("Recorded {} lines\n", RECORDED.lock().1);
+            } else {
+                RECORDED.lock().1 = 0;
+                RECORDING.store(true, Ordering::Relaxed);
+                printk!("Recording, until `record` or Ctrl+X r\n");
+            }
+            Ok(())
+        }
+        Some("play") => {
+            args.expect_end()?;
+            if PLAYING.swap(true, Ordering::Relaxed) {
+                return Err(ShellError::Failed("cannot be nested"));
+            }
+            let result = play_recording();
+            PLAYING.store(false, Ordering::Relaxed);
+            result
+        }
+        Some(_) => Err(args.invalid("`play`")),
+    }
+}
+
+/// Runs the lines recorded by `record`.
+fn play_recording() -> Result<(), ShellError> {
+    let len = RECORDED.lock().1;
+    if len == 0 {
+        return Err(ShellError::Failed("nothing recorded"));
+    }
+    for i in 0..len {
+        // Copied, so that the lines may record or change the bindings.
+        let line = RECORDED.lock().0[i].clone();
+        printk!("{}\n", line.as_str());
+        let succeeded = execute(line.as_str());
+        if cancelled() {
+            return Err(ShellError::Interrupted);
+        }
+        if !succeeded {
+            return Err(ShellError::Failed("stopped after an error"));
+        }
+    }
+    Ok(())
+}
+
+/// Implements the `bind` command.
+fn bind(args: &mut Args) -> Result<(), ShellError> {
+    let unbind = args.flag("-d");
+    let Some(key) = args.next() else {
+        let functions = (0..FUNCTION_KEYS).map(BoundKey::Function);
+        let chords = (0..CHORD_KEYS.len()).map(BoundKey::Chord);
+        for key in functions.chain(chords) {
+            key.with_binding(|command| {
+                if !command.as_str().is_empty() {
+                    printk!("{key}\t{}\n", command.as_str());
+                }
+            });
+        }
+        return Ok(());
+    };
+    let key = BoundKey::parse(key).ok_or(args.invalid(BoundKey::EXPECTED))?;
+    let command = args.rest_raw().trim();
+    if unbind && !command.is_empty() {
+        return Err(args.invalid("nothing after the key"));
+    }
+    key.with_binding(|binding| {
+        if unbind {
+            binding.set("");
+        } else if command.is_empty() {
+            match binding.as_str() {
+                "" => printk!("{key} is not bound\n"),
+                command => printk!("{command}\n"),
+            }
+        } else if !binding.set(command) {
+            return Err(ShellError::Failed("the command is too long"));
+        }
+        Ok(())
+    })
+}
+
+/// The lockdown state: `None` when off, otherwise the PIN needed to lift it, if any.
+///
+/// While it is on, the commands marked as dangerous are refused. It is kept in CMOS NVRAM as
+/// well, and loaded back at boot, so that rebooting does not lift it.
+static LOCKDOWN: Mutex<Option<Option<[u8; 4]>>> = Mutex::new(None);
+
+/// The CMOS register holding the lockdown tag, followed by the 4 digits of the PIN. Neither the
+/// BIOS nor QEMU use the registers from there on.
+const LOCKDOWN_NVRAM: u8 = 0x40;
+
+/// The lockdown tag when lockdown is on without a PIN. Any other tag than these two means off.
+const LOCKDOWN_ON: u8 = b'L';
+
+/// The lockdown tag when lockdown is on with a PIN.
+const LOCKDOWN_ON_PIN: u8 = b'P';
+
+/// Sets the lockdown state, and saves it to CMOS NVRAM.
+fn set_lockdown(state: Option<Option<[u8; 4]>>) {
+    *LOCKDOWN.lock() = state;
+    let (tag, pin) = match state {
+        None => (0, [0; 4]),
+        Some(None) => (LOCKDOWN_ON, [0; 4]),
+        Some(Some(pin)) => (LOCKDOWN_ON_PIN, pin),
+    };
+    io::rtc::write(LOCKDOWN_NVRAM, tag);
+    for (register, digit) in (LOCKDOWN_NVRAM + 1..).zip(pin) {
+        io::rtc::write(register, digit);
+    }
+}
+
+/// Loads the lockdown state saved to CMOS NVRAM. A PIN that is not 4 digits is taken as junk,
+/// and lockdown as off.
+pub fn load_lockdown() {
+    let state = match io::rtc::read(LOCKDOWN_NVRAM) {
+        LOCKDOWN_ON => Some(None),
+        LOCKDOWN_ON_PIN => {
+            let mut pin = [0; 4];
+            for (register, digit) in (LOCKDOWN_NVRAM + 1..).zip(&mut pin) {
+                *digit = io::rtc::read(register);
+            }
+            pin.iter().all(u8::is_ascii_digit).then_some(Some(pin))
+        }
+        _ => None,
+    };
+    *LOCKDOWN.lock() = state;
+}
+
+/// Reads a PIN of 4 digits, or nothing, showing each digit as `*`.
+///
+/// The PIN is not taken as an argument, which would keep it in the history and the kernel log.
+fn read_pin(prompt: &str) -> Result<Option<[u8; 4]>, ShellError> {
+    printk!("{prompt}");
+    let mut pin = [0; 4];
+    let mut len = 0;
+    loop {
+        if cancelled() {
+            printk!("\n");
+            return Err(ShellError::Interrupted);
+        }
+        let Some(c) = KEYBOARD.lock().get_char() else {
+            core::hint::spin_loop();
+            continue;
+        };
+        match c {
+            '\n' => break,
+            '\x08' if len > 0 => {
+                len -= 1;
+                printk!("\x08 \x08");
+            }
+            c if c.is_ascii_digit() && len < pin.len() => {
+                pin[len] = c as u8;
+                len += 1;
+                printk!("*");
+            }
+            _ => terminal().ring_bell(),
+        }
+    }
+    printk!("\n");
+    match len {
+        0 => Ok(None),
+        4 => Ok(Some(pin)),
+        _ => Err(ShellError::Failed("the PIN must have 4 digits")),
+    }
+}
+
+/// Implements the `lockdown` command.
+fn lockdown(args: &mut Args) -> Result<(), ShellError> {
+    let action = args.next();
+    if !matches!(action, None | Some("on" | "off")) {
+        return Err(args.invalid("`on` or `off`"));
+    }
+    args.expect_end()?;
+
+    let state = *LOCKDOWN.lock();
+    match (action, state) {
+        (None, None) => printk!("Lockdown is off\n"),
+        (None, Some(_)) => {
+            printk!("Lockdown is on, refusing:");
+            for command in COMMANDS.iter().filter(|command| command.dangerous) {
+                printk!(" {}", command.name);
+            }
+            printk!("\n");
+        }
+        (Some("on"), None) => {
+            let pin = read_pin("PIN to lift it (Enter for none): ")?;
+            set_lockdown(Some(pin));
+        }
+        (Some("on"), Some(_)) => printk!("Lockdown is already on\n"),
+        (Some(_), None) => printk!("Lockdown is already off\n"),
+        (Some(_), Some(None)) => set_lockdown(None),
+        (Some(_), Some(expected)) => {
+            if read_pin("PIN: ")? != expected {
+                return Err(ShellError::Failed("wrong PIN"));
+            }
+            set_lockdown(None);
+        }
+    }
+    Ok(())
+}
+
+/// Asks a yes or no question, and returns whether the answer is yes.
+pub fn confirm(question: &str) -> bool {
+    printk!("{question} [y/N] ");
+    let answer = loop {
+        if let Some(c) = KEYBOARD.lock().get_char() {
+            break c;
+        }
+        core::hint::spin_loop();
+    };
+    printk!("{answer}\n");
+    matches!(answer, 'y' | 'Y')
+}
+
+/// Executes a command line.
+///
+/// A first word of `!!` or `!<n>` is first replaced by a line of the history of the shown TTY,
+/// which is echoed and recorded in the history in place of the line typed. The first word is then
+/// replaced by its expansion if it is an alias. Each expansion happens once, so that the result
+/// is run literally.
+///
+/// Once the command is found, the line is recorded for `repeat`, as typed or recalled.
+///
+/// Returns whether the command ran and succeeded. Errors are printed here.
+fn execute(line: &str) -> bool {
+    let mut recalled = Cmdline::new();
+    let shown = terminal().shown_console();
+    let line = match HISTORIES.lock()[shown].expand(line) {
+        Ok(None) => line,
+        Ok(Some((entry, rest))) => {
+            if !(recalled.set(entry) && rest.chars().all(|c| recalled.push(c))) {
+                printk!("Line too long after history expansion\n");
+                return false;
+            }
+            recalled.as_str()
+        }
+        Err(err) => {
+            let first = line.split_whitespace().next().unwrap_or_default();
+            printk!("{first}: {err}\n");
+            return false;
+        }
+    };
+    if !recalled.as_str().is_empty() {
+        printk!("{line}\n");
+        HISTORIES.lock()[shown].push(line);
+    }
+
+    let typed = line;
+
+    let mut expanded = Cmdline::new();
+    let line = match ALIASES.lock().expand(line) {
+        None => line,
+        Some((value, rest)) => {
+            if !(expanded.set(value) && rest.chars().all(|c| expanded.push(c))) {
+                printk!("Line too long after alias expansion\n");
+                return false;
+            }
+            expanded.as_str()
+        }
+    };
+    let mut args = Args::new(line);
+    let Some(command) = args.next() else {
+        return true;
+    };
+    if let Err(err) = shell::check_quotes(line) {
+        printk!("{command}: {err}\n");
+        return false;
+    }
+
+    let Some(found) = find_command(command) else {
+        printk!("Unknown command: {command} (see `help`)\n");
+        return false;
+    };
+    if found.dangerous && LOCKDOWN.lock().is_some() {
+        printk!("{command}: refused, the kernel is in lockdown (see `lockdown`)\n");
+        return false;
+    }
+    // What `repeat` runs is not recorded, and neither is a bare `repeat`, which would run itself.
+    // The line is kept as typed or recalled, so that its aliases are expanded again.
+    let bare_repeat = command == "repeat" && Args::new(line).nth(1).is_none();
+    if !bare_repeat && !REPEATING.load(Ordering::Relaxed) {
+        LAST_COMMAND.lock().set(typed);
+    }
+    // A CTRL+C pressed at the prompt is not meant for this command.
+    CANCEL.store(false, Ordering::Relaxed);
+    match (found.run)(&mut args) {
+        Ok(()) => true,
+        Err(err) => {
+            printk!("{command}: {err}\n");
+            false
+        }
+    }
+}
+
+/// The aliases defined with `alias`.
+static ALIASES: Mutex<Aliases> = Mutex::new(Aliases::new());
+
+/// Implements the `alias` command.
+fn alias(args: &mut Args) -> Result<(), ShellError> {
+    let definition = args.rest_raw();
+    let mut aliases = ALIASES.lock();
+    if definition.is_empty() {
+        for (name, value) in aliases.iter() {
+            printk!("alias {name}={value}\n");
+        }
+        return Ok(());
+    }
+    match definition.split_once('=') {
+        Some((name, value)) => aliases
+            .set(name, value)
+            .map_err(|err| ShellError::Failed(err.as_str())),
+        None => {
+            let value = aliases.get(definition);
+            let value = value.ok_or(ShellError::Failed("no such alias"))?;
+            printk!("alias {definition}={value}\n");
+            Ok(())
+        }
+    }
+}
+
+/// Whether **CTRL+C** was pressed since the current command started.
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// Asks the current command to stop, on **CTRL+C**.
+///
+/// This is called while reading the keyboard, with the terminal possibly locked by the caller.
+pub fn cancel() {
+    CANCEL.store(true, Ordering::Relaxed);
+}
+
+/// Reads the keyboard, and returns whether **CTRL+C** was pressed since the current command
+/// started.
+///
+/// Commands that take long call this now and then, and stop early when it returns true.
+pub fn cancelled() -> bool {
+    if let Some(mut keyboard) = KEYBOARD.get().and_then(Mutex::try_lock) {
+        keyboard.poll();
+    }
+    CANCEL.load(Ordering::Relaxed)
+}
+
+/// Returns the command named `name`.
+fn find_command(name: &str) -> Option<&'static shell::Command> {
+    shell::find(COMMANDS, name)
+}
+
+/// Implements the `help` command.
+fn help(args: &mut Args) -> Result<(), ShellError> {
+    let locked = |command: &shell::Command| command.dangerous && LOCKDOWN.lock().is_some();
+    let Some(name) = args.next() else {
+        let width = COMMANDS.iter().map(|command| command.name.len()).max();
+        let width = width.unwrap_or(0);
+        for command in COMMANDS {
+            let tag = if locked(command) { " [locked]" } else { "" };
+            printk!("{:width$}  {}{tag}\n", command.name, command.help);
+        }
+        return Ok(());
+    };
+    let command = find_command(name).ok_or(args.invalid("a command listed by `help`"))?;
+    args.expect_end()?;
+    printk!("{}\nusage: {}\n", command.help, command.usage);
+    if locked(command) {
+        printk!("Refused while the kernel is in lockdown (see `lockdown`)\n");
+    }
+    Ok(())
+}
+
+/// How long the keyboard must stay idle in the REPL before the screensaver starts, in seconds,
+/// or 0 if it never does.
+static SCREENSAVER_TIMEOUT_S: AtomicU32 = AtomicU32::new(0);
+
+/// Runs the 42 animation until a key is pressed, then puts the screen back.
+fn screensaver() {
+    take_over_screen(|| animate_42(ANIMATION_STEP_NS));
+}
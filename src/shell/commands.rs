@@ -0,0 +1,2179 @@
+//! The commands of the shell.
+
+use core::{arch::asm, fmt::Write, ops::Range, sync::atomic::Ordering};
+
+use kfs_core::{
+    calc,
+    cmdline::Cmdline,
+    cpuid::{self, Brand, Signature},
+    descriptor::{GateDescriptor, SegmentDescriptor},
+    frame::{AllocError, FRAME_SIZE, FailEvery},
+    hexdump, keyboard,
+    mutex::Mutex,
+    notes::NoteParser,
+    shell::{self, Args, ShellError},
+    util::duration::HumanDuration,
+    vga_chars,
+    wrap::WrapMode,
+};
+
+use crate::{
+    ANIMATION_STEP_NS, KEYBOARD, Printk, SAFE_MODE, SERIAL, TERM_BUDGET_US, animate_42,
+    animate_42_dac, cpu, io, kbc, klog, log,
+    memory::{self, kernel_image},
+    multiboot, print_registers, print_stack, scancode_path, stack,
+    stack::KERNEL_STACK,
+    take_over_screen, terminal, time, version, wait_for_key,
+};
+
+use super::{
+    ALIASES, HISTORIES, SCREENSAVER_TIMEOUT_S, alias, bind, cancelled, help, lockdown,
+    record_command, repeat, screensaver,
+};
+
+/// The commands of the shell, as listed by `help`.
+pub(super) static COMMANDS: &[shell::Command] = &[
+    shell::Command {
+        name: "help",
+        help: "Lists the commands, or shows how to run one",
+        usage: "help [command]",
+        dangerous: false,
+        run: help,
+    },
+    shell::Command {
+        name: "reboot",
+        help: "Reboots the machine",
+        usage: "reboot",
+        dangerous: true,
+        run: |_| io::qemu_reboot(),
+    },
+    shell::Command {
+        name: "poweroff",
+        help: "Powers the machine off",
+        usage: "poweroff",
+        dangerous: true,
+        run: |_| io::qemu_shutdown(),
+    },
+    shell::Command {
+        name: "shutdown",
+        help: "Powers the machine off, like poweroff",
+        usage: "shutdown",
+        dangerous: true,
+        run: |_| io::qemu_shutdown(),
+    },
+    shell::Command {
+        name: "halt",
+        help: "Halts the CPU until the next interrupt",
+        usage: "halt",
+        dangerous: true,
+        run: |_| {
+            unsafe { asm!("hlt") }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "stack",
+        help: "Dumps the kernel stack",
+        usage: "stack [-w 8|16|32]",
+        dangerous: false,
+        run: |args| {
+            let width = hex_width(args)?;
+            args.expect_end()?;
+            // The widest rows do not fit on the screen.
+            truncating(|| print_stack(&mut Printk, width));
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "hexdump",
+        help: "Dumps memory",
+        usage: "hexdump [-w 8|16|32] <address> [length]",
+        dangerous: false,
+        run: |args| {
+            let width = hex_width(args)?;
+            let addr = args.next_u32()? as usize;
+            let len = match args.next() {
+                None => 256,
+                Some(len) => shell::parse_size(len)
+                    .filter(|&len| len <= HEXDUMP_MAX_LEN)
+                    .ok_or(args.invalid("a length up to 64K"))?,
+            };
+            args.expect_end()?;
+            // Safety: what is at the address is the user's problem.
+            _ = truncating(|| unsafe { io::hexdump(&mut Printk, addr as *const u8, len, width) });
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "peek",
+        help: "Reads a byte, word or dword of memory",
+        usage: "peek <address> [b | w | d]",
+        dangerous: false,
+        run: |args| {
+            let addr = args.next_u32()? as usize;
+            let width = parse_width(args)?;
+            args.expect_end()?;
+            // Safety: what is at the address is the user's problem.
+            let value = unsafe { io::read_memory(addr, width) };
+            printk!(
+                "{addr:#010x}: {value:#0digits$x}\n",
+                digits = 2 + 2 * width.bytes()
+            );
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "poke",
+        help: "Writes a byte, word or dword of memory",
+        usage: "poke [--force] <address> <value> [b | w | d]",
+        dangerous: true,
+        run: |args| {
+            let force = args.flag("--force");
+            let addr = args.next_u32()? as usize;
+            let value = args.next_u32()?;
+            let value_index = args.index();
+            let width = parse_width(args)?;
+            args.expect_end()?;
+            check_fits(value, value_index, width)?;
+            let target = addr..addr.saturating_add(width.bytes());
+            let overlaps =
+                |range: Range<usize>| target.start < range.end && range.start < target.end;
+            if !force && (overlaps(kernel_image()) || overlaps(stack_range())) {
+                return Err(ShellError::Failed(
+                    "the address is in the kernel image or stack, use --force to write anyway",
+                ));
+            }
+            // Safety: what is at the address is the user's problem.
+            let read_back = unsafe {
+                io::write_memory(addr, width, value);
+                io::read_memory(addr, width)
+            };
+            let digits = hex_digits(width);
+            printk!("{addr:#010x}: wrote {value:#0digits$x}, read back {read_back:#0digits$x}\n");
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "inb",
+        help: "Reads a byte from an I/O port",
+        usage: "inb <port> [x<count>]",
+        dangerous: false,
+        run: |args| port_in(args, io::Width::Byte),
+    },
+    shell::Command {
+        name: "inw",
+        help: "Reads a word from an I/O port",
+        usage: "inw <port> [x<count>]",
+        dangerous: false,
+        run: |args| port_in(args, io::Width::Word),
+    },
+    shell::Command {
+        name: "inl",
+        help: "Reads a dword from an I/O port",
+        usage: "inl <port> [x<count>]",
+        dangerous: false,
+        run: |args| port_in(args, io::Width::Dword),
+    },
+    shell::Command {
+        name: "outb",
+        help: "Writes a byte to an I/O port",
+        usage: "outb <port> <value>",
+        dangerous: true,
+        run: |args| port_out(args, io::Width::Byte),
+    },
+    shell::Command {
+        name: "outw",
+        help: "Writes a word to an I/O port",
+        usage: "outw <port> <value>",
+        dangerous: true,
+        run: |args| port_out(args, io::Width::Word),
+    },
+    shell::Command {
+        name: "outl",
+        help: "Writes a dword to an I/O port",
+        usage: "outl <port> <value>",
+        dangerous: true,
+        run: |args| port_out(args, io::Width::Dword),
+    },
+    shell::Command {
+        name: "echo",
+        help: "Prints its arguments, with backslash escapes interpreted unless -E",
+        usage: "echo [-n] [-e|-E] [text]...",
+        dangerous: false,
+        run: |args| {
+            // Only the leading options count, so that `-n` can be printed after some text.
+            let (mut newline, mut escapes) = (true, true);
+            while let Some(flag) = args.leading_flag(&["-n", "-e", "-E"]) {
+                match flag {
+                    "-n" => newline = false,
+                    "-E" => escapes = false,
+                    // Interpreting escapes is the default, but `-e` undoes an earlier `-E`.
+                    _ => escapes = true,
+                }
+            }
+            for (i, arg) in args.enumerate() {
+                let separator = if i == 0 { "" } else { " " };
+                if escapes {
+                    printk!("{separator}{}", shell::Unescaped(arg));
+                } else {
+                    printk!("{separator}{arg}");
+                }
+            }
+            if newline {
+                printk!("\n");
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "screendump",
+        help: "Writes the text on screen to the serial port",
+        usage: "screendump [--attrs]",
+        dangerous: false,
+        run: |args| {
+            let attrs = args.flag("--attrs");
+            args.expect_end()?;
+            screendump(attrs)
+        },
+    },
+    shell::Command {
+        name: "dmesg",
+        help: "Prints the kernel log",
+        usage: "dmesg",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            // Straight to the terminal, so that the log is not logged again.
+            _ = klog::replay(&mut *terminal());
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "flush",
+        help: "Writes the output held back while the terminal was busy",
+        usage: "flush",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            // Straight to the terminal, like `dmesg`, which also keeps the count exact.
+            let count = klog::drain(&mut *terminal());
+            printk!("{count} deferred write(s) flushed\n");
+            if klog::pending() != 0 {
+                pr_warn!("flush: output was deferred again while flushing\n");
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "color",
+        help: "Shows or sets the text color",
+        usage: "color [<fg> [bg] | <hex attribute>]",
+        dangerous: false,
+        run: |args| {
+            let Some(color) = args.next() else {
+                print_swatches();
+                return Ok(());
+            };
+            if let Some(fg) = io::Color::from_name(color) {
+                let bg = match args.next() {
+                    Some(bg) => {
+                        io::Color::from_name(bg).ok_or(args.invalid(io::Color::EXPECTED))?
+                    }
+                    None => terminal().colors().1,
+                };
+                args.expect_end()?;
+                terminal().set_colors(fg, bg);
+            } else {
+                let color = u8::from_str_radix(color.strip_prefix("0x").unwrap_or(color), 16)
+                    .map_err(|_| args.invalid(io::Color::EXPECTED))?;
+                args.expect_end()?;
+                terminal().set_color(color);
+            }
+            terminal().refresh_cmdline(&Cmdline::new());
+            print_color();
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "safemode",
+        help: "Shows or leaves safe mode",
+        usage: "safemode [off]",
+        dangerous: true,
+        run: |args| {
+            let off = args.next().map(|arg| arg == "off");
+            if off == Some(false) {
+                return Err(args.invalid("`off`"));
+            }
+            args.expect_end()?;
+            match (off, SAFE_MODE.load(Ordering::Relaxed)) {
+                (None, true) => printk!("Safe mode is on\n"),
+                (None, false) => printk!("Safe mode is off\n"),
+                (Some(_), true) => {
+                    pr_info!("Leaving safe mode, rebooting...\n");
+                    io::qemu_reboot();
+                }
+                (Some(_), false) => printk!("Not in safe mode\n"),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "wrap",
+        help: "Shows or sets how long lines wrap",
+        usage: "wrap [on | off | hard | word | trunc]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", terminal().wrap_mode().name()),
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    terminal().set_wrap(state == "on");
+                }
+                Some(mode) => {
+                    let mode = WrapMode::from_name(mode)
+                        .ok_or(args.invalid("on, off, hard, word or trunc"))?;
+                    args.expect_end()?;
+                    terminal().set_wrap_mode(mode);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "mode",
+        help: "Shows or sets the text mode",
+        usage: "mode [80x25 | 80x50]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", terminal().text_mode().name()),
+                Some(mode) => {
+                    let mode =
+                        io::TextMode::from_name(mode).ok_or(args.invalid("80x25 or 80x50"))?;
+                    args.expect_end()?;
+                    terminal().set_text_mode(mode);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "font",
+        help: "Shows or sets the font, or changes a glyph",
+        usage: "font [bios | lcd | set <char> [scanline]...]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", io::vga_font::current_font().name()),
+                Some("set") => set_glyph(args)?,
+                Some(font) => {
+                    let font = io::vga_font::FontKind::from_name(font)
+                        .ok_or(args.invalid("bios or lcd"))?;
+                    args.expect_end()?;
+                    io::vga_font::select_font(&mut terminal(), font);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "statusbar",
+        help: "Shows or sets the status bar",
+        usage: "statusbar [on | off | toggle]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", if terminal().status_bar() { "on" } else { "off" }),
+                Some(state @ ("on" | "off" | "toggle")) => {
+                    args.expect_end()?;
+                    let mut terminal = terminal();
+                    let on = match state {
+                        "toggle" => !terminal.status_bar(),
+                        state => state == "on",
+                    };
+                    terminal.set_status_bar(on);
+                }
+                Some(_) => return Err(args.invalid("`on`, `off` or `toggle`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "tabs",
+        help: "Shows or sets the tab width",
+        usage: "tabs [1-80]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", terminal().tab_width()),
+                Some(width) => {
+                    let width = shell::parse_u32(width)
+                        .filter(|width| (1..=80).contains(width))
+                        .ok_or(args.invalid("a width from 1 to 80"))?;
+                    args.expect_end()?;
+                    terminal().set_tab_width(width as usize);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "blink",
+        help: "Shows or sets blinking, rather than bright backgrounds",
+        usage: "blink [on | off]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", if terminal().blink() { "on" } else { "off" }),
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    terminal().set_blink(state == "on");
+                }
+                Some(_) => return Err(args.invalid("`on` or `off`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "hwscroll",
+        help: "Shows or sets hardware scrolling",
+        usage: "hwscroll [on | off]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!(
+                    "{}\n",
+                    if terminal().hardware_scroll() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ),
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    terminal().set_hardware_scroll(state == "on");
+                }
+                Some(_) => return Err(args.invalid("`on` or `off`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "scrollregion",
+        help: "Shows or sets the rows that scroll",
+        usage: "scrollregion [off | <top> <bottom>]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => match terminal().scroll_region() {
+                    Some((top, bottom)) => printk!("{} {}\n", top + 1, bottom),
+                    None => printk!("off\n"),
+                },
+                Some("off") => {
+                    args.expect_end()?;
+                    terminal().reset_scroll_region();
+                }
+                // Rows are 1-based and inclusive, like in the DECSTBM escape sequence.
+                Some(top) => {
+                    let height = terminal().height() as u32;
+                    let top = shell::parse_u32(top)
+                        .filter(|top| (1..=height).contains(top))
+                        .ok_or(args.invalid("`off` or a top row"))?;
+                    let bottom = args
+                        .next()
+                        .and_then(shell::parse_u32)
+                        .filter(|&bottom| (top..=height).contains(&bottom))
+                        .ok_or(args.invalid("a bottom row, below the top row"))?;
+                    args.expect_end()?;
+                    terminal()
+                        .set_scroll_region(top as usize - 1, bottom as usize)
+                        .map_err(|_| ShellError::Failed("the region does not fit on the screen"))?;
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "loglevel",
+        help: "Shows or sets the lowest level of the messages shown",
+        usage: "loglevel [0-3 | debug | info | warn | err]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", log::min_level().name()),
+                Some(level) => {
+                    let level = log::Level::parse(level)
+                        .ok_or(args.invalid("0 to 3, or debug, info, warn or err"))?;
+                    args.expect_end()?;
+                    log::set_min_level(level);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "palette",
+        help: "Shows, sets or resets the colors of the palette",
+        usage: "palette [reset | <index> <rrggbb>]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => {
+                    let palette = io::vga_palette::palette(&mut terminal());
+                    for (index, (r, g, b)) in palette.into_iter().enumerate() {
+                        // Scale the 6-bit components to 8 bits.
+                        let [r, g, b] = [r, g, b].map(|c| c << 2 | c >> 4);
+                        let name = io::Color::from_u8(index as u8).name();
+                        printk!("{index:2} {r:02x}{g:02x}{b:02x} {name}\n");
+                    }
+                }
+                Some("reset") => {
+                    args.expect_end()?;
+                    io::vga_palette::reset_palette(&mut terminal());
+                }
+                Some(index) => {
+                    let index = shell::parse_u32(index)
+                        .filter(|&index| index < 16)
+                        .ok_or(args.invalid("`reset` or a color index below 16"))?;
+                    let rgb = args
+                        .next()
+                        .filter(|rgb| rgb.len() == 6)
+                        .and_then(|rgb| u32::from_str_radix(rgb, 16).ok())
+                        .ok_or(args.invalid("a color as 6 hexadecimal digits, such as ff8000"))?;
+                    args.expect_end()?;
+                    // Keep the top 6 bits of each component.
+                    let [_, r, g, b] = rgb.to_be_bytes().map(|c| c >> 2);
+                    io::vga_palette::set_color(&mut terminal(), index as u8, r, g, b);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "theme",
+        help: "Shows, lists or applies a color theme",
+        usage: "theme [list | accessible [on | off] | <name>]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", terminal().theme().name),
+                Some("list") => {
+                    args.expect_end()?;
+                    for theme in io::THEMES {
+                        printk!("{}\n", theme.name);
+                    }
+                }
+                Some("accessible") => match args.next() {
+                    None => {
+                        let accessible = terminal().theme().accessible;
+                        printk!("{}\n", if accessible { "on" } else { "off" });
+                    }
+                    Some(on_off @ ("on" | "off")) => {
+                        args.expect_end()?;
+                        terminal().set_accessible(on_off == "on");
+                    }
+                    Some(_) => return Err(args.invalid("`on` or `off`")),
+                },
+                Some(name) => {
+                    let theme = io::Theme::from_name(name)
+                        .ok_or(args.invalid("`list` or a theme listed by `theme list`"))?;
+                    args.expect_end()?;
+                    terminal().apply_theme(theme);
+                }
+            }
+            Ok(())
+        },
+    },
+    // The shape is kept while the cursor is hidden, and setting one shows the cursor again.
+    shell::Command {
+        name: "cursor",
+        help: "Shows or sets the shape of the cursor",
+        usage: "cursor [block | underline | off | <start> <end>]",
+        dangerous: false,
+        run: |args| {
+            let mode = terminal().text_mode();
+            let (start, end) = match args.next() {
+                None => {
+                    // Read first: printk locks the terminal itself.
+                    let ((start, end), hidden) = {
+                        let mut lock = terminal();
+                        (lock.cursor_shape(), lock.cursor_hidden())
+                    };
+                    let state = if hidden { ", hidden" } else { "" };
+                    printk!("{start} {end}{state}\n");
+                    return Ok(());
+                }
+                Some("off") => {
+                    args.expect_end()?;
+                    terminal().cursor_hide();
+                    return Ok(());
+                }
+                Some("block") => mode.block_cursor(),
+                Some("underline") => mode.underline_cursor(),
+                Some(start) => {
+                    let start = shell::parse_u32(start).filter(|&start| start <= 15).ok_or(
+                        args.invalid(
+                            "`block`, `underline`, `off`, or a first scanline from 0 to 15",
+                        ),
+                    )?;
+                    let end = args
+                        .next()
+                        .and_then(shell::parse_u32)
+                        .filter(|&end| (start..=15).contains(&end))
+                        .ok_or(args.invalid("a last scanline from the first one to 15"))?;
+                    (start as u8, end as u8)
+                }
+            };
+            args.expect_end()?;
+            let mut lock = terminal();
+            lock.set_cursor_shape(start, end);
+            lock.cursor_show();
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "highlight",
+        help: "Shows or sets the highlighting of the edit position",
+        usage: "highlight [on | off]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!(
+                    "{}\n",
+                    if terminal().cmdline_highlight() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ),
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    terminal().set_cmdline_highlight(state == "on");
+                }
+                Some(_) => return Err(args.invalid("`on` or `off`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "keymap",
+        help: "Shows or sets the keyboard layout",
+        usage: "keymap [us | fr | dvorak]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", KEYBOARD.lock().layout().name),
+                Some(name) => {
+                    let layout = keyboard::Layout::from_name(name)
+                        .ok_or(args.invalid("`us`, `fr` or `dvorak`"))?;
+                    args.expect_end()?;
+                    KEYBOARD.lock().set_layout(layout);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "ctrlaltdel",
+        help: "Shows or sets whether Ctrl+Alt+Delete reboots",
+        usage: "ctrlaltdel [on | off]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!(
+                    "{}\n",
+                    if KEYBOARD.lock().ctrl_alt_del() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ),
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    KEYBOARD.lock().set_ctrl_alt_del(state == "on");
+                }
+                Some(_) => return Err(args.invalid("`on` or `off`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "sysrq",
+        help: "Shows or sets whether the SysRq keys are enabled",
+        usage: "sysrq [on | off]",
+        dangerous: true,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", if KEYBOARD.lock().sysrq() { "on" } else { "off" }),
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    KEYBOARD.lock().set_sysrq(state == "on");
+                }
+                Some(_) => return Err(args.invalid("`on` or `off`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "mods",
+        help: "Shows the modifiers held until Escape is pressed",
+        usage: "mods",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            watch_modifiers();
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "showkeys",
+        help: "Prints the scancodes of the keys pressed",
+        usage: "showkeys",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            showkeys();
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "kbinfo",
+        help: "Prints the keyboard layout and scancode path",
+        usage: "kbinfo",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            let keyboard = KEYBOARD.lock();
+            printk!("layout: {}\n", keyboard.layout().name);
+            printk!("scancodes: {}\n", scancode_path(keyboard.translation()));
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "kbrate",
+        help: "Sets the typematic delay and rate",
+        usage: "kbrate <250-1000 ms> <2-30 cps>",
+        dangerous: false,
+        run: |args| {
+            let delay = args.next_u32()?;
+            if !keyboard::TYPEMATIC_DELAYS.contains(&delay) {
+                return Err(args.invalid("a delay from 250 to 1000 ms"));
+            }
+            let rate = args.next_u32()?;
+            if !keyboard::TYPEMATIC_RATES.contains(&rate) {
+                return Err(args.invalid("a rate from 2 to 30 characters per second"));
+            }
+            args.expect_end()?;
+            let byte = KEYBOARD
+                .lock()
+                .set_typematic(delay, rate)
+                .ok_or(ShellError::Failed(
+                    "the keyboard did not acknowledge the command",
+                ))?;
+            let rate = keyboard::typematic_rate_mcps(byte);
+            printk!(
+                "delay {} ms, rate {}.{} cps\n",
+                keyboard::typematic_delay_ms(byte),
+                rate / 1000,
+                rate % 1000 / 100
+            );
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "bell",
+        help: "Shows or sets the bell",
+        usage: "bell [on | off | <frequency>]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => {
+                    // Read first: printk locks the terminal itself.
+                    let (bell, freq) = {
+                        let lock = terminal();
+                        (lock.bell(), lock.bell_frequency())
+                    };
+                    let state = if bell { "on" } else { "off" };
+                    printk!("{state}, {freq} Hz\n");
+                }
+                Some(state @ ("on" | "off")) => {
+                    args.expect_end()?;
+                    terminal().set_bell(state == "on");
+                }
+                Some(freq) => {
+                    let freq = parse_frequency(freq)
+                        .ok_or(args.invalid("`on`, `off`, or a frequency from 19 to 20000 Hz"))?;
+                    args.expect_end()?;
+                    let mut lock = terminal();
+                    lock.set_bell_frequency(freq);
+                    lock.set_bell(true);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "beep",
+        help: "Plays a tone or a few notes on the speaker",
+        usage: "beep [frequency] [ms] | beep play <notes...>",
+        dangerous: false,
+        run: beep,
+    },
+    shell::Command {
+        name: "screensaver",
+        help: "Shows or sets the screensaver timeout",
+        usage: "screensaver [off | now | <seconds>]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => match SCREENSAVER_TIMEOUT_S.load(Ordering::Relaxed) {
+                    0 => printk!("off\n"),
+                    timeout => printk!("{timeout} s\n"),
+                },
+                Some("off") => {
+                    args.expect_end()?;
+                    SCREENSAVER_TIMEOUT_S.store(0, Ordering::Relaxed);
+                }
+                Some("now") => {
+                    args.expect_end()?;
+                    screensaver();
+                }
+                Some(timeout) => match shell::parse_u32(timeout) {
+                    Some(timeout @ 1..) => {
+                        args.expect_end()?;
+                        SCREENSAVER_TIMEOUT_S.store(timeout, Ordering::Relaxed);
+                    }
+                    _ => return Err(args.invalid("`off`, `now` or a positive number of seconds")),
+                },
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "replacement",
+        help: "Shows or sets the character shown for those VGA lacks",
+        usage: "replacement [<char> | <code>]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{}\n", terminal().replacement()),
+                // Either the character itself, or its code.
+                Some(arg) => {
+                    let mut chars = arg.chars();
+                    let c = match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => shell::parse_u32(arg)
+                            .and_then(|code| u8::try_from(code).ok())
+                            .map(vga_chars::to_char),
+                    };
+                    let c = c
+                        .filter(|&c| vga_chars::from_char(c).is_some())
+                        .ok_or(args.invalid("a VGA character, or a character code below 256"))?;
+                    args.expect_end()?;
+                    terminal().set_replacement(c);
+                }
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "demo",
+        help: "Shows a demo",
+        usage: "demo box | 42 [dac] | progress",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                Some("box") => {
+                    args.expect_end()?;
+                    demo_box();
+                }
+                Some("42") => {
+                    let dac = match args.next() {
+                        None => false,
+                        Some("dac") => true,
+                        Some(_) => return Err(args.invalid("`dac`")),
+                    };
+                    args.expect_end()?;
+                    demo_42(dac);
+                }
+                Some("progress") => {
+                    args.expect_end()?;
+                    demo_progress();
+                }
+                _ => return Err(args.invalid("`box`, `42` or `progress`")),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "lockdown",
+        help: "Shows, enters or lifts lockdown",
+        usage: "lockdown [on | off]",
+        dangerous: false,
+        run: lockdown,
+    },
+    shell::Command {
+        name: "alias",
+        help: "Lists the aliases, or defines one standing for the start of a line",
+        usage: "alias [name[=value]]",
+        dangerous: false,
+        run: alias,
+    },
+    shell::Command {
+        name: "unalias",
+        help: "Removes an alias",
+        usage: "unalias <name>",
+        dangerous: false,
+        run: |args| {
+            let name = args.next_str()?;
+            args.expect_end()?;
+            if !ALIASES.lock().remove(name) {
+                return Err(ShellError::Failed("no such alias"));
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "bind",
+        help: "Shows or sets the commands of the function keys and the Ctrl+X chords",
+        usage: "bind [-d] [f<n> | C-x-<key> [command]]",
+        dangerous: false,
+        run: bind,
+    },
+    shell::Command {
+        name: "record",
+        help: "Starts or stops recording the lines entered, or runs them again",
+        usage: "record [play]",
+        dangerous: false,
+        run: record_command,
+    },
+    shell::Command {
+        name: "history",
+        help: "Lists the lines entered in this TTY, to run again with `!!` or `!<n>`",
+        usage: "history",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            let shown = terminal().shown_console();
+            for (n, line) in HISTORIES.lock()[shown].numbered() {
+                printk!("{n:>5}  {line}\n");
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "repeat",
+        help: "Runs the last command entered again, or a command a number of times",
+        usage: "repeat [<count> <command...>]",
+        dangerous: false,
+        run: repeat,
+    },
+    shell::Command {
+        name: "kbc",
+        help: "Shows or sets the keyboard controller registers",
+        usage: "kbc [set <irq1 | irq12 | translation | a20> <0 | 1>]",
+        dangerous: true,
+        run: kbc::command,
+    },
+    shell::Command {
+        name: "termbudget",
+        help: "Shows or sets how long printing may hold the terminal",
+        usage: "termbudget [microseconds]",
+        dangerous: false,
+        run: |args| {
+            match args.next() {
+                None => printk!("{} us\n", TERM_BUDGET_US.load(Ordering::Relaxed)),
+                Some(budget) => match shell::parse_u32(budget) {
+                    Some(budget @ 1..) => {
+                        args.expect_end()?;
+                        TERM_BUDGET_US.store(budget, Ordering::Relaxed);
+                    }
+                    _ => return Err(args.invalid("a positive number of microseconds")),
+                },
+            }
+            Ok(())
+        },
+    },
+    #[cfg(feature = "lockstat")]
+    shell::Command {
+        name: "lockstat",
+        help: "Shows or resets the lock hold times",
+        usage: "lockstat [reset]",
+        dangerous: false,
+        run: |args| {
+            let reset = args.next().map(|arg| arg == "reset");
+            if reset == Some(false) {
+                return Err(args.invalid("`reset`"));
+            }
+            args.expect_end()?;
+            match reset {
+                None => truncating(|| {
+                    lockstat::for_each(|location, us| printk!("{location}: {us} us\n"))
+                }),
+                Some(_) => lockstat::reset(),
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "date",
+        help: "Prints the date and time of the real-time clock",
+        usage: "date [--raw]",
+        dangerous: false,
+        run: |args| {
+            let raw = args.flag("--raw");
+            args.expect_end()?;
+            if !raw {
+                printk!("{}\n", io::rtc::now());
+                return Ok(());
+            }
+            // Reading status C acknowledges the interrupts of the clock, which are not used.
+            for (register, name) in io::rtc::REGISTERS {
+                printk!(
+                    "{register:02x} {name:<12} {:02x}\n",
+                    io::rtc::read(register)
+                );
+            }
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "gdt",
+        help: "Prints the descriptors of the GDT and the segment registers",
+        usage: "gdt",
+        dangerous: false,
+        run: gdt,
+    },
+    shell::Command {
+        name: "idt",
+        help: "Prints the gates of the IDT",
+        usage: "idt [--all]",
+        dangerous: false,
+        run: idt,
+    },
+    shell::Command {
+        name: "cpuid",
+        help: "Identifies the CPU, or dumps the registers of a CPUID leaf",
+        usage: "cpuid [leaf [subleaf]]",
+        dangerous: false,
+        run: print_cpuid,
+    },
+    shell::Command {
+        name: "regs",
+        help: "Prints the registers, as they are inside this command",
+        usage: "regs",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            print_registers(&mut Printk);
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "multiboot",
+        help: "Prints the information provided by the boot loader",
+        usage: "multiboot",
+        dangerous: false,
+        run: print_multiboot,
+    },
+    shell::Command {
+        name: "meminfo",
+        help: "Shows the free memory, and with -v the allocation counters",
+        usage: "meminfo [-v]",
+        dangerous: false,
+        run: |args| {
+            let verbose = args.flag("-v");
+            args.expect_end()?;
+            print_meminfo(verbose);
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "alloctest",
+        help: "Allocates frames until memory runs out, then frees them",
+        usage: "alloctest [count]",
+        dangerous: false,
+        run: alloctest,
+    },
+    shell::Command {
+        name: "set",
+        help: "Lists the memory tunables, or sets one",
+        usage: "set [FAILALLOC off|1/<n> | WMARK_LOW <size> | WMARK_MIN <size>]",
+        dangerous: true,
+        run: set,
+    },
+    shell::Command {
+        name: "version",
+        help: "Prints the version of the kernel and how it was built",
+        usage: "version",
+        dangerous: false,
+        run: print_version,
+    },
+    shell::Command {
+        name: "uname",
+        help: "Same as `version`",
+        usage: "uname",
+        dangerous: false,
+        run: print_version,
+    },
+    shell::Command {
+        name: "calc",
+        help: "Evaluates an integer expression, such as `(0xb8000 + 160*24) & ~0xf`",
+        usage: "calc <expression>",
+        dangerous: false,
+        run: |args| {
+            let expr = args.rest_raw();
+            if expr.is_empty() {
+                return Err(ShellError::MissingArgument { index: 1 });
+            }
+            let value = calc::evaluate(expr).map_err(|err| ShellError::Failed(err.as_str()))?;
+            // Negative values are shown in hexadecimal as their 64-bit two's complement.
+            printk!("{value} = {:#x}\n", value as u64);
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "uptime",
+        help: "Prints how long ago the kernel booted",
+        usage: "uptime",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            let ticks = time::ticks();
+            let ms = time::millis();
+            printk!(
+                "{}.{:03} s ({}), {ticks} ticks at {} Hz\n",
+                ms / 1000,
+                ms % 1000,
+                HumanDuration(ms / 1000),
+                time::TICK_HZ
+            );
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "sleep",
+        help: "Waits for some seconds, or until CTRL+C is pressed",
+        usage: "sleep <seconds>",
+        dangerous: false,
+        run: |args| {
+            let ms = args.next_u32()?.checked_mul(1000);
+            let ms = ms.ok_or(args.invalid("a number of seconds up to 4294967"))?;
+            args.expect_end()?;
+            time::msleep(ms).map_err(|time::Interrupted| ShellError::Interrupted)
+        },
+    },
+    shell::Command {
+        name: "timesource",
+        help: "Prints the clock source and its resolution",
+        usage: "timesource",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            printk!("source: {}\n", time::source());
+            printk!("TSC frequency: {} kHz\n", time::tsc_khz());
+            printk!("resolution: {} ns\n", time::resolution_ns());
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "bench",
+        help: "Measures how fast lines are printed",
+        usage: "bench [lines]",
+        dangerous: false,
+        run: |args| {
+            let lines = match args.next().map(shell::parse_u32) {
+                None => 100,
+                Some(Some(lines @ 1..)) => lines,
+                Some(_) => return Err(args.invalid("a positive number of lines")),
+            };
+            args.expect_end()?;
+            bench(lines);
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "clear",
+        help: "Clears the screen",
+        usage: "clear",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            // The prompt is drawn again on the first row once the command returns.
+            terminal().clear();
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "clocktest",
+        help: "Compares the clock sources",
+        usage: "clocktest [seconds]",
+        dangerous: false,
+        run: |args| {
+            let seconds = match args.next().map(shell::parse_u32) {
+                None => 5,
+                Some(Some(seconds @ 1..)) => seconds,
+                Some(_) => return Err(args.invalid("a positive number of seconds")),
+            };
+            args.expect_end()?;
+            clocktest(seconds)?;
+            Ok(())
+        },
+    },
+    shell::Command {
+        name: "snapshottest",
+        help: "Checks that the terminal is put back after nested full-screen features",
+        usage: "snapshottest",
+        dangerous: false,
+        run: |args| {
+            args.expect_end()?;
+            snapshottest()
+        },
+    },
+];
+
+/// Implements `font set <char> [scanline]...`, which replaces the glyph of a character. Missing
+/// scanlines are blank.
+fn set_glyph(args: &mut Args) -> Result<(), ShellError> {
+    let c = args.next_u32()?;
+    let c = u8::try_from(c).map_err(|_| args.invalid("a character code below 256"))?;
+    let mut glyph = [0; 16];
+    for scanline in &mut glyph {
+        let Some(arg) = args.next() else { break };
+        let value = shell::parse_u32(arg).and_then(|value| u8::try_from(value).ok());
+        *scanline = value.ok_or(args.invalid("a scanline below 256"))?;
+    }
+    args.expect_end()?;
+    io::vga_font::set_glyph(&mut terminal(), c, &glyph);
+    Ok(())
+}
+
+/// Prints the scancodes read from the keyboard, one key event per line, until Escape is pressed
+/// twice.
+///
+/// The scancodes do not go through the decoder, which is reset afterwards, so that the keys held
+/// meanwhile are not left pressed.
+fn showkeys() {
+    printk!("Showing set 1 scancodes, press Escape twice to stop.\n");
+    let mut sequence = [0; 3];
+    let mut len = 0;
+    // The number of scancodes of an Escape press, release and press seen in a row.
+    let mut escapes = 0;
+    while escapes < 3 {
+        let Some(scancode) = KEYBOARD.lock().get_kb_data() else {
+            core::hint::spin_loop();
+            continue;
+        };
+        escapes = match (escapes, scancode) {
+            (0 | 2, 0x01) | (1, 0x81) => escapes + 1,
+            (_, 0x01) => 1,
+            _ => 0,
+        };
+
+        sequence[len] = scancode;
+        len += 1;
+        // Prefixes are printed along with the codes they apply to: one for E0, two for E1.
+        let complete = match sequence[0] {
+            0xE0 => len == 2,
+            0xE1 => len == 3,
+            _ => true,
+        };
+        if !complete {
+            continue;
+        }
+        for byte in &sequence[..len] {
+            printk!("{byte:02x} ");
+        }
+        let kind = if scancode & 0x80 == 0 {
+            "make"
+        } else {
+            "break"
+        };
+        printk!("{:1$}{kind}\n", "", (sequence.len() - len) * 3);
+        len = 0;
+    }
+    KEYBOARD.lock().reset_decoder();
+}
+
+/// Shows the modifiers on a row redrawn in place as keys are pressed and released, until Escape
+/// is pressed.
+fn watch_modifiers() {
+    printk!("Press Escape to stop.\n\n");
+    // The row left blank above the output cursor.
+    let row = terminal().output_cursor().1.saturating_sub(1);
+    let mut shown = None;
+    loop {
+        let (event, modifiers) = {
+            let mut keyboard = KEYBOARD.lock();
+            (keyboard.get_event(), keyboard.modifiers())
+        };
+        if event.is_some_and(|event| event.pressed && event.key == keyboard::Key::Escape) {
+            break;
+        }
+        if shown != Some(modifiers) {
+            shown = Some(modifiers);
+            let mut text = io::RowText::new();
+            _ = write!(text, "{modifiers}");
+            let mut lock = terminal();
+            let width = lock.width();
+            _ = lock.clear_region(0, row, width, 1);
+            lock.write_str_at(0, row, text.as_str());
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Prints the current color and its attribute byte, split into its background and foreground.
+fn print_color() {
+    let (fg, bg) = terminal().colors();
+    let color = terminal().get_color();
+    printk!(
+        "{} on {}: attribute {color:#04x} (background {:x}, foreground {:x})\n",
+        fg.name(),
+        bg.name(),
+        color >> 4,
+        color & 0x0F
+    );
+}
+
+/// Prints the 16 foregrounds on the current background, with their values, in two columns.
+fn print_swatches() {
+    const ROWS: usize = 8;
+    const COLUMN_WIDTH: usize = 20;
+
+    {
+        let mut lock = terminal();
+        for _ in 0..ROWS {
+            lock.putchar('\n');
+        }
+        // A scrolling region shorter than the swatches leaves fewer rows above the cursor.
+        let region_top = lock.scroll_region().map_or(0, |(top, _)| top);
+        let top = lock.output_cursor().1.saturating_sub(ROWS).max(region_top);
+        let current = lock.get_color();
+        for value in 0..16u8 {
+            let x = usize::from(value) / ROWS * COLUMN_WIDTH;
+            let y = top + usize::from(value) % ROWS;
+            let mut hex = io::RowText::new();
+            _ = write!(
+                hex,
+                "{} {value:x} ",
+                if current & 0x0F == value { '*' } else { ' ' }
+            );
+            let written = lock.write_str_at(x, y, hex.as_str());
+            let name = io::Color::from_u8(value).name();
+            lock.write_str_at_color(x + written, y, name, current & 0xF0 | value);
+        }
+    }
+    print_color();
+}
+
+/// Takes over the screen with a page of text in a scrolling region, then draws the box of
+/// `demo box` over it, and checks that each is undone exactly.
+fn snapshottest() -> Result<(), ShellError> {
+    const NO_SLOT: ShellError = ShellError::Failed("no snapshot slot is free");
+
+    let mut lock = terminal();
+    let page = lock.snapshot().ok_or(NO_SLOT)?;
+    let status_bar = lock.status_bar();
+    lock.set_status_bar(!status_bar);
+    let blink = lock.blink();
+    lock.set_blink(!blink);
+    lock.set_cursor_shape(0, 15);
+    lock.clear();
+    let height = lock.height();
+    _ = lock.set_scroll_region(1, height - 1);
+    lock.set_color(0x1E);
+    for n in 0..height * 2 {
+        _ = writeln!(lock, "page line {n}");
+    }
+    let Some(message) = lock.snapshot() else {
+        lock.restore(&page);
+        return Err(NO_SLOT);
+    };
+    drop(lock);
+
+    demo_box();
+
+    let mut lock = terminal();
+    let drawn = !lock.matches(&message);
+    lock.restore(&message);
+    let message_undone = drawn && lock.matches(&message);
+    drop(message);
+    lock.restore(&page);
+    let page_undone = lock.matches(&page);
+    drop(lock);
+    match (message_undone, page_undone) {
+        (true, true) => printk!("ok\n"),
+        (false, _) => return Err(ShellError::Failed("the box was not undone exactly")),
+        (_, false) => return Err(ShellError::Failed("the page was not undone exactly")),
+    }
+    Ok(())
+}
+
+fn clocktest(seconds: u32) -> Result<(), ShellError> {
+    printk!(
+        "TSC: {} kHz, measuring over {} RTC seconds...\n",
+        time::tsc_khz(),
+        seconds
+    );
+    let measured =
+        time::measure_against_rtc(seconds).ok_or(ShellError::Failed("RTC not responding"))?;
+    let expected = seconds as u64 * 1_000_000_000;
+    let drift = measured as i64 - expected as i64;
+    printk!(
+        "monotonic: {} ns, RTC: {} ns, drift: {} ppm\n",
+        measured,
+        expected,
+        drift / (seconds as i64 * 1000)
+    );
+    Ok(())
+}
+
+/// Runs `f` with line wrapping off, so that the rows of a table wider than the screen are cut
+/// rather than broken.
+fn truncating<R>(f: impl FnOnce() -> R) -> R {
+    let wrap = terminal().wrap_mode();
+    terminal().set_wrap(false);
+    let result = f();
+    terminal().set_wrap_mode(wrap);
+    result
+}
+
+/// Writes `lines` lines through the terminal path of [`Printk`] one character at a time, as it
+/// used to, then by chunks, then through [`printk!`] as a whole, and prints how many TSC cycles a
+/// line took each way.
+fn bench(lines: u32) {
+    const LINE: &str = "The quick brown fox jumps over the lazy dog, 0123456789 times over.\n";
+
+    let start = time::rdtsc();
+    for _ in 0..lines {
+        Printk::write_terminal(LINE, |lock, chunk| {
+            chunk.chars().for_each(|c| lock.putchar(c))
+        });
+    }
+    let per_char = time::rdtsc() - start;
+    let start = time::rdtsc();
+    for _ in 0..lines {
+        Printk::write_terminal(LINE, |lock, chunk| {
+            _ = core::fmt::Write::write_str(lock, chunk)
+        });
+    }
+    let per_chunk = time::rdtsc() - start;
+    let start = time::rdtsc();
+    for _ in 0..lines {
+        printk!("{LINE}");
+    }
+    let printk = time::rdtsc() - start;
+    printk!(
+        "terminal path of printk, by character: {} cycles/line, by chunk: {} cycles/line\n\
+         printk! with the kernel log and the serial port: {} cycles/line\n",
+        per_char / lines as u64,
+        per_chunk / lines as u64,
+        printk / lines as u64
+    );
+}
+
+/// Draws a framed "hello" window in the middle of the screen.
+fn demo_box() {
+    use io::draw::{self, BoxStyle};
+
+    const W: usize = 30;
+    const H: usize = 7;
+
+    let mut lock = terminal();
+    let height = lock.height();
+    let x = (80 - W) / 2;
+    let y = height.saturating_sub(H) / 2;
+    lock.begin_frame();
+    _ = lock.clear_region(x, y, W, H.min(height - y));
+    draw::draw_box(&mut lock, x, y, W, H, BoxStyle::Double);
+    draw::draw_text_centered(&mut lock, x + 1, y + 1, W - 2, "kfs");
+    draw::draw_hline(&mut lock, x + 1, y + 2, W - 2, BoxStyle::Single);
+    draw::draw_text_centered(&mut lock, x + 1, y + 4, W - 2, "hello");
+    lock.end_frame();
+}
+
+/// Fills a progress bar on the current row over a few seconds, with a spinner on the next one,
+/// until done or a key is pressed.
+fn demo_progress() {
+    use io::progress::{ProgressBar, Spinner};
+
+    const STEPS: u64 = 100;
+    const STEP_NS: u64 = 30_000_000;
+
+    let (y, mut bar, mut spinner) = {
+        let mut lock = terminal();
+        let (_, mut y) = lock.output_cursor();
+        // Make room for the spinner below the bar.
+        if y + 1 == lock.height() {
+            lock.putchar('\n');
+            y -= 1;
+        }
+        let bar = ProgressBar::new(&mut lock, y, STEPS);
+        let spinner = Spinner::new(&mut lock, 0, y + 1);
+        (y, bar, spinner)
+    };
+    for step in 1..=STEPS {
+        if wait_for_key(STEP_NS) {
+            break;
+        }
+        let mut lock = terminal();
+        bar.set(&mut lock, step);
+        spinner.tick(&mut lock);
+    }
+    let mut lock = terminal();
+    spinner.finish(&mut lock, true);
+    bar.finish(&mut lock, false);
+    lock.set_output_cursor(0, y + 1);
+}
+
+/// The number of bytes per row supported by hex dumps, widest first.
+const HEX_WIDTHS: [usize; 3] = [32, 16, 8];
+
+/// Parses the optional width ending the arguments of `peek` and `poke`, a byte by default.
+fn parse_width(args: &mut Args) -> Result<io::Width, ShellError> {
+    match args.next() {
+        None => Ok(io::Width::Byte),
+        Some(name) => io::Width::from_name(name).ok_or(args.invalid("`b`, `w` or `d`")),
+    }
+}
+
+/// Returns an error unless `value`, the argument at `index`, fits in `width`.
+fn check_fits(value: u32, index: usize, width: io::Width) -> Result<(), ShellError> {
+    if value <= width.max() {
+        return Ok(());
+    }
+    Err(ShellError::InvalidArgument {
+        index,
+        expected: match width {
+            io::Width::Byte => "a value up to 0xff",
+            io::Width::Word => "a value up to 0xffff",
+            io::Width::Dword => "a value up to 0xffffffff",
+        },
+    })
+}
+
+/// Returns the width of a value of `width` written with `{:#0x}`, prefix included.
+fn hex_digits(width: io::Width) -> usize {
+    2 + 2 * width.bytes()
+}
+
+/// The most reads `inb x<count>` and the like make.
+const MAX_PORT_READS: u32 = 64;
+
+/// Implements `inb`, `inw` and `inl`, which read from a port `width` at a time.
+fn port_in(args: &mut Args, width: io::Width) -> Result<(), ShellError> {
+    let port = parse_port(args)?;
+    let count = match args.next() {
+        None => 1,
+        Some(count) => count
+            .strip_prefix('x')
+            .and_then(shell::parse_u32)
+            .filter(|count| (1..=MAX_PORT_READS).contains(count))
+            .ok_or(args.invalid("a count from x1 to x64"))?,
+    };
+    args.expect_end()?;
+    let digits = hex_digits(width);
+    printk!("{port:#06x}:");
+    for _ in 0..count {
+        // Safety: reading ports is the user's problem.
+        let value = unsafe { io::read_port(port, width) };
+        printk!(" {value:#0digits$x}");
+    }
+    printk!("\n");
+    Ok(())
+}
+
+/// Implements `outb`, `outw` and `outl`, which write to a port `width` at a time.
+fn port_out(args: &mut Args, width: io::Width) -> Result<(), ShellError> {
+    let port = parse_port(args)?;
+    let value = args.next_u32()?;
+    check_fits(value, args.index(), width)?;
+    args.expect_end()?;
+    // Writes can wedge the machine, so say what is written first.
+    printk!(
+        "{port:#06x} <- {value:#0digits$x}\n",
+        digits = hex_digits(width)
+    );
+    // Safety: writing ports is the user's problem.
+    unsafe { io::write_port(port, width, value) };
+    Ok(())
+}
+
+/// Parses the next argument as an I/O port.
+fn parse_port(args: &mut Args) -> Result<u16, ShellError> {
+    let port = args.next_u32()?;
+    u16::try_from(port).map_err(|_| args.invalid("a port up to 0xffff"))
+}
+
+/// Returns the addresses of the kernel stack.
+fn stack_range() -> Range<usize> {
+    KERNEL_STACK.bottom().addr()..KERNEL_STACK.top().addr()
+}
+
+/// The longest dump `hexdump` prints, in bytes.
+const HEXDUMP_MAX_LEN: usize = 64 * 1024;
+
+/// Returns the bytes per row of a hex dump: the ones given with the `-w` flag, or the widest
+/// that fit on the screen.
+fn hex_width(args: &mut Args) -> Result<usize, ShellError> {
+    if !args.flag("-w") {
+        return Ok(auto_hex_width(terminal().width()));
+    }
+    let width = args.next_u32()? as usize;
+    if !HEX_WIDTHS.contains(&width) {
+        return Err(args.invalid("8, 16 or 32"));
+    }
+    Ok(width)
+}
+
+/// Returns the widest hex dump row that fits on a line of `columns` columns.
+fn auto_hex_width(columns: usize) -> usize {
+    // A row must not fill the line completely, or the terminal would wrap before the newline.
+    HEX_WIDTHS
+        .into_iter()
+        .find(|&width| hexdump::line_len(width) < columns)
+        .unwrap_or(HEX_WIDTHS[HEX_WIDTHS.len() - 1])
+}
+
+/// Writes the text on screen to the serial port, or to the terminal without one, with the color
+/// of each cell in hex after each row if `attrs`.
+fn screendump(attrs: bool) -> Result<(), ShellError> {
+    // The screen is copied first, so that the terminal is not locked while writing it out.
+    let screen = terminal()
+        .snapshot()
+        .ok_or(ShellError::Failed("no snapshot slot is free"))?;
+
+    let mut serial = SERIAL.get().map(Mutex::lock);
+    let out: &mut dyn core::fmt::Write = match &mut serial {
+        Some(serial) => &mut **serial,
+        None => &mut Printk,
+    };
+    for y in 0..screen.height() {
+        let row = screen.row(y);
+        let text = row.map(|cell| match cell as u8 {
+            0 => ' ',
+            byte => vga_chars::to_char(byte),
+        });
+        let len = text.iter().rposition(|&c| c != ' ').map_or(0, |i| i + 1);
+        for &c in &text[..len] {
+            _ = out.write_char(c);
+        }
+        _ = out.write_char('\n');
+        if attrs {
+            for &cell in row {
+                _ = write!(out, "{:02x}", cell >> 8);
+            }
+            _ = out.write_char('\n');
+        }
+    }
+    Ok(())
+}
+
+/// The value of the GDTR or the IDTR: where a descriptor table is, and the offset of its last
+/// byte.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Default)]
+struct TableRegister {
+    limit: u16,
+    base: usize,
+}
+
+impl TableRegister {
+    /// Returns the number of 8-byte entries of the table.
+    fn len(self) -> usize {
+        (usize::from(self.limit) + 1) / 8
+    }
+
+    /// Reads the entry `index` of the table.
+    ///
+    /// # Safety
+    ///
+    /// The table must be readable.
+    unsafe fn read(self, index: usize) -> u64 {
+        let addr = self.base + index * 8;
+        // Safety: the caller guarantees it.
+        let (low, high) = unsafe {
+            (
+                io::read_memory(addr, io::Width::Dword),
+                io::read_memory(addr + 4, io::Width::Dword),
+            )
+        };
+        u64::from(high) << 32 | u64::from(low)
+    }
+}
+
+/// Returns the current value of the GDTR.
+fn sgdt() -> TableRegister {
+    let mut gdtr = TableRegister::default();
+    // Safety: `sgdt` only writes the 6 bytes of the register to `gdtr`.
+    unsafe { asm!("sgdt [{}]", in(reg) &mut gdtr, options(nostack, preserves_flags)) };
+    gdtr
+}
+
+/// Implements the `gdt` command.
+fn gdt(args: &mut Args) -> Result<(), ShellError> {
+    args.expect_end()?;
+    let gdtr = sgdt();
+    let (base, limit) = (gdtr.base, gdtr.limit);
+    printk!(
+        "GDT at {base:#010x}, limit {limit:#06x}, {} entries\n",
+        gdtr.len()
+    );
+    // Safety: the GDTR points to the GDT, which stays mapped.
+    let entry = |index| SegmentDescriptor(unsafe { gdtr.read(index) });
+    truncating(|| {
+        for index in 0..gdtr.len() {
+            let descriptor = entry(index);
+            printk!(
+                "{:04x}  base {:08x}  limit {:08x}  dpl {}  {} type {:x}  {}  {descriptor}\n",
+                index * 8,
+                descriptor.base(),
+                descriptor.limit(),
+                descriptor.dpl(),
+                if descriptor.s() { 'S' } else { '-' },
+                descriptor.kind(),
+                if descriptor.present() { 'P' } else { '-' },
+            );
+        }
+    });
+    for (name, selector) in cpu::segment_registers() {
+        let index = usize::from(selector >> 3);
+        printk!("{name} {selector:04x}  ");
+        if selector & 0b100 != 0 {
+            printk!("in the LDT\n");
+        } else if index >= gdtr.len() {
+            printk!("beyond the end of the GDT\n");
+        } else {
+            printk!("rpl {}  {}\n", selector & 0b11, entry(index));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the current value of the IDTR.
+fn sidt() -> TableRegister {
+    let mut idtr = TableRegister::default();
+    // Safety: `sidt` only writes the 6 bytes of the register to `idtr`.
+    unsafe { asm!("sidt [{}]", in(reg) &mut idtr, options(nostack, preserves_flags)) };
+    idtr
+}
+
+/// Implements the `idt` command.
+fn idt(args: &mut Args) -> Result<(), ShellError> {
+    let all = args.flag("--all");
+    args.expect_end()?;
+    let idtr = sidt();
+    let (base, limit) = (idtr.base, idtr.limit);
+    printk!(
+        "IDT at {base:#010x}, limit {limit:#06x}, {} entries\n",
+        idtr.len()
+    );
+    let mut empty = 0;
+    for vector in 0..idtr.len() {
+        // Safety: the IDTR points to the IDT, which stays mapped.
+        let gate = GateDescriptor(unsafe { idtr.read(vector) });
+        if gate.is_empty() && !all {
+            empty += 1;
+            continue;
+        }
+        printk!(
+            "{vector:3}  handler {:08x}  selector {:04x}  dpl {}  {}  {gate}\n",
+            gate.offset(),
+            gate.selector(),
+            gate.dpl(),
+            if gate.present() { 'P' } else { '-' },
+        );
+    }
+    if empty != 0 {
+        printk!("{empty} empty entries skipped (see --all)\n");
+    }
+    Ok(())
+}
+
+/// Runs the 42 animation on a cleared screen until a key is pressed, then puts the screen back.
+fn demo_42(dac: bool) {
+    take_over_screen(|| {
+        if dac {
+            animate_42_dac();
+        } else {
+            animate_42(ANIMATION_STEP_NS);
+        }
+    });
+}
+
+/// Returns whether the speaker can play `freq`, up to the limit of hearing.
+fn is_audible(freq: u32) -> bool {
+    (io::speaker::MIN_FREQUENCY..=20_000).contains(&freq)
+}
+
+/// Parses a frequency the speaker can play, up to the limit of hearing.
+fn parse_frequency(s: &str) -> Option<u32> {
+    shell::parse_u32(s).filter(|&freq| is_audible(freq))
+}
+
+/// What `beep play` accepts for each note.
+const NOTE_EXPECTED: &str = "a note such as c, f#5, bb3:400 or r:100";
+
+/// Implements the `beep` command.
+///
+/// The sound stops early on **CTRL+C**.
+fn beep(args: &mut Args) -> Result<(), ShellError> {
+    let freq = match args.next() {
+        None => io::speaker::BEEP_FREQUENCY,
+        Some("play") => return play(args),
+        Some(freq) => {
+            parse_frequency(freq).ok_or(args.invalid("a frequency from 19 to 20000 Hz"))?
+        }
+    };
+    let ms = match args.next() {
+        None => io::speaker::BEEP_MS,
+        Some(ms) => shell::parse_u32(ms)
+            .filter(|ms| (1..=io::speaker::MAX_MS).contains(ms))
+            .ok_or(args.invalid("a length from 1 to 10000 ms"))?,
+    };
+    args.expect_end()?;
+    io::speaker::tone(freq, ms).map_err(|time::Interrupted| ShellError::Interrupted)
+}
+
+/// Implements `beep play`, which plays notes parsed by [`NoteParser`] once they are all valid.
+fn play(args: &mut Args) -> Result<(), ShellError> {
+    let notes = args.rest_raw();
+    if notes.is_empty() {
+        return Err(ShellError::MissingArgument { index: 2 });
+    }
+    let mut parser = NoteParser::new();
+    for (i, note) in notes.split_whitespace().enumerate() {
+        let valid = parser
+            .parse(note)
+            .is_some_and(|note| note.ms <= io::speaker::MAX_MS && note.freq.is_none_or(is_audible));
+        if !valid {
+            return Err(ShellError::InvalidArgument {
+                index: i + 2,
+                expected: NOTE_EXPECTED,
+            });
+        }
+    }
+    let mut parser = NoteParser::new();
+    for note in notes
+        .split_whitespace()
+        .filter_map(|note| parser.parse(note))
+    {
+        match note.freq {
+            Some(freq) => io::speaker::tone(freq, note.ms),
+            None => time::msleep(note.ms),
+        }
+        .map_err(|time::Interrupted| ShellError::Interrupted)?;
+    }
+    Ok(())
+}
+
+/// Implements the `cpuid` command.
+fn print_cpuid(args: &mut Args) -> Result<(), ShellError> {
+    let mut optional_number = || match args.next() {
+        None => Ok(None),
+        Some(arg) => shell::parse_u32(arg)
+            .map(Some)
+            .ok_or(args.invalid("a number")),
+    };
+    let leaf = optional_number()?;
+    let subleaf = optional_number()?;
+    args.expect_end()?;
+    let Some(leaf0) = cpu::cpuid(0, 0) else {
+        return Err(ShellError::Failed("this CPU has no CPUID instruction"));
+    };
+    let cpuid = |leaf| cpu::cpuid(leaf, 0).unwrap_or_default();
+
+    if let Some(leaf) = leaf {
+        let r = cpu::cpuid(leaf, subleaf.unwrap_or(0)).unwrap_or_default();
+        printk!(
+            "eax {:08x}  ebx {:08x}  ecx {:08x}  edx {:08x}\n",
+            r.eax,
+            r.ebx,
+            r.ecx,
+            r.edx
+        );
+        return Ok(());
+    }
+
+    let vendor = cpuid::vendor(&leaf0);
+    printk!(
+        "vendor: {}\nhighest leaf: {:#x}\n",
+        vendor.escape_ascii(),
+        leaf0.eax
+    );
+    if leaf0.eax >= 1 {
+        let leaf1 = cpuid(1);
+        let signature = Signature::new(leaf1.eax);
+        printk!(
+            "family {:#x}, model {:#x}, stepping {}\n",
+            signature.family,
+            signature.model,
+            signature.stepping
+        );
+        printk!("features:");
+        for name in cpuid::features(leaf1.edx, leaf1.ecx) {
+            printk!(" {name}");
+        }
+        printk!("\n");
+    }
+    if cpuid(cpuid::EXTENDED_LEAF).eax >= cpuid::BRAND_LEAVES[2] {
+        printk!("brand: {}\n", Brand::new(&cpuid::BRAND_LEAVES.map(cpuid)));
+    } else {
+        printk!("brand: not provided\n");
+    }
+    Ok(())
+}
+
+/// Implements the `version` and `uname` commands.
+fn print_version(args: &mut Args) -> Result<(), ShellError> {
+    args.expect_end()?;
+    printk!(
+        "{} {} ({})\n\
+         built {}, {} profile\n\
+         {}\n\
+         target: {} (i386, multiboot)\n\
+         kernel stack: {} bytes\n",
+        version::NAME,
+        version::VERSION,
+        version::GIT_DESCRIBE,
+        version::BUILD_TIMESTAMP,
+        version::PROFILE,
+        version::RUSTC_VERSION,
+        version::TARGET,
+        stack::KERNEL_STACK_SIZE,
+    );
+    Ok(())
+}
+
+/// Implements the `multiboot` command.
+fn print_multiboot(args: &mut Args) -> Result<(), ShellError> {
+    const NOT_PROVIDED: &str = "not provided";
+
+    args.expect_end()?;
+    let info = multiboot::info();
+    let Some(flags) = info.flags() else {
+        printk!("not loaded by a Multiboot boot loader\n");
+        return Ok(());
+    };
+    printk!("flags: {flags:#010x}");
+    for (bit, name) in multiboot::FLAG_NAMES.iter().enumerate() {
+        if flags & (1 << bit) != 0 {
+            printk!(" {name}");
+        }
+    }
+    printk!("\n");
+
+    match multiboot::memory() {
+        Some(memory) => printk!(
+            "mem_lower: {} KiB, mem_upper: {} KiB\n",
+            memory.lower_kib,
+            memory.upper_kib
+        ),
+        None => printk!("mem_lower, mem_upper: {NOT_PROVIDED}\n"),
+    }
+
+    {
+        let strings = multiboot::strings();
+        let strings = [
+            ("boot loader", strings.boot_loader_name()),
+            ("command line", strings.cmdline()),
+        ];
+        for (name, string) in strings {
+            match string {
+                Some(Ok(string)) => printk!("{name}: {string}\n"),
+                Some(Err(err)) => printk!("{name}: invalid, {err}\n"),
+                None => printk!("{name}: {NOT_PROVIDED}\n"),
+            }
+        }
+    }
+
+    match info.module_count() {
+        Some(count) => {
+            printk!("modules: {count}\n");
+            for module in info.modules() {
+                printk!("  {:08x}-{:08x}", module.start, module.end);
+                match module.string() {
+                    Some(Ok(string)) => printk!("  {string}\n"),
+                    Some(Err(err)) => printk!("  invalid string, {err}\n"),
+                    None => printk!("\n"),
+                }
+            }
+            if count > info.modules().len() {
+                printk!("  and {} more\n", count - info.modules().len());
+            }
+        }
+        None => printk!("modules: {NOT_PROVIDED}\n"),
+    }
+
+    match info.memory_map_len() {
+        Some(len) => {
+            let available: u64 = info
+                .memory_map()
+                .iter()
+                .filter(|region| region.is_available())
+                .map(|region| region.len)
+                .sum();
+            printk!(
+                "memory map: {len} entries, {} KiB available\n",
+                available / 1024
+            );
+            for region in info.memory_map() {
+                printk!(
+                    "  {:016x}-{:016x}  {}\n",
+                    region.base,
+                    region.base.wrapping_add(region.len).wrapping_sub(1),
+                    region.kind_name()
+                );
+            }
+            if len > info.memory_map().len() {
+                printk!("  and {} more\n", len - info.memory_map().len());
+            }
+        }
+        None => printk!("memory map: {NOT_PROVIDED}\n"),
+    }
+    Ok(())
+}
+
+/// Prints the free memory, along with the allocation counters if `verbose` is set.
+fn print_meminfo(verbose: bool) {
+    let (stats, pressure, watermarks, fail_every) = {
+        let frames = memory::frames();
+        (
+            frames.stats(),
+            frames.pressure(),
+            frames.watermarks(),
+            frames.fail_every(),
+        )
+    };
+    let kib = |frames: usize| frames * FRAME_SIZE / 1024;
+    printk!(
+        "{} KiB free of {} KiB, pressure {}\n",
+        kib(stats.free),
+        kib(stats.total),
+        pressure.name()
+    );
+    printk!(
+        "watermarks: low {} KiB, min {} KiB\n",
+        kib(watermarks.low),
+        kib(watermarks.min)
+    );
+    if verbose {
+        printk!(
+            "allocations: {}, out of memory: {}\n",
+            stats.allocations,
+            stats.out_of_memory
+        );
+        printk!(
+            "FAILALLOC {fail_every}: {} failure(s) injected\n",
+            stats.injected
+        );
+        printk!(
+            "panics after an injected failure: {}\n",
+            memory::injected_panics()
+        );
+    }
+}
+
+/// Implements the `alloctest` command.
+///
+/// Up to `count` frames are allocated, all of them by default, going on past injected failures.
+/// Each frame holds the address of the one allocated before it, and the list is walked back to
+/// free them, which checks that no frame was handed out twice.
+fn alloctest(args: &mut Args) -> Result<(), ShellError> {
+    let count = match args.next() {
+        None => u32::MAX,
+        Some(count) => shell::parse_u32(count).ok_or(args.invalid("a number"))?,
+    };
+    args.expect_end()?;
+    // Frame 0 is never allocated, so it ends the list.
+    let mut head = 0;
+    let (mut allocated, mut injected) = (0, 0);
+    let mut out_of_memory = false;
+    let mut interrupted = false;
+    for i in 0..count {
+        if i % 256 == 0 && cancelled() {
+            interrupted = true;
+            break;
+        }
+        match memory::alloc() {
+            Ok(frame) => {
+                // Safety: The frame is ours, and there is no paging.
+                unsafe { (frame as *mut usize).write_volatile(head) };
+                head = frame;
+                allocated += 1;
+            }
+            Err(AllocError::Injected) => injected += 1,
+            Err(AllocError::OutOfMemory) => {
+                out_of_memory = true;
+                break;
+            }
+        }
+    }
+    let mut freed = 0;
+    while head != 0 {
+        // Safety: Same as above, the frame was not freed yet.
+        let next = unsafe { (head as *const usize).read_volatile() };
+        if !memory::free(head) {
+            return Err(ShellError::Failed("a frame was allocated twice"));
+        }
+        freed += 1;
+        head = next;
+    }
+    printk!("alloctest: {freed} frame(s) allocated and freed, {injected} failure(s) injected");
+    printk!(
+        "{}\n",
+        if out_of_memory {
+            ", then out of memory"
+        } else {
+            ""
+        }
+    );
+    if interrupted {
+        return Err(ShellError::Interrupted);
+    }
+    if freed != allocated {
+        return Err(ShellError::Failed("the list of frames was corrupted"));
+    }
+    Ok(())
+}
+
+/// Implements the `set` command.
+fn set(args: &mut Args) -> Result<(), ShellError> {
+    let Some(name) = args.next() else {
+        let (fail_every, watermarks) = {
+            let frames = memory::frames();
+            (frames.fail_every(), frames.watermarks())
+        };
+        printk!("FAILALLOC {fail_every}\n");
+        printk!("WMARK_LOW {}K\n", watermarks.low * FRAME_SIZE / 1024);
+        printk!("WMARK_MIN {}K\n", watermarks.min * FRAME_SIZE / 1024);
+        return Ok(());
+    };
+    match name {
+        "FAILALLOC" => {
+            let fail_every =
+                FailEvery::parse(args.next_str()?).ok_or(args.invalid("off or 1/<n>"))?;
+            args.expect_end()?;
+            memory::frames().set_fail_every(fail_every);
+            // A new soak test starts.
+            memory::reset_injected_panics();
+        }
+        "WMARK_LOW" | "WMARK_MIN" => {
+            let frames = args.next_size()? / FRAME_SIZE;
+            args.expect_end()?;
+            let mut watermarks = memory::frames().watermarks();
+            if name == "WMARK_LOW" {
+                watermarks.low = frames;
+            } else {
+                watermarks.min = frames;
+            }
+            if watermarks.min > watermarks.low {
+                return Err(ShellError::Failed("WMARK_MIN must not be above WMARK_LOW"));
+            }
+            memory::set_watermarks(watermarks);
+        }
+        _ => return Err(args.invalid("FAILALLOC, WMARK_LOW or WMARK_MIN")),
+    }
+    Ok(())
+}
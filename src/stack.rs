@@ -0,0 +1,46 @@
+use core::arch::asm;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+
+/// The size of the kernel stack, in bytes.
+pub const KERNEL_STACK_SIZE: usize = 0x1000 * 32;
+
+/// Memory reserved for a stack.
+///
+/// The memory is only ever accessed through the stack pointer, which is why it can be shared.
+#[repr(C, align(16))]
+pub struct Stack<const SIZE: usize>(UnsafeCell<MaybeUninit<[u8; SIZE]>>);
+
+unsafe impl<const SIZE: usize> Sync for Stack<SIZE> {}
+
+impl<const SIZE: usize> Stack<SIZE> {
+    pub const fn new() -> Self {
+        Stack(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    /// Returns the lowest address of the stack.
+    #[inline]
+    pub fn bottom(&self) -> *const u8 {
+        self.0.get().cast()
+    }
+
+    /// Returns the address right past the end of the stack, where it starts growing from.
+    #[inline]
+    pub fn top(&self) -> *const u8 {
+        self.bottom().wrapping_add(SIZE)
+    }
+}
+
+/// The stack the kernel runs on, set up by `_start`.
+pub static KERNEL_STACK: Stack<KERNEL_STACK_SIZE> = Stack::new();
+
+/// Returns the current value of the stack pointer.
+#[inline(always)]
+pub fn current() -> *const u8 {
+    let esp: usize;
+    // Safety: nothing is touched, we only get the value of ESP
+    unsafe {
+        asm!("mov {}, esp", out (reg) esp, options(nostack, nomem, preserves_flags));
+    }
+    core::ptr::with_exposed_provenance(esp)
+}
@@ -0,0 +1,285 @@
+//! The kernel's notion of time.
+//!
+//! [`monotonic_ns`] is the single time source the rest of the kernel should use. It is driven
+//! by the TSC, calibrated once at boot against channel 2 of the PIT. If the PIT does not
+//! respond, the RTC is used instead, and as a last resort a fixed frequency is assumed.
+
+use {
+    crate::io::{inb, outb},
+    core::arch::asm,
+    kfs_core::mutex::Mutex,
+};
+
+/// The frequency of the PIT oscillator, in Hz.
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+/// The duration of the calibration window, in milliseconds.
+const CALIBRATION_MS: u32 = 10;
+
+/// The number of TSC cycles after which the PIT is considered missing. This is far longer than
+/// the calibration window on any CPU.
+const PIT_TIMEOUT_CYCLES: u64 = 1 << 30;
+
+/// The number of TSC cycles after which the RTC is considered missing. This must be longer than
+/// one second on any CPU.
+const RTC_TIMEOUT_CYCLES: u64 = 1 << 34;
+
+/// The frequency of [`ticks`], in Hz.
+pub const TICK_HZ: u64 = 100;
+
+/// The TSC frequency assumed when no device could be used to measure it, in kHz.
+const FALLBACK_TSC_KHZ: u64 = 1_000_000;
+
+/// The device the TSC was calibrated against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSource {
+    /// The clock has not been calibrated yet.
+    None,
+    /// Channel 2 of the PIT.
+    Pit,
+    /// The CMOS real-time clock, used when the PIT did not respond.
+    Rtc,
+    /// Nothing responded, and a fixed TSC frequency is assumed.
+    Fallback,
+}
+
+impl core::fmt::Display for TimeSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            TimeSource::None => "none",
+            TimeSource::Pit => "TSC calibrated against the PIT",
+            TimeSource::Rtc => "TSC calibrated against the RTC (PIT not responding)",
+            TimeSource::Fallback => "uncalibrated TSC (PIT and RTC not responding)",
+        })
+    }
+}
+
+/// The state of the monotonic clock.
+struct Clock {
+    /// The device the TSC was calibrated against.
+    source: TimeSource,
+    /// The number of TSC cycles per millisecond, or 0 if the clock is not calibrated yet.
+    tsc_khz: u64,
+    /// The TSC value taken at calibration time.
+    tsc_base: u64,
+    /// The last value returned by [`monotonic_ns`].
+    last_ns: u64,
+}
+
+static CLOCK: Mutex<Clock> = Mutex::new(Clock {
+    source: TimeSource::None,
+    tsc_khz: 0,
+    tsc_base: 0,
+    last_ns: 0,
+});
+
+/// Reads the time stamp counter.
+#[inline]
+pub fn rdtsc() -> u64 {
+    let lo: u32;
+    let hi: u32;
+    // Safety: `rdtsc` only reads the time stamp counter.
+    unsafe {
+        asm!("rdtsc", out("eax") lo, out("edx") hi, options(nomem, nostack, preserves_flags));
+    }
+    (hi as u64) << 32 | lo as u64
+}
+
+/// Busy-waits until `done` returns true, giving up after `timeout` TSC cycles.
+///
+/// Returns whether `done` returned true.
+fn wait_bounded(timeout: u64, mut done: impl FnMut() -> bool) -> bool {
+    let start = rdtsc();
+    while !done() {
+        if rdtsc().wrapping_sub(start) > timeout {
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+    true
+}
+
+/// Measures the number of TSC cycles per millisecond using channel 2 of the PIT.
+///
+/// This does not rely on interrupts. The speaker output is kept disabled. Returns `None` if
+/// the PIT does not respond.
+fn calibrate_with_pit() -> Option<u64> {
+    let count = PIT_FREQUENCY / 1000 * CALIBRATION_MS;
+    assert!(count <= 0xFFFF);
+    unsafe {
+        // Enable the channel 2 gate, keep the speaker disconnected.
+        let port_b = inb(0x61);
+        outb(0x61, (port_b & !0x02) | 0x01);
+        // Channel 2, lobyte/hibyte, mode 0 (interrupt on terminal count).
+        outb(0x43, 0b1011_0000);
+        outb(0x42, count as u8);
+        outb(0x42, (count >> 8) as u8);
+        // OUT2 (bit 5 of port B) goes high once the count reaches zero.
+        let start = rdtsc();
+        let responded = wait_bounded(PIT_TIMEOUT_CYCLES, || inb(0x61) & 0x20 != 0);
+        let end = rdtsc();
+        outb(0x61, port_b);
+        responded.then(|| (end - start) / CALIBRATION_MS as u64)
+    }
+}
+
+/// Measures the number of TSC cycles per millisecond using the RTC.
+///
+/// This takes up to two seconds. Returns `None` if the RTC does not tick.
+fn calibrate_with_rtc() -> Option<u64> {
+    let start = wait_rtc_second(rdtsc)?;
+    let end = wait_rtc_second(rdtsc)?;
+    Some((end - start) / 1000)
+}
+
+/// Calibrates the TSC, and returns the device it was calibrated against.
+///
+/// Must be called once during early boot, before any call to [`monotonic_ns`].
+pub fn init() -> TimeSource {
+    let (source, tsc_khz) = match calibrate_with_pit() {
+        Some(khz) => (TimeSource::Pit, khz),
+        None => match calibrate_with_rtc() {
+            Some(khz) => (TimeSource::Rtc, khz),
+            None => (TimeSource::Fallback, FALLBACK_TSC_KHZ),
+        },
+    };
+
+    let mut clock = CLOCK.lock();
+    clock.source = source;
+    clock.tsc_khz = tsc_khz.max(1);
+    clock.tsc_base = rdtsc();
+    clock.last_ns = 0;
+    source
+}
+
+/// Returns the device the TSC was calibrated against.
+pub fn source() -> TimeSource {
+    CLOCK.lock().source
+}
+
+/// Returns the smallest non-zero step of [`monotonic_ns`] observed over a few samples.
+pub fn resolution_ns() -> u64 {
+    let mut best = u64::MAX;
+    for _ in 0..16 {
+        let start = monotonic_ns();
+        let mut now = start;
+        if !wait_bounded(PIT_TIMEOUT_CYCLES, || {
+            now = monotonic_ns();
+            now != start
+        }) {
+            break;
+        }
+        best = best.min(now - start);
+    }
+    best
+}
+
+/// Returns the TSC frequency measured at boot, in kHz, or 0 if it has not been measured yet.
+pub fn tsc_khz() -> u64 {
+    CLOCK.lock().tsc_khz
+}
+
+/// Returns the number of nanoseconds elapsed since the clock was calibrated.
+///
+/// The returned value never goes backwards, even if the underlying counter does.
+pub fn monotonic_ns() -> u64 {
+    let tsc = rdtsc();
+    let mut clock = CLOCK.lock();
+    if clock.tsc_khz == 0 {
+        return 0;
+    }
+
+    // Split the computation to avoid overflowing the intermediate product.
+    let delta = tsc.saturating_sub(clock.tsc_base);
+    let tsc_hz = clock.tsc_khz * 1000;
+    let ns = delta / tsc_hz * 1_000_000_000 + delta % tsc_hz * 1_000_000 / clock.tsc_khz;
+
+    clock.last_ns = clock.last_ns.max(ns);
+    clock.last_ns
+}
+
+/// Returns the number of ticks of [`TICK_HZ`] elapsed since the clock was calibrated.
+///
+/// There is no timer interrupt counting them: they are derived from [`monotonic_ns`], and are
+/// just as monotonic.
+pub fn ticks() -> u64 {
+    monotonic_ns() / (1_000_000_000 / TICK_HZ)
+}
+
+/// Returns the number of milliseconds elapsed since the clock was calibrated.
+pub fn millis() -> u64 {
+    monotonic_ns() / 1_000_000
+}
+
+/// The error returned by [`msleep`] when the wait is cut short by **CTRL+C**.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted;
+
+/// Returns whether the CPU accepts interrupts.
+fn interrupts_enabled() -> bool {
+    /// The interrupt flag of EFLAGS.
+    const EFLAGS_IF: usize = 1 << 9;
+
+    let eflags: usize;
+    // Safety: the flags are only read.
+    unsafe { asm!("pushfd", "pop {}", out(reg) eflags, options(nomem, preserves_flags)) };
+    eflags & EFLAGS_IF != 0
+}
+
+/// Waits for `ms` milliseconds, give or take a tick, unless **CTRL+C** is pressed first.
+///
+/// The CPU halts between two checks of the clock when interrupts are enabled, as the next one
+/// wakes it up. Until then, nothing would, and it spins instead.
+pub fn msleep(ms: u32) -> Result<(), Interrupted> {
+    let deadline = ticks() + (ms as u64 * TICK_HZ).div_ceil(1000);
+    while ticks() < deadline {
+        if crate::shell::cancelled() {
+            return Err(Interrupted);
+        }
+        if interrupts_enabled() {
+            // Safety: interrupts are enabled, so the CPU does not halt for good.
+            unsafe { asm!("hlt", options(nomem, nostack)) };
+        } else {
+            core::hint::spin_loop();
+        }
+    }
+    Ok(())
+}
+
+/// Reads the raw seconds register of the CMOS real-time clock.
+///
+/// The value may be BCD-encoded, which is irrelevant when only looking for changes. Returns
+/// `None` if the RTC stays stuck in an update.
+fn rtc_seconds_raw() -> Option<u8> {
+    unsafe {
+        // Wait for any update in progress to complete.
+        outb(0x70, 0x0A);
+        if !wait_bounded(RTC_TIMEOUT_CYCLES, || inb(0x71) & 0x80 == 0) {
+            return None;
+        }
+        outb(0x70, 0x00);
+        Some(inb(0x71))
+    }
+}
+
+/// Busy-waits until the RTC seconds register changes, and returns the value of `clock` at that
+/// moment.
+///
+/// Returns `None` if the RTC does not tick.
+fn wait_rtc_second(clock: fn() -> u64) -> Option<u64> {
+    let start = rtc_seconds_raw()?;
+    wait_bounded(RTC_TIMEOUT_CYCLES, || rtc_seconds_raw() != Some(start)).then(clock)
+}
+
+/// Measures the drift of the monotonic clock against the RTC over `seconds` seconds.
+///
+/// Returns the number of nanoseconds measured by the monotonic clock, or `None` if the RTC does
+/// not tick.
+pub fn measure_against_rtc(seconds: u32) -> Option<u64> {
+    let start = wait_rtc_second(monotonic_ns)?;
+    let mut end = start;
+    for _ in 0..seconds {
+        end = wait_rtc_second(monotonic_ns)?;
+    }
+    Some(end - start)
+}
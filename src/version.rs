@@ -0,0 +1,22 @@
+//! Information about how the kernel was built, captured by `build.rs`.
+
+/// The name of the kernel.
+pub const NAME: &str = env!("CARGO_PKG_NAME");
+
+/// The version of the kernel crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The output of `git describe`, such as `7ce96a5-dirty`.
+pub const GIT_DESCRIBE: &str = env!("KFS_GIT_DESCRIBE");
+
+/// The output of `rustc --version`.
+pub const RUSTC_VERSION: &str = env!("KFS_RUSTC_VERSION");
+
+/// The Cargo profile the kernel was built with, `debug` or `release`.
+pub const PROFILE: &str = env!("KFS_PROFILE");
+
+/// The target the kernel was built for.
+pub const TARGET: &str = env!("KFS_TARGET");
+
+/// When the build script last ran, in UTC.
+pub const BUILD_TIMESTAMP: &str = env!("KFS_BUILD_TIMESTAMP");